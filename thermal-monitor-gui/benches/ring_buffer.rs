@@ -0,0 +1,53 @@
+//! Push + iterate over a full 60-sample history: VecDeque vs RingBuffer
+//!
+//! Run with `cargo bench --bench ring_buffer`.
+
+use std::collections::VecDeque;
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+// The crate only builds a binary, so the module is compiled in directly
+// (its unit tests are not run here, hence the unused imports)
+#[allow(dead_code, unused_imports)]
+#[path = "../src/ring_buffer.rs"]
+mod ring_buffer;
+
+use ring_buffer::RingBuffer;
+
+/// Samples kept by TemperatureHistory (2 minutes at 2-second intervals)
+const CAPACITY: usize = 60;
+
+/// Same work as one history update plus building the plot points
+fn push_and_points(c: &mut Criterion) {
+    let mut group = c.benchmark_group("push_and_points");
+
+    let mut deque: VecDeque<f32> = (0..CAPACITY).map(|i| i as f32).collect();
+    group.bench_function("VecDeque", |b| {
+        b.iter(|| {
+            deque.pop_front();
+            deque.push_back(black_box(55.0));
+            let points: Vec<[f64; 2]> =
+                deque.iter().enumerate().map(|(i, &t)| [i as f64 * 2.0, t as f64]).collect();
+            black_box(points)
+        })
+    });
+
+    let mut ring = RingBuffer::new(CAPACITY);
+    for i in 0..CAPACITY {
+        ring.push(i as f32);
+    }
+    group.bench_function("RingBuffer", |b| {
+        b.iter(|| {
+            ring.push(black_box(55.0));
+            let points: Vec<[f64; 2]> =
+                ring.iter().enumerate().map(|(i, &t)| [i as f64 * 2.0, t as f64]).collect();
+            black_box(points)
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, push_and_points);
+criterion_main!(benches);
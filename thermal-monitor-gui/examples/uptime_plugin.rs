@@ -0,0 +1,130 @@
+//! Example plugin: time spent in each mode and the hottest reading seen
+//!
+//! Build and install with:
+//!
+//! ```sh
+//! cargo build --release --example uptime_plugin
+//! mkdir -p ~/.local/share/lenovo-thermal-monitor/plugins
+//! cp target/release/examples/libuptime_plugin.so ~/.local/share/lenovo-thermal-monitor/plugins/
+//! ```
+//!
+//! then set `plugins_enabled = true` in the config and run an app built with
+//! `--features plugins`. The plugin only shares the C layout of the types in
+//! `src/plugin.rs`, declared again below, so it can be built by any compiler.
+
+use std::collections::HashMap;
+use std::ffi::{c_char, c_void, CString};
+use std::time::{Duration, Instant};
+
+/// `PLUGIN_ABI_VERSION` this plugin is written for
+const ABI_VERSION: u32 = 1;
+
+/// Mode names by ABI mode code
+const MODE_NAMES: [&str; 6] = ["Performance", "Comfort", "Balanced", "Quiet", "Auto", "Unknown"];
+
+/// Mirror of `plugin::PluginState`
+#[repr(C)]
+struct PluginState {
+    cpu_temp: f32,
+    keyboard_temp: f32,
+    perf_pct: u32,
+    mode: u32,
+}
+
+/// Mirror of `plugin::PluginVTable`
+#[repr(C)]
+struct PluginVTable {
+    abi_version: u32,
+    name: unsafe extern "C" fn(*mut c_void) -> *const c_char,
+    on_state_update: unsafe extern "C" fn(*mut c_void, *const PluginState),
+    on_mode_change: unsafe extern "C" fn(*mut c_void, u32, u32),
+    panel_text: unsafe extern "C" fn(*mut c_void) -> *const c_char,
+    destroy: unsafe extern "C" fn(*mut c_void),
+}
+
+/// Mirror of `plugin::PluginInstance`
+#[repr(C)]
+pub struct PluginInstance {
+    data: *mut c_void,
+    vtable: *const PluginVTable,
+}
+
+/// Accumulates time per mode between state updates
+struct UptimePlugin {
+    mode: u32,
+    since: Instant,
+    time_in_mode: HashMap<u32, Duration>,
+    hottest: f32,
+    /// Backs the pointers handed out by `name` and `panel_text`
+    name: CString,
+    panel: CString,
+}
+
+impl UptimePlugin {
+    /// Credit the time since the last update to the current mode
+    fn account(&mut self) {
+        let now = Instant::now();
+        *self.time_in_mode.entry(self.mode).or_default() += now - self.since;
+        self.since = now;
+    }
+
+    fn panel_text(&mut self) -> String {
+        self.account();
+        let mut text = String::new();
+        for (code, name) in MODE_NAMES.iter().enumerate().take(5) {
+            let secs = self.time_in_mode.get(&(code as u32)).map_or(0, Duration::as_secs);
+            text.push_str(&format!("{}: {}m {:02}s\n", name, secs / 60, secs % 60));
+        }
+        text.push_str(&format!("Hottest: {:.0}°C", self.hottest));
+        text
+    }
+}
+
+/// # Safety
+/// `data` must come from `thermal_plugin_create` and not be destroyed yet.
+unsafe fn plugin<'a>(data: *mut c_void) -> &'a mut UptimePlugin {
+    &mut *data.cast::<UptimePlugin>()
+}
+
+unsafe extern "C" fn name(data: *mut c_void) -> *const c_char {
+    plugin(data).name.as_ptr()
+}
+
+unsafe extern "C" fn on_state_update(data: *mut c_void, state: *const PluginState) {
+    let (plugin, state) = (plugin(data), &*state);
+    plugin.account();
+    plugin.mode = state.mode;
+    plugin.hottest = plugin.hottest.max(state.cpu_temp);
+}
+
+unsafe extern "C" fn on_mode_change(data: *mut c_void, _old: u32, new: u32) {
+    let plugin = plugin(data);
+    plugin.account();
+    plugin.mode = new;
+}
+
+unsafe extern "C" fn panel_text(data: *mut c_void) -> *const c_char {
+    let plugin = plugin(data);
+    plugin.panel = CString::new(plugin.panel_text()).unwrap_or_default();
+    plugin.panel.as_ptr()
+}
+
+unsafe extern "C" fn destroy(data: *mut c_void) {
+    drop(Box::from_raw(data.cast::<UptimePlugin>()));
+}
+
+static VTABLE: PluginVTable = PluginVTable { abi_version: ABI_VERSION, name, on_state_update, on_mode_change, panel_text, destroy };
+
+/// Entry point looked up by the app (`plugin::PLUGIN_ENTRY_POINT`)
+#[no_mangle]
+pub extern "C" fn thermal_plugin_create() -> PluginInstance {
+    let plugin = Box::new(UptimePlugin {
+        mode: 4,
+        since: Instant::now(),
+        time_in_mode: HashMap::new(),
+        hottest: 0.0,
+        name: CString::new("Mode uptime").unwrap_or_default(),
+        panel: CString::default(),
+    });
+    PluginInstance { data: Box::into_raw(plugin).cast(), vtable: &VTABLE }
+}
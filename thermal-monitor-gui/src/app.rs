@@ -2,20 +2,248 @@
 //!
 //! Implements eframe::App trait for egui integration.
 
-use std::collections::VecDeque;
-use std::time::{Duration, Instant};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use eframe::egui;
-use egui_plot::{Line, Plot, PlotPoints};
+use egui_plot::{Bar, BarChart, Line, Plot, PlotPoints, Polygon};
 
-use crate::system::{Mode, ThermalState, ThermalZone, set_mode, set_fan_boost, apply_thermal_control};
+use crate::config::{self, Config, ConfigWatcher, TemperatureUnit, CONFIG_VERSION};
+use crate::expr::Expr;
+use crate::help::{with_help, HelpText};
+use crate::plugin::ThermalPlugin;
+use crate::ring_buffer::RingBuffer;
+use crate::session::SessionStats;
+use crate::state_machine::{ControlAction, ThermalStateMachine};
+use crate::system::{
+    Mode, ThermalError, ThermalState, ThermalZone, set_mode, set_fan_boost, apply_thermal_control, FanCurve,
+    set_turbo, supports_turbo_toggle, send_notification, is_read_only_error, read_wake_count, resumed_from_suspend,
+    calculate_keyboard_temp, read_ambient_temp, FTRACE_PATH,
+    discover_writable_paths, detect_udev_group, udev_rules_content, install_udev_rules,
+    UDEV_RULES_PATH, ProcessCpuSample, read_process_cpu_sample, top_process_usage,
+    read_self_cpu_ticks, cpu_percent, open_url, read_system_high_contrast, read_input_interrupts, get_platform_profile_driver, PlatformProfileDriver, read_cpu_temp, read_cpufreq_driver, DriverStatus, ideapad_acpi_driver_check, load_ideapad_driver,
+    SENSOR_NAMES, TripPoint, TripType, read_thermal_trip_points, autostart_path, set_autostart,
+    ScreenRefresh, read_screen_refresh, set_screen_refresh_rate, CRITICAL_REFRESH_HZ, set_cpu_boost,
+    estimate_throttle_headroom, THROTTLE_WARNING_HEADROOM,
+    AcpiEventStream, AcpiEventType,
+};
+#[cfg(feature = "ec_access")]
+use crate::system::{apply_fan_curve, release_fan_curve, FAN_LEVEL_MAX};
+use crate::widgets::{render_sparkline, ButtonGrid, ButtonItem};
 
 /// Update interval in seconds
 const UPDATE_INTERVAL_SECS: f32 = 2.0;
 
+/// Minimum time between early refreshes triggered by ACPI thermal trips
+const TRIP_REFRESH_MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Repaint interval while the screen is off (lid closed but still running)
+const SCREEN_OFF_REPAINT_MS: u64 = 1000;
+
+/// Allowed benchmark duration range in minutes
+const BENCHMARK_MINUTES_RANGE: std::ops::RangeInclusive<u64> = 1..=120;
+
+/// Degrees that weigh as much as one fan level when picking a curve point
+#[cfg(feature = "ec_access")]
+const FAN_CURVE_DEGREES_PER_LEVEL: f32 = 10.0;
+
+/// Tooltip for controls disabled because sysfs cannot be written
+const READ_ONLY_HINT: &str = "System is in read-only mode";
+
+/// Banner and tooltip while low-battery safe mode holds Quiet mode
+const LOW_BATTERY_HINT: &str = "Low battery – Quiet mode enforced";
+
+/// Battery charge (%) above which low-battery safe mode ends
+const LOW_BATTERY_RESTORE_PCT: u8 = 15;
+
 /// History capacity (2 minutes at 2-second intervals)
 const HISTORY_CAPACITY: usize = 60;
 
+/// Percentile and window (30 s) of the optional rolling percentile line
+const PERCENTILE_LINE_P: f32 = 95.0;
+const PERCENTILE_LINE_WINDOW: usize = 15;
+
+/// Number of processes shown in the CPU usage pie chart
+const TOP_PROCESS_COUNT: usize = 5;
+
+/// Slice colors for the CPU usage pie chart
+const PIE_COLORS: [egui::Color32; TOP_PROCESS_COUNT] = [
+    egui::Color32::from_rgb(255, 100, 100),
+    egui::Color32::from_rgb(255, 200, 100),
+    egui::Color32::from_rgb(150, 220, 100),
+    egui::Color32::from_rgb(100, 200, 255),
+    egui::Color32::from_rgb(180, 180, 220),
+];
+
+/// Key widths (in key units, 15 per row) of a rough IdeaPad keyboard: function
+/// row, number row, three letter rows, then modifiers, space bar and arrows
+const KEYBOARD_ROWS: [&[f32]; 6] = [
+    &[1.0; 15],
+    &[1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 2.0],
+    &[1.5, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.5],
+    &[1.75, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 2.25],
+    &[2.25, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 2.75],
+    &[1.25, 1.0, 1.25, 1.25, 5.5, 1.25, 1.25, 0.75, 0.75, 0.75],
+];
+
+/// Where the keyboard runs hottest (fractions of its width and height from the
+/// top left): above the CPU, just left of center under the number row
+const KEYBOARD_HOTSPOT: (f32, f32) = (0.45, 0.3);
+
+/// Surface temperatures mapped onto the blue → yellow → red heatmap gradient (°C)
+const KEYBOARD_HEAT_RANGE: std::ops::RangeInclusive<f32> = 30.0..=50.0;
+
+/// Plot colors of user-added lines (custom metrics, then cores), reused in order
+const SERIES_COLORS: [egui::Color32; 4] = [
+    egui::Color32::from_rgb(120, 230, 160),
+    egui::Color32::from_rgb(240, 140, 220),
+    egui::Color32::from_rgb(230, 230, 120),
+    egui::Color32::from_rgb(140, 170, 255),
+];
+
+/// Largest lag (in samples) shown in the thermal periodicity chart
+const AUTOCORRELATION_MAX_LAG: usize = 10;
+
+/// Cores shown in the die map before it scrolls (2 per row)
+const TOPOLOGY_VISIBLE_CORES: usize = 8;
+
+/// Readings shown in each core's sparkline
+const CORE_SPARKLINE_SAMPLES: usize = 10;
+
+/// Duration of the monitoring overhead measurement
+const OVERHEAD_BENCHMARK_SECS: u64 = 30;
+
+/// Self CPU usage (% of one core) above which a longer polling interval is suggested
+const OVERHEAD_WARNING_PCT: f32 = 2.0;
+
+/// Number of recent samples used to estimate the temperature trend
+const TREND_SAMPLES: usize = 10;
+
+/// Temperature the heating prediction counts down to
+const PREDICTION_THRESHOLD: f32 = 80.0;
+
+/// Degrees of headroom above the alarm threshold needed before the headroom alarm can fire again
+const HEADROOM_ALARM_REARM: f32 = 3.0;
+
+/// Temperature span shown by the zone legend scale
+const LEGEND_RANGE: std::ops::RangeInclusive<f32> = 30.0..=75.0;
+
+/// Auto-saved history snapshot, restored on startup
+const SNAPSHOT_PATH: &str = "/tmp/lenovo-thermal-monitor.snap";
+
+/// Interval between history snapshots
+const SNAPSHOT_INTERVAL_SECS: u64 = 60;
+
+/// History written by the panic handler
+const CRASH_SNAPSHOT_PATH: &str = "/tmp/lenovo-thermal-monitor-crash.snap";
+
+/// Where crash reports are filed
+const NEW_ISSUE_URL: &str = "https://github.com/andresgarcia0313/lenovo-ideapad/issues/new";
+
+/// Latest release as reported by the GitHub API, and its download page
+#[cfg(feature = "update-check")]
+const LATEST_RELEASE_API_URL: &str = "https://api.github.com/repos/andresgarcia0313/lenovo-ideapad/releases/latest";
+const LATEST_RELEASE_URL: &str = "https://github.com/andresgarcia0313/lenovo-ideapad/releases/latest";
+
+/// `--update-bar` output when no `--bar-format` is given (waybar's JSON)
+const DEFAULT_BAR_FORMAT: &str = r#"{"text": "⚡{cpu_temp}° {mode}", "color": "{color}"}"#;
+
+/// How long a release check is reused before GitHub is asked again
+const UPDATE_CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Latest serialized history, kept where the panic hook can reach it
+static CRASH_HISTORY: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+/// Set by the SIGTERM/SIGINT handler; the app then exits the normal way,
+/// so the session summary is still written
+static TERMINATE_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Signal numbers handled by [`ThermalApp::install_terminate_handler`]
+const SIGINT: std::ffi::c_int = 2;
+const SIGTERM: std::ffi::c_int = 15;
+
+/// Binary snapshot magic bytes and format version
+const SNAPSHOT_MAGIC: &[u8; 4] = b"LTHM";
+const SNAPSHOT_VERSION: u32 = 2;
+
+/// Destination of the "Export CSV" button
+const CSV_EXPORT_PATH: &str = "/tmp/lenovo-thermal-history.csv";
+
+/// Destination of the "Export Gnuplot" button
+const GNUPLOT_EXPORT_PATH: &str = "/tmp/lenovo-thermal-history.gp";
+
+/// Destination of the "Export HTML" button
+const HTML_EXPORT_PATH: &str = "/tmp/lenovo-thermal-history.html";
+
+/// Destination of the "Export InfluxDB" button
+const INFLUXDB_EXPORT_PATH: &str = "/tmp/lenovo-thermal-history.lp";
+
+/// Destination of the "Export NumPy" button
+#[cfg(feature = "ndarray")]
+const NPY_EXPORT_PATH: &str = "/tmp/lenovo-thermal-history.npy";
+
+/// Destination of the "Export Protobuf" button, read back by "Import Protobuf"
+#[cfg(feature = "protobuf")]
+const PROTOBUF_EXPORT_PATH: &str = "/tmp/lenovo-thermal-history.pb";
+
+/// Destination of the "Stop & Export" recording button
+const RECORDING_EXPORT_PATH: &str = "/tmp/lenovo-thermal-recording.sh";
+
+/// Plotly.js build loaded by the exported HTML page
+const PLOTLY_CDN_URL: &str = "https://cdn.plot.ly/plotly-2.35.2.min.js";
+
+/// Entries kept in the event log
+const EVENT_LOG_CAPACITY: usize = 100;
+
+/// Give up on a webhook POST after this long
+#[cfg(feature = "webhook")]
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Give up on the release check after this long
+#[cfg(feature = "update-check")]
+const UPDATE_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// InfluxDB measurement name used by `--influxdb-url` and the InfluxDB export
+const INFLUXDB_MEASUREMENT: &str = "thermal";
+
+/// Samples in each window of the CPU envelope (1 minute)
+const ENVELOPE_WINDOW: usize = 30;
+
+/// Samples averaged by the moving-average smoothing (16 s)
+const SMOOTHING_WINDOW: usize = 8;
+
+/// Range of the wavelet noise threshold slider (°C)
+const WAVELET_THRESHOLD_RANGE: std::ops::RangeInclusive<f32> = 0.1..=3.0;
+
+/// Samples projected ahead by the history forecast (30 s)
+const FORECAST_SAMPLES: usize = 15;
+
+/// Temperature span and bin count of the distribution view
+const DISTRIBUTION_RANGE: std::ops::RangeInclusive<f32> = 30.0..=90.0;
+const DISTRIBUTION_BINS: usize = 12;
+
+/// Minimum time between mode changes from the mouse wheel, so a touchpad
+/// fling does not run through every mode
+const MODE_WHEEL_COOLDOWN: Duration = Duration::from_millis(500);
+
+/// Window over which repeated auto-control actions are summarized
+const AUTO_LOG_WINDOW: Duration = Duration::from_secs(60);
+
+/// Suppressed auto-control actions that trigger an oscillation summary
+const AUTO_LOG_SUMMARY_EVERY: u32 = 5;
+
+/// Typical IdeaPad battery capacity, for runtime estimates
+const TYPICAL_BATTERY_WH: f32 = 57.0;
+
+/// Typical draw in Balanced mode at light load
+const BASELINE_DRAW_WATTS: f32 = 10.0;
+
 /// Get localized app description (max 8 words)
 /// Supports: English, Spanish, Chinese, Portuguese, German
 fn get_localized_description() -> &'static str {
@@ -36,552 +264,7421 @@ fn get_localized_description() -> &'static str {
     }
 }
 
-/// Temperature history buffer
-#[derive(Debug)]
-pub struct TemperatureHistory {
-    cpu_temps: VecDeque<f32>,
-    kbd_temps: VecDeque<f32>,
-    capacity: usize,
+/// Milliseconds since the Unix epoch (0 for times before it)
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
 }
 
-impl Default for TemperatureHistory {
-    fn default() -> Self {
-        Self::new(HISTORY_CAPACITY)
-    }
+/// One InfluxDB line protocol point: `measurement cpu_temp=..,kbd_temp=.. <unix ns>`.
+/// Commas and spaces in the measurement name are backslash-escaped as the spec requires.
+fn influxdb_line(measurement: &str, cpu: f32, kbd: f32, time: SystemTime) -> String {
+    let nanos = time.duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    let measurement = measurement.replace(',', "\\,").replace(' ', "\\ ");
+    format!("{} cpu_temp={:.1},kbd_temp={:.1} {}", measurement, cpu, kbd, nanos)
 }
 
-impl TemperatureHistory {
-    pub fn new(capacity: usize) -> Self {
-        Self {
-            cpu_temps: VecDeque::with_capacity(capacity),
-            kbd_temps: VecDeque::with_capacity(capacity),
-            capacity,
-        }
-    }
+/// `major.minor.patch` of a version or release tag; a leading `v` and any
+/// pre-release suffix are ignored, missing components count as 0
+fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let version = version.trim().trim_start_matches('v');
+    let core = version.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|part| part.parse::<u32>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    Some((major, minor, patch))
+}
+
+/// The release `tag` is a newer version than `current`
+fn version_is_newer(tag: &str, current: &str) -> bool {
+    matches!((parse_version(tag), parse_version(current)), (Some(tag), Some(current)) if tag > current)
+}
 
-    pub fn push(&mut self, cpu: f32, kbd: f32) {
-        if self.cpu_temps.len() >= self.capacity {
-            self.cpu_temps.pop_front();
-            self.kbd_temps.pop_front();
+/// `tag_name` of a GitHub release JSON document
+#[cfg_attr(not(feature = "update-check"), allow(dead_code))]
+fn release_tag_from_json(json: &str) -> Option<String> {
+    let rest = &json[json.find("\"tag_name\"")? + "\"tag_name\"".len()..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start().strip_prefix('"')?;
+    let tag = &rest[..rest.find('"')?];
+    (!tag.is_empty()).then(|| tag.to_string())
+}
+
+/// `text` percent-encoded for a URL query value
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// `text` as a quoted JSON string
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
         }
-        self.cpu_temps.push_back(cpu);
-        self.kbd_temps.push_back(kbd);
     }
+    quoted.push('"');
+    quoted
+}
 
-    /// Get CPU temperature points for plotting
-    pub fn cpu_points(&self) -> PlotPoints {
-        PlotPoints::new(
-            self.cpu_temps
-                .iter()
-                .enumerate()
-                .map(|(i, &t)| [i as f64, t as f64])
-                .collect(),
-        )
+/// `celsius` converted to `unit` for display
+pub fn to_display(celsius: f32, unit: TemperatureUnit) -> f32 {
+    match unit {
+        TemperatureUnit::Celsius => celsius,
+        TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
     }
+}
 
-    /// Get keyboard temperature points for plotting
-    pub fn kbd_points(&self) -> PlotPoints {
-        PlotPoints::new(
-            self.kbd_temps
-                .iter()
-                .enumerate()
-                .map(|(i, &t)| [i as f64, t as f64])
-                .collect(),
-        )
+/// Inverse of [`to_display`], for values entered in `unit`
+pub fn from_display(value: f32, unit: TemperatureUnit) -> f32 {
+    match unit {
+        TemperatureUnit::Celsius => value,
+        TemperatureUnit::Fahrenheit => (value - 32.0) * 5.0 / 9.0,
     }
+}
 
-    pub fn len(&self) -> usize {
-        self.cpu_temps.len()
-    }
+/// A temperature difference in °C converted to `unit` (no offset)
+pub fn to_display_delta(celsius: f32, unit: TemperatureUnit) -> f32 {
+    to_display(celsius, unit) - to_display(0.0, unit)
+}
 
-    pub fn is_empty(&self) -> bool {
-        self.cpu_temps.is_empty()
-    }
+/// Inverse of [`to_display_delta`], for differences entered in `unit`
+pub fn from_display_delta(value: f32, unit: TemperatureUnit) -> f32 {
+    from_display(value, unit) - from_display(0.0, unit)
 }
 
-/// Main application state
-pub struct ThermalApp {
-    state: ThermalState,
-    history: TemperatureHistory,
-    last_update: Instant,
-    status_message: Option<(String, Instant)>,
-    target_temp: f32,
-    auto_control: bool,
-    fan_boost_manual: bool,
+/// `celsius` rounded to whole degrees of `unit`, e.g. `122°F`
+pub fn format_temp(celsius: f32, unit: TemperatureUnit) -> String {
+    format!("{:.0}{}", to_display(celsius, unit), unit.label())
 }
 
-impl Default for ThermalApp {
-    fn default() -> Self {
-        let state = ThermalState::read();
-        let mut history = TemperatureHistory::default();
-        history.push(state.cpu_temp, state.keyboard_temp);
+/// Format as an ISO 8601 UTC timestamp, e.g. `2024-05-01T14:03:09Z`
+pub fn format_iso8601(time: SystemTime) -> String {
+    let secs = unix_millis(time) / 1000;
+    let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
 
-        Self {
-            state,
-            history,
-            last_update: Instant::now(),
-            status_message: None,
-            target_temp: 55.0,
-            auto_control: false,
-            fan_boost_manual: false,
-        }
-    }
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, rem / 3600, rem % 3600 / 60, rem % 60
+    )
 }
 
-impl ThermalApp {
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        Self::default()
+/// Parse a timestamp written by [`format_iso8601`]
+pub fn parse_iso8601(text: &str) -> Option<SystemTime> {
+    let (date, time) = text.strip_suffix('Z')?.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let mut time = time.splitn(3, ':').map(str::parse::<u64>);
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 59 {
+        return None;
     }
 
-    /// Update state from system
-    fn update_state(&mut self) {
-        self.state = ThermalState::read();
-        self.history.push(self.state.cpu_temp, self.state.keyboard_temp);
+    // Days since 1970-01-01 from the civil date, the inverse of format_iso8601
+    let y = year - i64::from(month <= 2);
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = u64::try_from(era * 146_097 + doe - 719_468).ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(days * 86_400 + hour * 3600 + minute * 60 + second))
+}
 
-        // Apply automatic thermal control if enabled
-        if self.auto_control {
-            if let Ok(msg) = apply_thermal_control(self.state.cpu_temp, self.target_temp) {
-                if msg != "On target" {
-                    self.status_message = Some((msg, Instant::now()));
-                }
-            }
+/// Summary statistics of one temperature channel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelStats {
+    pub avg: f32,
+    pub p95: f32,
+    pub p99: f32,
+    pub max: f32,
+}
+
+/// Control change captured while recording
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordedStep {
+    Mode(Mode),
+    FanBoost(bool),
+    Target(f32),
+}
+
+impl RecordedStep {
+    /// Command-line flags that replay this step
+    pub fn cli_flags(&self) -> String {
+        match self {
+            RecordedStep::Mode(mode) => format!("--set-mode {}", mode.command()),
+            RecordedStep::FanBoost(enable) => format!("--set-fan-boost {}", if *enable { "on" } else { "off" }),
+            RecordedStep::Target(target) => format!("--set-target {:.0}", target),
         }
     }
+}
 
-    /// Change CPU mode
-    fn change_mode(&mut self, mode: Mode) {
-        match set_mode(mode) {
-            Ok(()) => {
-                self.status_message = Some((
-                    format!("Mode changed to {}", mode.label()),
-                    Instant::now(),
-                ));
-                self.update_state();
-            }
-            Err(e) => {
-                self.status_message = Some((
-                    format!("Error: {}", e),
-                    Instant::now(),
-                ));
+/// A [`RecordedStep`] and when it happened, relative to the start of the recording
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecordedAction {
+    pub at: Duration,
+    pub step: RecordedStep,
+}
+
+impl RecordedAction {
+    /// Shell script replaying `actions` with the CLI flags, sleeping for the
+    /// time that passed between them
+    pub fn shell_script(actions: &[RecordedAction]) -> String {
+        let mut script = format!(
+            "#!/bin/sh\n# Recorded by Thermal Monitor {}: {} actions\nset -e\n",
+            env!("CARGO_PKG_VERSION"),
+            actions.len()
+        );
+        let mut previous = Duration::ZERO;
+        for action in actions {
+            let pause = action.at.saturating_sub(previous).as_secs_f32().round() as u64;
+            if pause > 0 {
+                script.push_str(&format!("sleep {}\n", pause));
             }
+            script.push_str(&format!("thermal-monitor {}\n", action.step.cli_flags()));
+            previous = action.at;
         }
+        script
     }
+}
 
-    /// Set status message
-    fn set_status(&mut self, msg: String) {
-        self.status_message = Some((msg, Instant::now()));
+/// Sessions with manual mode choices needed before mode statistics are offered
+const LEARNING_MIN_SESSIONS: usize = 5;
+
+/// Mode choices kept in the learning log; older ones are dropped
+const LEARNING_LOG_MAX_LINES: usize = 2000;
+
+/// Manual mode choice kept in the learning log: the session it happened in
+/// (start time, unix seconds), the CPU zone and the mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LearningObservation {
+    pub session: u64,
+    pub zone: ThermalZone,
+    pub mode: Mode,
+}
+
+impl LearningObservation {
+    /// Learning log line, e.g. `1700000000 warm balanced`
+    pub fn to_line(self) -> String {
+        format!("{} {} {}", self.session, self.zone.label().to_lowercase(), self.mode.command())
     }
 
-    /// Get zone color as egui Color32
-    fn zone_color(zone: ThermalZone) -> egui::Color32 {
-        let (r, g, b) = zone.color_rgb();
-        egui::Color32::from_rgb(r, g, b)
+    /// Parse a log line; lines written by older versions carry the hour of
+    /// day before the mode, which is skipped
+    pub fn parse(line: &str) -> Option<Self> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (session, zone, mode) = match fields[..] {
+            [session, zone, mode] => (session, zone, mode),
+            [session, zone, hour, mode] if hour.parse::<u8>().is_ok_and(|hour| hour < 24) => (session, zone, mode),
+            _ => return None,
+        };
+        let session = session.parse().ok()?;
+        let zone = ThermalZone::all().iter().copied().find(|z| z.label().eq_ignore_ascii_case(zone))?;
+        let mode = Mode::all().iter().copied().find(|m| m.command() == mode)?;
+        Some(Self { session, zone, mode })
     }
 
-    /// Get mode color
-    fn mode_color(mode: Mode) -> egui::Color32 {
-        match mode {
-            Mode::Performance => egui::Color32::from_rgb(255, 100, 100),
-            Mode::Comfort => egui::Color32::from_rgb(100, 200, 255),
-            Mode::Balanced => egui::Color32::from_rgb(150, 220, 100),
-            Mode::Quiet => egui::Color32::from_rgb(180, 180, 220),
-            Mode::Auto => egui::Color32::from_rgb(255, 200, 100),
-            Mode::Unknown => egui::Color32::GRAY,
-        }
+    /// Most chosen mode per zone as (zone, mode, votes, choices in that zone);
+    /// ties go to the mode listed first in [`Mode::all`]
+    pub fn majority_modes(observations: &[LearningObservation]) -> Vec<(ThermalZone, Mode, usize, usize)> {
+        ThermalZone::all()
+            .iter()
+            .filter_map(|&zone| {
+                let votes: Vec<(Mode, usize)> = Mode::all()
+                    .iter()
+                    .map(|&mode| (mode, observations.iter().filter(|o| o.zone == zone && o.mode == mode).count()))
+                    .collect();
+                let total: usize = votes.iter().map(|(_, count)| count).sum();
+                let (mode, count) = votes.into_iter().rev().max_by_key(|(_, count)| *count)?;
+                (total > 0).then_some((zone, mode, count, total))
+            })
+            .collect()
     }
 
+    /// Distinct sessions in `observations`
+    pub fn session_count(observations: &[LearningObservation]) -> usize {
+        let mut sessions: Vec<u64> = observations.iter().map(|o| o.session).collect();
+        sessions.sort_unstable();
+        sessions.dedup();
+        sessions.len()
+    }
+}
 
-    /// Render temperatures - adaptive version
-    fn render_temperatures_adaptive(&self, ui: &mut egui::Ui, is_medium: bool) {
-        let zone = self.state.thermal_zone();
-        let color = Self::zone_color(zone);
-        let font_size = if is_medium { 24.0 } else { 18.0 };
-        let label_size = if is_medium { 11.0 } else { 9.0 };
+/// Benchmark mode run: the settings to restore and, once Performance mode
+/// was applied, when it ends
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Benchmark {
+    saved_mode: Mode,
+    saved_fan_boost: bool,
+    saved_auto_control: bool,
+    duration: Duration,
+    /// `None` until the switch to Performance succeeded
+    ends_at: Option<Instant>,
+}
 
-        ui.horizontal_wrapped(|ui| {
-            // CPU
-            ui.vertical(|ui| {
-                ui.label(egui::RichText::new("CPU").size(label_size).color(egui::Color32::GRAY));
-                ui.label(egui::RichText::new(format!("{:.0}°", self.state.cpu_temp))
-                    .size(font_size).color(color).strong());
-            });
-            ui.add_space(10.0);
-            // Keyboard
-            ui.vertical(|ui| {
-                ui.label(egui::RichText::new("KBD").size(label_size).color(egui::Color32::GRAY));
-                ui.label(egui::RichText::new(format!("{:.0}°", self.state.keyboard_temp))
-                    .size(font_size).color(color).strong());
-            });
-            ui.add_space(10.0);
-            // Zone label
-            ui.vertical(|ui| {
-                ui.label(egui::RichText::new("Zone").size(label_size).color(egui::Color32::GRAY));
-                ui.label(egui::RichText::new(zone.label()).size(label_size + 2.0).color(color));
-            });
-        });
+impl Benchmark {
+    /// Start the countdown; later calls keep the first end time
+    fn start_timer(&mut self, now: Instant) {
+        self.ends_at.get_or_insert(now + self.duration);
     }
 
-    /// Render performance - adaptive version
-    fn render_performance_adaptive(&self, ui: &mut egui::Ui, is_medium: bool) {
-        let font_size = if is_medium { 20.0 } else { 16.0 };
-        let label_size = if is_medium { 11.0 } else { 9.0 };
-        let mode_color = Self::mode_color(self.state.mode);
+    /// Time left, `None` while the countdown has not started
+    fn remaining(&self, now: Instant) -> Option<Duration> {
+        self.ends_at.map(|end| end.saturating_duration_since(now))
+    }
 
-        ui.horizontal_wrapped(|ui| {
-            ui.vertical(|ui| {
-                ui.label(egui::RichText::new("Perf").size(label_size).color(egui::Color32::GRAY));
-                ui.label(egui::RichText::new(format!("{}%", self.state.perf_pct))
-                    .size(font_size).strong());
-            });
-            ui.add_space(10.0);
-            ui.vertical(|ui| {
-                ui.label(egui::RichText::new("Freq").size(label_size).color(egui::Color32::GRAY));
-                ui.label(egui::RichText::new(format!("{:.1}G", self.state.current_freq_ghz()))
-                    .size(font_size).strong());
-            });
-            ui.add_space(10.0);
-            ui.vertical(|ui| {
-                ui.label(egui::RichText::new("Mode").size(label_size).color(egui::Color32::GRAY));
-                ui.label(egui::RichText::new(self.state.mode.label())
-                    .size(label_size + 2.0).color(mode_color).strong());
-            });
-        });
+    /// Mode to switch back to; nothing when it was unknown at the start
+    fn mode_to_restore(&self) -> Option<Mode> {
+        (self.saved_mode != Mode::Unknown).then_some(self.saved_mode)
     }
+}
 
-    /// Render controls - adaptive version with wrapping
-    fn render_controls_adaptive(&mut self, ui: &mut egui::Ui, available_width: f32) {
-        let button_width = if available_width > 600.0 { 90.0 } else { 70.0 };
-        let button_height = if available_width > 600.0 { 28.0 } else { 24.0 };
-        let font_size = if available_width > 600.0 { 11.0 } else { 9.0 };
+/// Titles of the first-run setup wizard steps
+const SETUP_STEPS: [&str; 5] = ["Hardware", "Units", "Target temperature", "Notifications", "Start automatically"];
 
-        ui.horizontal_wrapped(|ui| {
-            for mode in Mode::all() {
-                let is_current = self.state.mode == *mode;
-                let color = Self::mode_color(*mode);
+/// Settings edited by the first-run wizard, applied when it finishes
+struct SetupWizard {
+    step: usize,
+    config: Config,
+    autostart: bool,
+}
 
-                let button = egui::Button::new(
-                    egui::RichText::new(mode.label())
-                        .size(font_size)
-                        .color(if is_current { egui::Color32::BLACK } else { color }),
-                )
-                .fill(if is_current { color } else { egui::Color32::TRANSPARENT })
-                .stroke(egui::Stroke::new(1.0, color))
-                .min_size(egui::vec2(button_width, button_height));
+/// Smoothed CPU line drawn over the history
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Smoothing {
+    /// Mean of the last few samples; lags behind and rounds off sharp rises
+    MovingAverage,
+    /// Haar wavelet denoising; removes small wiggles and keeps steps
+    Wavelet,
+}
 
-                if ui.add(button).clicked() && !is_current {
-                    self.change_mode(*mode);
-                }
-            }
-        });
+impl Smoothing {
+    pub fn all() -> &'static [Smoothing] {
+        &[Smoothing::MovingAverage, Smoothing::Wavelet]
     }
 
-    /// Render target temperature - adaptive version
-    fn render_target_temp_adaptive(&mut self, ui: &mut egui::Ui, is_wide: bool) {
-        let slider_width = if is_wide { 120.0 } else { 80.0 };
-        let font_size = if is_wide { 11.0 } else { 9.0 };
+    pub fn label(&self) -> &'static str {
+        match self {
+            Smoothing::MovingAverage => "Moving average",
+            Smoothing::Wavelet => "Wavelet",
+        }
+    }
+}
 
-        ui.horizontal_wrapped(|ui| {
-            let slider = egui::Slider::new(&mut self.target_temp, 40.0..=80.0)
-                .suffix("°")
-                .step_by(1.0)
-                .text("");
-            ui.add_sized([slider_width, 20.0], slider);
+/// Minimum, mean and maximum of the finite `samples`
+fn min_avg_max<'a>(samples: impl Iterator<Item = &'a f32>) -> Option<(f32, f32, f32)> {
+    let (min, max, sum, count) = samples
+        .filter(|t| t.is_finite())
+        .fold((f32::INFINITY, f32::NEG_INFINITY, 0.0, 0), |(min, max, sum, count), &t| {
+            (min.min(t), max.max(t), sum + t, count + 1)
+        });
+    (count > 0).then(|| (min, sum / count as f32, max))
+}
 
-            // Auto button
-            let auto_color = if self.auto_control {
-                egui::Color32::from_rgb(100, 220, 100)
-            } else {
-                egui::Color32::GRAY
-            };
-            if ui.add(egui::Button::new(
-                egui::RichText::new(if self.auto_control { "AUTO" } else { "OFF" })
-                    .size(font_size).color(auto_color)
-            ).min_size(egui::vec2(40.0, 20.0))).clicked() {
-                self.auto_control = !self.auto_control;
-                self.set_status(if self.auto_control { "Auto ON".into() } else { "Auto OFF".into() });
-            }
+/// Mean of the last `window` values at each position
+fn trailing_mean(signal: &[f32], window: usize) -> Vec<f32> {
+    let window = window.max(1);
+    (0..signal.len())
+        .map(|end| {
+            let values = &signal[(end + 1).saturating_sub(window)..=end];
+            values.iter().sum::<f32>() / values.len() as f32
+        })
+        .collect()
+}
 
-            // Status
-            if self.state.cpu_temp > self.target_temp {
-                ui.label(egui::RichText::new(format!("+{:.0}°", self.state.cpu_temp - self.target_temp))
-                    .size(font_size).color(egui::Color32::from_rgb(255, 150, 100)));
-            } else {
-                ui.label(egui::RichText::new("OK").size(font_size)
-                    .color(egui::Color32::from_rgb(100, 220, 100)));
-            }
-        });
+/// Haar wavelet denoising: repeatedly split the signal into pairwise averages
+/// and half-differences, zero the differences smaller than `threshold`, then
+/// rebuild it. The signal is padded with its last value to a power of two.
+fn haar_smooth(signal: &[f32], threshold: f32) -> Vec<f32> {
+    let Some(&last) = signal.last() else {
+        return Vec::new();
+    };
+    let mut coefficients = signal.to_vec();
+    coefficients.resize(signal.len().next_power_of_two(), last);
+    let mut len = coefficients.len();
+    while len > 1 {
+        let half = len / 2;
+        let (averages, details): (Vec<f32>, Vec<f32>) = coefficients[..len]
+            .chunks(2)
+            .map(|pair| ((pair[0] + pair[1]) / 2.0, (pair[0] - pair[1]) / 2.0))
+            .unzip();
+        coefficients[..half].copy_from_slice(&averages);
+        for (slot, detail) in coefficients[half..len].iter_mut().zip(details) {
+            *slot = if detail.abs() < threshold { 0.0 } else { detail };
+        }
+        len = half;
     }
+    while len < coefficients.len() {
+        let rebuilt: Vec<f32> = (0..len)
+            .flat_map(|i| {
+                let (average, detail) = (coefficients[i], coefficients[len + i]);
+                [average + detail, average - detail]
+            })
+            .collect();
+        len *= 2;
+        coefficients[..len].copy_from_slice(&rebuilt);
+    }
+    coefficients.truncate(signal.len());
+    coefficients
+}
 
-    /// Render fan control - adaptive version
-    fn render_fan_control_adaptive(&mut self, ui: &mut egui::Ui, is_wide: bool) {
-        let font_size = if is_wide { 11.0 } else { 9.0 };
-        let fan_active = self.state.fan_boost || self.fan_boost_manual;
-        let fan_color = if fan_active {
-            egui::Color32::from_rgb(255, 150, 100)
-        } else {
-            egui::Color32::GRAY
-        };
+/// How [`TemperatureHistory::forecast_next_n`] projects the CPU temperature
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ForecastModel {
+    /// Continue the current trend unchanged
+    Linear,
+    /// Continue the current trend, fading it out as the temperature settles
+    Exponential,
+    /// Hold the newest sample
+    LastValue,
+}
 
-        ui.horizontal_wrapped(|ui| {
-            if ui.add(egui::Button::new(
-                egui::RichText::new(if fan_active { "BOOST" } else { "NORMAL" })
-                    .size(font_size)
-                    .color(if fan_active { egui::Color32::BLACK } else { fan_color })
-            )
-            .fill(if fan_active { fan_color } else { egui::Color32::TRANSPARENT })
-            .stroke(egui::Stroke::new(1.0, fan_color))
-            .min_size(egui::vec2(60.0, 20.0))).clicked() {
-                self.fan_boost_manual = !self.fan_boost_manual;
-                let _ = set_fan_boost(self.fan_boost_manual);
-                self.set_status(if self.fan_boost_manual { "Fan boost".into() } else { "Fan auto".into() });
+impl ForecastModel {
+    pub fn all() -> &'static [ForecastModel] {
+        &[ForecastModel::Linear, ForecastModel::Exponential, ForecastModel::LastValue]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ForecastModel::Linear => "Linear",
+            ForecastModel::Exponential => "Exponential",
+            ForecastModel::LastValue => "Last value",
+        }
+    }
+}
+
+/// One row of the session comparison table
+#[derive(Debug, Clone, PartialEq)]
+struct ComparisonRow {
+    metric: String,
+    current: f32,
+    comparison: f32,
+    unit: &'static str,
+    lower_is_better: bool,
+}
+
+impl ComparisonRow {
+    /// Whether the current session beats the comparison (`None` when equal)
+    fn current_is_better(&self) -> Option<bool> {
+        if (self.current - self.comparison).abs() < f32::EPSILON {
+            None
+        } else {
+            Some((self.current < self.comparison) == self.lower_is_better)
+        }
+    }
+}
+
+/// What the history panel shows
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum HistoryView {
+    /// Temperatures over time
+    Timeline,
+    /// How often each CPU temperature occurred
+    Distribution,
+    /// How often each CPU temperature occurred at each hour of the day
+    TimeOfDay,
+}
+
+/// Actions reachable from the keyboard
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ShortcutAction {
+    SetMode(Mode),
+    ToggleAutoControl,
+    ToggleFanBoost,
+    ToggleZoneBands,
+    ExportCsv,
+    ShowKeyboardMap,
+}
+
+impl ShortcutAction {
+    fn description(&self) -> String {
+        match self {
+            ShortcutAction::SetMode(mode) => format!("Switch to {} mode", mode.label()),
+            ShortcutAction::ToggleAutoControl => "Turn automatic control on/off".into(),
+            ShortcutAction::ToggleFanBoost => "Turn fan boost on/off".into(),
+            ShortcutAction::ToggleZoneBands => "Show/hide zone bands in the graph".into(),
+            ShortcutAction::ExportCsv => format!("Export history to {}", CSV_EXPORT_PATH),
+            ShortcutAction::ShowKeyboardMap => "Show this list".into(),
+        }
+    }
+}
+
+/// Keyboard shortcuts: key, how it is shown to the user, action.
+/// The handler and the keyboard map are both driven by this table.
+const SHORTCUTS: &[(egui::Key, &str, ShortcutAction)] = &[
+    (egui::Key::Num1, "1", ShortcutAction::SetMode(Mode::Performance)),
+    (egui::Key::Num2, "2", ShortcutAction::SetMode(Mode::Comfort)),
+    (egui::Key::Num3, "3", ShortcutAction::SetMode(Mode::Balanced)),
+    (egui::Key::Num4, "4", ShortcutAction::SetMode(Mode::Quiet)),
+    (egui::Key::Num5, "5", ShortcutAction::SetMode(Mode::Auto)),
+    (egui::Key::A, "A", ShortcutAction::ToggleAutoControl),
+    (egui::Key::F, "F", ShortcutAction::ToggleFanBoost),
+    (egui::Key::B, "B", ShortcutAction::ToggleZoneBands),
+    (egui::Key::E, "E", ShortcutAction::ExportCsv),
+    (egui::Key::Questionmark, "?", ShortcutAction::ShowKeyboardMap),
+    (egui::Key::F1, "F1", ShortcutAction::ShowKeyboardMap),
+];
+
+/// Kind of an event passed to [`ThermalApp::record_event`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum AlertKind {
+    ModeChange,
+    ZoneTransition,
+    /// Zone transition into Critical
+    CriticalZone,
+    ThresholdCrossing,
+    FanBoost,
+}
+
+impl AlertKind {
+    /// Event type in webhook payloads
+    fn name(&self) -> &'static str {
+        match self {
+            AlertKind::ModeChange => "mode_change",
+            AlertKind::ZoneTransition | AlertKind::CriticalZone => "zone_transition",
+            AlertKind::ThresholdCrossing => "threshold_breach",
+            AlertKind::FanBoost => "fan_boost",
+        }
+    }
+
+    /// Minimum time between desktop notifications of this kind; `None` for
+    /// kinds that are never notified
+    fn notification_cooldown(&self, config: &Config) -> Option<Duration> {
+        let secs = match self {
+            AlertKind::ThresholdCrossing => config.notify_cooldown_high_temp_secs,
+            AlertKind::CriticalZone => config.notify_cooldown_critical_secs,
+            AlertKind::ModeChange => config.notify_cooldown_mode_change_secs,
+            AlertKind::ZoneTransition | AlertKind::FanBoost => return None,
+        };
+        Some(Duration::from_secs(secs))
+    }
+
+    /// Color of the annotation on the history plot; ordinary zone
+    /// transitions happen too often to be drawn
+    fn annotation_color(&self) -> Option<egui::Color32> {
+        match self {
+            AlertKind::ModeChange => Some(egui::Color32::from_rgb(255, 220, 0)),
+            AlertKind::ThresholdCrossing => Some(egui::Color32::from_rgb(255, 150, 0)),
+            AlertKind::CriticalZone => Some(egui::Color32::from_rgb(255, 60, 60)),
+            AlertKind::FanBoost => Some(egui::Color32::from_rgb(0, 220, 220)),
+            AlertKind::ZoneTransition => None,
+        }
+    }
+}
+
+/// Event kept with its kind so it can be placed on the history plot
+#[derive(Debug, Clone)]
+struct AlertEvent {
+    time: SystemTime,
+    kind: AlertKind,
+    message: String,
+}
+
+/// One line of the event log
+#[derive(Debug, Clone, PartialEq)]
+struct LogEntry {
+    time: SystemTime,
+    message: String,
+}
+
+/// What to do with an auto-control action under rate limiting
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LogDecision {
+    /// First action in the window: log it as is
+    Log,
+    /// Log "Mode oscillated N times" instead of the action
+    Summary(u32),
+    Suppress,
+}
+
+/// Logs the first action of a window, then only periodic summaries
+#[derive(Debug, Default)]
+struct LogRateLimiter {
+    window_start: Option<Instant>,
+    count: u32,
+    since_summary: u32,
+}
+
+impl LogRateLimiter {
+    fn check(&mut self, now: Instant) -> LogDecision {
+        match self.window_start {
+            Some(start) if now.duration_since(start) < AUTO_LOG_WINDOW => {
+                self.count += 1;
+                self.since_summary += 1;
+                if self.since_summary == AUTO_LOG_SUMMARY_EVERY {
+                    self.since_summary = 0;
+                    LogDecision::Summary(self.count)
+                } else {
+                    LogDecision::Suppress
+                }
+            }
+            _ => {
+                self.window_start = Some(now);
+                self.count = 0;
+                self.since_summary = 0;
+                LogDecision::Log
+            }
+        }
+    }
+}
+
+/// Fixed-capacity buffer of readings from a single sensor, oldest first
+#[derive(Debug, Clone)]
+pub struct SensorHistory<T> {
+    samples: VecDeque<T>,
+    capacity: usize,
+}
+
+impl<T> SensorHistory<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self { samples: VecDeque::with_capacity(capacity), capacity }
+    }
+
+    pub fn push(&mut self, value: T) {
+        if self.samples.len() >= self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(value);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.samples.iter()
+    }
+}
+
+impl<T> Default for SensorHistory<T> {
+    fn default() -> Self {
+        Self::new(HISTORY_CAPACITY)
+    }
+}
+
+/// User-defined metric evaluated from sensor readings every update
+#[derive(Debug, Clone)]
+pub struct CustomMetric {
+    pub name: String,
+    /// Expression as typed by the user
+    pub source: String,
+    expr: Expr,
+    /// Value at each update; NaN when it could not be computed
+    pub history: SensorHistory<f32>,
+}
+
+impl CustomMetric {
+    /// Parse `source`, accepting only names from SENSOR_NAMES as variables
+    pub fn new(name: String, source: String) -> Result<Self, ThermalError> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(ThermalError::InvalidData(format!(
+                "metric name '{}' may only use letters, digits and _",
+                name
+            )));
+        }
+        let expr = Expr::parse(&source)?;
+        if let Some(unknown) = expr.variables().into_iter().find(|var| !SENSOR_NAMES.contains(var)) {
+            return Err(ThermalError::InvalidData(format!(
+                "unknown sensor '{}' (available: {})",
+                unknown,
+                SENSOR_NAMES.join(", ")
+            )));
+        }
+        Ok(Self { name, source, expr, history: SensorHistory::default() })
+    }
+
+    /// Evaluate against `state` and record the result
+    pub fn update(&mut self, state: &ThermalState) {
+        let value = self.expr.eval(&|name| state.sensor(name));
+        self.history.push(value.map_or(f32::NAN, |v| v as f32));
+    }
+
+    /// Newest value, if it could be computed
+    pub fn latest(&self) -> Option<f32> {
+        self.history.iter().last().copied().filter(|v| v.is_finite())
+    }
+}
+
+/// Readings of one CPU core, for the die map and its optional plot line
+#[derive(Debug, Clone, Default)]
+struct CoreHistory {
+    temps: SensorHistory<f32>,
+    /// Hottest reading this session
+    max: f32,
+    /// Shown in the history plot
+    plotted: bool,
+}
+
+/// Temperature history buffer
+#[derive(Debug, Clone)]
+pub struct TemperatureHistory {
+    cpu_temps: RingBuffer<f32>,
+    kbd_temps: RingBuffer<f32>,
+    /// Wall-clock time of each sample
+    timestamps: VecDeque<SystemTime>,
+    /// Package power in watts, pushed separately as it is not sampled on the first poll
+    #[cfg(feature = "intel-rapl")]
+    package_watts: VecDeque<f32>,
+    /// Memory in use (%), pushed separately so restored snapshots stay valid
+    mem_used: VecDeque<f32>,
+    /// Constant reference lines (e.g. TJmax), filled to capacity; not serialized
+    static_channels: Vec<(&'static str, RingBuffer<f32>)>,
+    capacity: usize,
+}
+
+impl Default for TemperatureHistory {
+    fn default() -> Self {
+        Self::new(HISTORY_CAPACITY)
+    }
+}
+
+impl TemperatureHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            cpu_temps: RingBuffer::new(capacity),
+            kbd_temps: RingBuffer::new(capacity),
+            timestamps: VecDeque::with_capacity(capacity),
+            #[cfg(feature = "intel-rapl")]
+            package_watts: VecDeque::with_capacity(capacity),
+            mem_used: VecDeque::with_capacity(capacity),
+            static_channels: Vec::new(),
+            capacity,
+        }
+    }
+
+    /// Add a reference channel holding `constant` for every slot, replacing
+    /// one of the same name
+    pub fn channel_add_static(&mut self, name: &'static str, constant: f32) {
+        let mut samples = RingBuffer::new(self.capacity);
+        for _ in 0..self.capacity {
+            samples.push(constant);
+        }
+        self.channel_remove_static(name);
+        self.static_channels.push((name, samples));
+    }
+
+    pub fn channel_remove_static(&mut self, name: &str) {
+        self.static_channels.retain(|(channel, _)| *channel != name);
+    }
+
+    /// Every reference channel as (name, plot points) spanning the full history window
+    pub fn static_channel_points(&self) -> Vec<(&'static str, PlotPoints)> {
+        let interval = UPDATE_INTERVAL_SECS as f64;
+        self.static_channels
+            .iter()
+            .map(|(name, samples)| {
+                let points = samples.iter().enumerate().map(|(i, &v)| [i as f64 * interval, v as f64]).collect();
+                (*name, PlotPoints::new(points))
+            })
+            .collect()
+    }
+
+    /// Resample a (timestamp, CPU °C) series into history slots `interval` apart,
+    /// keeping the last reading per slot and the newest `capacity` slots.
+    /// The newest reading is placed at the current wall-clock time.
+    pub fn from_time_series(
+        series: &[(Duration, f32)],
+        interval: Duration,
+        capacity: usize,
+        ambient_temp: f32,
+    ) -> Self {
+        let mut history = Self::new(capacity);
+        let (Some(&(start, _)), Some(&(end, _))) = (series.first(), series.last()) else {
+            return history;
+        };
+        let now = SystemTime::now();
+        let wall_time = |time: Duration| now - end.saturating_sub(time);
+
+        let mut current_slot = None;
+        let mut slot_reading = (start, 0.0);
+        for &(time, temp) in series {
+            let slot = (time.saturating_sub(start).as_secs_f64() / interval.as_secs_f64()) as u64;
+            if current_slot.is_some_and(|s| s != slot) {
+                let (slot_time, slot_temp) = slot_reading;
+                history.push(slot_temp, calculate_keyboard_temp(slot_temp, ambient_temp), wall_time(slot_time));
+            }
+            current_slot = Some(slot);
+            slot_reading = (time, temp);
+        }
+        let (slot_time, slot_temp) = slot_reading;
+        history.push(slot_temp, calculate_keyboard_temp(slot_temp, ambient_temp), wall_time(slot_time));
+        history
+    }
+
+    pub fn push(&mut self, cpu: f32, kbd: f32, time: SystemTime) {
+        if self.timestamps.len() >= self.capacity {
+            self.timestamps.pop_front();
+        }
+        self.cpu_temps.push(cpu);
+        self.kbd_temps.push(kbd);
+        self.timestamps.push_back(time);
+    }
+
+    /// One bar per perf_pct sample, aligned with the newest temperature samples
+    /// and colored by the CPU zone at that time
+    pub fn perf_bars(&self, perf_pct: &SensorHistory<f32>) -> Vec<(f64, f64, ThermalZone)> {
+        let skip = self.cpu_temps.len().saturating_sub(perf_pct.len());
+        self.elapsed_secs()
+            .zip(&self.cpu_temps)
+            .skip(skip)
+            .zip(perf_pct.iter())
+            .map(|((x, &cpu), &pct)| (x, pct as f64, ThermalZone::from_cpu_temp(cpu)))
+            .collect()
+    }
+
+    /// Copy with every NaN gap sentinel replaced by the preceding valid sample of
+    /// its channel, or by `fill_value` when no valid sample precedes it
+    pub fn fill_gaps(&self, fill_value: f32) -> TemperatureHistory {
+        let mut filled = self.clone();
+        for samples in [&mut filled.cpu_temps, &mut filled.kbd_temps] {
+            let mut last_valid = fill_value;
+            for sample in samples.iter_mut() {
+                if sample.is_nan() {
+                    *sample = last_valid;
+                } else {
+                    last_valid = *sample;
+                }
+            }
+        }
+        filled
+    }
+
+    /// Number of samples that are NaN gap sentinels
+    pub fn count_gaps(&self) -> usize {
+        self.cpu_temps
+            .iter()
+            .zip(&self.kbd_temps)
+            .filter(|(cpu, kbd)| cpu.is_nan() || kbd.is_nan())
+            .count()
+    }
+
+    /// Record a memory usage reading
+    pub fn push_mem(&mut self, pct: f32) {
+        if self.mem_used.len() >= self.capacity {
+            self.mem_used.pop_front();
+        }
+        self.mem_used.push_back(pct);
+    }
+
+    /// Points of a separately sampled series, aligned with the newest temperature
+    /// samples; NaN values are left out
+    pub fn series_points(&self, series: &SensorHistory<f32>) -> PlotPoints {
+        let skip = self.cpu_temps.len().saturating_sub(series.len());
+        PlotPoints::new(
+            self.elapsed_secs()
+                .skip(skip)
+                .zip(series.iter())
+                .filter(|(_, value)| !value.is_nan())
+                .map(|(x, &value)| [x, value as f64])
+                .collect(),
+        )
+    }
+
+    /// Memory usage points, aligned with the newest temperature samples
+    pub fn mem_points(&self) -> PlotPoints {
+        let skip = self.cpu_temps.len().saturating_sub(self.mem_used.len());
+        PlotPoints::new(
+            self.elapsed_secs()
+                .skip(skip)
+                .zip(&self.mem_used)
+                .map(|(x, &pct)| [x, pct as f64])
+                .collect(),
+        )
+    }
+
+    /// Record a package power reading
+    #[cfg(feature = "intel-rapl")]
+    pub fn push_power(&mut self, watts: f32) {
+        if self.package_watts.len() >= self.capacity {
+            self.package_watts.pop_front();
+        }
+        self.package_watts.push_back(watts);
+    }
+
+    /// Average package power over the history window
+    #[cfg(feature = "intel-rapl")]
+    pub fn avg_power(&self) -> Option<f32> {
+        if self.package_watts.is_empty() {
+            return None;
+        }
+        Some(self.package_watts.iter().sum::<f32>() / self.package_watts.len() as f32)
+    }
+
+    /// Seconds elapsed since the oldest sample, one per sample
+    fn elapsed_secs(&self) -> impl Iterator<Item = f64> + '_ {
+        let oldest = self.timestamps.front().copied();
+        self.timestamps.iter().map(move |&time| {
+            oldest
+                .and_then(|oldest| time.duration_since(oldest).ok())
+                .map_or(0.0, |d| d.as_secs_f64())
+        })
+    }
+
+    /// Runs of valid (x, °C) points, split at NaN gap sentinels
+    fn segments(&self, samples: &RingBuffer<f32>) -> Vec<Vec<[f64; 2]>> {
+        let mut segments = vec![Vec::new()];
+        for (x, &t) in self.elapsed_secs().zip(samples) {
+            if t.is_nan() {
+                segments.push(Vec::new());
+            } else if let Some(segment) = segments.last_mut() {
+                segment.push([x, t as f64]);
+            }
+        }
+        segments.retain(|segment| !segment.is_empty());
+        segments
+    }
+
+    /// CPU points as separate runs, so the plot shows a break at each gap
+    pub fn cpu_segments(&self) -> Vec<PlotPoints> {
+        self.segments(&self.cpu_temps).into_iter().map(PlotPoints::new).collect()
+    }
+
+    /// Keyboard points as separate runs, so the plot shows a break at each gap
+    pub fn kbd_segments(&self) -> Vec<PlotPoints> {
+        self.segments(&self.kbd_temps).into_iter().map(PlotPoints::new).collect()
+    }
+
+    /// Put `other`'s samples (older data, e.g. an imported CSV) before ours;
+    /// those not older than our oldest sample are skipped. A NaN sentinel
+    /// marks a gap of more than two update intervals between them; the oldest
+    /// samples are dropped beyond capacity.
+    pub fn append(&mut self, other: &TemperatureHistory) {
+        let oldest = self.timestamps.front().copied();
+        let mut merged = Self::new(self.capacity);
+        for ((&cpu, &kbd), &time) in other.cpu_temps.iter().zip(&other.kbd_temps).zip(&other.timestamps) {
+            if oldest.is_none_or(|oldest| time < oldest) {
+                merged.push(cpu, kbd, time);
+            }
+        }
+        let interval = Duration::from_secs_f32(UPDATE_INTERVAL_SECS);
+        if let (Some(&last), Some(first)) = (merged.timestamps.back(), oldest) {
+            if first.duration_since(last).is_ok_and(|gap| gap > interval * 2) {
+                merged.push(f32::NAN, f32::NAN, last + interval);
+            }
+        }
+        for ((&cpu, &kbd), &time) in self.cpu_temps.iter().zip(&self.kbd_temps).zip(&self.timestamps) {
+            merged.push(cpu, kbd, time);
+        }
+        self.cpu_temps = merged.cpu_temps;
+        self.kbd_temps = merged.kbd_temps;
+        self.timestamps = merged.timestamps;
+    }
+
+    /// Parse CSV written by [`TemperatureHistory::as_csv_bytes`], keeping the
+    /// newest `capacity` samples. `NaN` cells become gap sentinels.
+    pub fn from_csv(data: &str, capacity: usize) -> io::Result<Self> {
+        let invalid = |line: usize| {
+            io::Error::new(ErrorKind::InvalidData, format!("CSV line {}: expected timestamp,cpu_temp,kbd_temp", line))
+        };
+        let mut history = Self::new(capacity);
+        for (index, line) in data.lines().enumerate() {
+            if line.trim().is_empty() || (index == 0 && line.starts_with("timestamp")) {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let [time, cpu, kbd] = fields[..] else {
+                return Err(invalid(index + 1));
+            };
+            let time = parse_iso8601(time).ok_or_else(|| invalid(index + 1))?;
+            let cpu: f32 = cpu.parse().map_err(|_| invalid(index + 1))?;
+            let kbd: f32 = kbd.parse().map_err(|_| invalid(index + 1))?;
+            history.push(cpu, kbd, time);
+        }
+        Ok(history)
+    }
+
+    /// Self-contained gnuplot script with the samples inline that renders
+    /// `thermal_history.png`; the target line uses its thermal zone color
+    pub fn gnuplot_script(&self, target_temp: f32) -> String {
+        let (r, g, b) = ThermalZone::from_cpu_temp(target_temp).color_rgb();
+        let mut script = String::from(
+            "# Temperature history exported by Thermal Monitor\n\
+             # Run with: gnuplot <this file>\n\
+             set terminal pngcairo size 1200,600 enhanced\n\
+             set output \"thermal_history.png\"\n\
+             set xlabel \"Time (s)\"\n\
+             set ylabel \"Temperature (°C)\"\n\
+             set grid\n\
+             set key top right\n\
+             $history << EOD\n",
+        );
+        for ((x, cpu), kbd) in self.elapsed_secs().zip(&self.cpu_temps).zip(&self.kbd_temps) {
+            script.push_str(&format!("{:.1} {:.1} {:.1}\n", x, cpu, kbd));
+        }
+        script.push_str("EOD\n");
+        script.push_str(&format!(
+            "plot $history using 1:2 with lines lw 2 lc rgb \"#ff6464\" title \"CPU\", \\\n     \
+             $history using 1:3 with lines lw 2 lc rgb \"#64c8ff\" title \"Kbd\", \\\n     \
+             {:.1} with lines dt 2 lw 1 lc rgb \"#{:02x}{:02x}{:02x}\" title \"Target\"\n",
+            target_temp, r, g, b
+        ));
+        script
+    }
+
+    /// Interactive Plotly page with the samples inline: CPU and keyboard
+    /// traces, the target line and the zone bands as background shapes.
+    /// Only Plotly.js itself is loaded from its CDN.
+    pub fn plotly_html(&self, target_temp: f32) -> String {
+        let json_array = |values: Vec<String>| format!("[{}]", values.join(","));
+        // JSON has no NaN; null leaves a gap in the trace
+        let json_temps = |samples: &RingBuffer<f32>| {
+            json_array(samples.iter().map(|t| if t.is_finite() { format!("{:.1}", t) } else { "null".into() }).collect())
+        };
+        let times = json_array(self.timestamps.iter().map(|&t| json_string(&format_iso8601(t))).collect());
+        let (first, last) = match (self.timestamps.front(), self.timestamps.back()) {
+            (Some(&first), Some(&last)) => (json_string(&format_iso8601(first)), json_string(&format_iso8601(last))),
+            _ => ("null".into(), "null".into()),
+        };
+        let shapes = json_array(
+            ThermalZone::all()
+                .iter()
+                .map(|zone| {
+                    let (low, high) = zone.temp_range();
+                    let (r, g, b) = zone.color_rgb();
+                    format!(
+                        "{{\"type\":\"rect\",\"xref\":\"paper\",\"x0\":0,\"x1\":1,\"y0\":{:.1},\"y1\":{:.1},\
+                         \"fillcolor\":\"rgba({},{},{},0.12)\",\"line\":{{\"width\":0}},\"layer\":\"below\"}}",
+                        low.max(0.0),
+                        high.min(110.0),
+                        r,
+                        g,
+                        b
+                    )
+                })
+                .collect(),
+        );
+        format!(
+            "<!DOCTYPE html>\n\
+             <html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Thermal Monitor history</title>\n\
+             <script src=\"{cdn}\"></script>\n\
+             </head>\n<body>\n\
+             <div id=\"history\" style=\"width:100%;height:90vh\"></div>\n\
+             <script>\n\
+             const time = {times};\n\
+             const traces = [\n  \
+             {{\"x\":time,\"y\":{cpu},\"name\":\"CPU\",\"mode\":\"lines\",\"line\":{{\"color\":\"#ff6464\",\"width\":2}}}},\n  \
+             {{\"x\":time,\"y\":{kbd},\"name\":\"Kbd\",\"mode\":\"lines\",\"line\":{{\"color\":\"#64c8ff\",\"width\":2}}}},\n  \
+             {{\"x\":[{first},{last}],\"y\":[{target:.1},{target:.1}],\"name\":\"Target\",\"mode\":\"lines\",\
+             \"line\":{{\"color\":\"#ffc864\",\"width\":1,\"dash\":\"dash\"}}}}\n\
+             ];\n\
+             const layout = {{\"xaxis\":{{\"title\":\"Time (UTC)\"}},\"yaxis\":{{\"title\":\"Temperature (°C)\"}},\
+             \"hovermode\":\"x unified\",\"shapes\":{shapes}}};\n\
+             Plotly.newPlot(\"history\", traces, layout);\n\
+             </script>\n\
+             </body>\n</html>\n",
+            cdn = PLOTLY_CDN_URL,
+            times = times,
+            cpu = json_temps(&self.cpu_temps),
+            kbd = json_temps(&self.kbd_temps),
+            first = first,
+            last = last,
+            target = target_temp,
+            shapes = shapes,
+        )
+    }
+
+    /// Write [`TemperatureHistory::plotly_html`] to `path`
+    pub fn export_plotly_html(&self, path: &Path, target_temp: f32) -> io::Result<()> {
+        fs::write(path, self.plotly_html(target_temp))
+    }
+
+    /// Write [`TemperatureHistory::gnuplot_script`] to `path`
+    pub fn to_gnuplot_script(&self, path: &Path, target_temp: f32) -> io::Result<()> {
+        fs::write(path, self.gnuplot_script(target_temp))
+    }
+
+    /// CPU samples packed into 8-lane SIMD vectors, zero-padded to a multiple of 8
+    #[cfg(feature = "simd")]
+    pub fn as_simd_array(&self) -> Vec<std::simd::f32x8> {
+        let samples: Vec<f32> = self.cpu_temps.iter().copied().collect();
+        samples.chunks(8).map(std::simd::f32x8::load_or_default).collect()
+    }
+
+    /// Samples as a C-order `samples × 2` array with columns `[cpu, kbd]`. Gap
+    /// sentinels are left out, so row `i` is point `i` of
+    /// [`TemperatureHistory::cpu_points`].
+    ///
+    /// ```ignore
+    /// let temps = history.as_ndarray();
+    /// let mean_cpu = temps.column(0).mean().unwrap_or(0.0);
+    /// let hottest_kbd = temps.column(1).fold(f32::MIN, |a, &b| a.max(b));
+    /// ```
+    #[cfg(feature = "ndarray")]
+    pub fn as_ndarray(&self) -> ndarray::Array2<f32> {
+        let data: Vec<f32> = self
+            .cpu_temps
+            .iter()
+            .zip(&self.kbd_temps)
+            .filter(|(cpu, _)| !cpu.is_nan())
+            .flat_map(|(&cpu, &kbd)| [cpu, kbd])
+            .collect();
+        ndarray::Array2::from_shape_vec((data.len() / 2, 2), data)
+            .expect("two values per row")
+    }
+
+    /// [`TemperatureHistory::as_ndarray`] in the NumPy `.npy` format (version
+    /// 1.0, little-endian f32), readable with `numpy.load`
+    #[cfg(feature = "ndarray")]
+    pub fn as_npy_bytes(&self) -> Vec<u8> {
+        let temps = self.as_ndarray();
+        let mut header = format!(
+            "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+            temps.nrows(),
+            temps.ncols()
+        );
+        // Magic, version and length take 10 bytes; the data starts 64-byte aligned
+        let end = (10 + header.len() + 1).div_ceil(64) * 64;
+        header.push_str(&" ".repeat(end - 10 - header.len() - 1));
+        header.push('\n');
+
+        let mut npy = b"\x93NUMPY\x01\x00".to_vec();
+        npy.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        npy.extend_from_slice(header.as_bytes());
+        for value in temps.iter() {
+            npy.extend_from_slice(&value.to_le_bytes());
+        }
+        npy
+    }
+
+    /// Seconds between the oldest and newest sample
+    pub fn span_secs(&self) -> f64 {
+        self.elapsed_secs().last().unwrap_or(0.0)
+    }
+
+    /// Seconds of samples a full history holds at the default update rate
+    pub fn window_secs(&self) -> f64 {
+        self.capacity as f64 * UPDATE_INTERVAL_SECS as f64
+    }
+
+    /// Whether every timestamp is at or after its predecessor. Imported data
+    /// failing this check plots and resamples incorrectly.
+    pub fn validate_monotone_timestamps(&self) -> bool {
+        self.timestamps.iter().zip(self.timestamps.iter().skip(1)).all(|(prev, next)| next >= prev)
+    }
+
+    /// Wall-clock time of the oldest sample
+    pub fn oldest_time(&self) -> Option<SystemTime> {
+        self.timestamps.front().copied()
+    }
+
+    /// Wall-clock time of the newest sample
+    pub fn newest_time(&self) -> Option<SystemTime> {
+        self.timestamps.back().copied()
+    }
+
+    /// Index of the first sample taken at or after `split`; 0 once `split`
+    /// has scrolled out of the window
+    fn split_index(&self, split: SystemTime) -> usize {
+        self.timestamps.iter().take_while(|&&time| time < split).count()
+    }
+
+    /// Plot x of an A/B split at `split`, i.e. of the first sample of period B
+    pub fn split_x(&self, split: SystemTime) -> f64 {
+        self.elapsed_secs().nth(self.split_index(split)).unwrap_or_else(|| self.span_secs())
+    }
+
+    /// Runs of `channel` in periods A (before `split`) and B (from `split` on).
+    /// The first B sample also ends A, so the line stays joined at the split.
+    pub fn split_segments(&self, channel: &str, split: SystemTime) -> (Vec<PlotPoints>, Vec<PlotPoints>) {
+        let Some((_, samples)) = self.channels().into_iter().find(|(name, _)| *name == channel) else {
+            return (Vec::new(), Vec::new());
+        };
+        let index = self.split_index(split);
+        let extend = |segments: &mut Vec<Vec<[f64; 2]>>, x: f64, t: f32| {
+            if t.is_nan() {
+                segments.push(Vec::new());
+            } else if let Some(segment) = segments.last_mut() {
+                segment.push([x, t as f64]);
+            }
+        };
+        let (mut before, mut after) = (vec![Vec::new()], vec![Vec::new()]);
+        for (i, (x, &t)) in self.elapsed_secs().zip(samples).enumerate() {
+            if i <= index {
+                extend(&mut before, x, t);
+            }
+            if i >= index {
+                extend(&mut after, x, t);
+            }
+        }
+        let finish = |segments: Vec<Vec<[f64; 2]>>| {
+            segments.into_iter().filter(|segment| !segment.is_empty()).map(PlotPoints::new).collect()
+        };
+        (finish(before), finish(after))
+    }
+
+    /// Min, mean and max of `channel` in periods A and B of a split at `split`;
+    /// `None` for a period without valid samples
+    pub fn split_stats(&self, channel: &str, split: SystemTime) -> [Option<(f32, f32, f32)>; 2] {
+        let Some((_, samples)) = self.channels().into_iter().find(|(name, _)| *name == channel) else {
+            return [None, None];
+        };
+        let index = self.split_index(split);
+        [min_avg_max(samples.iter().take(index)), min_avg_max(samples.iter().skip(index))]
+    }
+
+    /// Plot x of the first sample taken at or after `time`; `None` when
+    /// `time` is older than the window or newer than the last sample
+    pub fn sample_x_at(&self, time: SystemTime) -> Option<f64> {
+        if self.oldest_time().is_none_or(|oldest| time < oldest) {
+            return None;
+        }
+        let index = self.timestamps.iter().position(|&sample| sample >= time)?;
+        self.elapsed_secs().nth(index)
+    }
+
+    /// CSV bytes with an ISO 8601 timestamp per sample
+    pub fn as_csv_bytes(&self) -> Vec<u8> {
+        let mut csv = String::from("timestamp,cpu_temp,kbd_temp\n");
+        for ((time, cpu), kbd) in self.timestamps.iter().zip(&self.cpu_temps).zip(&self.kbd_temps) {
+            csv.push_str(&format!("{},{:.1},{:.1}\n", format_iso8601(*time), cpu, kbd));
+        }
+        csv.into_bytes()
+    }
+
+    /// Every sample as InfluxDB line protocol, one point per line
+    pub fn to_influxdb_line_protocol(&self, measurement: &str) -> String {
+        let mut lines = String::new();
+        for ((time, &cpu), &kbd) in self.timestamps.iter().zip(&self.cpu_temps).zip(&self.kbd_temps) {
+            lines.push_str(&influxdb_line(measurement, cpu, kbd, *time));
+            lines.push('\n');
+        }
+        lines
+    }
+
+    /// Write [`TemperatureHistory::to_influxdb_line_protocol`] to `path`
+    pub fn export_influxdb(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_influxdb_line_protocol(INFLUXDB_MEASUREMENT))
+    }
+
+    /// Write [`TemperatureHistory::as_csv_bytes`] to `writer`
+    pub fn export_csv(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&self.as_csv_bytes())
+    }
+
+    pub fn len(&self) -> usize {
+        self.cpu_temps.len()
+    }
+
+    /// Named sample channels stored in the history
+    fn channels(&self) -> [(&'static str, &RingBuffer<f32>); 2] {
+        [("cpu", &self.cpu_temps), ("kbd", &self.kbd_temps)]
+    }
+
+    /// Min and max of every channel, computed in a single pass over each.
+    /// Empty channels and non-finite samples are skipped.
+    pub fn channel_min_max_all(&self) -> HashMap<&'static str, (f32, f32)> {
+        let mut result = HashMap::new();
+        for (name, samples) in self.channels() {
+            let bounds = samples
+                .iter()
+                .filter(|t| t.is_finite())
+                .fold(None, |acc: Option<(f32, f32)>, &t| match acc {
+                    Some((min, max)) => Some((min.min(t), max.max(t))),
+                    None => Some((t, t)),
+                });
+            if let Some(bounds) = bounds {
+                result.insert(name, bounds);
             }
+        }
+        result
+    }
+
+    /// Average, 95th/99th percentile (nearest rank) and maximum of a channel
+    pub fn channel_stats(&self, channel: &str) -> Option<ChannelStats> {
+        let (_, samples) = self.channels().into_iter().find(|(name, _)| *name == channel)?;
+        let mut sorted: Vec<f32> = samples.iter().copied().filter(|t| t.is_finite()).collect();
+        if sorted.is_empty() {
+            return None;
+        }
+        sorted.sort_by(f32::total_cmp);
+
+        let percentile = |p: f32| {
+            let rank = (p / 100.0 * sorted.len() as f32).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+        Some(ChannelStats {
+            avg: sorted.iter().sum::<f32>() / sorted.len() as f32,
+            p95: percentile(95.0),
+            p99: percentile(99.0),
+            max: sorted[sorted.len() - 1],
+        })
+    }
+
+    /// Rolling `p`-th percentile (nearest rank) of the CPU temperature over the
+    /// last `window` samples, one value per sample. Gap sentinels are ignored;
+    /// a window holding only gaps yields NaN.
+    pub fn percentile_series(&self, p: f32, window: usize) -> Vec<f32> {
+        let samples: Vec<f32> = self.cpu_temps.iter().copied().collect();
+        (0..samples.len())
+            .map(|end| {
+                let start = (end + 1).saturating_sub(window.max(1));
+                let mut sorted: Vec<f32> = samples[start..=end].iter().copied().filter(|t| !t.is_nan()).collect();
+                if sorted.is_empty() {
+                    return f32::NAN;
+                }
+                sorted.sort_by(f32::total_cmp);
+                let rank = (p / 100.0 * sorted.len() as f32).ceil() as usize;
+                sorted[rank.clamp(1, sorted.len()) - 1]
+            })
+            .collect()
+    }
+
+    /// Highest CPU temperature in a window of `window` samples around each
+    /// sample, one value per sample. The window is centered where possible and
+    /// kept inside the buffer at the ends, so a window as large as the buffer
+    /// gives the global maximum everywhere. Gap sentinels are ignored; a window
+    /// holding only gaps yields NaN.
+    pub fn moving_max(&self, window: usize) -> Vec<f32> {
+        let samples: Vec<f32> = self.cpu_temps.iter().copied().collect();
+        let window = window.clamp(1, samples.len().max(1));
+        // Indices of decreasing samples; the front is the maximum of the window
+        let mut candidates: VecDeque<usize> = VecDeque::new();
+        let mut end = 0;
+        (0..samples.len())
+            .map(|i| {
+                let start = i.saturating_sub(window / 2).min(samples.len() - window);
+                while end < start + window {
+                    if !samples[end].is_nan() {
+                        while candidates.back().is_some_and(|&j| samples[j] <= samples[end]) {
+                            candidates.pop_back();
+                        }
+                        candidates.push_back(end);
+                    }
+                    end += 1;
+                }
+                while candidates.front().is_some_and(|&j| j < start) {
+                    candidates.pop_front();
+                }
+                candidates.front().map_or(f32::NAN, |&j| samples[j])
+            })
+            .collect()
+    }
+
+    /// Area between the CPU line and its [`TemperatureHistory::moving_max`],
+    /// as one quad per pair of neighbouring samples (egui_plot only fills
+    /// convex polygons). Flat stretches and gaps produce no quad.
+    pub fn envelope_polygons(&self, window: usize) -> Vec<PlotPoints> {
+        let envelope = self.moving_max(window);
+        let xs: Vec<f64> = self.elapsed_secs().collect();
+        let temps: Vec<f32> = self.cpu_temps.iter().copied().collect();
+        (1..temps.len())
+            .filter(|&i| [temps[i - 1], temps[i], envelope[i - 1], envelope[i]].iter().all(|t| t.is_finite()))
+            .filter(|&i| envelope[i - 1] > temps[i - 1] || envelope[i] > temps[i])
+            .map(|i| {
+                PlotPoints::new(vec![
+                    [xs[i - 1], temps[i - 1] as f64],
+                    [xs[i], temps[i] as f64],
+                    [xs[i], envelope[i] as f64],
+                    [xs[i - 1], envelope[i - 1] as f64],
+                ])
+            })
+            .collect()
+    }
+
+    /// Copy with `smooth` applied to each run of CPU and keyboard samples
+    /// between gap sentinels
+    fn smoothed_with(&self, smooth: impl Fn(&[f32]) -> Vec<f32>) -> TemperatureHistory {
+        let smooth_channel = |samples: &mut RingBuffer<f32>| {
+            let values: Vec<f32> = samples.iter().copied().collect();
+            let smoothed: Vec<f32> = values
+                .split(|t| t.is_nan())
+                .flat_map(|run| smooth(run).into_iter().chain([f32::NAN]))
+                .collect();
+            for (slot, value) in samples.iter_mut().zip(smoothed) {
+                *slot = value;
+            }
+        };
+        let mut smoothed = self.clone();
+        smooth_channel(&mut smoothed.cpu_temps);
+        smooth_channel(&mut smoothed.kbd_temps);
+        smoothed
+    }
+
+    /// Copy with every temperature replaced by the mean of the last `window` samples
+    pub fn moving_average(&self, window: usize) -> TemperatureHistory {
+        self.smoothed_with(|run| trailing_mean(run, window))
+    }
+
+    /// Copy with the temperatures denoised by a Haar wavelet transform: detail
+    /// coefficients under `threshold` °C are dropped, so small fluctuations
+    /// vanish while sudden rises, which have large coefficients, are kept
+    pub fn wavelet_smoothed(&self, threshold: f32) -> TemperatureHistory {
+        self.smoothed_with(|run| haar_smooth(run, threshold))
+    }
+
+    /// (x, °C) points of [`TemperatureHistory::percentile_series`], gaps left out
+    pub fn percentile_points(&self, p: f32, window: usize) -> PlotPoints {
+        PlotPoints::new(
+            self.elapsed_secs()
+                .zip(self.percentile_series(p, window))
+                .filter(|(_, t)| !t.is_nan())
+                .map(|(x, t)| [x, t as f64])
+                .collect(),
+        )
+    }
+
+    /// Number of CPU samples in each of `bins` equal-width bins between `min`
+    /// and `max`. Bins include their lower edge; samples outside the range are
+    /// counted in the first or last bin, so the counts always add up to `len()`.
+    pub fn heatmap_data(&self, bins: usize, min: f32, max: f32) -> Vec<u32> {
+        let mut counts = vec![0; bins];
+        if bins == 0 {
+            return counts;
+        }
+        for &temp in &self.cpu_temps {
+            counts[Self::temp_bin(temp, bins, min, max)] += 1;
+        }
+        counts
+    }
+
+    /// `[hour of day (UTC)][temperature bin]` counts of the CPU samples, with
+    /// `n_bins` temperature bins over the distribution view's range. Gaps are
+    /// skipped. Only histories spanning several hours, e.g. an imported CSV,
+    /// fill more than one column.
+    pub fn channel_heatmap_data(&self, n_bins: usize) -> Vec<Vec<u32>> {
+        let (min, max) = (*DISTRIBUTION_RANGE.start(), *DISTRIBUTION_RANGE.end());
+        let mut counts = vec![vec![0; n_bins]; 24];
+        if n_bins == 0 {
+            return counts;
+        }
+        for (&time, &temp) in self.timestamps.iter().zip(&self.cpu_temps) {
+            if temp.is_finite() {
+                let hour = (unix_millis(time) / 3_600_000 % 24) as usize;
+                counts[hour][Self::temp_bin(temp, n_bins, min, max)] += 1;
+            }
+        }
+        counts
+    }
+
+    /// Index of the equal-width bin between `min` and `max` holding `temp`,
+    /// clamped to the first and last bin
+    fn temp_bin(temp: f32, bins: usize, min: f32, max: f32) -> usize {
+        let width = (max - min) / bins as f32;
+        (((temp - min) / width).floor().max(0.0) as usize).min(bins - 1)
+    }
+
+    /// Autocorrelation of the CPU temperature at `lag` samples, from -1 to 1.
+    /// A high value at lag k suggests a cycle of k update intervals. Gaps hold
+    /// the preceding sample so lags still span k intervals. NaN when there are
+    /// not more than `lag` samples or the temperature is constant.
+    pub fn compute_autocorrelation(&self, lag: usize) -> f32 {
+        let Some(&first) = self.cpu_temps.iter().find(|t| t.is_finite()) else {
+            return f32::NAN;
+        };
+        let filled = self.fill_gaps(first);
+        let samples: Vec<f64> = filled.cpu_temps.iter().map(|&t| t as f64).collect();
+        if lag >= samples.len() {
+            return f32::NAN;
+        }
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        let variance: f64 = samples.iter().map(|x| (x - mean).powi(2)).sum();
+        if variance == 0.0 {
+            return f32::NAN;
+        }
+        let covariance: f64 = samples.iter().zip(&samples[lag..]).map(|(a, b)| (a - mean) * (b - mean)).sum();
+        (covariance / variance) as f32
+    }
+
+    /// Pearson correlation (-1 to 1) between our CPU temperatures and `other`'s
+    /// keyboard temperatures, aligned from the newest sample. Pass `self` to
+    /// check how closely the keyboard follows the CPU. NaN when either series
+    /// is empty or constant.
+    pub fn correlation(&self, other: &TemperatureHistory) -> f32 {
+        let skip_cpu = self.cpu_temps.len().saturating_sub(other.kbd_temps.len());
+        let skip_kbd = other.kbd_temps.len().saturating_sub(self.cpu_temps.len());
+        let pairs: Vec<(f64, f64)> = self
+            .cpu_temps
+            .iter()
+            .skip(skip_cpu)
+            .zip(other.kbd_temps.iter().skip(skip_kbd))
+            .filter(|(cpu, kbd)| cpu.is_finite() && kbd.is_finite())
+            .map(|(&cpu, &kbd)| (cpu as f64, kbd as f64))
+            .collect();
+        if pairs.is_empty() {
+            return f32::NAN;
+        }
+        let n = pairs.len() as f64;
+        let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+        for (x, y) in &pairs {
+            cov += (x - mean_x) * (y - mean_y);
+            var_x += (x - mean_x).powi(2);
+            var_y += (y - mean_y).powi(2);
+        }
+        if var_x == 0.0 || var_y == 0.0 {
+            return f32::NAN;
+        }
+        (cov / (var_x * var_y).sqrt()) as f32
+    }
+
+    /// CPU temperature trend in °C per second: least-squares slope over the
+    /// last TREND_SAMPLES samples. `None` with fewer than two samples.
+    pub fn rate_of_change(&self) -> Option<f32> {
+        let skip = self.len().saturating_sub(TREND_SAMPLES);
+        let points: Vec<(f64, f64)> = self
+            .elapsed_secs()
+            .zip(&self.cpu_temps)
+            .skip(skip)
+            .map(|(x, &t)| (x, t as f64))
+            .collect();
+        if points.len() < 2 {
+            return None;
+        }
+
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let covariance: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+        let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+        if variance == 0.0 {
+            return None;
+        }
+        Some((covariance / variance) as f32)
+    }
+
+    /// Least-squares fit of the CPU temperature over the whole history as
+    /// `(slope, intercept)`, in °C per sample and °C at the oldest sample.
+    /// Gaps are skipped; `None` with fewer than two samples.
+    pub fn linear_regression(&self) -> Option<(f32, f32)> {
+        let points: Vec<(f64, f64)> = self
+            .cpu_temps
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.is_finite())
+            .map(|(i, &t)| (i as f64, t as f64))
+            .collect();
+        if points.len() < 2 {
+            return None;
+        }
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let covariance: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+        let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+        let slope = covariance / variance;
+        Some((slope as f32, (mean_y - slope * mean_x) as f32))
+    }
+
+    /// [`TemperatureHistory::linear_regression`] as a line across the plot
+    pub fn trend_points(&self) -> PlotPoints {
+        let Some((slope, intercept)) = self.linear_regression() else {
+            return PlotPoints::new(Vec::new());
+        };
+        let last = (self.len() - 1) as f32;
+        PlotPoints::new(vec![[0.0, intercept as f64], [self.span_secs(), (intercept + slope * last) as f64]])
+    }
+
+    /// CPU temperature for each of the next `n` polls. The trend comes from
+    /// [`TemperatureHistory::rate_of_change`]; the exponential model shrinks it
+    /// by 20% per poll, so the projection levels off like a heating curve does.
+    /// Empty when no sample is available.
+    pub fn forecast_next_n(&self, n: usize, model: ForecastModel) -> Vec<f32> {
+        let Some(&last) = self.cpu_temps.iter().rev().find(|t| t.is_finite()) else {
+            return Vec::new();
+        };
+        let step = self.rate_of_change().filter(|r| r.is_finite()).unwrap_or(0.0) * UPDATE_INTERVAL_SECS;
+        let mut forecast = Vec::with_capacity(n);
+        let (mut temp, mut delta) = (last, step);
+        for _ in 0..n {
+            match model {
+                ForecastModel::Linear => temp += step,
+                ForecastModel::Exponential => {
+                    temp += delta;
+                    delta *= 0.8;
+                }
+                ForecastModel::LastValue => {}
+            }
+            forecast.push(temp);
+        }
+        forecast
+    }
+
+    /// Forecast plot points continuing from the newest sample
+    pub fn forecast_points(&self, n: usize, model: ForecastModel) -> PlotPoints {
+        let Some(&last) = self.cpu_temps.iter().rev().find(|t| t.is_finite()) else {
+            return PlotPoints::new(Vec::new());
+        };
+        let end = self.span_secs();
+        let interval = UPDATE_INTERVAL_SECS as f64;
+        let forecast = self.forecast_next_n(n, model);
+        let points = std::iter::once([end, last as f64])
+            .chain(forecast.iter().enumerate().map(|(i, &t)| [end + (i + 1) as f64 * interval, t as f64]))
+            .collect();
+        PlotPoints::new(points)
+    }
+
+    /// Time until the CPU reaches `threshold` at the current heating rate.
+    /// `None` when cooling or stable; zero when already at or above it.
+    pub fn predict_time_to_threshold(&self, threshold: f32) -> Option<Duration> {
+        let rate = self.rate_of_change()?;
+        if rate <= 0.0 {
+            return None;
+        }
+        let current = *self.cpu_temps.back()?;
+        Some(Duration::from_secs_f32(((threshold - current) / rate).max(0.0)))
+    }
+
+    /// Percentage of CPU samples spent in each thermal zone
+    pub fn time_in_zones(&self) -> Vec<(ThermalZone, f32)> {
+        ThermalZone::all()
+            .iter()
+            .map(|&zone| {
+                let count = self.cpu_temps.iter().filter(|&&t| ThermalZone::from_cpu_temp(t) == zone).count();
+                let pct = if self.is_empty() { 0.0 } else { count as f32 / self.len() as f32 * 100.0 };
+                (zone, pct)
+            })
+            .collect()
+    }
+
+    /// Indices where CPU temperature crossed `threshold` upward
+    /// (previous sample <= threshold, current sample > threshold).
+    /// A first sample already above the threshold counts as a crossing.
+    pub fn alert_threshold_crossings(&self, threshold: f32) -> Vec<usize> {
+        let mut previous_above = false;
+        let mut crossings = Vec::new();
+        for (i, &temp) in self.cpu_temps.iter().enumerate() {
+            let above = temp > threshold;
+            if above && !previous_above {
+                crossings.push(i);
+            }
+            previous_above = above;
+        }
+        crossings
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cpu_temps.is_empty()
+    }
+
+    /// Serialize to the binary snapshot format:
+    /// magic `LTHM`, version (u32), capacity (u32), then per sample
+    /// Unix time in ms (u64), cpu (f32) and kbd (f32).
+    /// All integers and floats are little-endian.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(12 + self.len() * 16);
+        data.extend_from_slice(SNAPSHOT_MAGIC);
+        data.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+        data.extend_from_slice(&(self.capacity as u32).to_le_bytes());
+        for ((time, cpu), kbd) in self.timestamps.iter().zip(&self.cpu_temps).zip(&self.kbd_temps) {
+            data.extend_from_slice(&unix_millis(*time).to_le_bytes());
+            data.extend_from_slice(&cpu.to_le_bytes());
+            data.extend_from_slice(&kbd.to_le_bytes());
+        }
+        data
+    }
+
+    /// Encode as a protobuf `TemperatureHistory` message
+    #[cfg(feature = "protobuf")]
+    pub fn as_protobuf(&self) -> Vec<u8> {
+        use prost::Message;
+
+        let message = crate::proto::TemperatureHistory {
+            capacity: self.capacity as u32,
+            sample_count: self.len() as u32,
+            timestamps_unix_ms: self.timestamps.iter().map(|&time| unix_millis(time) as i64).collect(),
+            channels: self
+                .channels()
+                .into_iter()
+                .map(|(name, samples)| crate::proto::Channel {
+                    name: name.to_string(),
+                    samples: samples.iter().copied().collect(),
+                })
+                .collect(),
+        };
+        message.encode_to_vec()
+    }
+
+    /// Decode a protobuf message produced by [`TemperatureHistory::as_protobuf`]
+    #[cfg(feature = "protobuf")]
+    pub fn from_protobuf(data: &[u8]) -> io::Result<Self> {
+        use prost::Message;
+
+        let message = crate::proto::TemperatureHistory::decode(data)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        let channel = |name: &str| {
+            message
+                .channels
+                .iter()
+                .find(|c| c.name == name)
+                .map(|c| c.samples.clone())
+                .unwrap_or_default()
+        };
+        let (cpu, kbd) = (channel("cpu"), channel("kbd"));
+        if cpu.len() != kbd.len() {
+            return Err(io::Error::new(ErrorKind::InvalidData, "Channel lengths differ"));
+        }
+        // Older exports carry no timestamps; space those samples one poll apart ending now
+        let timestamps: Vec<SystemTime> = if message.timestamps_unix_ms.len() == cpu.len() {
+            message
+                .timestamps_unix_ms
+                .iter()
+                .map(|&ms| UNIX_EPOCH + Duration::from_millis(ms.max(0) as u64))
+                .collect()
+        } else {
+            let now = SystemTime::now();
+            (0..cpu.len())
+                .rev()
+                .map(|i| now - Duration::from_secs_f32(UPDATE_INTERVAL_SECS * i as f32))
+                .collect()
+        };
+
+        // Imported files are untrusted; never let their header size an allocation
+        let capacity = message.capacity as usize;
+        if capacity == 0 || capacity > HISTORY_CAPACITY {
+            return Err(io::Error::new(ErrorKind::InvalidData, "Export capacity out of range"));
+        }
+
+        let mut history = Self::new(capacity);
+        for ((cpu, kbd), time) in cpu.into_iter().zip(kbd).zip(timestamps) {
+            history.push(cpu, kbd, time);
+        }
+        Ok(history)
+    }
+
+    /// Parse a binary snapshot produced by [`TemperatureHistory::serialize`]
+    pub fn deserialize(data: &[u8]) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(ErrorKind::InvalidData, msg.to_string());
+        let read_u32 = |offset: usize| u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]);
+        let read_f32 = |offset: usize| f32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]);
+        let read_u64 = |offset: usize| u64::from(read_u32(offset)) | (u64::from(read_u32(offset + 4)) << 32);
+
+        if data.len() < 12 || &data[..4] != SNAPSHOT_MAGIC {
+            return Err(invalid("Not a thermal history snapshot"));
+        }
+        if read_u32(4) != SNAPSHOT_VERSION {
+            return Err(invalid("Unsupported snapshot version"));
+        }
+        let capacity = read_u32(8) as usize;
+        let samples = &data[12..];
+        if capacity == 0 || !samples.len().is_multiple_of(16) {
+            return Err(invalid("Corrupt snapshot"));
+        }
+        // The file lives in /tmp; never let its header size an allocation
+        if capacity > HISTORY_CAPACITY {
+            return Err(invalid("Snapshot capacity exceeds the history size"));
+        }
+
+        let mut history = Self::new(capacity);
+        for offset in (12..data.len()).step_by(16) {
+            let time = UNIX_EPOCH + Duration::from_millis(read_u64(offset));
+            history.push(read_f32(offset + 8), read_f32(offset + 12), time);
+        }
+        Ok(history)
+    }
+}
+
+/// Main application state
+pub struct ThermalApp {
+    state: ThermalState,
+    history: TemperatureHistory,
+    last_update: Instant,
+    /// Last early refresh for an ACPI thermal trip, see [`TRIP_REFRESH_MIN_INTERVAL`]
+    last_trip_refresh: Option<Instant>,
+    status_message: Option<(String, Instant)>,
+    target_temp: f32,
+    auto_control: bool,
+    fan_boost_manual: bool,
+    fan_curve: FanCurve,
+    /// Drive the fan from `fan_curve` through the EC
+    #[cfg(feature = "ec_access")]
+    fan_curve_enabled: bool,
+    /// Last level written to the EC, to avoid rewriting it every update
+    #[cfg(feature = "ec_access")]
+    fan_curve_level: Option<u8>,
+    /// Curve point picked in the editor
+    #[cfg(feature = "ec_access")]
+    fan_curve_selected: Option<usize>,
+    threshold_crossings: usize,
+    show_zone_bands: bool,
+    /// Scale temperatures are shown in
+    temperature_unit: TemperatureUnit,
+    /// Draw memory usage in the history plot
+    show_mem_line: bool,
+    /// Draw the rolling CPU percentile in the history plot
+    show_percentile_line: bool,
+    /// Draw TJmax as a reference line in the history plot
+    show_tjmax_line: bool,
+    /// Draw the regression line of the CPU temperature in the history plot
+    show_trend_line: bool,
+    /// Shade the gap between the CPU line and its moving maximum
+    show_envelope: bool,
+    /// Screen refresh rate, read at startup and after each change
+    screen_refresh: Option<ScreenRefresh>,
+    /// Rate to restore once auto-control leaves the Critical zone
+    pre_critical_refresh_hz: Option<u32>,
+    /// CPU boost set by hand, kept apart from what auto-control would choose
+    cpu_boost_override: Option<bool>,
+    /// The stored override has been written to the boost switch (or there was
+    /// none); done once, when the switch is first read
+    cpu_boost_override_applied: bool,
+    /// Boost state auto-control last asked for, so a refused switch is not retried every poll
+    cpu_boost_auto_target: Option<bool>,
+    /// Project the CPU temperature past the newest sample (`None`: off)
+    forecast_model: Option<ForecastModel>,
+    /// Draw a smoothed CPU line (`None`: off)
+    smoothing: Option<Smoothing>,
+    /// Wavelet detail coefficients below this many °C are treated as sensor noise
+    wavelet_threshold: f32,
+    /// Smoothed copy of `history` with the smoothing and threshold it was
+    /// computed for; cleared on every new sample
+    smoothed_history: Option<(Smoothing, f32, TemperatureHistory)>,
+    /// ACPI trip points of thermal_zone0, read once at startup
+    trip_points: Vec<TripPoint>,
+    /// Draw the trip points in the history plot
+    show_trip_points: bool,
+    /// Timeline or temperature distribution
+    history_view: HistoryView,
+    /// perf_pct at each update, for the performance history chart
+    perf_history: SensorHistory<f32>,
+    /// Benchmark in progress, from the click until its settings are restored
+    benchmark: Option<Benchmark>,
+    benchmark_minutes: String,
+    /// Mode applied when the AC adapter is plugged in (None = keep current)
+    ac_mode: Option<Mode>,
+    /// Mode applied when running on battery (None = keep current)
+    battery_mode: Option<Mode>,
+    previous_ac_state: Option<bool>,
+    /// History imported for comparison (e.g. from an ftrace capture)
+    comparison_history: Option<TemperatureHistory>,
+    /// A/B split of the history graph: time of the first sample of period B
+    comparison_mode: Option<SystemTime>,
+    /// `--update-bar` template; `None` when not writing a status file
+    status_bar_format: Option<String>,
+    last_snapshot: Instant,
+    process_sample: Option<ProcessCpuSample>,
+    /// Top processes by CPU share since the previous update
+    process_usage: Vec<(String, f32)>,
+    /// Start time and CPU ticks of a running overhead measurement
+    overhead_benchmark: Option<(Instant, u64)>,
+    /// Measured CPU usage of this app (% of one core)
+    overhead_pct: Option<f32>,
+    /// ideapad-laptop module state, checked at startup
+    driver_status: Option<DriverStatus>,
+    /// Predicted time until the CPU reaches PREDICTION_THRESHOLD
+    time_to_threshold: Option<Duration>,
+    /// Degrees left before the passive trip point, see [`estimate_throttle_headroom`]
+    throttle_headroom: Option<f32>,
+    /// Set after a write fails with EPERM/EROFS; controls are disabled, monitoring continues
+    read_only_mode: bool,
+    /// Battery is low: Quiet mode and no fan boost, mode controls disabled
+    low_battery_safe_mode: bool,
+    /// Mode, manual fan boost and auto-control to restore once the battery recovers
+    low_battery_saved: Option<(Mode, bool, bool)>,
+    /// No user input since then (`None` right after input)
+    idle_since: Option<Instant>,
+    /// Mode to restore on the next input while idle mode holds Quiet
+    pre_idle_mode: Option<Mode>,
+    /// Keyboard/touchpad interrupt count at the last poll
+    input_interrupts: Option<u64>,
+    /// Target at which auto-control last reported "On target"; while the state
+    /// is unchanged and the target the same, the next call would do nothing
+    auto_control_settled_at: Option<f32>,
+    /// Last mode change made with the mouse wheel
+    last_mode_wheel: Option<Instant>,
+    /// Receives the latest release tag from the background update check
+    update_check_result: Option<Receiver<Option<String>>>,
+    /// Receives the outcome of the "Test notification" button
+    notification_test_result: Option<Receiver<io::Result<()>>>,
+    /// Newer release to announce, until the banner is dismissed
+    available_update: Option<String>,
+    /// First-run setup wizard, while it is open
+    setup_wizard: Option<SetupWizard>,
+    /// This session in the learning log (start time, unix seconds)
+    learning_session: u64,
+    /// Manual mode choices of this and earlier sessions
+    learning_observations: Vec<LearningObservation>,
+    /// Mode statistics window is open
+    show_mode_statistics: bool,
+    /// Start of the action recording and the actions captured so far
+    recording: Option<(Instant, Vec<RecordedAction>)>,
+    /// Mode being applied on a background thread
+    pending_mode_change: Option<Mode>,
+    /// Receives the result of the pending mode change
+    mode_change_result: Option<Receiver<Result<(), ThermalError>>>,
+    /// Mode requested while another change was pending
+    queued_mode_change: Option<Mode>,
+    /// Soft warning about the last control action, shown under the mode buttons
+    control_warning: Option<String>,
+    /// Time spent in each mode since the app started
+    mode_durations: HashMap<Mode, Duration>,
+    /// The last auto export write failed (already reported)
+    auto_export_failed: bool,
+    /// Settings as last written to disk
+    saved_config: Config,
+    /// Picks up edits made to the config file while the app runs
+    config_watcher: ConfigWatcher,
+    /// ACPI notifications from acpid; `None` when no event source could be opened
+    acpi_events: Option<AcpiEventStream>,
+    previous_wake_count: Option<u64>,
+    /// Monotonic and wall-clock time of the previous resume check
+    last_wake_check: (Instant, SystemTime),
+    /// Headroom alarm already fired; re-armed once the CPU cools back down
+    headroom_alarm_active: bool,
+    /// Last desktop notification of each kind, for the per-kind cooldowns
+    notification_cooldown_map: HashMap<AlertKind, Instant>,
+    /// Counters and statistics for the exit summary
+    session: SessionStats,
+    /// "Reset to defaults" was clicked and awaits confirmation
+    confirm_reset: bool,
+    /// Keyboard shortcut list is open
+    show_keyboard_map: bool,
+    /// About window is open
+    show_about: bool,
+    /// The desktop's accessibility settings ask for high contrast (read at startup)
+    high_contrast: bool,
+    /// Report left by a crash of the previous session, until dismissed
+    crash_report: Option<String>,
+    /// The crash dialog shows the full report
+    show_crash_details: bool,
+    /// Platform profile driver, detected once at startup
+    profile_driver: PlatformProfileDriver,
+    custom_metrics: Vec<CustomMetric>,
+    /// Per-core history, indexed like `state.per_core_temps`
+    cores: Vec<CoreHistory>,
+    /// Name and expression typed into the "Custom" section
+    new_metric_name: String,
+    new_metric_expr: String,
+    /// Notable events, oldest first
+    event_log: VecDeque<LogEntry>,
+    /// Recent events drawn as annotations on the history plot
+    alert_events: Vec<AlertEvent>,
+    auto_log_limiter: LogRateLimiter,
+    /// Loaded extensions, see [`crate::plugin`]
+    plugins: Vec<Box<dyn ThermalPlugin>>,
+}
+
+impl Default for ThermalApp {
+    fn default() -> Self {
+        let state = ThermalState::read();
+        let config = Self::load_config();
+        // Restore the graph from the last snapshot instead of starting empty
+        let mut history = fs::read(SNAPSHOT_PATH)
+            .and_then(|data| TemperatureHistory::deserialize(&data))
+            .unwrap_or_default();
+        if !history.validate_monotone_timestamps() {
+            eprintln!("snapshot: {}: timestamps out of order", SNAPSHOT_PATH);
+        }
+        history.push(state.cpu_temp, state.keyboard_temp, SystemTime::now());
+
+        Self {
+            state,
+            history,
+            last_update: Instant::now(),
+            last_trip_refresh: None,
+            status_message: None,
+            target_temp: config.target_temp,
+            auto_control: false,
+            fan_boost_manual: false,
+            fan_curve: config.fan_curve.clone(),
+            #[cfg(feature = "ec_access")]
+            fan_curve_enabled: false,
+            #[cfg(feature = "ec_access")]
+            fan_curve_level: None,
+            #[cfg(feature = "ec_access")]
+            fan_curve_selected: None,
+            threshold_crossings: 0,
+            show_zone_bands: config.show_zone_bands,
+            temperature_unit: config.temperature_unit,
+            show_mem_line: false,
+            show_percentile_line: false,
+            show_tjmax_line: false,
+            show_trend_line: false,
+            show_envelope: false,
+            screen_refresh: read_screen_refresh(),
+            pre_critical_refresh_hz: None,
+            cpu_boost_override: config.cpu_boost_override,
+            cpu_boost_override_applied: false,
+            cpu_boost_auto_target: None,
+            forecast_model: None,
+            smoothing: None,
+            wavelet_threshold: config.wavelet_threshold,
+            smoothed_history: None,
+            trip_points: read_thermal_trip_points(),
+            show_trip_points: true,
+            history_view: HistoryView::Timeline,
+            perf_history: SensorHistory::default(),
+            benchmark: None,
+            benchmark_minutes: config.benchmark_minutes.to_string(),
+            ac_mode: config.ac_mode,
+            battery_mode: config.battery_mode,
+            previous_ac_state: None,
+            comparison_history: None,
+            comparison_mode: None,
+            status_bar_format: None,
+            last_snapshot: Instant::now(),
+            process_sample: None,
+            process_usage: Vec::new(),
+            overhead_benchmark: None,
+            overhead_pct: None,
+            driver_status: ideapad_acpi_driver_check().ok(),
+            time_to_threshold: None,
+            throttle_headroom: None,
+            read_only_mode: false,
+            low_battery_safe_mode: false,
+            low_battery_saved: None,
+            idle_since: None,
+            pre_idle_mode: None,
+            input_interrupts: read_input_interrupts(),
+            auto_control_settled_at: None,
+            last_mode_wheel: None,
+            update_check_result: None,
+            notification_test_result: None,
+            available_update: None,
+            recording: None,
+            setup_wizard: None,
+            learning_session: unix_millis(SystemTime::now()) / 1000,
+            learning_observations: fs::read_to_string(Self::learning_log_path())
+                .map(|log| log.lines().filter_map(LearningObservation::parse).collect())
+                .unwrap_or_default(),
+            show_mode_statistics: false,
+            pending_mode_change: None,
+            mode_change_result: None,
+            queued_mode_change: None,
+            control_warning: None,
+            mode_durations: HashMap::new(),
+            auto_export_failed: false,
+            saved_config: config.clone(),
+            config_watcher: ConfigWatcher::spawn(Config::path(), Duration::from_secs_f32(UPDATE_INTERVAL_SECS)),
+            acpi_events: None,
+            previous_wake_count: read_wake_count(),
+            last_wake_check: (Instant::now(), SystemTime::now()),
+            headroom_alarm_active: false,
+            notification_cooldown_map: HashMap::new(),
+            session: SessionStats::default(),
+            confirm_reset: false,
+            show_keyboard_map: false,
+            show_about: false,
+            high_contrast: Self::accessibility_high_contrast_forced(),
+            crash_report: None,
+            show_crash_details: false,
+            profile_driver: get_platform_profile_driver(),
+            custom_metrics: Self::custom_metrics_from_config(&config),
+            cores: Vec::new(),
+            new_metric_name: String::new(),
+            new_metric_expr: String::new(),
+            event_log: VecDeque::with_capacity(EVENT_LOG_CAPACITY),
+            alert_events: Vec::with_capacity(EVENT_LOG_CAPACITY),
+            auto_log_limiter: LogRateLimiter::default(),
+            plugins: Vec::new(),
+        }
+    }
+}
+
+impl ThermalApp {
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let mut app = Self::default();
+        let ctx = cc.egui_ctx.clone();
+        app.acpi_events = AcpiEventStream::spawn(move || ctx.request_repaint());
+        app.load_and_display_previous_crash_report();
+        if Self::first_run_needed(Config::read_raw().ok().flatten().is_some(), &app.saved_config) {
+            app.setup_wizard = Some(SetupWizard { step: 0, config: app.current_config(), autostart: false });
+        }
+        if app.saved_config.check_updates {
+            app.check_for_updates(env!("CARGO_PKG_VERSION"));
+        }
+        #[cfg(feature = "plugins")]
+        if app.saved_config.plugins_enabled {
+            app.load_plugins();
+        }
+        app
+    }
+
+    /// Update state from system. Only a regular poll (`sample`) feeds the
+    /// history, session statistics and per-core graphs; an early refresh just
+    /// updates the readings and the control logic.
+    fn update_state(&mut self, sample: bool) {
+        // Counter deltas spanning a suspend are meaningless; start them over
+        let resumed = self.sleep_mode_detector();
+        if resumed {
+            #[cfg(feature = "intel-rapl")]
+            {
+                self.state.rapl_sample = None;
+            }
+            self.process_sample = None;
+            self.set_status("Resumed from suspend".into());
+        }
+
+        // Typing in other windows never reaches egui
+        let interrupts = read_input_interrupts();
+        let previous_interrupts = std::mem::replace(&mut self.input_interrupts, interrupts);
+        if matches!((previous_interrupts, interrupts), (Some(previous), Some(current)) if current != previous) {
+            self.idle_since = None;
+        }
+
+        let state = ThermalState::read_after(&self.state);
+        let previous = std::mem::replace(&mut self.state, state);
+        let diff = self.state.diff(&previous);
+        // The interval since the last poll was spent in the previous mode, unless asleep
+        if sample && !resumed {
+            *self.mode_durations.entry(previous.mode).or_default() += self.last_update.elapsed();
+        }
+        // While our own change is in flight the script may be half way through
+        if diff.mode_changed && self.pending_mode_change.is_none() {
+            self.apply_profile_from_dbus_signal(previous.mode);
+        }
+        if diff.zone_changed {
+            let kind = if self.state.thermal_zone() == ThermalZone::Critical {
+                AlertKind::CriticalZone
+            } else {
+                AlertKind::ZoneTransition
+            };
+            let message = format!(
+                "Zone {} → {} ({})",
+                previous.thermal_zone().label(),
+                self.state.thermal_zone().label(),
+                format_temp(self.state.cpu_temp, self.temperature_unit)
+            );
+            if kind == AlertKind::CriticalZone {
+                self.notify(kind, &message);
+            }
+            self.record_event(kind, message);
+        }
+        self.low_battery_safe_mode();
+        if sample {
+            if resumed {
+                // Break the graph across the suspend instead of bridging it
+                self.history.push(f32::NAN, f32::NAN, SystemTime::now());
+            }
+            self.record_sample();
+        }
+
+        self.process_usage = self.per_process_thermal_contribution();
+        self.time_to_threshold = self.history.predict_time_to_threshold(PREDICTION_THRESHOLD);
+        self.throttle_headroom = estimate_throttle_headroom(&self.state);
+        self.workspace_integration();
+        self.thermal_headroom_alarm(self.saved_config.min_headroom_alarm);
+
+        // Count upward crossings of the target temperature over the whole session
+        let newest = self.history.len() - 1;
+        if sample && self.history.alert_threshold_crossings(self.target_temp).last() == Some(&newest) {
+            self.threshold_crossings += 1;
+            self.record_event(
+                AlertKind::ThresholdCrossing,
+                format!("CPU crossed the {} target", format_temp(self.target_temp, self.temperature_unit)),
+            );
+        }
+
+        // Switch profile on AC plug/unplug transitions only
+        if let Some(on_ac) = self.state.on_ac {
+            let previous = self.previous_ac_state.replace(on_ac);
+            if previous.is_some_and(|previous| previous != on_ac) {
+                if on_ac {
+                    self.auto_mode_on_ac_connect();
+                } else {
+                    self.auto_mode_on_battery();
+                }
+            }
+        }
+
+        // Follow the custom fan curve, writing the EC only when the level changes
+        #[cfg(feature = "ec_access")]
+        if self.fan_curve_enabled && self.fan_curve_level != Some(self.fan_curve.level_at(self.state.cpu_temp)) {
+            match apply_fan_curve(&self.fan_curve, self.state.cpu_temp) {
+                Ok(level) => self.fan_curve_level = Some(level),
+                Err(ThermalError::Io(e)) => {
+                    self.fan_curve_enabled = false;
+                    self.handle_write_error(&e);
+                }
+                Err(e) => {
+                    self.fan_curve_enabled = false;
+                    self.set_status(format!("Error: {}", e));
+                }
+            }
+        }
+
+        // Apply automatic thermal control if enabled, skipping polls where
+        // nothing changed since it last reported being on target
+        let settled = diff.is_unchanged() && self.auto_control_settled_at == Some(self.target_temp);
+        if !self.auto_control {
+            self.auto_control_settled_at = None;
+        } else if !settled {
+            self.auto_control_settled_at = None;
+            match apply_thermal_control(self.state.cpu_temp, self.target_temp, &self.state.cpufreq_driver) {
+                Ok(msg) => {
+                    if msg == "On target" {
+                        self.auto_control_settled_at = Some(self.target_temp);
+                    } else {
+                        self.session.auto_control_interventions += 1;
+                        self.rate_limited_auto_control_log(&msg);
+                        self.status_message = Some((msg, Instant::now()));
+                    }
+                }
+                Err(e) if is_read_only_error(&e) => self.enter_read_only_mode(),
+                Err(_) => {}
+            }
+        }
+        self.limit_refresh_rate_when_critical();
+        self.manage_cpu_boost();
+        for plugin in &mut self.plugins {
+            plugin.on_state_update(&self.state);
+        }
+    }
+
+    /// Whether an ACPI thermal trip at `now` may refresh early, given the
+    /// previous early refresh; trips can arrive in bursts
+    fn trip_refresh_allowed(last_trip_refresh: Option<Instant>, now: Instant) -> bool {
+        last_trip_refresh.is_none_or(|refreshed| now.duration_since(refreshed) >= TRIP_REFRESH_MIN_INTERVAL)
+    }
+
+    /// `history` smoothed as currently selected, recomputed only after a new
+    /// sample or a change of smoothing or threshold
+    fn smoothed_history(&mut self) -> Option<(Smoothing, &TemperatureHistory)> {
+        let smoothing = self.smoothing?;
+        let threshold = self.wavelet_threshold;
+        let stale = self.smoothed_history.as_ref().is_none_or(|(s, t, _)| *s != smoothing || *t != threshold);
+        if stale {
+            let smoothed = match smoothing {
+                Smoothing::MovingAverage => self.history.moving_average(SMOOTHING_WINDOW),
+                Smoothing::Wavelet => self.history.wavelet_smoothed(threshold),
+            };
+            self.smoothed_history = Some((smoothing, threshold, smoothed));
+        }
+        self.smoothed_history.as_ref().map(|(_, _, smoothed)| (smoothing, smoothed))
+    }
+
+    /// Add the current readings to the history, session statistics, custom
+    /// metrics and per-core graphs, once per regular poll
+    fn record_sample(&mut self) {
+        self.history.push(self.state.cpu_temp, self.state.keyboard_temp, SystemTime::now());
+        self.smoothed_history = None;
+        self.history.push_mem(self.state.mem_used_pct);
+        self.auto_export_session_jsonl();
+        self.perf_history.push(self.state.perf_pct as f32);
+        for metric in &mut self.custom_metrics {
+            metric.update(&self.state);
+        }
+        self.cores.resize_with(self.state.per_core_temps.len(), CoreHistory::default);
+        for (core, &temp) in self.cores.iter_mut().zip(&self.state.per_core_temps) {
+            core.temps.push(temp);
+            core.max = core.max.max(temp);
+        }
+        self.session.record(
+            self.state.cpu_temp,
+            self.state.keyboard_temp,
+            Duration::from_secs_f32(UPDATE_INTERVAL_SECS),
+        );
+        #[cfg(feature = "intel-rapl")]
+        if let Some(tdp) = self.state.tdp {
+            self.history.push_power(tdp.package_watts);
+        }
+    }
+
+    /// Load the plugins in [`crate::plugin::plugin_dir`], logging the ones that fail
+    #[cfg(feature = "plugins")]
+    fn load_plugins(&mut self) {
+        let (plugins, errors) = crate::plugin::load_plugins(&crate::plugin::plugin_dir());
+        for plugin in &plugins {
+            self.log_event(format!("Loaded plugin {}", plugin.name()));
+        }
+        for error in errors {
+            self.log_event(format!("Plugin not loaded: {}", error));
+        }
+        self.plugins = plugins;
+    }
+
+    /// Tell every plugin the mode changed
+    fn notify_plugins_of_mode_change(&mut self, old: Mode, new: Mode) {
+        for plugin in &mut self.plugins {
+            plugin.on_mode_change(old, new);
+        }
+    }
+
+    /// One collapsible panel per plugin
+    fn render_plugin_panels(&mut self, ui: &mut egui::Ui) {
+        for (index, plugin) in self.plugins.iter_mut().enumerate() {
+            egui::CollapsingHeader::new(egui::RichText::new(plugin.name()).size(11.0))
+                .id_salt(("plugin", index))
+                .default_open(true)
+                .show(ui, |ui| plugin.render_panel(ui))
+                .header_response
+                .on_hover_text(HelpText::get("plugins"));
+        }
+    }
+
+    /// Boost state auto-control wants in `mode`: off in Quiet, on in
+    /// Performance and Balanced, untouched otherwise
+    fn auto_cpu_boost(mode: Mode) -> Option<bool> {
+        match mode {
+            Mode::Quiet => Some(false),
+            Mode::Performance | Mode::Balanced => Some(true),
+            _ => None,
+        }
+    }
+
+    /// Follow the mode with the CPU boost switch while auto-control is on and
+    /// the user has not chosen a boost state by hand
+    fn manage_cpu_boost(&mut self) {
+        if !self.cpu_boost_override_applied {
+            let Some(current) = self.state.cpu_boost else {
+                return;
+            };
+            // The override saved by an earlier session holds from the first poll
+            self.cpu_boost_override_applied = true;
+            if let Some(wanted) = self.cpu_boost_override.filter(|&wanted| wanted != current) {
+                if !self.read_only_mode {
+                    self.apply_cpu_boost(wanted);
+                }
+                return;
+            }
+        }
+        if !self.auto_control || self.cpu_boost_override.is_some() || self.read_only_mode {
+            self.cpu_boost_auto_target = None;
+            return;
+        }
+        let (Some(current), Some(wanted)) = (self.state.cpu_boost, Self::auto_cpu_boost(self.state.mode)) else {
+            return;
+        };
+        if current == wanted || self.cpu_boost_auto_target == Some(wanted) {
+            return;
+        }
+        self.cpu_boost_auto_target = Some(wanted);
+        self.apply_cpu_boost(wanted);
+    }
+
+    fn apply_cpu_boost(&mut self, enabled: bool) {
+        match set_cpu_boost(enabled) {
+            Ok(()) => {
+                self.state.cpu_boost = Some(enabled);
+                self.log_event(if enabled { "CPU boost on".into() } else { "CPU boost off".into() });
+            }
+            Err(ThermalError::Io(e)) => self.handle_write_error(&e),
+            Err(e) => self.set_status(format!("Error: {}", e)),
+        }
+    }
+
+    /// "CPU Boost" toggle; a click makes the choice an override until "Auto" is clicked
+    fn render_cpu_boost(&mut self, ui: &mut egui::Ui) {
+        let Some(mut boost) = self.state.cpu_boost else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!self.read_only_mode, egui::Checkbox::new(&mut boost, egui::RichText::new("CPU Boost").size(10.0)))
+                .on_hover_text(HelpText::get("cpu_boost"))
+                .changed()
+            {
+                self.cpu_boost_override = Some(boost);
+                self.apply_cpu_boost(boost);
+            }
+            if self.cpu_boost_override.is_some() {
+                if ui.small_button("Auto").on_hover_text(HelpText::get("cpu_boost_auto")).clicked() {
+                    self.cpu_boost_override = None;
+                    self.cpu_boost_auto_target = None;
+                }
+            } else if self.auto_control {
+                ui.label(egui::RichText::new("auto").size(10.0).color(egui::Color32::GRAY));
+            }
+        });
+    }
+
+    /// Notify once when the CPU gets within `min_headroom` °C of TJmax, whatever its zone
+    fn thermal_headroom_alarm(&mut self, min_headroom: f32) {
+        let headroom = self.state.thermal_headroom();
+        if headroom < min_headroom && !self.headroom_alarm_active {
+            let message = format!(
+                "CPU within {:.0}{} of thermal limit",
+                to_display_delta(min_headroom, self.temperature_unit),
+                self.temperature_unit.label()
+            );
+            self.notify(AlertKind::ThresholdCrossing, &message);
+            self.set_status(message.clone());
+            self.record_event(AlertKind::ThresholdCrossing, message);
+        }
+        self.headroom_alarm_active = Self::headroom_alarm_active(self.headroom_alarm_active, headroom, min_headroom);
+    }
+
+    /// Whether the headroom alarm stays fired: it fires below `min_headroom`
+    /// and re-arms only [`HEADROOM_ALARM_REARM`] above it, so a temperature
+    /// hovering at the threshold notifies once
+    fn headroom_alarm_active(active: bool, headroom: f32, min_headroom: f32) -> bool {
+        if active {
+            headroom < min_headroom + HEADROOM_ALARM_REARM
+        } else {
+            headroom < min_headroom
+        }
+    }
+
+    /// A notification is due when none of its kind was sent within `cooldown`
+    fn notification_due(last: Option<Instant>, now: Instant, cooldown: Duration) -> bool {
+        last.is_none_or(|last| now.duration_since(last) >= cooldown)
+    }
+
+    /// Desktop notification for an event, unless notifications are off, the
+    /// kind is never notified or one of the same kind went out within its cooldown
+    fn notify(&mut self, kind: AlertKind, message: &str) {
+        let Some(cooldown) = kind.notification_cooldown(&self.saved_config).filter(|_| self.saved_config.notifications)
+        else {
+            return;
+        };
+        let now = Instant::now();
+        if !Self::notification_due(self.notification_cooldown_map.get(&kind).copied(), now, cooldown) {
+            return;
+        }
+        self.notification_cooldown_map.insert(kind, now);
+        // notify-send can block on a slow notification daemon; keep it off the UI thread
+        let message = message.to_string();
+        std::thread::spawn(move || {
+            if let Err(e) = send_notification("Thermal Monitor", &message) {
+                eprintln!("notification: {}", e);
+            }
+        });
+    }
+
+    /// Send a desktop notification right away so the user can check that
+    /// alerts reach them without waiting for a thermal event
+    fn test_notification_system(&mut self) {
+        // notify-send can block on a slow notification daemon; keep it off the UI thread
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(send_notification("Thermal Monitor", "Lenovo Thermal Monitor – notification test"));
+        });
+        self.notification_test_result = Some(rx);
+    }
+
+    /// Report the outcome of the test notification, once it has arrived
+    fn finish_notification_test(&mut self) {
+        let Some(rx) = &self.notification_test_result else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => Err(io::Error::other("notification thread exited")),
+        };
+        self.notification_test_result = None;
+        match result {
+            Ok(()) => self.set_status("Test notification sent".into()),
+            Err(e) => self.set_status(format!("Notification failed: {} (is a notification daemon running?)", e)),
+        }
+    }
+
+    /// Log an event, keep it for the history plot and post it to the configured webhook
+    fn record_event(&mut self, kind: AlertKind, message: String) {
+        let time = SystemTime::now();
+        if let Some(url) = self.saved_config.webhook_url.clone() {
+            let body = Self::webhook_payload(time, kind.name(), &message, &self.state);
+            Self::thermal_event_webhook(url, body);
+        }
+        if self.alert_events.len() >= EVENT_LOG_CAPACITY {
+            self.alert_events.remove(0);
+        }
+        self.alert_events.push(AlertEvent { time, kind, message: message.clone() });
+        self.log_event(message);
+    }
+
+    /// POST a JSON `body` to `url` from a background thread; failures are only printed
+    pub fn thermal_event_webhook(url: String, body: String) {
+        Self::post_in_background(url, "application/json", body);
+    }
+
+    /// POST `body` to `url` from a background thread so a slow server cannot
+    /// stall polling; failures are only printed
+    fn post_in_background(url: String, content_type: &'static str, body: String) {
+        #[cfg(feature = "webhook")]
+        std::thread::spawn(move || {
+            let result = reqwest::blocking::Client::new()
+                .post(&url)
+                .header("Content-Type", content_type)
+                .timeout(WEBHOOK_TIMEOUT)
+                .body(body)
+                .send()
+                .and_then(|response| response.error_for_status());
+            if let Err(e) = result {
+                eprintln!("webhook {}: {}", url, e);
+            }
+        });
+        #[cfg(not(feature = "webhook"))]
+        {
+            let _ = (content_type, body);
+            eprintln!("webhook {}: built without the webhook feature", url);
+        }
+    }
+
+    /// JSON body describing an event and the state it happened in
+    pub fn webhook_payload(time: SystemTime, event_type: &str, message: &str, state: &ThermalState) -> String {
+        format!(
+            "{{\"timestamp\":{},\"event\":{},\"message\":{},\"mode\":{},\"zone\":{},\
+             \"cpu_temp\":{:.1},\"keyboard_temp\":{:.1},\"perf_pct\":{},\"fan_boost\":{}}}",
+            json_string(&format_iso8601(time)),
+            json_string(event_type),
+            json_string(message),
+            json_string(state.mode.label()),
+            json_string(state.thermal_zone().label()),
+            state.cpu_temp,
+            state.keyboard_temp,
+            state.perf_pct,
+            state.fan_boost,
+        )
+    }
+
+    /// Append the current state to the day's JSONL file in the configured
+    /// export directory. Only the first failure after a success is reported.
+    fn auto_export_session_jsonl(&mut self) {
+        let Some(dir) = self.saved_config.auto_export_path.clone() else {
+            return;
+        };
+        let now = SystemTime::now();
+        let path = dir.join(Self::auto_export_file_name(now));
+        let written = fs::create_dir_all(&dir)
+            .and_then(|_| fs::OpenOptions::new().create(true).append(true).open(&path))
+            .and_then(|mut file| writeln!(file, "{}", Self::state_json_line(now, &self.state)));
+        match written {
+            Ok(()) => self.auto_export_failed = false,
+            Err(e) if !self.auto_export_failed => {
+                self.auto_export_failed = true;
+                self.set_status(format!("Auto export: {}: {}", path.display(), e));
+            }
+            Err(_) => {}
+        }
+    }
+
+    /// `thermal-<date>.jsonl`; the UTC date changes at midnight, starting a new file
+    pub fn auto_export_file_name(time: SystemTime) -> String {
+        format!("thermal-{}.jsonl", &format_iso8601(time)[..10])
+    }
+
+    /// One poll as a single-line JSON object
+    pub fn state_json_line(time: SystemTime, state: &ThermalState) -> String {
+        let or_null = |value: Option<String>| value.unwrap_or_else(|| "null".into());
+        format!(
+            "{{\"timestamp\":{},\"cpu_temp\":{:.1},\"keyboard_temp\":{:.1},\"ambient_temp\":{:.1},\
+             \"perf_pct\":{},\"freq_mhz\":{},\"mode\":{},\"zone\":{},\"platform_profile\":{},\
+             \"fan_boost\":{},\"fan_rpm\":{},\"on_ac\":{},\"battery_pct\":{},\"battery_health_pct\":{},\"mem_used_pct\":{:.1}}}",
+            json_string(&format_iso8601(time)),
+            state.cpu_temp,
+            state.keyboard_temp,
+            state.ambient_temp,
+            state.perf_pct,
+            state.current_freq_mhz,
+            json_string(state.mode.label()),
+            json_string(state.thermal_zone().label()),
+            json_string(&state.platform_profile),
+            state.fan_boost,
+            or_null(state.fan_rpm.map(|rpm| rpm.to_string())),
+            or_null(state.on_ac.map(|on_ac| on_ac.to_string())),
+            or_null(state.battery_pct.map(|pct| pct.to_string())),
+            or_null(state.battery_health.map(|health| format!("{:.1}", health.health_pct))),
+            state.mem_used_pct,
+        )
+    }
+
+    /// Add an entry to the event log, dropping the oldest when full
+    fn log_event(&mut self, message: String) {
+        if self.event_log.len() >= EVENT_LOG_CAPACITY {
+            self.event_log.pop_front();
+        }
+        self.event_log.push_back(LogEntry { time: SystemTime::now(), message });
+    }
+
+    /// Log an auto-control action without flooding the log while temperatures oscillate
+    fn rate_limited_auto_control_log(&mut self, action: &str) {
+        match self.auto_log_limiter.check(Instant::now()) {
+            LogDecision::Log => self.log_event(format!("Auto: {}", action)),
+            LogDecision::Summary(count) => self.log_event(format!(
+                "Mode oscillated {} times in the last {} s",
+                count,
+                AUTO_LOG_WINDOW.as_secs()
+            )),
+            LogDecision::Suppress => {}
+        }
+    }
+
+    /// Detect a resume since the previous poll: `/sys/power/wake_count` went
+    /// up and more wall-clock time passed than the monotonic clock counted
+    fn sleep_mode_detector(&mut self) -> bool {
+        let current = read_wake_count();
+        let previous = std::mem::replace(&mut self.previous_wake_count, current);
+        let now = (Instant::now(), SystemTime::now());
+        let (instant, wall) = std::mem::replace(&mut self.last_wake_check, now);
+        let asleep = now.1.duration_since(wall).unwrap_or_default().saturating_sub(now.0 - instant);
+        resumed_from_suspend(previous, current, asleep, Duration::from_secs_f32(UPDATE_INTERVAL_SECS))
+    }
+
+    /// CPU share of the top processes since the previous call, to show what is heating the CPU
+    fn per_process_thermal_contribution(&mut self) -> Vec<(String, f32)> {
+        let current = read_process_cpu_sample();
+        let usage = self
+            .process_sample
+            .as_ref()
+            .map(|previous| top_process_usage(previous, &current, TOP_PROCESS_COUNT))
+            .unwrap_or_default();
+        self.process_sample = Some(current);
+        usage
+    }
+
+    /// Load saved settings, migrating files written by older versions
+    fn load_config() -> Config {
+        match Config::read_raw() {
+            Ok(Some((table, version))) if version == CONFIG_VERSION => {
+                Config::from_table(&table).unwrap_or_default()
+            }
+            Ok(Some((_, version))) => match Self::config_migration(&version, CONFIG_VERSION) {
+                Ok(config) => {
+                    let _ = config.save();
+                    config
+                }
+                Err(e) => {
+                    eprintln!("config: {}, using defaults", e);
+                    Config::default()
+                }
+            },
+            Ok(None) => Config::default(),
+            Err(e) => {
+                eprintln!("config: {}, using defaults", e);
+                Config::default()
+            }
+        }
+    }
+
+    /// Read the config file as written by `from_version` and migrate it to `to_version`
+    pub fn config_migration(from_version: &str, to_version: &str) -> Result<Config, ThermalError> {
+        let (table, _) = Config::read_raw()?.unwrap_or_default();
+        config::migrate(table, from_version, to_version)
+    }
+
+    /// Settings currently shown in the UI
+    fn current_config(&self) -> Config {
+        Config {
+            target_temp: self.target_temp,
+            ac_mode: self.ac_mode,
+            battery_mode: self.battery_mode,
+            show_zone_bands: self.show_zone_bands,
+            temperature_unit: self.temperature_unit,
+            notify_cooldown_high_temp_secs: self.saved_config.notify_cooldown_high_temp_secs,
+            notify_cooldown_critical_secs: self.saved_config.notify_cooldown_critical_secs,
+            notify_cooldown_mode_change_secs: self.saved_config.notify_cooldown_mode_change_secs,
+            cpu_boost_override: self.cpu_boost_override,
+            benchmark_minutes: self.benchmark_minutes.trim().parse().unwrap_or(self.saved_config.benchmark_minutes),
+            min_headroom_alarm: self.saved_config.min_headroom_alarm,
+            fan_curve: self.fan_curve.clone(),
+            log_sessions: self.saved_config.log_sessions,
+            low_battery_pct: self.saved_config.low_battery_pct,
+            idle_timeout_mins: self.saved_config.idle_timeout_mins,
+            webhook_url: self.saved_config.webhook_url.clone(),
+            auto_export_path: self.saved_config.auto_export_path.clone(),
+            notifications: self.saved_config.notifications,
+            first_run_completed: self.saved_config.first_run_completed,
+            plugins_enabled: self.saved_config.plugins_enabled,
+            check_updates: self.saved_config.check_updates,
+            wavelet_threshold: self.wavelet_threshold,
+            custom_metrics: self
+                .custom_metrics
+                .iter()
+                .map(|metric| (metric.name.clone(), metric.source.clone()))
+                .collect(),
+        }
+    }
+
+    /// Parse the configured metrics, skipping (and reporting) invalid ones
+    fn custom_metrics_from_config(config: &Config) -> Vec<CustomMetric> {
+        config
+            .custom_metrics
+            .iter()
+            .filter_map(|(name, source)| match CustomMetric::new(name.clone(), source.clone()) {
+                Ok(metric) => Some(metric),
+                Err(e) => {
+                    eprintln!("config: custom metric {}: {}", name, e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Add a user-defined metric, or replace the expression of an existing one
+    pub fn custom_metric(&mut self, name: String, expr: String) -> Result<(), ThermalError> {
+        let mut metric = CustomMetric::new(name, expr)?;
+        metric.update(&self.state);
+        match self.custom_metrics.iter_mut().find(|m| m.name == metric.name) {
+            Some(existing) => *existing = metric,
+            None => self.custom_metrics.push(metric),
+        }
+        Ok(())
+    }
+
+    fn toggle_auto_control(&mut self) {
+        self.auto_control = !self.auto_control;
+        self.set_status(if self.auto_control { "Auto ON".into() } else { "Auto OFF".into() });
+    }
+
+    fn toggle_fan_boost(&mut self) {
+        if !self.validate_action(ControlAction::SetFanBoost(!self.fan_boost_manual)) {
+            return;
+        }
+        self.fan_boost_manual = !self.fan_boost_manual;
+        match set_fan_boost(self.fan_boost_manual) {
+            Ok(()) => {
+                if self.fan_boost_manual {
+                    self.session.fan_boost_activations += 1;
+                }
+                self.record_action(RecordedStep::FanBoost(self.fan_boost_manual));
+                self.record_event(
+                    AlertKind::FanBoost,
+                    if self.fan_boost_manual { "Fan boost on".into() } else { "Fan boost off".into() },
+                );
+                self.set_status(if self.fan_boost_manual { "Fan boost".into() } else { "Fan auto".into() });
+            }
+            Err(e) => {
+                self.fan_boost_manual = !self.fan_boost_manual;
+                self.handle_write_error(&e);
+            }
+        }
+    }
+
+    /// (shortcut, action) rows for the keyboard map, generated from SHORTCUTS
+    fn keyboard_map() -> Vec<(String, String)> {
+        SHORTCUTS
+            .iter()
+            .map(|(_, shortcut, action)| (shortcut.to_string(), action.description()))
+            .collect()
+    }
+
+    /// Run the actions of shortcuts pressed this frame (not while typing in a text field)
+    fn handle_keyboard_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let (actions, escape): (Vec<ShortcutAction>, bool) = ctx.input(|i| {
+            let actions = SHORTCUTS
+                .iter()
+                .filter(|(key, _, _)| i.key_pressed(*key))
+                .map(|&(_, _, action)| action)
+                .collect();
+            (actions, i.key_pressed(egui::Key::Escape))
+        });
+        if escape {
+            self.show_keyboard_map = false;
+            self.show_about = false;
+        }
+
+        let controls_locked = self.controls_lock_reason().is_some();
+        for action in actions {
+            match action {
+                ShortcutAction::SetMode(mode) if !controls_locked && mode != self.state.mode => {
+                    self.profile_learning_mode(mode);
+                    self.change_mode(mode);
+                }
+                ShortcutAction::SetMode(_) => {}
+                ShortcutAction::ToggleAutoControl if !controls_locked => self.toggle_auto_control(),
+                ShortcutAction::ToggleFanBoost if !self.read_only_mode && !self.low_battery_safe_mode => {
+                    self.toggle_fan_boost()
+                }
+                ShortcutAction::ToggleAutoControl | ShortcutAction::ToggleFanBoost => {
+                    let reason = self.controls_lock_reason().unwrap_or(LOW_BATTERY_HINT);
+                    self.set_status(reason.into())
+                }
+                ShortcutAction::ToggleZoneBands => self.show_zone_bands = !self.show_zone_bands,
+                ShortcutAction::ExportCsv => self.export_csv(),
+                ShortcutAction::ShowKeyboardMap => self.show_keyboard_map = true,
+            }
+        }
+    }
+
+    /// Window listing every keyboard shortcut
+    fn render_control_keyboard_map(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Keyboard shortcuts")
+            .open(&mut self.show_keyboard_map)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                egui::Grid::new("keyboard_map").num_columns(2).striped(true).show(ui, |ui| {
+                    for (shortcut, action) in Self::keyboard_map() {
+                        ui.label(egui::RichText::new(shortcut).monospace().strong());
+                        ui.label(action);
+                        ui.end_row();
+                    }
+                });
+            });
+    }
+
+    /// Version and detected drivers
+    fn render_about(&mut self, ctx: &egui::Context) {
+        let profile_driver = match self.profile_driver.profile_path() {
+            Some(path) => format!("{} (/sys/{})", self.profile_driver.label(), path),
+            None => self.profile_driver.label().to_string(),
+        };
+        let cpufreq_driver = if self.state.cpufreq_driver.is_empty() { "unknown" } else { &self.state.cpufreq_driver };
+        let thermal_policy = self.state.kernel_thermal_policy.as_deref().unwrap_or("unknown");
+        let battery_health = self.state.battery_health.map_or("no battery".into(), |health| {
+            format!(
+                "{:.0}% ({} of {} mWh, {}, {} cycles)",
+                health.health_pct,
+                health.capacity_full_mwh,
+                health.capacity_design_mwh,
+                health.status.label(),
+                health.cycle_count
+            )
+        });
+        let policy_conflict = self.state.thermal_policy_conflict();
+        egui::Window::new("About")
+            .open(&mut self.show_about)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(egui::RichText::new(format!("Thermal Monitor {}", env!("CARGO_PKG_VERSION"))).strong());
+                egui::Grid::new("about").num_columns(2).show(ui, |ui| {
+                    for (name, value) in [
+                        ("Platform profile driver", profile_driver.as_str()),
+                        ("Platform profile", self.state.platform_profile.as_str()),
+                        ("Kernel thermal policy", thermal_policy),
+                        ("cpufreq driver", cpufreq_driver),
+                        ("Battery health", battery_health.as_str()),
+                    ] {
+                        ui.label(egui::RichText::new(name).color(egui::Color32::GRAY));
+                        ui.label(value);
+                        ui.end_row();
+                    }
+                });
+                if let Some(conflict) = policy_conflict {
+                    ui.label(egui::RichText::new(format!("⚠ {}", conflict)).color(egui::Color32::from_rgb(255, 165, 0)));
+                }
+            });
+    }
+
+    /// The setup wizard runs when there is no config file yet, or when the
+    /// saved config asks for it again
+    pub fn first_run_needed(config_file_exists: bool, config: &Config) -> bool {
+        !config_file_exists || !config.first_run_completed
+    }
+
+    /// First-run setup wizard: one step per window page, settings are only
+    /// written when the last step is finished
+    fn render_setup_wizard(&mut self, ctx: &egui::Context) {
+        let Some(wizard) = &mut self.setup_wizard else {
+            return;
+        };
+        let hardware_report = self.state.hardware_report();
+        let mut finished = false;
+        let mut skipped = false;
+        egui::Window::new(format!("Setup ({}/{}): {}", wizard.step + 1, SETUP_STEPS.len(), SETUP_STEPS[wizard.step]))
+            .id(egui::Id::new("setup_wizard"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                match wizard.step {
+                    0 => {
+                        ui.label("Detected hardware:");
+                        ui.label(egui::RichText::new(&hardware_report).monospace().size(10.0));
+                    }
+                    1 => {
+                        ui.label("Show temperatures in:");
+                        for &unit in TemperatureUnit::all() {
+                            ui.radio_value(&mut wizard.config.temperature_unit, unit, unit.label());
+                        }
+                    }
+                    2 => {
+                        ui.label("Auto-control keeps the CPU at or below this temperature.");
+                        let unit = wizard.config.temperature_unit;
+                        let mut target = to_display(wizard.config.target_temp, unit);
+                        let range = to_display(40.0, unit)..=to_display(80.0, unit);
+                        if ui.add(egui::Slider::new(&mut target, range).suffix(unit.label()).step_by(1.0))
+                            .on_hover_text(HelpText::get("target_temp"))
+                            .changed()
+                        {
+                            wizard.config.target_temp = from_display(target, unit);
+                        }
+                    }
+                    3 => {
+                        ui.checkbox(&mut wizard.config.notifications, "Desktop notifications for thermal alarms");
+                        let unit = wizard.config.temperature_unit;
+                        let mut headroom = to_display_delta(wizard.config.min_headroom_alarm, unit);
+                        let range = to_display_delta(5.0, unit)..=to_display_delta(30.0, unit);
+                        if ui
+                            .add_enabled(
+                                wizard.config.notifications,
+                                egui::Slider::new(&mut headroom, range)
+                                    .suffix(format!("{} below TJmax", unit.label()))
+                                    .step_by(1.0),
+                            )
+                            .changed()
+                        {
+                            wizard.config.min_headroom_alarm = from_display_delta(headroom, unit);
+                        }
+                    }
+                    _ => {
+                        ui.checkbox(&mut wizard.autostart, "Start Thermal Monitor when I log in");
+                        if cfg!(feature = "update-check") {
+                            ui.checkbox(&mut wizard.config.check_updates, "Check GitHub for a new release once a day")
+                                .on_hover_text(HelpText::get("check_updates"));
+                        }
+                    }
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(wizard.step > 0, egui::Button::new("Back")).clicked() {
+                        wizard.step -= 1;
+                    }
+                    if wizard.step + 1 < SETUP_STEPS.len() {
+                        if ui.button("Next").clicked() {
+                            wizard.step += 1;
+                        }
+                    } else {
+                        finished = ui.button("Finish").clicked();
+                    }
+                    skipped = ui.small_button("Skip").on_hover_text(HelpText::get("setup_wizard")).clicked();
+                });
+            });
+        if !(finished || skipped) {
+            return;
+        }
+        let Some(wizard) = self.setup_wizard.take() else {
+            return;
+        };
+        let config = if finished { wizard.config } else { self.current_config() };
+        let config = Config { first_run_completed: true, ..config };
+        if let Err(e) = config.save() {
+            self.set_status(format!("Error saving settings: {}", e));
+        }
+        if finished {
+            if let Err(e) = set_autostart(wizard.autostart) {
+                self.set_status(format!("{}: {}", autostart_path().display(), e));
+            }
+        }
+        let start_update_check = config.check_updates && !self.saved_config.check_updates;
+        self.apply_config(config);
+        if start_update_check {
+            self.check_for_updates(env!("CARGO_PKG_VERSION"));
+        }
+    }
+
+    /// Show `config` in the UI. History, alerts and session counters are kept.
+    fn apply_config(&mut self, config: Config) {
+        self.target_temp = config.target_temp;
+        self.wavelet_threshold = config.wavelet_threshold;
+        self.ac_mode = config.ac_mode;
+        self.battery_mode = config.battery_mode;
+        self.show_zone_bands = config.show_zone_bands;
+        self.temperature_unit = config.temperature_unit;
+        self.cpu_boost_override = config.cpu_boost_override;
+        self.benchmark_minutes = config.benchmark_minutes.to_string();
+        self.fan_curve = config.fan_curve.clone();
+        self.custom_metrics = Self::custom_metrics_from_config(&config);
+        self.saved_config = config;
+    }
+
+    /// Replace every setting with its default and save
+    fn restore_defaults(&mut self) {
+        let defaults = Config::default();
+        match defaults.save() {
+            Ok(()) => self.set_status("Settings reset to defaults".into()),
+            Err(e) => self.set_status(format!("Error saving settings: {}", e)),
+        }
+        self.apply_config(defaults);
+    }
+
+    /// How the current mode changes battery runtime
+    fn estimated_battery_impact(&self) -> String {
+        Self::battery_impact(self.state.mode, self.state.on_ac)
+    }
+
+    /// Runtime change of `mode` against Balanced on a typical battery
+    fn battery_impact(mode: Mode, on_ac: Option<bool>) -> String {
+        match (on_ac, mode.power_delta_watts()) {
+            (Some(true), _) => "On AC: no impact".into(),
+            (None, _) => "Power source unknown".into(),
+            (Some(false), None) => "On battery: impact varies with load".into(),
+            (Some(false), Some(delta)) => {
+                let baseline_hours = TYPICAL_BATTERY_WH / BASELINE_DRAW_WATTS;
+                let hours = TYPICAL_BATTERY_WH / (BASELINE_DRAW_WATTS + delta) - baseline_hours;
+                if hours.abs() < 0.05 {
+                    "On battery: no change in runtime".into()
+                } else if hours < 0.0 {
+                    format!("On battery: ~{:.1}h reduction in runtime", -hours)
+                } else {
+                    format!("On battery: ~{:.1}h more runtime", hours)
+                }
+            }
+        }
+    }
+
+    /// Run auto-control without a window for `duration`, printing each cycle.
+    /// Returns the process exit code: 0 when done, 2 if the CPU reached Critical.
+    pub fn oneshot_mode(duration: Duration, influxdb_url: Option<String>) -> i32 {
+        let driver = read_cpufreq_driver();
+        let config = Self::load_config();
+        let interval = Duration::from_secs_f32(UPDATE_INTERVAL_SECS);
+        let ambient_temp = read_ambient_temp();
+        let mut session = SessionStats::default();
+        let code = Self::run_control_loop(
+            duration,
+            interval,
+            || read_cpu_temp().unwrap_or(50.0),
+            |temp| {
+                let kbd = calculate_keyboard_temp(temp, ambient_temp);
+                session.record(temp, kbd, interval);
+                if let Some(url) = &influxdb_url {
+                    let line = influxdb_line(INFLUXDB_MEASUREMENT, temp, kbd, SystemTime::now());
+                    Self::post_in_background(url.clone(), "text/plain; charset=utf-8", line);
+                }
+                match apply_thermal_control(temp, config.target_temp, &driver) {
+                    Ok(action) => {
+                        if action != "On target" {
+                            session.auto_control_interventions += 1;
+                        }
+                        action
+                    }
+                    Err(e) => format!("Error: {}", e),
+                }
+            },
+            &mut io::stdout(),
+        );
+        Self::finish_session(&session, config.log_sessions);
+        code
+    }
+
+    /// Print the session summary and append it to the log if enabled
+    fn finish_session(session: &SessionStats, log_sessions: bool) {
+        let report = session.report_now();
+        print!("{}", report);
+        if log_sessions {
+            if let Err(e) = SessionStats::append_to_log(&report) {
+                eprintln!("session log: {}", e);
+            }
+        }
+    }
+
+    /// Turn SIGTERM and SIGINT into [`TERMINATE_REQUESTED`], checked by the
+    /// window and by `--run-for`, instead of killing the process before the
+    /// session summary is logged
+    pub fn install_terminate_handler() {
+        extern "C" fn request_terminate(_signal: std::ffi::c_int) {
+            // Only an atomic store: nothing else is async-signal-safe here
+            TERMINATE_REQUESTED.store(true, Ordering::SeqCst);
+        }
+        extern "C" {
+            fn signal(signum: std::ffi::c_int, handler: extern "C" fn(std::ffi::c_int)) -> usize;
+        }
+        for signum in [SIGTERM, SIGINT] {
+            // SAFETY: libc `signal` with a handler that only touches an atomic
+            unsafe { signal(signum, request_terminate) };
+        }
+    }
+
+    /// A terminate signal arrived, see [`ThermalApp::install_terminate_handler`]
+    fn terminate_requested() -> bool {
+        TERMINATE_REQUESTED.load(Ordering::SeqCst)
+    }
+
+    /// Run [`ThermalApp::panic_handler`] before the default panic hook
+    pub fn install_panic_handler() {
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            Self::panic_handler(&info.to_string());
+            default_hook(info);
+        }));
+    }
+
+    /// Save the last history snapshot and log the panic. The fallback to
+    /// Balanced happens on the next start, not here: the hook must not spawn
+    /// pkexec or wait on anything.
+    pub fn panic_handler(message: &str) {
+        // Never block: the panicking thread may be the one holding the lock.
+        // A panic while the lock was held still leaves usable bytes behind.
+        let history = match CRASH_HISTORY.try_lock() {
+            Ok(history) => Some(history),
+            Err(std::sync::TryLockError::Poisoned(e)) => Some(e.into_inner()),
+            Err(std::sync::TryLockError::WouldBlock) => None,
+        };
+        if let Some(history) = history.filter(|history| !history.is_empty()) {
+            if let Err(e) = fs::write(CRASH_SNAPSHOT_PATH, &*history) {
+                eprintln!("{}: {}", CRASH_SNAPSHOT_PATH, e);
+            }
+        }
+
+        let path = Self::crash_log_path();
+        let entry = Self::crash_log_entry(SystemTime::now(), message);
+        let logged = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::OpenOptions::new().create(true).append(true).open(&path))
+            .and_then(|mut file| writeln!(file, "{}", entry));
+        if let Err(e) = logged {
+            eprintln!("{}: {}", path.display(), e);
+        }
+
+        // Picked up by the next start, which offers to report it
+        let report = format!("Thermal Monitor {}\n{}\n", env!("CARGO_PKG_VERSION"), entry);
+        if let Err(e) = fs::write(Self::crash_report_path(), report) {
+            eprintln!("{}: {}", Self::crash_report_path().display(), e);
+        }
+    }
+
+    /// `crashes.log` next to the session log
+    pub fn crash_log_path() -> PathBuf {
+        SessionStats::log_path().with_file_name("crashes.log")
+    }
+
+    /// Report of the last crash, removed once the user has seen it
+    pub fn crash_report_path() -> PathBuf {
+        SessionStats::log_path().with_file_name("last-crash.txt")
+    }
+
+    /// Pick up the report left by a crash of the previous session; it is shown
+    /// by [`ThermalApp::render_crash_report`] until dismissed. The mode the
+    /// crashed session left behind is not trusted: fall back to Balanced.
+    fn load_and_display_previous_crash_report(&mut self) {
+        self.crash_report = fs::read_to_string(Self::crash_report_path())
+            .ok()
+            .filter(|report| !report.trim().is_empty());
+        if self.crash_report.is_some() && self.state.mode != Mode::Balanced {
+            self.change_mode(Mode::Balanced);
+        }
+    }
+
+    /// New-issue URL with the crash report as the body
+    pub fn crash_issue_url(report: &str) -> String {
+        format!(
+            "{}?title={}&body={}",
+            NEW_ISSUE_URL,
+            percent_encode("Crash report"),
+            percent_encode(&format!("The app crashed:\n\n```\n{}```\n", report))
+        )
+    }
+
+    /// `$XDG_CACHE_HOME/lenovo-thermal-monitor/status.txt` (falls back to `~/.cache`)
+    pub fn status_bar_path() -> PathBuf {
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .unwrap_or_else(|| PathBuf::from("/tmp"));
+        base.join("lenovo-thermal-monitor").join("status.txt")
+    }
+
+    /// Write [`ThermalApp::status_bar_path`] on every poll, using `template`
+    /// or [`DEFAULT_BAR_FORMAT`]
+    pub fn enable_status_bar(&mut self, template: Option<String>) {
+        self.status_bar_format = Some(template.unwrap_or_else(|| DEFAULT_BAR_FORMAT.into()));
+    }
+
+    /// Fill the `{cpu_temp}`, `{mode}`, `{zone}` and `{color}` placeholders of
+    /// a status bar template. The color is the mode's, as `#rrggbb`.
+    fn status_bar_line(template: &str, cpu_temp: f32, mode: Mode, zone: ThermalZone) -> String {
+        let color = Self::mode_color(mode);
+        template
+            .replace("{cpu_temp}", &format!("{:.0}", cpu_temp))
+            .replace("{mode}", mode.short_label())
+            .replace("{zone}", zone.label())
+            .replace("{color}", &format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b()))
+    }
+
+    /// Report the mode and temperature to i3/Sway/Hyprland bars through the
+    /// `--update-bar` status file. Stops after a failed write.
+    pub fn workspace_integration(&mut self) {
+        let Some(template) = &self.status_bar_format else {
+            return;
+        };
+        let line = Self::status_bar_line(
+            template,
+            to_display(self.state.cpu_temp, self.temperature_unit),
+            self.state.mode,
+            self.state.thermal_zone(),
+        );
+        let path = Self::status_bar_path();
+        if let Err(e) = Self::write_status_bar_file(&path, &format!("{}\n", line)) {
+            self.status_bar_format = None;
+            self.log_event(format!("Status bar file not written: {}: {}", path.display(), e));
+        }
+    }
+
+    /// Replace `path` with `contents` through a temporary file and a rename,
+    /// so a bar polling the file never reads a half-written line
+    fn write_status_bar_file(path: &Path, contents: &str) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("txt.tmp");
+        fs::write(&tmp, contents)?;
+        fs::rename(&tmp, path)
+    }
+
+    /// Blank the status file on exit so bars stop showing a stale temperature
+    fn clear_status_bar(&self) {
+        if self.status_bar_format.is_some() {
+            let _ = Self::write_status_bar_file(&Self::status_bar_path(), "");
+        }
+    }
+
+    /// Last release check: unix seconds and the tag that was found
+    pub fn update_check_path() -> PathBuf {
+        SessionStats::log_path().with_file_name("last-update-check")
+    }
+
+    /// Tag stored by a release check made less than [`UPDATE_CHECK_INTERVAL`]
+    /// before `now`; `None` when the cache is missing, stale or malformed
+    fn cached_release_tag(cache: &str, now: SystemTime) -> Option<String> {
+        let (checked, tag) = cache.trim().split_once(' ')?;
+        let checked = UNIX_EPOCH + Duration::from_secs(checked.parse().ok()?);
+        let age = now.duration_since(checked).ok()?;
+        (age < UPDATE_CHECK_INTERVAL).then(|| tag.to_string())
+    }
+
+    /// Compare the latest GitHub release with `current_version`. A check from the
+    /// last 24 hours is reused; otherwise the API is queried on a background
+    /// thread and the answer is picked up by [`ThermalApp::finish_update_check`].
+    pub fn check_for_updates(&mut self, current_version: &str) {
+        let cached = fs::read_to_string(Self::update_check_path())
+            .ok()
+            .and_then(|cache| Self::cached_release_tag(&cache, SystemTime::now()));
+        if let Some(tag) = cached {
+            self.available_update = version_is_newer(&tag, current_version).then_some(tag);
+        } else {
+            #[cfg(feature = "update-check")]
+            self.spawn_update_check();
+        }
+    }
+
+    /// Ask GitHub for the latest release tag on a background thread
+    #[cfg(feature = "update-check")]
+    fn spawn_update_check(&mut self) {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let tag = reqwest::blocking::Client::new()
+                .get(LATEST_RELEASE_API_URL)
+                .header("User-Agent", concat!("thermal-monitor-gui/", env!("CARGO_PKG_VERSION")))
+                .header("Accept", "application/vnd.github+json")
+                .timeout(UPDATE_CHECK_TIMEOUT)
+                .send()
+                .and_then(|response| response.error_for_status())
+                .and_then(|response| response.text());
+            let _ = tx.send(match tag {
+                Ok(json) => release_tag_from_json(&json),
+                Err(e) => {
+                    eprintln!("update check {}: {}", LATEST_RELEASE_API_URL, e);
+                    None
+                }
+            });
+        });
+        self.update_check_result = Some(rx);
+    }
+
+    /// Take the answer of the background release check, once it has arrived
+    fn finish_update_check(&mut self) {
+        let Some(rx) = &self.update_check_result else {
+            return;
+        };
+        let tag = match rx.try_recv() {
+            Ok(tag) => tag,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => None,
+        };
+        self.update_check_result = None;
+        // Failed checks are not cached so the next start tries again
+        let Some(tag) = tag else {
+            return;
+        };
+        let cache = format!("{} {}\n", unix_millis(SystemTime::now()) / 1000, tag);
+        if let Err(e) = fs::write(Self::update_check_path(), cache) {
+            self.log_event(format!("{}: {}", Self::update_check_path().display(), e));
+        }
+        if version_is_newer(&tag, env!("CARGO_PKG_VERSION")) {
+            self.log_event(format!("Update available: {}", tag));
+            self.available_update = Some(tag);
+        }
+    }
+
+    /// "Update available" banner with a download link and a dismiss button
+    fn render_update_banner(&mut self, ui: &mut egui::Ui, font_size: f32) {
+        let Some(tag) = self.available_update.clone() else {
+            return;
+        };
+        ui.horizontal_wrapped(|ui| {
+            ui.label(
+                egui::RichText::new(format!("Update available: v{} –", tag.trim_start_matches('v')))
+                    .size(font_size)
+                    .color(egui::Color32::from_rgb(100, 200, 255)),
+            )
+            .on_hover_text(HelpText::get("update_check"));
+            if ui.link("download").clicked() {
+                if let Err(e) = open_url(LATEST_RELEASE_URL) {
+                    self.set_status(format!("Could not open browser: {}", e));
+                }
+            }
+            if ui.small_button("✕").on_hover_text("Dismiss").clicked() {
+                self.available_update = None;
+            }
+        });
+    }
+
+    /// "Previous session crashed" dialog
+    fn render_crash_report(&mut self, ctx: &egui::Context) {
+        let Some(report) = self.crash_report.clone() else {
+            return;
+        };
+        let mut dismissed = false;
+        egui::Window::new("Previous session crashed")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "The history was saved to {} and the mode was set back to Balanced.",
+                    CRASH_SNAPSHOT_PATH
+                ));
+                if self.show_crash_details {
+                    egui::ScrollArea::vertical().max_height(150.0).show(ui, |ui| {
+                        ui.label(egui::RichText::new(&report).monospace().size(10.0));
+                    });
+                }
+                ui.horizontal(|ui| {
+                    let details = if self.show_crash_details { "Hide report" } else { "View report" };
+                    if ui.button(details).clicked() {
+                        self.show_crash_details = !self.show_crash_details;
+                    }
+                    if ui.button("Report issue").on_hover_text(HelpText::get("crash_report")).clicked() {
+                        if let Err(e) = open_url(&Self::crash_issue_url(&report)) {
+                            self.set_status(format!("Could not open browser: {}", e));
+                        }
+                    }
+                    dismissed = ui.button("Dismiss").clicked();
+                });
+            });
+        if dismissed {
+            self.crash_report = None;
+            if let Err(e) = fs::remove_file(Self::crash_report_path()) {
+                self.set_status(format!("{}: {}", Self::crash_report_path().display(), e));
+            }
+        }
+    }
+
+    /// One crash log line: timestamp and the panic message on a single line
+    pub fn crash_log_entry(time: SystemTime, message: &str) -> String {
+        format!("{} {}", format_iso8601(time), message.lines().collect::<Vec<_>>().join(" | "))
+    }
+
+    /// Control loop behind [`ThermalApp::oneshot_mode`], with injectable sensor and actuator
+    fn run_control_loop(
+        duration: Duration,
+        interval: Duration,
+        mut read_temp: impl FnMut() -> f32,
+        mut control: impl FnMut(f32) -> String,
+        out: &mut impl io::Write,
+    ) -> i32 {
+        let start = Instant::now();
+        loop {
+            let temp = read_temp();
+            if ThermalZone::from_cpu_temp(temp) == ThermalZone::Critical {
+                let _ = writeln!(out, "{:.1}°C CRITICAL, stopping", temp);
+                return 2;
+            }
+            let _ = writeln!(out, "{:.1}°C {}", temp, control(temp));
+
+            let elapsed = start.elapsed();
+            if elapsed >= duration || Self::terminate_requested() {
+                return 0;
+            }
+            std::thread::sleep(interval.min(duration - elapsed));
+        }
+    }
+
+    /// Apply the AC profile after the adapter was connected
+    fn auto_mode_on_ac_connect(&mut self) {
+        if let Some(mode) = self.ac_mode {
+            if mode != self.state.mode && self.benchmark.is_none() && !self.low_battery_safe_mode {
+                self.change_mode(mode);
+            }
+        }
+    }
+
+    /// Apply the battery profile after the adapter was disconnected
+    fn auto_mode_on_battery(&mut self) {
+        if let Some(mode) = self.battery_mode {
+            if mode != self.state.mode && self.benchmark.is_none() && !self.low_battery_safe_mode {
+                self.change_mode(mode);
+            }
+        }
+    }
+
+    /// Change CPU mode on a background thread; the UI keeps running while
+    /// pkexec and the firmware respond. A request made while another change is
+    /// in flight runs after it.
+    fn change_mode(&mut self, mode: Mode) {
+        if !self.validate_action(ControlAction::SetMode(mode)) {
+            return;
+        }
+        if self.pending_mode_change.is_some() {
+            self.queued_mode_change = Some(mode);
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(set_mode(mode));
+        });
+        self.pending_mode_change = Some(mode);
+        self.mode_change_result = Some(rx);
+    }
+
+    /// Check `action` against the current state. Refused actions are reported in
+    /// the status bar; allowed ones replace the inline warning with their own.
+    fn validate_action(&mut self, action: ControlAction) -> bool {
+        let result = ThermalStateMachine {
+            state: &self.state,
+            auto_control: self.auto_control,
+            fan_boost: self.fan_boost_manual,
+            low_battery_pct: self.saved_config.low_battery_pct,
+        }
+        .validate(action);
+        if !result.allowed {
+            self.set_status(result.warning.unwrap_or_default());
+            return false;
+        }
+        self.control_warning = result.warning;
+        true
+    }
+
+    /// Apply the result of the mode change in flight, once it has arrived
+    fn finish_mode_change(&mut self) {
+        let (Some(mode), Some(rx)) = (self.pending_mode_change, &self.mode_change_result) else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                Err(ThermalError::InvalidData("mode change thread exited without a result".into()))
+            }
+        };
+        self.pending_mode_change = None;
+        self.mode_change_result = None;
+
+        // A benchmark counts from a successful switch to Performance and is
+        // abandoned if the switch fails
+        if mode == Mode::Performance && self.benchmark.is_some_and(|benchmark| benchmark.ends_at.is_none()) {
+            match (&result, &mut self.benchmark) {
+                (Ok(()), Some(benchmark)) => benchmark.start_timer(Instant::now()),
+                _ => self.end_benchmark(),
+            }
+        }
+
+        match result {
+            Ok(()) => {
+                // Our own change must not be reported as an external one
+                let previous = self.state.mode;
+                self.state.mode = mode;
+                if previous != mode {
+                    self.notify_plugins_of_mode_change(previous, mode);
+                }
+                self.session.mode_changes += 1;
+                self.record_action(RecordedStep::Mode(mode));
+                self.record_event(AlertKind::ModeChange, format!("Mode changed to {}", mode.label()));
+                self.status_message = Some((
+                    format!("Mode changed to {}", mode.label()),
+                    Instant::now(),
+                ));
+                self.update_state(true);
+            }
+            Err(e @ ThermalError::VerificationFailed { .. }) => {
+                self.set_status(e.to_string());
+                self.update_state(true);
+            }
+            Err(ThermalError::Io(e)) => self.handle_write_error(&e),
+            Err(e) => {
+                self.status_message = Some((
+                    format!("Error: {}", e),
+                    Instant::now(),
+                ));
+            }
+        }
+
+        if let Some(next) = self.queued_mode_change.take().filter(|&next| next != self.state.mode) {
+            self.change_mode(next);
+        }
+    }
+
+    /// Log a mode change made outside the app (TLP, auto-cpufreq, thermald,
+    /// a desktop power applet). These tools signal over D-Bus, but the resulting
+    /// profile is visible in sysfs, so the change is detected by comparing the
+    /// mode read this update with `previous`. The displayed mode follows the
+    /// fresh reading either way.
+    fn apply_profile_from_dbus_signal(&mut self, previous: Mode) {
+        if previous != self.state.mode {
+            self.notify_plugins_of_mode_change(previous, self.state.mode);
+        }
+        if let Some(message) = Self::external_mode_change(previous, self.state.mode, self.auto_control) {
+            self.set_status(message.clone());
+            self.notify(AlertKind::ModeChange, &message);
+            self.record_event(AlertKind::ModeChange, message);
+        }
+    }
+
+    /// Event text for a mode that changed between two reads; `None` when unchanged
+    /// or when auto-control (which switches modes itself) is on
+    fn external_mode_change(previous: Mode, current: Mode, auto_control: bool) -> Option<String> {
+        (previous != current && !auto_control)
+            .then(|| format!("External profile change detected: {} → {}", previous.label(), current.label()))
+    }
+
+    /// Report a failed write, switching to read-only mode on EPERM/EROFS
+    fn handle_write_error(&mut self, e: &io::Error) {
+        if is_read_only_error(e) {
+            self.enter_read_only_mode();
+        } else {
+            self.set_status(format!("Error: {}", e));
+        }
+    }
+
+    /// Why mode and auto-control buttons are disabled, if they are
+    fn controls_lock_reason(&self) -> Option<&'static str> {
+        if self.read_only_mode {
+            Some(READ_ONLY_HINT)
+        } else if self.low_battery_safe_mode {
+            Some(LOW_BATTERY_HINT)
+        } else if self.benchmark.is_some() {
+            Some("Benchmark in progress")
+        } else {
+            None
+        }
+    }
+
+    /// Rate to switch the screen to: [`CRITICAL_REFRESH_HZ`] when the Critical
+    /// zone starts with a faster screen, the saved rate once it ends
+    fn refresh_rate_transition(critical: bool, current: u32, saved: Option<u32>) -> Option<u32> {
+        match saved {
+            None if critical && current > CRITICAL_REFRESH_HZ => Some(CRITICAL_REFRESH_HZ),
+            Some(saved) if !critical => Some(saved),
+            _ => None,
+        }
+    }
+
+    /// Lower the screen refresh rate while auto-control sees the Critical zone,
+    /// since a fast panel keeps the GPU busy, and restore it afterwards
+    fn limit_refresh_rate_when_critical(&mut self) {
+        let Some(current) = self.screen_refresh.as_ref().map(|screen| screen.current) else {
+            return;
+        };
+        let critical = self.auto_control && self.state.thermal_zone() == ThermalZone::Critical;
+        let Some(hz) = Self::refresh_rate_transition(critical, current, self.pre_critical_refresh_hz) else {
+            return;
+        };
+        // Remembered even if the switch fails, so it is not retried every poll
+        self.pre_critical_refresh_hz = if critical { Some(current) } else { None };
+        self.set_refresh_rate(hz);
+    }
+
+    /// Switch the screen to `hz` and read back the rate it ended up at
+    fn set_refresh_rate(&mut self, hz: u32) {
+        match set_screen_refresh_rate(hz) {
+            Ok(()) => self.log_event(format!("Screen refresh rate set to {} Hz", hz)),
+            Err(e) => self.set_status(format!("Error: {}", e)),
+        }
+        self.screen_refresh = read_screen_refresh();
+    }
+
+    /// Screen refresh rate selector under the performance figures
+    fn render_refresh_rate(&mut self, ui: &mut egui::Ui) {
+        let Some(screen) = self.screen_refresh.clone() else {
+            return;
+        };
+        let mut selected = screen.current;
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Screen").size(10.0).color(egui::Color32::GRAY));
+            egui::ComboBox::from_id_salt("refresh_rate")
+                .width(70.0)
+                .selected_text(format!("{} Hz", screen.current))
+                .show_ui(ui, |ui| {
+                    for &rate in &screen.rates {
+                        ui.selectable_value(&mut selected, rate, format!("{} Hz", rate));
+                    }
+                })
+                .response
+                .on_hover_text(HelpText::get("refresh_rate"));
+            if self.pre_critical_refresh_hz.is_some() {
+                ui.label(egui::RichText::new("lowered: Critical zone").size(10.0).color(egui::Color32::from_rgb(255, 165, 0)));
+            }
+        });
+        if selected != screen.current {
+            self.set_refresh_rate(selected);
+        }
+    }
+
+    /// `Some(true)` to enter idle mode after `timeout` without input,
+    /// `Some(false)` to leave it once input arrives or the timeout is disabled
+    fn idle_transition(active: bool, idle_for: Duration, timeout: Option<Duration>) -> Option<bool> {
+        let timed_out = timeout.is_some_and(|timeout| idle_for >= timeout);
+        if !active && timed_out {
+            Some(true)
+        } else if active && !timed_out {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Switch to Quiet after `idle_timeout_mins` without keyboard or mouse
+    /// input and restore the previous mode on the next input. Input is seen
+    /// through the window's events and the built-in keyboard/touchpad
+    /// interrupts only, which is why the timeout is off unless configured.
+    fn schedule_idle_mode_switch(&mut self, ctx: &egui::Context) {
+        let input = ctx.input(|i| {
+            i.pointer.is_moving()
+                || i.pointer.any_down()
+                || i.raw_scroll_delta != egui::Vec2::ZERO
+                || i.events.iter().any(|event| matches!(event, egui::Event::Key { .. } | egui::Event::Text(_)))
+        });
+        if input {
+            self.idle_since = None;
+        }
+        let idle_since = *self.idle_since.get_or_insert_with(Instant::now);
+        let timeout = self.saved_config.idle_timeout_mins.map(|mins| Duration::from_secs(mins as u64 * 60));
+        match Self::idle_transition(self.pre_idle_mode.is_some(), idle_since.elapsed(), timeout) {
+            Some(true) => {
+                // Other overrides own the mode; Quiet needs no switch
+                let mode = self.state.mode;
+                if mode == Mode::Quiet || self.controls_lock_reason().is_some() || self.pending_mode_change.is_some() {
+                    return;
+                }
+                self.pre_idle_mode = Some(mode);
+                self.change_mode(Mode::Quiet);
+                self.log_event(format!("Idle for {} min, switched to Quiet", timeout.unwrap_or_default().as_secs() / 60));
+            }
+            Some(false) => {
+                let Some(mode) = self.pre_idle_mode.take() else {
+                    return;
+                };
+                // Leave the mode alone if something else changed it meanwhile
+                let quiet = self.state.mode == Mode::Quiet || self.pending_mode_change == Some(Mode::Quiet);
+                if quiet && mode != Mode::Unknown && self.controls_lock_reason().is_none() {
+                    self.change_mode(mode);
+                    self.log_event(format!("Input detected, {} restored", mode.label()));
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// `Some(true)` to enter low-battery safe mode, `Some(false)` to leave it.
+    /// Entering happens below `threshold`, leaving above LOW_BATTERY_RESTORE_PCT
+    /// (or the threshold, if set higher) so the mode does not flap.
+    fn low_battery_transition(active: bool, battery_pct: Option<u8>, threshold: u8) -> Option<bool> {
+        let pct = battery_pct?;
+        if !active && pct < threshold {
+            Some(true)
+        } else if active && pct > LOW_BATTERY_RESTORE_PCT.max(threshold) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Force Quiet mode without fan boost while the battery is low, overriding
+    /// the user and auto-control; restore the previous settings once it recovers
+    fn low_battery_safe_mode(&mut self) {
+        match Self::low_battery_transition(
+            self.low_battery_safe_mode,
+            self.state.battery_pct,
+            self.saved_config.low_battery_pct,
+        ) {
+            Some(true) => {
+                if self.benchmark.is_some() {
+                    self.end_benchmark();
+                }
+                self.low_battery_saved = Some((self.state.mode, self.fan_boost_manual, self.auto_control));
+                self.low_battery_safe_mode = true;
+                self.auto_control = false;
+                if self.fan_boost_manual || self.state.fan_boost {
+                    self.fan_boost_manual = false;
+                    if let Err(e) = set_fan_boost(false) {
+                        self.handle_write_error(&e);
+                    }
+                }
+                if self.state.mode != Mode::Quiet {
+                    self.change_mode(Mode::Quiet);
+                }
+                self.log_event(LOW_BATTERY_HINT.into());
+            }
+            Some(false) => {
+                self.low_battery_safe_mode = false;
+                if let Some((mode, fan_boost, auto_control)) = self.low_battery_saved.take() {
+                    self.auto_control = auto_control;
+                    if fan_boost {
+                        match set_fan_boost(true) {
+                            Ok(()) => self.fan_boost_manual = true,
+                            Err(e) => self.handle_write_error(&e),
+                        }
+                    }
+                    if mode != Mode::Unknown && mode != self.state.mode {
+                        self.change_mode(mode);
+                    }
+                }
+                self.log_event("Battery recovered, previous settings restored".into());
+            }
+            None => {}
+        }
+    }
+
+    /// Disable all controls after sysfs turned out to be read-only
+    fn enter_read_only_mode(&mut self) {
+        self.read_only_mode = true;
+        if self.auto_control {
+            self.auto_control = false;
+            self.set_status("Auto control disabled: system is in read-only mode".into());
+        } else {
+            self.set_status(READ_ONLY_HINT.into());
+        }
+    }
+
+    /// Start or cancel benchmark mode: Performance + fan boost for a fixed
+    /// duration, counted from when Performance mode was applied
+    fn benchmark_mode(&mut self) {
+        if self.benchmark.is_some() {
+            self.end_benchmark();
+            self.set_status("Benchmark cancelled".into());
+            return;
+        }
+
+        let minutes = match self.benchmark_minutes.trim().parse::<u64>() {
+            Ok(m) if BENCHMARK_MINUTES_RANGE.contains(&m) => m,
+            _ => {
+                self.set_status("Benchmark duration must be 1-120 minutes".into());
+                return;
+            }
+        };
+
+        self.benchmark = Some(Benchmark {
+            saved_mode: self.state.mode,
+            saved_fan_boost: self.fan_boost_manual,
+            saved_auto_control: self.auto_control,
+            duration: Duration::from_secs(minutes * 60),
+            ends_at: None,
+        });
+        self.auto_control = false;
+        if let Err(e) = set_fan_boost(true) {
+            self.end_benchmark();
+            self.handle_write_error(&e);
+            return;
+        }
+        self.fan_boost_manual = true;
+        // The countdown starts in `finish_mode_change` once the mode is applied
+        self.change_mode(Mode::Performance);
+        if self.pending_mode_change.is_none() && self.queued_mode_change != Some(Mode::Performance) {
+            // Refused; `validate_action` already said why
+            self.end_benchmark();
+        }
+    }
+
+    /// Restore the mode, fan boost and auto-control saved when benchmark mode started
+    fn end_benchmark(&mut self) {
+        let Some(benchmark) = self.benchmark.take() else {
+            return;
+        };
+        self.auto_control = benchmark.saved_auto_control;
+        self.fan_boost_manual = benchmark.saved_fan_boost;
+        if let Err(e) = set_fan_boost(benchmark.saved_fan_boost) {
+            self.handle_write_error(&e);
+        }
+        // A switch to Performance may still be in flight; this queues behind it
+        if let Some(mode) = benchmark.mode_to_restore() {
+            if mode != self.state.mode || self.pending_mode_change.is_some() {
+                self.change_mode(mode);
+            }
+        }
+    }
+
+    /// Remaining benchmark time, once the benchmark countdown has started
+    fn benchmark_remaining(&self) -> Option<Duration> {
+        self.benchmark.and_then(|benchmark| benchmark.remaining(Instant::now()))
+    }
+
+    /// Start measuring this app's own CPU usage over OVERHEAD_BENCHMARK_SECS
+    fn benchmark_polling_overhead(&mut self) {
+        match read_self_cpu_ticks() {
+            Ok(ticks) => {
+                self.overhead_benchmark = Some((Instant::now(), ticks));
+                self.overhead_pct = None;
+                self.set_status(format!("Measuring monitor overhead for {}s", OVERHEAD_BENCHMARK_SECS));
+            }
+            Err(e) => self.set_status(format!("Error: {}", e)),
+        }
+    }
+
+    /// Finish the overhead measurement once its duration has elapsed
+    fn check_overhead_benchmark(&mut self) {
+        let Some((started, start_ticks)) = self.overhead_benchmark else {
+            return;
+        };
+        if started.elapsed() < Duration::from_secs(OVERHEAD_BENCHMARK_SECS) {
+            return;
+        }
+        self.overhead_benchmark = None;
+        match read_self_cpu_ticks() {
+            Ok(ticks) => {
+                let pct = cpu_percent(ticks.saturating_sub(start_ticks), started.elapsed());
+                self.overhead_pct = Some(pct);
+                self.set_status(format!("Monitor CPU usage: {:.1}%", pct));
+            }
+            Err(e) => self.set_status(format!("Error: {}", e)),
+        }
+    }
+
+    /// Write a gnuplot script of the history to GNUPLOT_EXPORT_PATH
+    fn export_gnuplot(&mut self) {
+        match self.history.to_gnuplot_script(Path::new(GNUPLOT_EXPORT_PATH), self.target_temp) {
+            Ok(()) => self.set_status(format!("Gnuplot script written to {}", GNUPLOT_EXPORT_PATH)),
+            Err(e) => self.set_status(format!("Error: {}", e)),
+        }
+    }
+
+    /// Write an interactive HTML graph of the history to HTML_EXPORT_PATH
+    fn export_html(&mut self) {
+        match self.history.export_plotly_html(Path::new(HTML_EXPORT_PATH), self.target_temp) {
+            Ok(()) => self.set_status(format!("HTML graph written to {}", HTML_EXPORT_PATH)),
+            Err(e) => self.set_status(format!("Error: {}", e)),
+        }
+    }
+
+    /// Write the history as InfluxDB line protocol to INFLUXDB_EXPORT_PATH,
+    /// ready for `influx write` or the HTTP write API
+    fn export_influxdb(&mut self) {
+        match self.history.export_influxdb(Path::new(INFLUXDB_EXPORT_PATH)) {
+            Ok(()) => self.set_status(format!("InfluxDB line protocol written to {}", INFLUXDB_EXPORT_PATH)),
+            Err(e) => self.set_status(format!("Error: {}", e)),
+        }
+    }
+
+    /// Write the history as a NumPy array to NPY_EXPORT_PATH
+    #[cfg(feature = "ndarray")]
+    fn export_npy(&mut self) {
+        match fs::write(NPY_EXPORT_PATH, self.history.as_npy_bytes()) {
+            Ok(()) => self.set_status(format!("NumPy array written to {}", NPY_EXPORT_PATH)),
+            Err(e) => self.set_status(format!("Error: {}", e)),
+        }
+    }
+
+    /// Whether the system accessibility settings force a high-contrast theme
+    pub fn accessibility_high_contrast_forced() -> bool {
+        read_system_high_contrast()
+    }
+
+    /// Dark theme with white text and outlines on black
+    fn high_contrast_visuals() -> egui::Visuals {
+        let mut visuals = egui::Visuals::dark();
+        visuals.override_text_color = Some(egui::Color32::WHITE);
+        visuals.panel_fill = egui::Color32::BLACK;
+        visuals.window_fill = egui::Color32::BLACK;
+        visuals.extreme_bg_color = egui::Color32::BLACK;
+        visuals.faint_bg_color = egui::Color32::from_gray(30);
+        visuals.hyperlink_color = egui::Color32::YELLOW;
+        visuals.selection.bg_fill = egui::Color32::from_rgb(0, 90, 200);
+        visuals.selection.stroke = egui::Stroke::new(2.0, egui::Color32::WHITE);
+        for widget in [
+            &mut visuals.widgets.noninteractive,
+            &mut visuals.widgets.inactive,
+            &mut visuals.widgets.hovered,
+            &mut visuals.widgets.active,
+            &mut visuals.widgets.open,
+        ] {
+            widget.bg_stroke = egui::Stroke::new(1.5, egui::Color32::WHITE);
+            widget.fg_stroke = egui::Stroke::new(1.5, egui::Color32::WHITE);
+        }
+        visuals.widgets.hovered.bg_stroke = egui::Stroke::new(2.0, egui::Color32::YELLOW);
+        visuals
+    }
+
+    /// Load ideapad-laptop and check again
+    fn load_driver(&mut self) {
+        match load_ideapad_driver() {
+            Ok(()) => {
+                self.driver_status = ideapad_acpi_driver_check().ok();
+                self.set_status("ideapad-laptop driver loaded".into());
+            }
+            Err(e) => self.set_status(format!("Error: {}", e)),
+        }
+    }
+
+    /// Write the history to CSV_EXPORT_PATH
+    fn export_csv(&mut self) {
+        match fs::File::create(CSV_EXPORT_PATH).and_then(|mut file| self.history.export_csv(&mut file)) {
+            Ok(()) => self.set_status(format!("History exported to {}", CSV_EXPORT_PATH)),
+            Err(e) => self.set_status(format!("Error: {}", e)),
+        }
+    }
+
+    /// Put the history saved by "Export CSV" before the live samples
+    fn import_csv(&mut self) {
+        let imported = fs::read_to_string(CSV_EXPORT_PATH)
+            .and_then(|data| TemperatureHistory::from_csv(&data, HISTORY_CAPACITY));
+        self.merge_imported_history(CSV_EXPORT_PATH, imported);
+    }
+
+    /// Write the history as a protobuf message to PROTOBUF_EXPORT_PATH
+    #[cfg(feature = "protobuf")]
+    fn export_protobuf(&mut self) {
+        match fs::write(PROTOBUF_EXPORT_PATH, self.history.as_protobuf()) {
+            Ok(()) => self.set_status(format!("Protobuf history written to {}", PROTOBUF_EXPORT_PATH)),
+            Err(e) => self.set_status(format!("Error: {}", e)),
+        }
+    }
+
+    /// Put the history saved by "Export Protobuf" before the live samples
+    #[cfg(feature = "protobuf")]
+    fn import_protobuf(&mut self) {
+        let imported = fs::read(PROTOBUF_EXPORT_PATH).and_then(|data| TemperatureHistory::from_protobuf(&data));
+        self.merge_imported_history(PROTOBUF_EXPORT_PATH, imported);
+    }
+
+    /// Prepend a history read from `path`, see [`TemperatureHistory::append`]
+    fn merge_imported_history(&mut self, path: &str, imported: io::Result<TemperatureHistory>) {
+        match imported {
+            Ok(imported) => {
+                let before = self.history.len();
+                self.history.append(&imported);
+                self.smoothed_history = None;
+                let added = self.history.len().saturating_sub(before);
+                self.set_status(format!("Imported {} samples from {}", added, path));
+            }
+            Err(e) => self.set_status(format!("Error: {}", e)),
+        }
+    }
+
+    /// Import ftrace thermal events as a comparison history
+    fn import_trace_comparison(&mut self) {
+        let series = ThermalState::read_from_procfs_trace(std::path::Path::new(FTRACE_PATH));
+        if series.is_empty() {
+            self.set_status("No thermal_temperature events in trace (needs root and the event enabled)".into());
+            return;
+        }
+
+        let history = TemperatureHistory::from_time_series(
+            &series,
+            Duration::from_secs_f32(UPDATE_INTERVAL_SECS),
+            HISTORY_CAPACITY,
+            self.state.ambient_temp,
+        );
+        if history.validate_monotone_timestamps() {
+            self.set_status(format!("Imported {} trace events", series.len()));
+        } else {
+            self.set_status(format!("Imported {} trace events (warning: timestamps out of order)", series.len()));
+        }
+        self.comparison_history = Some(history);
+    }
+
+    /// Generate and install udev rules so controls work without root
+    fn udev_rule_generator(&mut self) {
+        let paths = discover_writable_paths();
+        if paths.is_empty() {
+            self.set_status("No writable sysfs paths found".into());
+            return;
+        }
+
+        let rules = udev_rules_content(&paths, detect_udev_group());
+        match install_udev_rules(&rules) {
+            Ok(()) => self.set_status(format!("udev rules written to {}", UDEV_RULES_PATH)),
+            Err(e) => self.set_status(format!("Error: {}", e)),
+        }
+    }
+
+    /// Set status message
+    fn set_status(&mut self, msg: String) {
+        self.status_message = Some((msg, Instant::now()));
+    }
+
+    /// Get zone color as egui Color32
+    fn zone_color(zone: ThermalZone) -> egui::Color32 {
+        let (r, g, b) = zone.color_rgb();
+        egui::Color32::from_rgb(r, g, b)
+    }
+
+    /// Green with plenty of throttle headroom, yellow from 15°C, red under 5°C
+    fn headroom_color(headroom: f32) -> egui::Color32 {
+        if headroom > 15.0 {
+            egui::Color32::from_rgb(100, 220, 100)
+        } else if headroom >= THROTTLE_WARNING_HEADROOM {
+            egui::Color32::from_rgb(255, 200, 0)
+        } else {
+            egui::Color32::from_rgb(255, 100, 100)
+        }
+    }
+
+    /// Whether to show the throttling warning: in the Critical zone, or close
+    /// to the firmware's passive trip point whatever the zone
+    fn throttle_warning_needed(zone: ThermalZone, headroom: Option<f32>) -> bool {
+        zone == ThermalZone::Critical || headroom.is_some_and(|headroom| headroom < THROTTLE_WARNING_HEADROOM)
+    }
+
+    /// Warning drawn over the window while the CPU is about to throttle
+    fn render_throttle_warning(&self, ctx: &egui::Context) {
+        if !Self::throttle_warning_needed(self.state.thermal_zone(), self.throttle_headroom) {
+            return;
+        }
+        let text = match self.throttle_headroom {
+            Some(headroom) => format!(
+                "⚠ CPU about to throttle: {:.0}{} headroom",
+                to_display_delta(headroom.max(0.0), self.temperature_unit),
+                self.temperature_unit.label()
+            ),
+            None => "⚠ CPU about to throttle".to_string(),
+        };
+        egui::Area::new(egui::Id::new("throttle_warning"))
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 40.0])
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::window(ui.style()).fill(egui::Color32::from_rgb(120, 20, 20)).show(ui, |ui| {
+                    ui.label(egui::RichText::new(text).size(14.0).color(egui::Color32::WHITE).strong());
+                });
+            });
+    }
+
+    /// Get mode color
+    fn mode_color(mode: Mode) -> egui::Color32 {
+        match mode {
+            Mode::Performance => egui::Color32::from_rgb(255, 100, 100),
+            Mode::Comfort => egui::Color32::from_rgb(100, 200, 255),
+            Mode::Balanced => egui::Color32::from_rgb(150, 220, 100),
+            Mode::Quiet => egui::Color32::from_rgb(180, 180, 220),
+            Mode::Auto => egui::Color32::from_rgb(255, 200, 100),
+            Mode::Unknown => egui::Color32::GRAY,
+        }
+    }
+
+
+    /// Render temperatures - adaptive version
+    fn render_temperatures_adaptive(&self, ui: &mut egui::Ui, is_medium: bool) {
+        let zone = self.state.thermal_zone();
+        let color = Self::zone_color(zone);
+        let font_size = if is_medium { 24.0 } else { 18.0 };
+        let label_size = if is_medium { 11.0 } else { 9.0 };
+
+        ui.horizontal_wrapped(|ui| {
+            // CPU
+            with_help(ui, HelpText::get("cpu_temp"), |ui| {
+                ui.vertical(|ui| {
+                    ui.label(egui::RichText::new("CPU").size(label_size).color(egui::Color32::GRAY));
+                    ui.label(egui::RichText::new(format_temp(self.state.cpu_temp, self.temperature_unit))
+                        .size(font_size).color(color).strong());
+                });
+            });
+            ui.add_space(10.0);
+            // Keyboard
+            with_help(ui, HelpText::get("kbd_temp"), |ui| {
+                ui.vertical(|ui| {
+                    ui.label(egui::RichText::new("KBD").size(label_size).color(egui::Color32::GRAY));
+                    ui.label(egui::RichText::new(format_temp(self.state.keyboard_temp, self.temperature_unit))
+                        .size(font_size).color(color).strong());
+                });
+            });
+            ui.add_space(10.0);
+            // Package and hottest core, when coretemp reports them
+            for (label, temp, help) in [
+                ("Package", self.state.cpu_package_temp, "cpu_package_temp"),
+                ("Core max", self.state.cpu_core_max_temp, "cpu_core_max_temp"),
+            ] {
+                let Some(temp) = temp else {
+                    continue;
+                };
+                with_help(ui, HelpText::get(help), |ui| {
+                    ui.vertical(|ui| {
+                        ui.label(egui::RichText::new(label).size(label_size).color(egui::Color32::GRAY));
+                        ui.label(egui::RichText::new(format_temp(temp, self.temperature_unit))
+                            .size(label_size + 2.0).color(Self::zone_color(ThermalZone::from_cpu_temp(temp))));
+                    });
+                });
+                ui.add_space(10.0);
+            }
+            // Zone label
+            with_help(ui, HelpText::get("zone"), |ui| {
+                ui.vertical(|ui| {
+                    ui.label(egui::RichText::new("Zone").size(label_size).color(egui::Color32::GRAY));
+                    ui.label(egui::RichText::new(zone.label()).size(label_size + 2.0).color(color));
+                });
+            });
+            ui.add_space(10.0);
+            // Throttle headroom
+            if let Some(headroom) = self.throttle_headroom {
+                with_help(ui, HelpText::get("throttle_headroom"), |ui| {
+                    ui.vertical(|ui| {
+                        ui.label(egui::RichText::new("Headroom").size(label_size).color(egui::Color32::GRAY));
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "{:.0}{}",
+                                to_display_delta(headroom, self.temperature_unit),
+                                self.temperature_unit.label()
+                            ))
+                            .size(label_size + 2.0)
+                            .color(Self::headroom_color(headroom)),
+                        );
+                    });
+                });
+            }
+        });
+
+        // Heating trend prediction
+        if let Some(remaining) = self.time_to_threshold.filter(|d| !d.is_zero()) {
+            let secs = remaining.as_secs();
+            let eta = if secs >= 60 { format!("~{} min", secs / 60) } else { format!("~{} s", secs) };
+            ui.label(
+                egui::RichText::new(format!("{} to {}", eta, format_temp(PREDICTION_THRESHOLD, self.temperature_unit)))
+                    .size(label_size)
+                    .color(egui::Color32::GRAY)
+                    .italics(),
+            );
+        }
+    }
+
+    /// Horizontal position of `temp` on a legend bar starting at `left`, clamped to LEGEND_RANGE
+    fn legend_x(temp: f32, left: f32, width: f32) -> f32 {
+        let (min, max) = (*LEGEND_RANGE.start(), *LEGEND_RANGE.end());
+        left + (temp.clamp(min, max) - min) / (max - min) * width
+    }
+
+    /// Render the zone color scale with boundary ticks and an arrow at the current temperature
+    fn render_zone_legend(&self, ui: &mut egui::Ui, is_medium: bool) {
+        let bar_height = 8.0;
+        let label_size = if is_medium { 9.0 } else { 8.0 };
+        let width = ui.available_width().min(320.0);
+        let (response, painter) =
+            ui.allocate_painter(egui::vec2(width, bar_height + 24.0), egui::Sense::hover());
+        let rect = response.rect;
+        let (left, top) = (rect.left(), rect.top() + 6.0);
+        let bottom = top + bar_height;
+
+        // Gradient through each zone's color at the middle of its (clamped) range
+        let mut stops = vec![(*LEGEND_RANGE.start(), Self::zone_color(ThermalZone::Cool))];
+        for zone in ThermalZone::all() {
+            let (low, high) = zone.temp_range();
+            let low = low.max(*LEGEND_RANGE.start());
+            let high = high.min(*LEGEND_RANGE.end());
+            stops.push(((low + high) / 2.0, Self::zone_color(*zone)));
+        }
+        stops.push((*LEGEND_RANGE.end(), Self::zone_color(ThermalZone::Critical)));
+
+        let mut mesh = egui::Mesh::default();
+        for (i, (temp, color)) in stops.iter().enumerate() {
+            let x = Self::legend_x(*temp, left, width);
+            mesh.colored_vertex(egui::pos2(x, top), *color);
+            mesh.colored_vertex(egui::pos2(x, bottom), *color);
+            if i > 0 {
+                let v = (i * 2) as u32;
+                mesh.add_triangle(v - 2, v - 1, v);
+                mesh.add_triangle(v - 1, v, v + 1);
+            }
+        }
+        painter.add(egui::Shape::mesh(mesh));
+
+        // Ticks and labels at zone boundaries
+        for zone in &ThermalZone::all()[1..] {
+            let (boundary, _) = zone.temp_range();
+            let x = Self::legend_x(boundary, left, width);
+            painter.line_segment(
+                [egui::pos2(x, top), egui::pos2(x, bottom + 3.0)],
+                egui::Stroke::new(1.0, egui::Color32::DARK_GRAY),
+            );
+            painter.text(
+                egui::pos2(x, bottom + 4.0),
+                egui::Align2::CENTER_TOP,
+                format!("{:.0}", boundary),
+                egui::FontId::proportional(label_size),
+                egui::Color32::GRAY,
+            );
+        }
+
+        // Arrow pointing down at the current temperature
+        let x = Self::legend_x(self.state.cpu_temp, left, width);
+        painter.add(egui::Shape::convex_polygon(
+            vec![egui::pos2(x - 4.0, top - 6.0), egui::pos2(x + 4.0, top - 6.0), egui::pos2(x, top)],
+            egui::Color32::WHITE,
+            egui::Stroke::NONE,
+        ));
+
+        let zones = ThermalZone::all().iter().map(|z| z.label()).collect::<Vec<_>>().join(" → ");
+        response.on_hover_text(zones);
+    }
+
+    /// Hypothetical surface temperature at (`x`, `y`) on the keyboard, both
+    /// fractions from the top left. Heat spreads from [`KEYBOARD_HOTSPOT`], so
+    /// the middle runs hotter than `keyboard_temp` and the edges cooler.
+    fn keyboard_surface_temp(keyboard_temp: f32, ambient_temp: f32, x: f32, y: f32) -> f32 {
+        let (hot_x, hot_y) = KEYBOARD_HOTSPOT;
+        // 0 at the hotspot, 1 at the far corners (the keyboard is about twice as wide as deep)
+        let distance = ((x - hot_x).powi(2) + ((y - hot_y) / 2.0).powi(2)).sqrt() / 0.6;
+        ambient_temp + (keyboard_temp - ambient_temp) * (1.3 - 0.6 * distance.min(1.0))
+    }
+
+    /// Heatmap color of a surface temperature: blue when cool, through yellow,
+    /// to red at the top of [`KEYBOARD_HEAT_RANGE`]
+    fn keyboard_heat_color(temp: f32) -> egui::Color32 {
+        let (min, max) = (*KEYBOARD_HEAT_RANGE.start(), *KEYBOARD_HEAT_RANGE.end());
+        let t = ((temp - min) / (max - min)).clamp(0.0, 1.0);
+        let lerp = |a: (u8, u8, u8), b: (u8, u8, u8), t: f32| {
+            let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+            egui::Color32::from_rgb(channel(a.0, b.0), channel(a.1, b.1), channel(a.2, b.2))
+        };
+        let (cool, warm, hot) =
+            (ThermalZone::Cool.color_rgb(), ThermalZone::Warm.color_rgb(), ThermalZone::Critical.color_rgb());
+        if t < 0.5 {
+            lerp(cool, warm, t * 2.0)
+        } else {
+            lerp(warm, hot, t * 2.0 - 1.0)
+        }
+    }
+
+    /// Schematic keyboard shaded by [`ThermalApp::keyboard_surface_temp`]. Only
+    /// the single keyboard estimate is real; the spread across keys is an
+    /// illustration of where a laptop usually gets warm, not per-key data.
+    fn render_keyboard_heatmap(&self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new(egui::RichText::new("Keyboard heatmap").size(11.0))
+            .default_open(false)
+            .show(ui, |ui| {
+                // Ease between polls instead of jumping every 2 seconds
+                let keyboard_temp = ui.ctx().animate_value_with_time(
+                    egui::Id::new("keyboard_heatmap"),
+                    self.state.keyboard_temp,
+                    UPDATE_INTERVAL_SECS,
+                );
+                let width = ui.available_width().min(300.0);
+                let unit = width / 15.0;
+                // The function row is shorter than the others
+                let row_height = |row: usize| if row == 0 { 0.6 * unit } else { unit };
+                let height: f32 = (0..KEYBOARD_ROWS.len()).map(row_height).sum();
+
+                with_help(ui, HelpText::get("keyboard_heatmap"), |ui| {
+                    let (response, painter) = ui.allocate_painter(egui::vec2(width, height), egui::Sense::hover());
+                    let origin = response.rect.min;
+                    let mut top = 0.0;
+                    for (row, keys) in KEYBOARD_ROWS.iter().enumerate() {
+                        let mut left = 0.0;
+                        for &keys_wide in keys.iter() {
+                            let key = egui::Rect::from_min_size(
+                                origin + egui::vec2(left, top),
+                                egui::vec2(keys_wide * unit - 1.5, row_height(row) - 1.5),
+                            );
+                            let center = key.center();
+                            let temp = Self::keyboard_surface_temp(
+                                keyboard_temp,
+                                self.state.ambient_temp,
+                                (center.x - origin.x) / width,
+                                (center.y - origin.y) / height,
+                            );
+                            painter.rect_filled(key, 2.0, Self::keyboard_heat_color(temp));
+                            left += keys_wide * unit;
+                        }
+                        top += row_height(row);
+                    }
+                });
+                ui.label(
+                    egui::RichText::new("Illustration from the keyboard estimate, not per-key readings")
+                        .size(9.0)
+                        .color(egui::Color32::GRAY)
+                        .italics(),
+                );
+            });
+    }
+
+    /// Start and sweep angle (radians) of each pie slice, proportional to usage
+    fn pie_angles(usage: &[(String, f32)]) -> Vec<(f32, f32)> {
+        let total: f32 = usage.iter().map(|(_, pct)| pct).sum();
+        if total <= 0.0 {
+            return Vec::new();
+        }
+
+        let mut start = -std::f32::consts::FRAC_PI_2;
+        usage
+            .iter()
+            .map(|(_, pct)| {
+                let sweep = pct / total * std::f32::consts::TAU;
+                let slice = (start, sweep);
+                start += sweep;
+                slice
+            })
+            .collect()
+    }
+
+    /// Render a pie chart of CPU usage for the top processes with a legend
+    fn render_process_pie(&self, ui: &mut egui::Ui, is_medium: bool) {
+        if self.process_usage.is_empty() {
+            return;
+        }
+        let radius = if is_medium { 32.0 } else { 24.0 };
+        let label_size = if is_medium { 11.0 } else { 9.0 };
+
+        with_help(ui, HelpText::get("process_pie"), |ui| {
+            ui.horizontal(|ui| {
+                let (response, painter) =
+                    ui.allocate_painter(egui::vec2(radius * 2.0, radius * 2.0), egui::Sense::hover());
+                let center = response.rect.center();
+                for (i, (start, sweep)) in Self::pie_angles(&self.process_usage).into_iter().enumerate() {
+                    // Split into small fan segments so each polygon stays convex
+                    let steps = ((sweep / 0.1).ceil() as usize).max(1);
+                    let arc: Vec<egui::Pos2> = (0..=steps)
+                        .map(|step| {
+                            let angle = start + sweep * step as f32 / steps as f32;
+                            egui::pos2(center.x + radius * angle.cos(), center.y + radius * angle.sin())
+                        })
+                        .collect();
+                    for edge in arc.windows(2) {
+                        painter.add(egui::Shape::convex_polygon(
+                            vec![center, edge[0], edge[1]],
+                            PIE_COLORS[i % PIE_COLORS.len()],
+                            egui::Stroke::NONE,
+                        ));
+                    }
+                }
+
+                ui.vertical(|ui| {
+                    for (i, (name, pct)) in self.process_usage.iter().enumerate() {
+                        ui.label(
+                            egui::RichText::new(format!("■ {} {:.0}%", name, pct))
+                                .size(label_size)
+                                .color(PIE_COLORS[i % PIE_COLORS.len()]),
+                        );
+                    }
+                });
+            });
+        });
+    }
+
+    /// Render performance - adaptive version
+    fn render_performance_adaptive(&self, ui: &mut egui::Ui, is_medium: bool) {
+        let font_size = if is_medium { 20.0 } else { 16.0 };
+        let label_size = if is_medium { 11.0 } else { 9.0 };
+        let mode_color = Self::mode_color(self.state.mode);
+
+        ui.horizontal_wrapped(|ui| {
+            with_help(ui, HelpText::get("perf_pct"), |ui| {
+                ui.vertical(|ui| {
+                    ui.label(egui::RichText::new("Perf").size(label_size).color(egui::Color32::GRAY));
+                    ui.label(egui::RichText::new(format!("{}%", self.state.perf_pct))
+                        .size(font_size).strong());
+                });
+            });
+            ui.add_space(10.0);
+            with_help(ui, HelpText::get("freq"), |ui| {
+                ui.vertical(|ui| {
+                    ui.label(egui::RichText::new("Freq").size(label_size).color(egui::Color32::GRAY));
+                    ui.label(egui::RichText::new(format!("{:.1}G", self.state.current_freq_ghz()))
+                        .size(font_size).strong());
+                });
+            });
+            ui.add_space(10.0);
+            with_help(ui, HelpText::get("mode"), |ui| {
+                ui.vertical(|ui| {
+                    ui.label(egui::RichText::new("Mode").size(label_size).color(egui::Color32::GRAY));
+                    ui.label(egui::RichText::new(self.state.mode.label())
+                        .size(label_size + 2.0).color(mode_color).strong());
+                });
+            });
+            #[cfg(feature = "intel-rapl")]
+            if let Some(tdp) = self.state.tdp {
+                ui.add_space(10.0);
+                ui.vertical(|ui| {
+                    ui.label(egui::RichText::new("Power").size(label_size).color(egui::Color32::GRAY))
+                        .on_hover_text(HelpText::get("power"));
+                    ui.label(egui::RichText::new(format!("{:.1}W", tdp.package_watts))
+                        .size(font_size).strong())
+                        .on_hover_text(format!(
+                            "Core {:.1}W, DRAM {:.1}W, 2-min avg {:.1}W",
+                            tdp.core_watts,
+                            tdp.dram_watts,
+                            self.history.avg_power().unwrap_or(tdp.package_watts)
+                        ));
+                });
+            }
+        });
+
+        with_help(ui, HelpText::get("mem_used"), |ui| {
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new("Mem").size(label_size).color(egui::Color32::GRAY));
+                ui.add(
+                    egui::ProgressBar::new(self.state.mem_used_pct / 100.0)
+                        .desired_width(120.0)
+                        .desired_height(label_size + 2.0)
+                        .text(egui::RichText::new(format!("{:.0}%", self.state.mem_used_pct)).size(label_size)),
+                );
+            });
+        });
+    }
+
+    /// Collapsible list of logged events, newest first
+    fn render_event_log(&self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new(egui::RichText::new(format!("Event log ({})", self.event_log.len())).size(11.0))
+            .default_open(false)
+            .show(ui, |ui| {
+                if self.event_log.is_empty() {
+                    ui.label(egui::RichText::new("No events yet").size(10.0).color(egui::Color32::GRAY));
+                }
+                for entry in self.event_log.iter().rev() {
+                    ui.label(
+                        egui::RichText::new(format!("{}  {}", format_iso8601(entry.time), entry.message))
+                            .size(10.0)
+                            .monospace(),
+                    );
+                }
+            })
+            .header_response
+            .on_hover_text(HelpText::get("event_log"));
+    }
+
+    /// Die map: one square per core colored by its zone, 2 per row, scrolling
+    /// beyond TOPOLOGY_VISIBLE_CORES. Clicking a core toggles its plot line.
+    fn render_cpu_topology(&mut self, ui: &mut egui::Ui) {
+        if self.state.per_core_temps.is_empty() {
+            return;
+        }
+        let cell_height = 22.0;
+        let row_height = cell_height + 4.0;
+        let mut toggled = None;
+
+        ui.label(egui::RichText::new("Cores").size(10.0).color(egui::Color32::GRAY))
+            .on_hover_text(HelpText::get("cpu_topology"));
+        egui::ScrollArea::vertical()
+            .id_salt("cpu_topology")
+            .max_height(row_height * (TOPOLOGY_VISIBLE_CORES / 2) as f32)
+            .show(ui, |ui| {
+                let spacing = ui.spacing().item_spacing.x;
+                let width = (ui.available_width() - 4.0) / 2.0;
+                let cell_width = width * 0.6;
+                let sparkline_width = width - cell_width - spacing * 2.0;
+                for (row, temps) in self.state.per_core_temps.chunks(2).enumerate() {
+                    ui.horizontal(|ui| {
+                        for (col, &temp) in temps.iter().enumerate() {
+                            let index = row * 2 + col;
+                            let (rect, response) =
+                                ui.allocate_exact_size(egui::vec2(cell_width, cell_height), egui::Sense::click());
+                            let painter = ui.painter();
+                            painter.rect_filled(rect, 3.0, Self::zone_color(ThermalZone::from_cpu_temp(temp)));
+                            let core = self.cores.get(index);
+                            if core.is_some_and(|core| core.plotted) {
+                                painter.rect_stroke(rect, 3.0, egui::Stroke::new(2.0, egui::Color32::WHITE));
+                            }
+                            painter.text(
+                                rect.center(),
+                                egui::Align2::CENTER_CENTER,
+                                format!("{}  {}", index, format_temp(temp, self.temperature_unit)),
+                                egui::FontId::proportional(11.0),
+                                egui::Color32::BLACK,
+                            );
+                            let recent: Vec<f32> = core.map_or_else(Vec::new, |core| {
+                                core.temps.iter().skip(core.temps.len().saturating_sub(CORE_SPARKLINE_SAMPLES)).copied().collect()
+                            });
+                            render_sparkline(
+                                &recent,
+                                sparkline_width,
+                                cell_height,
+                                Self::zone_color(ThermalZone::from_cpu_temp(temp)),
+                                ui,
+                            );
+                            let max = core.map_or(temp, |core| core.max);
+                            if response
+                                .on_hover_text(format!(
+                                    "Core {}\nNow: {:.1}{unit}\nSession max: {:.1}{unit}\nClick to show in the history graph",
+                                    index,
+                                    to_display(temp, self.temperature_unit),
+                                    to_display(max, self.temperature_unit),
+                                    unit = self.temperature_unit.label()
+                                ))
+                                .clicked()
+                            {
+                                toggled = Some(index);
+                            }
+                        }
+                    });
+                }
+            });
+
+        if let Some(core) = toggled.and_then(|index| self.cores.get_mut(index)) {
+            core.plotted = !core.plotted;
+        }
+    }
+
+    /// Custom metric values with remove buttons, and a form to add one
+    fn render_custom_metrics(&mut self, ui: &mut egui::Ui) {
+        let mut remove = None;
+        egui::Grid::new("custom_metrics").num_columns(4).show(ui, |ui| {
+            for (i, metric) in self.custom_metrics.iter().enumerate() {
+                let color = SERIES_COLORS[i % SERIES_COLORS.len()];
+                ui.label(egui::RichText::new(&metric.name).size(11.0).color(color));
+                let value = metric.latest().map_or("-".into(), |v| format!("{:.1}", v));
+                ui.label(egui::RichText::new(value).size(13.0).strong());
+                ui.label(egui::RichText::new(&metric.source).size(10.0).color(egui::Color32::GRAY).monospace());
+                if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = remove {
+            self.custom_metrics.remove(i);
+        }
+
+        with_help(ui, HelpText::get("custom_metric"), |ui| {
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut self.new_metric_name).hint_text("name").desired_width(60.0));
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.new_metric_expr)
+                        .hint_text("cpu_temp - kbd_temp")
+                        .desired_width(140.0),
+                );
+                if ui.button(egui::RichText::new("Add").size(11.0)).clicked() {
+                    let name = self.new_metric_name.trim().to_string();
+                    let expr = self.new_metric_expr.trim().to_string();
+                    match self.custom_metric(name, expr) {
+                        Ok(()) => {
+                            self.new_metric_name.clear();
+                            self.new_metric_expr.clear();
+                        }
+                        Err(e) => self.set_status(format!("Error: {}", e)),
+                    }
+                }
+            });
+        });
+    }
+
+    /// Collapsible bar chart of the CPU temperature autocorrelation for lags 1..=AUTOCORRELATION_MAX_LAG
+    fn render_periodicity_chart(&self, ui: &mut egui::Ui, height: f32) {
+        egui::CollapsingHeader::new(egui::RichText::new("Thermal periodicity").size(11.0))
+            .default_open(false)
+            .show(ui, |ui| {
+                let bars = (1..=AUTOCORRELATION_MAX_LAG)
+                    .map(|lag| (lag, self.history.compute_autocorrelation(lag)))
+                    .filter(|(_, r)| r.is_finite())
+                    .map(|(lag, r)| {
+                        Bar::new(lag as f64, r as f64)
+                            .width(0.7)
+                            .name(format!("{} s", lag as f32 * UPDATE_INTERVAL_SECS))
+                            .fill(egui::Color32::from_rgb(100, 200, 255))
+                    })
+                    .collect();
+
+                Plot::new("periodicity")
+                    .height(height)
+                    .show_axes(true)
+                    .show_grid(true)
+                    .include_y(-1.0)
+                    .include_y(1.0)
+                    .allow_zoom(false)
+                    .allow_drag(false)
+                    .allow_scroll(false)
+                    .show(ui, |plot_ui| {
+                        plot_ui.bar_chart(BarChart::new(bars).name("Autocorrelation"));
+                    });
+            })
+            .header_response
+            .on_hover_text(HelpText::get("periodicity"));
+    }
+
+    /// Dashed vertical line at the sample following each event in the history
+    /// window, colored by kind. Returns the details of the event under the pointer.
+    fn render_time_annotations(&self, plot_ui: &mut egui_plot::PlotUi, events: &[AlertEvent]) -> Option<String> {
+        let pointer_x = plot_ui.pointer_coordinate().map(|pointer| pointer.x);
+        let mut hovered = None;
+        for event in events {
+            let (Some(color), Some(x)) = (event.kind.annotation_color(), self.history.sample_x_at(event.time)) else {
+                continue;
+            };
+            plot_ui.vline(
+                egui_plot::VLine::new(x)
+                    .color(color)
+                    .width(1.0)
+                    .style(egui_plot::LineStyle::dashed_loose()),
+            );
+            if pointer_x.is_some_and(|pointer_x| (pointer_x - x).abs() <= UPDATE_INTERVAL_SECS as f64 / 2.0) {
+                hovered = Some(format!("{}  {}", format_iso8601(event.time), event.message));
+            }
+        }
+        hovered
+    }
+
+    /// Horizontal bar chart of how many samples fell in each CPU temperature bin
+    fn render_temperature_distribution(&self, ui: &mut egui::Ui, height: f32) {
+        let (min, max) = (*DISTRIBUTION_RANGE.start(), *DISTRIBUTION_RANGE.end());
+        let width = (max - min) / DISTRIBUTION_BINS as f32;
+        let bars: Vec<Bar> = self
+            .history
+            .heatmap_data(DISTRIBUTION_BINS, min, max)
+            .into_iter()
+            .enumerate()
+            .map(|(i, count)| {
+                let low = min + i as f32 * width;
+                Bar::new((low + width / 2.0) as f64, count as f64)
+                    .width(width as f64 * 0.8)
+                    .name(format!(
+                        "{:.0}–{}",
+                        to_display(low, self.temperature_unit),
+                        format_temp(low + width, self.temperature_unit)
+                    ))
+                    .fill(Self::zone_color(ThermalZone::from_cpu_temp(low + width / 2.0)))
+            })
+            .collect();
+
+        Plot::new("temperature_distribution")
+            .height(height)
+            .show_axes(true)
+            .show_grid(true)
+            .include_x(0.0)
+            .allow_zoom(false)
+            .allow_drag(false)
+            .allow_scroll(false)
+            .show(ui, |plot_ui| {
+                plot_ui.bar_chart(BarChart::new(bars).horizontal().name("Samples"));
+            });
+    }
+
+    /// Grid of CPU samples by hour of day (x, UTC) and temperature (y), in
+    /// the zone colors; more frequent cells are more opaque
+    fn render_time_of_day_heatmap(&self, ui: &mut egui::Ui, height: f32) {
+        let counts = self.history.channel_heatmap_data(DISTRIBUTION_BINS);
+        let peak = counts.iter().flatten().copied().max().unwrap_or(0).max(1);
+        let (min, max) = (*DISTRIBUTION_RANGE.start(), *DISTRIBUTION_RANGE.end());
+        let width = (max - min) / DISTRIBUTION_BINS as f32;
+
+        let (_, rect) = ui.allocate_space(egui::vec2(ui.available_width(), height));
+        let painter = ui.painter_at(rect);
+        let cell = egui::vec2(rect.width() / counts.len() as f32, rect.height() / DISTRIBUTION_BINS as f32);
+        for (hour, row) in counts.iter().enumerate() {
+            for (bin, &count) in row.iter().enumerate().filter(|(_, &count)| count > 0) {
+                let color = Self::zone_color(ThermalZone::from_cpu_temp(min + (bin as f32 + 0.5) * width));
+                let alpha = (60.0 + 195.0 * count as f32 / peak as f32) as u8;
+                let corner = egui::pos2(rect.left() + hour as f32 * cell.x, rect.bottom() - (bin + 1) as f32 * cell.y);
+                painter.rect_filled(
+                    egui::Rect::from_min_size(corner, cell),
+                    0.0,
+                    egui::Color32::from_rgba_unmultiplied(color.r(), color.g(), color.b(), alpha),
+                );
+            }
+        }
+
+        let font = egui::FontId::proportional(9.0);
+        for hour in (0..counts.len()).step_by(6) {
+            let pos = egui::pos2(rect.left() + hour as f32 * cell.x + 2.0, rect.bottom() - 2.0);
+            painter.text(pos, egui::Align2::LEFT_BOTTOM, format!("{:02}h", hour), font.clone(), egui::Color32::GRAY);
+        }
+        for (temp, anchor, y) in [(max, egui::Align2::RIGHT_TOP, rect.top()), (min, egui::Align2::RIGHT_BOTTOM, rect.bottom())] {
+            let pos = egui::pos2(rect.right() - 2.0, y);
+            painter.text(pos, anchor, format_temp(temp, self.temperature_unit), font.clone(), egui::Color32::GRAY);
+        }
+    }
+
+    /// Collapsible bar chart of perf_pct over time, one bar per update
+    fn render_performance_history_chart(&self, ui: &mut egui::Ui, height: f32) {
+        egui::CollapsingHeader::new(egui::RichText::new("Performance history").size(11.0))
+            .default_open(false)
+            .show(ui, |ui| {
+                let bars = self
+                    .history
+                    .perf_bars(&self.perf_history)
+                    .into_iter()
+                    .map(|(x, pct, zone)| {
+                        Bar::new(x, pct)
+                            .width(UPDATE_INTERVAL_SECS as f64 * 0.8)
+                            .fill(Self::zone_color(zone))
+                    })
+                    .collect();
+
+                Plot::new("perf_history")
+                    .height(height)
+                    .show_axes(true)
+                    .show_grid(true)
+                    .include_y(0.0)
+                    .include_y(100.0)
+                    .allow_zoom(false)
+                    .allow_drag(false)
+                    .allow_scroll(false)
+                    .show(ui, |plot_ui| {
+                        plot_ui.bar_chart(BarChart::new(bars).name("Perf %"));
+                    });
+            })
+            .header_response
+            .on_hover_text(HelpText::get("perf_history"));
+    }
+
+    /// Render controls - adaptive version with wrapping
+    fn render_controls_adaptive(&mut self, ui: &mut egui::Ui, available_width: f32) {
+        let button_width = if available_width > 600.0 { 90.0 } else { 70.0 };
+        let button_height = if available_width > 600.0 { 28.0 } else { 24.0 };
+        let font_size = if available_width > 600.0 { 11.0 } else { 9.0 };
+        // One column on mini, up to three on compact, a single row on wide
+        let max_cols = if available_width > 700.0 {
+            Mode::all().len()
+        } else if available_width > 500.0 {
+            3
+        } else {
+            1
+        };
+
+        let lock_reason = self
+            .controls_lock_reason()
+            .or(self.pending_mode_change.map(|_| "Waiting for the current mode change"));
+        let enabled = lock_reason.is_none();
+        let disabled_hover_text = lock_reason.unwrap_or_default();
+        if let Some(mode) = self.pending_mode_change {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(egui::RichText::new(format!("Switching to {}…", mode.label())).size(font_size + 1.0));
+            });
+        }
+        let items = Mode::all()
+            .iter()
+            .map(|mode| {
+                let is_current = self.state.mode == *mode;
+                let color = Self::mode_color(*mode);
+                ButtonItem {
+                    button: egui::Button::new(
+                        egui::RichText::new(mode.label())
+                            .size(font_size)
+                            .color(if is_current { egui::Color32::BLACK } else { color }),
+                    )
+                    .fill(if is_current { color } else { egui::Color32::TRANSPARENT })
+                    .stroke(egui::Stroke::new(1.0, color)),
+                    enabled,
+                    hover_text: format!(
+                        "{}\nPower: {}\n{}",
+                        mode.description(),
+                        mode.power_draw_estimate(),
+                        HelpText::get("mode_wheel")
+                    ),
+                    disabled_hover_text,
+                }
+            })
+            .collect();
+
+        let spacing = ui.spacing().item_spacing.x;
+        let grid = ButtonGrid::new(items, ui.available_width(), button_width, spacing, max_cols).button_height(button_height);
+        let grid = ui.scope(|ui| grid.show(ui));
+        if let Some(index) = grid.inner {
+            let mode = Mode::all()[index];
+            if self.state.mode != mode {
+                self.profile_learning_mode(mode);
+                self.change_mode(mode);
+            }
+        } else if enabled && grid.response.contains_pointer() {
+            self.mode_wheel_scroll(ui);
+        }
+        if let Some(warning) = &self.control_warning {
+            ui.label(egui::RichText::new(format!("⚠ {}", warning)).size(font_size + 1.0).color(egui::Color32::from_rgb(255, 165, 0)));
+        }
+        self.render_mode_usage(ui, font_size);
+        self.render_recording_controls(ui, font_size);
+        if LearningObservation::session_count(&self.learning_observations) >= LEARNING_MIN_SESSIONS
+            && ui
+                .small_button(egui::RichText::new("Show mode statistics").size(font_size))
+                .on_hover_text(HelpText::get("mode_statistics"))
+                .clicked()
+        {
+            self.show_mode_statistics = true;
+        }
+    }
+
+    /// Manual mode choices, one [`LearningObservation`] per line
+    pub fn learning_log_path() -> PathBuf {
+        SessionStats::log_path().with_file_name("learning.log")
+    }
+
+    /// Remember that the user picked `mode` in the current zone, so later
+    /// sessions can show which modes were chosen where. The log is rewritten
+    /// with the newest choices once it outgrows [`LEARNING_LOG_MAX_LINES`].
+    fn profile_learning_mode(&mut self, mode: Mode) {
+        let observation = LearningObservation { session: self.learning_session, zone: self.state.thermal_zone(), mode };
+        self.learning_observations.push(observation);
+        let path = Self::learning_log_path();
+        let written = path.parent().map_or(Ok(()), fs::create_dir_all).and_then(|()| {
+            if self.learning_observations.len() > LEARNING_LOG_MAX_LINES {
+                let excess = self.learning_observations.len() - LEARNING_LOG_MAX_LINES;
+                self.learning_observations.drain(..excess);
+                let log: String =
+                    self.learning_observations.iter().map(|observation| observation.to_line() + "\n").collect();
+                fs::write(&path, log)
+            } else {
+                fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .and_then(|mut file| writeln!(file, "{}", observation.to_line()))
+            }
+        });
+        if let Err(e) = written {
+            self.log_event(format!("{}: {}", path.display(), e));
+        }
+    }
+
+    /// Majority-vote mode per zone from the learning log
+    fn render_mode_statistics(&mut self, ctx: &egui::Context) {
+        let suggestion = LearningObservation::majority_modes(&self.learning_observations);
+        let sessions = LearningObservation::session_count(&self.learning_observations);
+        egui::Window::new("Mode statistics")
+            .open(&mut self.show_mode_statistics)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Modes you picked most often in each zone, over {} sessions:",
+                    sessions
+                ));
+                egui::Grid::new("mode_statistics").num_columns(3).striped(true).show(ui, |ui| {
+                    for (zone, mode, votes, total) in &suggestion {
+                        ui.label(egui::RichText::new(zone.label()).color(Self::zone_color(*zone)));
+                        ui.label(egui::RichText::new(mode.label()).color(Self::mode_color(*mode)).strong());
+                        ui.label(egui::RichText::new(format!("{} of {}", votes, total)).color(egui::Color32::GRAY));
+                        ui.end_row();
+                    }
+                });
+            });
+    }
+
+    /// Start recording mode, fan boost and target changes, or stop and export
+    /// the recording as a shell script when one is running
+    pub fn record_mode(&mut self) {
+        match self.recording.take() {
+            None => {
+                self.recording = Some((Instant::now(), Vec::new()));
+                self.set_status("Recording actions".into());
+            }
+            Some((_, actions)) => {
+                let written = fs::write(RECORDING_EXPORT_PATH, RecordedAction::shell_script(&actions)).and_then(|()| {
+                    use std::os::unix::fs::PermissionsExt;
+                    fs::set_permissions(RECORDING_EXPORT_PATH, fs::Permissions::from_mode(0o755))
+                });
+                match written {
+                    Ok(()) => self.set_status(format!("{} actions written to {}", actions.len(), RECORDING_EXPORT_PATH)),
+                    Err(e) => self.set_status(format!("Error: {}", e)),
+                }
+            }
+        }
+    }
+
+    /// Add `step` to the running recording. Dragging the target slider reports
+    /// every intermediate value, so consecutive target changes keep only the last.
+    fn record_action(&mut self, step: RecordedStep) {
+        let Some((started, actions)) = &mut self.recording else {
+            return;
+        };
+        let action = RecordedAction { at: started.elapsed(), step };
+        match actions.last_mut() {
+            Some(last) if matches!((last.step, step), (RecordedStep::Target(_), RecordedStep::Target(_))) => {
+                *last = action;
+            }
+            _ => actions.push(action),
+        }
+    }
+
+    /// "Record" toggle, which turns into "Stop & Export" while recording
+    fn render_recording_controls(&mut self, ui: &mut egui::Ui, font_size: f32) {
+        ui.horizontal(|ui| {
+            let (text, color) = match &self.recording {
+                Some(_) => ("⏹ Stop & Export", egui::Color32::from_rgb(255, 100, 100)),
+                None => ("⏺ Record", egui::Color32::GRAY),
+            };
+            if ui
+                .small_button(egui::RichText::new(text).size(font_size).color(color))
+                .on_hover_text(HelpText::get("record_actions"))
+                .clicked()
+            {
+                self.record_mode();
+            }
+            if let Some((started, actions)) = &self.recording {
+                ui.label(
+                    egui::RichText::new(format!("{} actions · {}s", actions.len(), started.elapsed().as_secs()))
+                        .size(font_size)
+                        .color(egui::Color32::GRAY),
+                );
+            }
+        });
+    }
+
+    /// Share of the session spent in each mode, most used first
+    pub fn mode_usage_statistics(&self) -> Vec<(Mode, f32)> {
+        Self::mode_usage_shares(&self.mode_durations)
+    }
+
+    /// Fraction of the total time per mode, most used first; modes never used are left out
+    fn mode_usage_shares(durations: &HashMap<Mode, Duration>) -> Vec<(Mode, f32)> {
+        let total: f32 = durations.values().map(Duration::as_secs_f32).sum();
+        if total <= 0.0 {
+            return Vec::new();
+        }
+        let mut shares: Vec<(Mode, f32)> = Mode::all()
+            .iter()
+            .chain(&[Mode::Unknown])
+            .filter_map(|mode| durations.get(mode).map(|d| (*mode, d.as_secs_f32() / total)))
+            .filter(|(_, share)| *share > 0.0)
+            .collect();
+        shares.sort_by(|a, b| b.1.total_cmp(&a.1));
+        shares
+    }
+
+    /// `BALANCED: 68% | QUIET: 20% | PERFORMANCE: 12%`
+    fn format_mode_usage(shares: &[(Mode, f32)]) -> String {
+        shares
+            .iter()
+            .map(|(mode, share)| format!("{}: {:.0}%", mode.label(), share * 100.0))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+
+    /// Proportional bar of the time spent in each mode, with the percentages below
+    fn render_mode_usage(&self, ui: &mut egui::Ui, font_size: f32) {
+        let shares = self.mode_usage_statistics();
+        if shares.is_empty() {
+            return;
+        }
+        let (rect, response) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 6.0), egui::Sense::hover());
+        let painter = ui.painter();
+        let mut x = rect.left();
+        for (mode, share) in &shares {
+            let width = rect.width() * share;
+            let segment = egui::Rect::from_min_size(egui::pos2(x, rect.top()), egui::vec2(width, rect.height()));
+            painter.rect_filled(segment, 0.0, Self::mode_color(*mode));
+            x += width;
+        }
+        response.on_hover_text(HelpText::get("mode_usage"));
+        ui.label(
+            egui::RichText::new(Self::format_mode_usage(&shares))
+                .size(font_size)
+                .color(egui::Color32::GRAY),
+        );
+    }
+
+    /// Cycle through the modes with the mouse wheel while hovering the mode buttons
+    fn mode_wheel_scroll(&mut self, ui: &mut egui::Ui) {
+        let scroll_y = ui.input(|i| i.raw_scroll_delta.y);
+        if scroll_y == 0.0 {
+            return;
+        }
+        // The wheel belongs to the buttons here, not to the surrounding scroll area
+        ui.ctx().input_mut(|i| i.smooth_scroll_delta = egui::Vec2::ZERO);
+        if self.last_mode_wheel.is_some_and(|last| last.elapsed() < MODE_WHEEL_COOLDOWN) {
+            return;
+        }
+        self.last_mode_wheel = Some(Instant::now());
+        // change_mode reports the new mode (or the error) in the status bar
+        self.change_mode(Self::wheel_mode(self.state.mode, scroll_y));
+    }
+
+    /// Mode one step from `current` in [`Mode::all`]: scrolling up (positive
+    /// `scroll_y`) moves left, scrolling down moves right, wrapping around
+    fn wheel_mode(current: Mode, scroll_y: f32) -> Mode {
+        let modes = Mode::all();
+        let next = match modes.iter().position(|&mode| mode == current) {
+            Some(index) if scroll_y > 0.0 => (index + modes.len() - 1) % modes.len(),
+            Some(index) => (index + 1) % modes.len(),
+            None if scroll_y > 0.0 => modes.len() - 1,
+            None => 0,
+        };
+        modes[next]
+    }
+
+    /// Render benchmark control: duration input and start/cancel button
+    fn render_benchmark_control(&mut self, ui: &mut egui::Ui) {
+        let running = self.benchmark.is_some();
+        with_help(ui, HelpText::get("benchmark"), |ui| {
+            ui.horizontal(|ui| {
+                ui.add_enabled(
+                    !running,
+                    egui::TextEdit::singleline(&mut self.benchmark_minutes).desired_width(30.0),
+                );
+                ui.label(egui::RichText::new("min").size(10.0).color(egui::Color32::GRAY));
+                let label = if running { "Cancel benchmark" } else { "Benchmark" };
+                if ui.add_enabled(
+                    !self.read_only_mode && !self.low_battery_safe_mode,
+                    egui::Button::new(egui::RichText::new(label).size(11.0)),
+                )
+                    .on_disabled_hover_text(if self.read_only_mode { READ_ONLY_HINT } else { LOW_BATTERY_HINT })
+                    .clicked()
+                {
+                    self.benchmark_mode();
+                }
+            });
+        });
+    }
+
+    /// Render AC / battery profile selectors
+    fn render_power_profiles(&mut self, ui: &mut egui::Ui) {
+        let mode_combo = |ui: &mut egui::Ui, id: &str, value: &mut Option<Mode>| {
+            egui::ComboBox::from_id_salt(id)
+                .width(90.0)
+                .selected_text(value.map_or("Keep", |m| m.label()))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(value, None, "Keep");
+                    for mode in Mode::all() {
+                        ui.selectable_value(value, Some(*mode), mode.label());
+                    }
+                });
+        };
+
+        let enabled = !self.read_only_mode;
+        with_help(ui, HelpText::get("power_profiles"), |ui| {
+            ui.add_enabled_ui(enabled, |ui| ui.horizontal_wrapped(|ui| {
+                ui.label(egui::RichText::new("On AC").size(10.0).color(egui::Color32::GRAY));
+                mode_combo(ui, "ac_mode", &mut self.ac_mode);
+                ui.label(egui::RichText::new("On battery").size(10.0).color(egui::Color32::GRAY));
+                mode_combo(ui, "battery_mode", &mut self.battery_mode);
+            }));
+        });
+    }
+
+    /// Render target temperature - adaptive version
+    fn render_target_temp_adaptive(&mut self, ui: &mut egui::Ui, is_wide: bool) {
+        let slider_width = if is_wide { 120.0 } else { 80.0 };
+        let font_size = if is_wide { 11.0 } else { 9.0 };
+
+        ui.horizontal_wrapped(|ui| {
+            // The target stays in °C; the slider works in the display unit
+            let unit = self.temperature_unit;
+            let mut target = to_display(self.target_temp, unit);
+            let slider = egui::Slider::new(&mut target, to_display(40.0, unit)..=to_display(80.0, unit))
+                .suffix(unit.label())
+                .step_by(1.0)
+                .text("");
+            if ui.add_sized([slider_width, 20.0], slider)
+                .on_hover_text(HelpText::get("target_temp"))
+                .changed()
+            {
+                self.target_temp = from_display(target, unit);
+                self.record_action(RecordedStep::Target(self.target_temp));
+            }
+
+            // Auto button
+            let auto_color = if self.auto_control {
+                egui::Color32::from_rgb(100, 220, 100)
+            } else {
+                egui::Color32::GRAY
+            };
+            let lock_reason = self.controls_lock_reason();
+            if ui.add_enabled(lock_reason.is_none(), egui::Button::new(
+                egui::RichText::new(if self.auto_control { "AUTO" } else { "OFF" })
+                    .size(font_size).color(auto_color)
+            ).min_size(egui::vec2(40.0, 20.0)))
+                .on_hover_text(HelpText::get("auto_control"))
+                .on_disabled_hover_text(lock_reason.unwrap_or_default())
+                .clicked() {
+                self.toggle_auto_control();
+            }
+
+            // Status
+            if self.state.cpu_temp > self.target_temp {
+                let over = to_display_delta(self.state.cpu_temp - self.target_temp, self.temperature_unit);
+                ui.label(egui::RichText::new(format!("+{:.0}°", over))
+                    .size(font_size).color(egui::Color32::from_rgb(255, 150, 100)))
+                    .on_hover_text(HelpText::get("target_status"));
+            } else {
+                ui.label(egui::RichText::new("OK").size(font_size)
+                    .color(egui::Color32::from_rgb(100, 220, 100)))
+                    .on_hover_text(HelpText::get("target_status"));
+            }
+        });
+    }
+
+    /// Render fan control - adaptive version
+    fn render_fan_control_adaptive(&mut self, ui: &mut egui::Ui, is_wide: bool) {
+        let font_size = if is_wide { 11.0 } else { 9.0 };
+        let fan_active = self.state.fan_boost || self.fan_boost_manual;
+        let fan_color = if fan_active {
+            egui::Color32::from_rgb(255, 150, 100)
+        } else {
+            egui::Color32::GRAY
+        };
+
+        ui.horizontal_wrapped(|ui| {
+            if ui.add_enabled(!self.read_only_mode && !self.low_battery_safe_mode, egui::Button::new(
+                egui::RichText::new(if fan_active { "BOOST" } else { "NORMAL" })
+                    .size(font_size)
+                    .color(if fan_active { egui::Color32::BLACK } else { fan_color })
+            )
+            .fill(if fan_active { fan_color } else { egui::Color32::TRANSPARENT })
+            .stroke(egui::Stroke::new(1.0, fan_color))
+            .min_size(egui::vec2(60.0, 20.0)))
+                .on_hover_text(HelpText::get("fan_boost"))
+                .on_disabled_hover_text(if self.read_only_mode { READ_ONLY_HINT } else { LOW_BATTERY_HINT })
+                .clicked()
+            {
+                self.toggle_fan_boost();
+            }
+
+            // Turbo switch only for drivers that expose one
+            if supports_turbo_toggle(&self.state.cpufreq_driver) {
+                if let Some(mut turbo) = self.state.turbo_enabled {
+                    if ui.add_enabled(!self.read_only_mode, egui::Checkbox::new(&mut turbo, egui::RichText::new("Turbo").size(font_size)))
+                        .on_hover_text(format!("{}\nDriver: {}", HelpText::get("turbo"), self.state.cpufreq_driver))
+                        .on_disabled_hover_text(READ_ONLY_HINT)
+                        .changed()
+                    {
+                        match set_turbo(&self.state.cpufreq_driver, turbo) {
+                            Ok(()) => {
+                                self.state.turbo_enabled = Some(turbo);
+                                self.set_status(if turbo { "Turbo on".into() } else { "Turbo off".into() });
+                            }
+                            Err(e) => self.handle_write_error(&e),
+                        }
+                    }
+                }
+            }
+
+            if let Some(rpm) = self.state.fan_rpm {
+                ui.label(egui::RichText::new(format!("{} RPM", rpm)).size(font_size).color(egui::Color32::GRAY))
+                    .on_hover_text(HelpText::get("fan_rpm"));
+            }
+            if self.state.ec_throttling {
+                ui.label(egui::RichText::new("EC throttling").size(font_size)
+                    .color(egui::Color32::from_rgb(255, 100, 100)))
+                    .on_hover_text(HelpText::get("ec_throttling"));
+            }
+
+            if is_wide {
+                ui.label(egui::RichText::new("Max cooling").size(9.0).color(egui::Color32::DARK_GRAY));
+            }
+        });
+
+        if let Some(pct) = self.state.battery_pct {
+            let health = self
+                .state
+                .battery_health
+                .map(|health| format!(" · health {:.0}%", health.health_pct))
+                .unwrap_or_default();
+            let cycles = self
+                .state
+                .power_supply_cycle_count
+                .map(|cycles| format!(" · {} cycles", cycles))
+                .unwrap_or_default();
+            ui.label(
+                egui::RichText::new(format!("Battery {}%{}{}", pct, health, cycles))
+                    .size(font_size)
+                    .color(egui::Color32::GRAY),
+            )
+            .on_hover_text(HelpText::get("battery_cycles"));
+            if self.state.battery_health.is_some_and(|health| health.is_degraded()) {
+                ui.label(
+                    egui::RichText::new("⚠ Battery degraded — may cause thermal instability")
+                        .size(font_size)
+                        .color(egui::Color32::from_rgb(255, 165, 0)),
+                );
+            }
+        }
+
+        if self.state.on_ac == Some(false) {
+            ui.label(
+                egui::RichText::new(self.estimated_battery_impact())
+                    .size(font_size)
+                    .color(egui::Color32::from_rgb(255, 200, 100)),
+            )
+            .on_hover_text(HelpText::get("battery_impact"));
+        }
+
+        #[cfg(feature = "ec_access")]
+        self.render_fan_curve_editor(ui, font_size);
+    }
+
+    /// Hand the fan back to the EC, then reapply the manual fan boost setting
+    #[cfg(feature = "ec_access")]
+    fn release_fan_curve(&mut self) {
+        match release_fan_curve() {
+            Ok(()) => {
+                if let Err(e) = set_fan_boost(self.fan_boost_manual) {
+                    self.handle_write_error(&e);
+                }
+            }
+            Err(ThermalError::Io(e)) => self.handle_write_error(&e),
+            Err(e) => self.set_status(format!("Error: {}", e)),
+        }
+    }
+
+    /// Fan curve toggle and editor: drag points to change them, add or delete points
+    #[cfg(feature = "ec_access")]
+    fn render_fan_curve_editor(&mut self, ui: &mut egui::Ui, font_size: f32) {
+        ui.horizontal_wrapped(|ui| {
+            if ui.add_enabled(
+                !self.read_only_mode,
+                egui::Checkbox::new(&mut self.fan_curve_enabled, egui::RichText::new("Fan curve").size(font_size)),
+            )
+                .on_hover_text(HelpText::get("fan_curve"))
+                .on_disabled_hover_text(READ_ONLY_HINT)
+                .changed()
+            {
+                self.fan_curve_level = None;
+                if !self.fan_curve_enabled {
+                    self.release_fan_curve();
+                }
+            }
+            if !self.fan_curve_enabled {
+                return;
+            }
+            if ui.small_button("Add point").clicked() {
+                self.fan_curve_selected = Some(self.fan_curve.add_point());
+            }
+            let can_delete = self.fan_curve_selected.is_some() && self.fan_curve.points.len() > 2;
+            if ui.add_enabled(can_delete, egui::Button::new("Delete point").small()).clicked() {
+                if let Some(index) = self.fan_curve_selected.take() {
+                    self.fan_curve.remove_point(index);
+                }
+            }
+        });
+        if !self.fan_curve_enabled {
+            return;
+        }
+
+        let points: Vec<[f64; 2]> = self.fan_curve.points.iter().map(|&(t, l)| [t as f64, l as f64]).collect();
+        let selected = self.fan_curve_selected.and_then(|i| points.get(i).copied());
+        let curve_color = egui::Color32::from_rgb(255, 150, 100);
+        let response = Plot::new("fan_curve")
+            .height(100.0)
+            .show_axes(true)
+            .show_grid(true)
+            .include_x(30.0)
+            .include_x(100.0)
+            .include_y(0.0)
+            .include_y(FAN_LEVEL_MAX as f64)
+            .allow_zoom(false)
+            .allow_drag(false)
+            .allow_scroll(false)
+            .show(ui, |plot_ui| {
+                plot_ui.line(Line::new(PlotPoints::new(points.clone())).color(curve_color).width(2.0));
+                plot_ui.points(egui_plot::Points::new(points).color(curve_color).radius(4.0));
+                if let Some(point) = selected {
+                    plot_ui.points(egui_plot::Points::new(point).color(egui::Color32::WHITE).radius(6.0));
+                }
+                let response = plot_ui.response();
+                (plot_ui.pointer_coordinate(), response.drag_started() || response.clicked(), response.dragged())
+            });
+
+        let (pointer, pressed, dragged) = response.inner;
+        if let Some(pointer) = pointer {
+            let (temp, level) = (pointer.x as f32, pointer.y as f32);
+            if pressed {
+                self.fan_curve_selected = self.fan_curve.nearest_point(temp, level, FAN_CURVE_DEGREES_PER_LEVEL);
+            }
+            if let Some(index) = self.fan_curve_selected.filter(|_| dragged) {
+                self.fan_curve.move_point(index, temp, level);
+                self.fan_curve_level = None;
+            }
+        }
+    }
+
+    /// Draw the zone color bands ([`ThermalZone::heatmap_band`]) as translucent
+    /// background fills behind the plot lines
+    fn render_heatmap_overlay(plot_ui: &mut egui_plot::PlotUi) {
+        let bounds = plot_ui.plot_bounds();
+        let [x_min, y_min] = bounds.min();
+        let [x_max, y_max] = bounds.max();
+
+        for zone in ThermalZone::all() {
+            let (low, high) = zone.heatmap_band();
+            let low = (low as f64).max(y_min);
+            let high = (high as f64).min(y_max);
+            if low >= high {
+                continue;
+            }
+
+            let (r, g, b) = zone.color_rgb();
+            let band = PlotPoints::new(vec![
+                [x_min, low],
+                [x_max, low],
+                [x_max, high],
+                [x_min, high],
+            ]);
+            plot_ui.polygon(
+                Polygon::new(band)
+                    .fill_color(egui::Color32::from_rgba_unmultiplied(r, g, b, 77)) // 30% opacity
+                    .stroke(egui::Stroke::NONE),
+            );
+        }
+    }
+
+    /// Line color of a trip point kind
+    fn trip_color(trip_type: TripType) -> egui::Color32 {
+        match trip_type {
+            TripType::Active => egui::Color32::from_rgb(100, 180, 255),
+            TripType::Passive => egui::Color32::from_rgb(255, 220, 80),
+            TripType::Hot => egui::Color32::from_rgb(255, 140, 0),
+            TripType::Critical => egui::Color32::from_rgb(255, 50, 50),
+        }
+    }
+
+    /// Render history graph - adaptive version
+    fn render_history_adaptive(&mut self, ui: &mut egui::Ui, target_temp: f32, height: f32) {
+        if self.history.is_empty() {
+            ui.label("Collecting data...");
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.history_view, HistoryView::Timeline, egui::RichText::new("Timeline").size(10.0));
+            ui.selectable_value(&mut self.history_view, HistoryView::Distribution, egui::RichText::new("Distribution").size(10.0))
+                .on_hover_text(HelpText::get("distribution"));
+            ui.selectable_value(&mut self.history_view, HistoryView::TimeOfDay, egui::RichText::new("Time of day").size(10.0))
+                .on_hover_text(HelpText::get("time_of_day"));
+        });
+        match self.history_view {
+            HistoryView::Distribution => return self.render_temperature_distribution(ui, height),
+            HistoryView::TimeOfDay => return self.render_time_of_day_heatmap(ui, height),
+            HistoryView::Timeline => {}
+        }
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.show_zone_bands, egui::RichText::new("Zone bands").size(10.0))
+                .on_hover_text(HelpText::get("zone_bands"));
+            ui.checkbox(&mut self.show_mem_line, egui::RichText::new("Memory").size(10.0))
+                .on_hover_text(HelpText::get("mem_line"));
+            ui.checkbox(
+                &mut self.show_percentile_line,
+                egui::RichText::new(format!("P{:.0}", PERCENTILE_LINE_P)).size(10.0),
+            )
+            .on_hover_text(HelpText::get("percentile_line"));
+            ui.checkbox(&mut self.show_trend_line, egui::RichText::new("Show trend").size(10.0))
+                .on_hover_text(HelpText::get("trend_line"));
+            ui.checkbox(&mut self.show_envelope, egui::RichText::new("Show envelope").size(10.0))
+                .on_hover_text(HelpText::get("envelope"));
+            if ui
+                .checkbox(&mut self.show_tjmax_line, egui::RichText::new("TJmax").size(10.0))
+                .on_hover_text(HelpText::get("tjmax_line"))
+                .changed()
+            {
+                if self.show_tjmax_line {
+                    self.history.channel_add_static("TJmax", self.state.tjmax);
+                } else {
+                    self.history.channel_remove_static("TJmax");
+                }
+            }
+            with_help(ui, HelpText::get("forecast"), |ui| {
+                egui::ComboBox::from_id_salt("forecast_model")
+                    .width(80.0)
+                    .selected_text(egui::RichText::new(self.forecast_model.map_or("No forecast", |m| m.label())).size(10.0))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.forecast_model, None, "No forecast");
+                        for model in ForecastModel::all() {
+                            ui.selectable_value(&mut self.forecast_model, Some(*model), model.label());
+                        }
+                    });
+            });
+            with_help(ui, HelpText::get("smoothing"), |ui| {
+                egui::ComboBox::from_id_salt("smoothing")
+                    .width(90.0)
+                    .selected_text(egui::RichText::new(self.smoothing.map_or("No smoothing", |s| s.label())).size(10.0))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.smoothing, None, "No smoothing");
+                        for smoothing in Smoothing::all() {
+                            ui.selectable_value(&mut self.smoothing, Some(*smoothing), smoothing.label());
+                        }
+                    });
+            });
+            if self.smoothing == Some(Smoothing::Wavelet) {
+                let unit = self.temperature_unit;
+                let range = to_display_delta(*WAVELET_THRESHOLD_RANGE.start(), unit)
+                    ..=to_display_delta(*WAVELET_THRESHOLD_RANGE.end(), unit);
+                let mut threshold = to_display_delta(self.wavelet_threshold, unit);
+                let response = ui
+                    .add(egui::Slider::new(&mut threshold, range).step_by(0.1).suffix(unit.label()))
+                    .on_hover_text(HelpText::get("wavelet_threshold"));
+                if response.changed() {
+                    self.wavelet_threshold = from_display_delta(threshold, unit);
+                }
+            }
+            let gaps = self.history.count_gaps();
+            if gaps > 0 {
+                ui.label(egui::RichText::new(format!("{} gap{}", gaps, if gaps == 1 { "" } else { "s" })).size(10.0))
+                    .on_hover_text(HelpText::get("history_gaps"));
+            }
+            if !self.trip_points.is_empty() {
+                ui.checkbox(&mut self.show_trip_points, egui::RichText::new("Trip points").size(10.0))
+                    .on_hover_text(HelpText::get("trip_points"));
+            }
+            let split_label = if self.comparison_mode.is_some() { "Clear A/B split" } else { "Set A/B split" };
+            if ui.small_button(split_label).on_hover_text(HelpText::get("ab_split")).clicked() {
+                self.comparison_mode = match self.comparison_mode {
+                    Some(_) => None,
+                    None => self.history.newest_time(),
+                };
+            }
+            if self.comparison_history.is_some() {
+                if ui.small_button("Clear trace").clicked() {
+                    self.comparison_history = None;
+                }
+            } else if ui.small_button("Import trace")
+                .on_hover_text(HelpText::get("import_trace"))
+                .clicked()
+            {
+                self.import_trace_comparison();
+            }
+        });
+        let show_zone_bands = self.show_zone_bands;
+
+        let comparison_lines: Vec<Line> = self.comparison_history.as_ref().map_or_else(Vec::new, |history| {
+            history
+                .cpu_segments()
+                .into_iter()
+                .map(|points| Line::new(points).name("Trace").color(egui::Color32::from_rgb(180, 180, 180)).width(1.0))
+                .collect()
+        });
+
+        // One line per run between gaps; lines sharing a name share a legend entry.
+        // With an A/B split, period A is drawn at 70% opacity.
+        let split_lines = |(before, after): (Vec<PlotPoints>, Vec<PlotPoints>), name: &str, color: egui::Color32| -> Vec<Line> {
+            before
+                .into_iter()
+                .map(|points| (points, color.gamma_multiply(0.7)))
+                .chain(after.into_iter().map(|points| (points, color)))
+                .map(|(points, color)| Line::new(points).name(name).color(color).width(2.0))
+                .collect()
+        };
+        let split = self.comparison_mode;
+        let cpu_lines = split_lines(
+            split.map_or_else(|| (Vec::new(), self.history.cpu_segments()), |split| self.history.split_segments("cpu", split)),
+            "CPU",
+            egui::Color32::from_rgb(255, 100, 100),
+        );
+        let kbd_lines = split_lines(
+            split.map_or_else(|| (Vec::new(), self.history.kbd_segments()), |split| self.history.split_segments("kbd", split)),
+            "Kbd",
+            egui::Color32::from_rgb(100, 200, 255),
+        );
+        let split_x = split.map(|split| self.history.split_x(split));
+
+        let mem_line = self.show_mem_line.then(|| {
+            Line::new(self.history.mem_points())
+                .name("Mem %")
+                .color(egui::Color32::from_rgba_unmultiplied(200, 160, 255, 90))
+                .width(1.0)
+        });
+
+        let percentile_line = self.show_percentile_line.then(|| {
+            Line::new(self.history.percentile_points(PERCENTILE_LINE_P, PERCENTILE_LINE_WINDOW))
+                .name(format!("CPU P{:.0}", PERCENTILE_LINE_P))
+                .color(egui::Color32::from_rgb(255, 160, 160))
+                .width(1.0)
+                .style(egui_plot::LineStyle::dotted_dense())
+        });
+
+        let static_lines: Vec<Line> = self
+            .history
+            .static_channel_points()
+            .into_iter()
+            .map(|(name, points)| {
+                Line::new(points)
+                    .name(name)
+                    .color(egui::Color32::from_rgb(220, 60, 60))
+                    .width(1.0)
+                    .style(egui_plot::LineStyle::dotted_loose())
+            })
+            .collect();
+
+        let envelope_polygons = if self.show_envelope {
+            self.history.envelope_polygons(ENVELOPE_WINDOW)
+        } else {
+            Vec::new()
+        };
+
+        let trend_line = self.show_trend_line.then(|| {
+            Line::new(self.history.trend_points())
+                .name("CPU trend")
+                .color(egui::Color32::from_rgba_unmultiplied(255, 100, 100, 110))
+                .width(1.5)
+                .style(egui_plot::LineStyle::dashed_loose())
+        });
+
+        let smoothed_lines: Vec<Line> = self.smoothed_history().map_or_else(Vec::new, |(smoothing, smoothed)| {
+            smoothed
+                .cpu_segments()
+                .into_iter()
+                .map(|points| {
+                    Line::new(points)
+                        .name(format!("CPU ({})", smoothing.label()))
+                        .color(egui::Color32::from_rgb(255, 200, 120))
+                        .width(2.0)
+                })
+                .collect()
+        });
+
+        let forecast_line = self.forecast_model.map(|model| {
+            Line::new(self.history.forecast_points(FORECAST_SAMPLES, model))
+                .name(format!("Forecast ({})", model.label()))
+                .color(egui::Color32::from_rgb(255, 100, 100))
+                .width(1.5)
+                .style(egui_plot::LineStyle::dashed_dense())
+        });
+
+        let custom_lines: Vec<Line> = self.custom_metrics.iter().enumerate().map(|(i, metric)| {
+            Line::new(self.history.series_points(&metric.history))
+                .name(&metric.name)
+                .color(SERIES_COLORS[i % SERIES_COLORS.len()])
+                .width(1.5)
+        }).collect();
+
+        // Core lines take the colors after the custom metrics
+        let core_lines: Vec<Line> = self
+            .cores
+            .iter()
+            .enumerate()
+            .filter(|(_, core)| core.plotted)
+            .enumerate()
+            .map(|(n, (index, core))| {
+                Line::new(self.history.series_points(&core.temps))
+                    .name(format!("Core {}", index))
+                    .color(SERIES_COLORS[(self.custom_metrics.len() + n) % SERIES_COLORS.len()])
+                    .width(1.0)
+            })
+            .collect();
+
+        let full_span = (HISTORY_CAPACITY - 1) as f64 * UPDATE_INTERVAL_SECS as f64;
+        let target_points = vec![
+            [0.0, target_temp as f64],
+            [self.history.span_secs().max(full_span), target_temp as f64],
+        ];
+        let trip_span = self.history.span_secs().max(full_span);
+        let trip_lines: Vec<Line> = self
+            .trip_points
+            .iter()
+            .filter(|_| self.show_trip_points)
+            .map(|trip| {
+                let y = trip.temp_celsius as f64;
+                Line::new(PlotPoints::new(vec![[0.0, y], [trip_span, y]]))
+                    .name(trip.label())
+                    .color(Self::trip_color(trip.trip_type))
+                    .width(1.0)
+                    .style(egui_plot::LineStyle::dashed_dense())
+            })
+            .collect();
+
+        let target_line = Line::new(PlotPoints::new(target_points))
+            .name("Target")
+            .color(egui::Color32::from_rgb(255, 200, 100))
+            .width(1.0)
+            .style(egui_plot::LineStyle::dashed_loose());
+
+        // Fit the Y axis to every visible channel plus the target line
+        let (y_min, y_max) = self
+            .history
+            .channel_min_max_all()
+            .values()
+            .fold((target_temp, target_temp), |(lo, hi), &(min, max)| (lo.min(min), hi.max(max)));
+
+        // Show a full window from the start instead of a line growing from the left.
+        // The history is not padded to capacity for this: copies of the first
+        // sample would count in the statistics, trend, forecast and exports.
+        let span = self.history.span_secs();
+        let unit = self.temperature_unit;
+        let plot = Plot::new("temp_history")
+            .height(height)
+            .y_axis_formatter(move |mark, _| format!("{:.0}{}", to_display(mark.value as f32, unit), unit.label()))
+            .show_axes(true)
+            .show_grid(true)
+            .include_x(span - self.history.window_secs())
+            .include_x(span)
+            .include_y(y_min - 5.0)
+            .include_y(y_max + 5.0)
+            .allow_zoom(false)
+            .allow_drag(false)
+            .allow_scroll(false)
+            .legend(egui_plot::Legend::default().position(egui_plot::Corner::RightTop))
+            .show(ui, |plot_ui| {
+                if show_zone_bands {
+                    Self::render_heatmap_overlay(plot_ui);
+                }
+                for polygon in envelope_polygons {
+                    plot_ui.polygon(
+                        Polygon::new(polygon)
+                            .fill_color(egui::Color32::from_rgba_unmultiplied(255, 100, 100, 50))
+                            .stroke(egui::Stroke::NONE),
+                    );
+                }
+                for line in cpu_lines.into_iter().chain(kbd_lines) {
+                    plot_ui.line(line);
+                }
+                for line in mem_line.into_iter().chain(percentile_line).chain(trend_line).chain(forecast_line).chain(smoothed_lines) {
+                    plot_ui.line(line);
+                }
+                for line in custom_lines.into_iter().chain(core_lines).chain(trip_lines).chain(static_lines) {
+                    plot_ui.line(line);
+                }
+                for line in comparison_lines {
+                    plot_ui.line(line);
+                }
+                plot_ui.line(target_line);
+                if let Some(x) = split_x {
+                    let marker = egui::Color32::from_rgb(220, 220, 220);
+                    plot_ui.vline(egui_plot::VLine::new(x).color(marker).width(1.5));
+                    let top = plot_ui.plot_bounds().max()[1];
+                    plot_ui.text(
+                        egui_plot::Text::new(egui_plot::PlotPoint::new(x, top), "A ")
+                            .color(marker)
+                            .anchor(egui::Align2::RIGHT_TOP),
+                    );
+                    plot_ui.text(
+                        egui_plot::Text::new(egui_plot::PlotPoint::new(x, top), " B")
+                            .color(marker)
+                            .anchor(egui::Align2::LEFT_TOP),
+                    );
+                }
+                self.render_time_annotations(plot_ui, &self.alert_events)
+            });
+        if let Some(details) = plot.inner {
+            plot.response.on_hover_text(details);
+        }
+
+        ui.horizontal(|ui| {
+            if let Some(oldest) = self.history.oldest_time() {
+                ui.label(
+                    egui::RichText::new(format!("Seconds since {}", format_iso8601(oldest)))
+                        .size(10.0)
+                        .color(egui::Color32::GRAY),
+                );
+            }
+            if ui.small_button("Export CSV").on_hover_text(HelpText::get("export_csv")).clicked() {
+                self.export_csv();
+            }
+            if ui.small_button("Import CSV").on_hover_text(HelpText::get("import_csv")).clicked() {
+                self.import_csv();
+            }
+            if ui.small_button("Copy CSV").on_hover_text(HelpText::get("copy_csv")).clicked() {
+                let csv = String::from_utf8_lossy(&self.history.as_csv_bytes()).into_owned();
+                ui.ctx().copy_text(csv);
+                self.set_status("History copied to clipboard as CSV".into());
+            }
+            if ui.small_button("Export Gnuplot").on_hover_text(HelpText::get("export_gnuplot")).clicked() {
+                self.export_gnuplot();
+            }
+            if ui.small_button("Export HTML").on_hover_text(HelpText::get("export_html")).clicked() {
+                self.export_html();
+            }
+            if ui.small_button("Export InfluxDB").on_hover_text(HelpText::get("export_influxdb")).clicked() {
+                self.export_influxdb();
+            }
+            #[cfg(feature = "protobuf")]
+            {
+                if ui.small_button("Export Protobuf").on_hover_text(HelpText::get("export_protobuf")).clicked() {
+                    self.export_protobuf();
+                }
+                if ui.small_button("Import Protobuf").on_hover_text(HelpText::get("import_protobuf")).clicked() {
+                    self.import_protobuf();
+                }
+            }
+            #[cfg(feature = "ndarray")]
+            if ui.small_button("Export NumPy").on_hover_text(HelpText::get("export_npy")).clicked() {
+                self.export_npy();
+            }
+        });
+
+        let correlation = self.history.correlation(&self.history);
+        if correlation.is_finite() {
+            let strength = match correlation.abs() {
+                r if r >= 0.7 => "strong",
+                r if r >= 0.4 => "moderate",
+                _ => "weak",
+            };
+            ui.label(
+                egui::RichText::new(format!("CPU↔KBD correlation: {:.2} ({})", correlation, strength))
+                    .size(10.0)
+                    .color(egui::Color32::GRAY),
+            )
+            .on_hover_text(HelpText::get("correlation"));
+        }
+
+        self.split_comparison_table(ui);
+        self.session_comparison_table(ui);
+
+        if self.threshold_crossings > 0 {
+            ui.label(
+                egui::RichText::new(format!(
+                    "Threshold crossed {} time{} this session",
+                    self.threshold_crossings,
+                    if self.threshold_crossings == 1 { "" } else { "s" }
+                ))
+                .size(10.0)
+                .color(egui::Color32::from_rgb(255, 150, 100)),
+            )
+            .on_hover_text(HelpText::get("threshold_crossings"));
+        }
+    }
+
+    /// Metric rows comparing two sessions: temperature statistics per channel,
+    /// then time spent in each zone (more time in cooler zones is better)
+    fn comparison_rows(current: &TemperatureHistory, comparison: &TemperatureHistory) -> Vec<ComparisonRow> {
+        let mut rows = Vec::new();
+        for (channel, label) in [("cpu", "CPU"), ("kbd", "KBD")] {
+            let (Some(cur), Some(cmp)) = (current.channel_stats(channel), comparison.channel_stats(channel)) else {
+                continue;
+            };
+            for (stat, a, b) in [
+                ("avg", cur.avg, cmp.avg),
+                ("p95", cur.p95, cmp.p95),
+                ("p99", cur.p99, cmp.p99),
+                ("max", cur.max, cmp.max),
+            ] {
+                rows.push(ComparisonRow {
+                    metric: format!("{} {}", label, stat),
+                    current: a,
+                    comparison: b,
+                    unit: "°C",
+                    lower_is_better: true,
+                });
+            }
+        }
+        for ((zone, cur), (_, cmp)) in current.time_in_zones().into_iter().zip(comparison.time_in_zones()) {
+            rows.push(ComparisonRow {
+                metric: format!("Time in {}", zone.label()),
+                current: cur,
+                comparison: cmp,
+                unit: "%",
+                lower_is_better: !matches!(zone, ThermalZone::Cool | ThermalZone::Comfort | ThermalZone::Optimal),
+            });
+        }
+        rows
+    }
+
+    /// Render min/avg/max of periods A and B of the A/B split below the plot
+    fn split_comparison_table(&self, ui: &mut egui::Ui) {
+        let Some(split) = self.comparison_mode else {
+            return;
+        };
+        let header = |ui: &mut egui::Ui, text: &str| {
+            ui.label(egui::RichText::new(text).size(10.0).strong());
+        };
+        let unit = self.temperature_unit;
+        let stats = |stats: Option<(f32, f32, f32)>| {
+            stats.map_or("–".to_string(), |(min, avg, max)| {
+                format!("{:.1} / {:.1} / {:.1}{}", to_display(min, unit), to_display(avg, unit), to_display(max, unit), unit.label())
+            })
+        };
+
+        egui::Grid::new("split_comparison")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                header(ui, "Min / avg / max");
+                header(ui, "A");
+                header(ui, "B");
+                ui.end_row();
+                for (channel, label) in [("cpu", "CPU"), ("kbd", "KBD")] {
+                    let [a, b] = self.history.split_stats(channel, split);
+                    ui.label(egui::RichText::new(label).size(10.0));
+                    ui.label(egui::RichText::new(stats(a)).size(10.0));
+                    ui.label(egui::RichText::new(stats(b)).size(10.0));
+                    ui.end_row();
+                }
+            });
+    }
+
+    /// Render the current vs comparison session table below the plot
+    fn session_comparison_table(&self, ui: &mut egui::Ui) {
+        let Some(comparison) = &self.comparison_history else {
+            return;
+        };
+        let header = |ui: &mut egui::Ui, text: &str| {
+            ui.label(egui::RichText::new(text).size(10.0).strong());
+        };
+
+        egui::Grid::new("session_comparison")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                header(ui, "Metric");
+                header(ui, "Current");
+                header(ui, "Comparison");
+                ui.end_row();
+
+                let unit = self.temperature_unit;
+                let value = |row: &ComparisonRow, value: f32| match row.unit {
+                    "°C" => format!("{:.1}{}", to_display(value, unit), unit.label()),
+                    other => format!("{:.1}{}", value, other),
+                };
+                for row in Self::comparison_rows(&self.history, comparison) {
+                    let color = match row.current_is_better() {
+                        Some(true) => egui::Color32::from_rgb(100, 220, 100),
+                        Some(false) => egui::Color32::from_rgb(255, 100, 100),
+                        None => egui::Color32::GRAY,
+                    };
+                    ui.label(egui::RichText::new(&row.metric).size(10.0).color(egui::Color32::GRAY));
+                    ui.label(egui::RichText::new(value(&row, row.current)).size(10.0).color(color));
+                    ui.label(egui::RichText::new(value(&row, row.comparison)).size(10.0));
+                    ui.end_row();
+                }
+            });
+    }
+
+    /// Render status bar
+    fn render_status(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            // Benchmark countdown stays visible until it ends
+            if let Some(remaining) = self.benchmark_remaining() {
+                let secs = remaining.as_secs();
+                ui.label(
+                    egui::RichText::new(format!("Benchmark mode: {}m {}s remaining", secs / 60, secs % 60))
+                        .size(12.0)
+                        .color(egui::Color32::from_rgb(255, 100, 100)),
+                );
+            }
+
+            if self.pre_idle_mode.is_some() {
+                ui.label(
+                    egui::RichText::new("Idle mode active")
+                        .size(12.0)
+                        .color(egui::Color32::from_rgb(180, 180, 255)),
+                )
+                .on_hover_text(HelpText::get("idle_mode"));
+            }
+
+            // Status message (auto-clear after 3 seconds)
+            if let Some((msg, time)) = &self.status_message {
+                if time.elapsed() < Duration::from_secs(3) {
+                    ui.label(egui::RichText::new(msg).size(12.0).color(egui::Color32::YELLOW));
+                } else {
+                    self.status_message = None;
+                }
+            }
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.label(
+                    egui::RichText::new("Thermal Monitor v1.3.0")
+                        .size(11.0)
+                        .color(egui::Color32::DARK_GRAY),
+                );
+                let measuring = self.overhead_benchmark.is_some();
+                if ui.add_enabled(!measuring, egui::Button::new("Measure overhead").small())
+                    .on_hover_text(HelpText::get("measure_overhead"))
+                    .clicked()
+                {
+                    self.benchmark_polling_overhead();
+                }
+                if ui.small_button("Test notification")
+                    .on_hover_text(HelpText::get("test_notification"))
+                    .clicked()
+                {
+                    self.test_notification_system();
+                }
+                if ui.small_button("Generate udev rules")
+                    .on_hover_text(HelpText::get("udev_rules"))
+                    .clicked()
+                {
+                    self.udev_rule_generator();
+                }
+                // Right-to-left: Cancel ends up rightmost
+                if self.confirm_reset {
+                    if ui.small_button("Cancel").clicked() {
+                        self.confirm_reset = false;
+                    }
+                    if ui.small_button("OK").clicked() {
+                        self.confirm_reset = false;
+                        self.restore_defaults();
+                    }
+                    ui.label(egui::RichText::new("Reset all settings?").size(11.0).color(egui::Color32::YELLOW));
+                } else if ui.small_button("Reset to defaults")
+                    .on_hover_text(HelpText::get("reset_defaults"))
+                    .clicked()
+                {
+                    self.confirm_reset = true;
+                }
+            });
+        });
+    }
+}
+
+impl eframe::App for ThermalApp {
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        Self::finish_session(&self.session, self.saved_config.log_sessions);
+        self.clear_status_bar();
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if Self::terminate_requested() {
+            // Closing runs `on_exit`, which logs the session summary
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+        // Update state every UPDATE_INTERVAL_SECS, and refresh early on a
+        // thermal trip, at most once per TRIP_REFRESH_MIN_INTERVAL
+        let trip_event = self.acpi_events.as_ref().is_some_and(|events| {
+            // Drain everything so stale events do not pile up
+            events.drain().filter(|event| event.event_type == AcpiEventType::ThermalZoneTrip).count() > 0
+        });
+        if self.last_update.elapsed() >= Duration::from_secs_f32(UPDATE_INTERVAL_SECS) {
+            self.update_state(true);
+            self.last_update = Instant::now();
+            *CRASH_HISTORY.lock().unwrap_or_else(|e| e.into_inner()) = self.history.serialize();
+        } else if trip_event && Self::trip_refresh_allowed(self.last_trip_refresh, Instant::now()) {
+            self.update_state(false);
+            self.last_trip_refresh = Some(Instant::now());
+        }
+
+        self.finish_update_check();
+        self.finish_notification_test();
+        self.finish_mode_change();
+        if self.pending_mode_change.is_some() || self.notification_test_result.is_some() {
+            ctx.request_repaint_after(Duration::from_millis(50));
+        }
+
+        self.schedule_idle_mode_switch(ctx);
+        self.handle_keyboard_shortcuts(ctx);
+        self.render_control_keyboard_map(ctx);
+        self.render_about(ctx);
+        self.render_crash_report(ctx);
+        self.render_setup_wizard(ctx);
+        self.render_mode_statistics(ctx);
+        self.render_throttle_warning(ctx);
+
+        // Periodically snapshot history so a restart can restore the graph
+        if self.last_snapshot.elapsed() >= Duration::from_secs(SNAPSHOT_INTERVAL_SECS) {
+            let _ = fs::write(SNAPSHOT_PATH, self.history.serialize());
+            self.last_snapshot = Instant::now();
+        }
+
+        self.check_overhead_benchmark();
+
+        // Apply edits made to the config file; our own saves match saved_config
+        if let Some(config) = self.config_watcher.latest().filter(|config| *config != self.saved_config) {
+            self.apply_config(config);
+            self.log_event("Config reloaded".into());
+            self.set_status("Config reloaded".into());
+        }
+
+        // Persist settings whenever they change
+        let config = self.current_config();
+        if config != self.saved_config {
+            if let Err(e) = config.save() {
+                self.set_status(format!("Error saving settings: {}", e));
+            }
+            self.saved_config = config;
+        }
+
+        // Restore previous state once the benchmark timer expires
+        if self.benchmark_remaining() == Some(Duration::ZERO) {
+            self.end_benchmark();
+            self.set_status("Benchmark finished".into());
+        }
+
+        // Request repaint to keep updating, slower when nobody can see the window
+        let repaint_ms = if self.state.screen_on { 100 } else { SCREEN_OFF_REPAINT_MS };
+        ctx.request_repaint_after(Duration::from_millis(repaint_ms));
+
+        // Dark theme, or high contrast when the desktop asks for it
+        ctx.set_visuals(if self.high_contrast { Self::high_contrast_visuals() } else { egui::Visuals::dark() });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            // Get available width to determine layout
+            let available_width = ui.available_width();
+            let is_wide = available_width > 700.0;
+            let is_medium = available_width > 500.0;
+
+            // Adaptive spacing
+            let spacing = if is_wide { 8.0 } else { 4.0 };
+            ui.spacing_mut().item_spacing = egui::vec2(spacing, spacing);
+
+            // Use ScrollArea for small windows
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                // Title - adaptive size
+                let title_size = if is_wide { 22.0 } else if is_medium { 18.0 } else { 16.0 };
+                ui.horizontal(|ui| {
+                    ui.heading(egui::RichText::new("Thermal Monitor").size(title_size));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.small_button("?").on_hover_text(HelpText::get("keyboard_map")).clicked() {
+                            self.show_keyboard_map = true;
+                        }
+                        if ui.small_button("ℹ").on_hover_text(HelpText::get("about")).clicked() {
+                            self.show_about = true;
+                        }
+                        let other_unit = match self.temperature_unit {
+                            TemperatureUnit::Celsius => TemperatureUnit::Fahrenheit,
+                            TemperatureUnit::Fahrenheit => TemperatureUnit::Celsius,
+                        };
+                        if ui
+                            .small_button(self.temperature_unit.label())
+                            .on_hover_text(HelpText::get("temperature_unit"))
+                            .clicked()
+                        {
+                            self.temperature_unit = other_unit;
+                        }
+                        ui.label(
+                            egui::RichText::new(format!("{}", self.state.platform_profile))
+                                .size(if is_wide { 12.0 } else { 10.0 })
+                                .color(egui::Color32::GRAY),
+                        )
+                        .on_hover_text(HelpText::get("platform_profile"));
+                    });
+                });
+                // Localized description
+                let desc_size = if is_wide { 12.0 } else { 10.0 };
+                ui.label(
+                    egui::RichText::new(get_localized_description())
+                        .size(desc_size)
+                        .color(egui::Color32::from_rgb(180, 180, 180))
+                        .italics(),
+                );
+                if self.read_only_mode {
+                    ui.label(
+                        egui::RichText::new(
+                            "Read-only mode: the kernel refused writes to sysfs (Secure Boot lockdown or IMA policy). \
+                             Temperatures are still monitored, but controls are disabled.",
+                        )
+                        .size(desc_size)
+                        .color(egui::Color32::from_rgb(255, 200, 100)),
+                    );
+                }
+
+                self.render_update_banner(ui, desc_size);
+
+                // Most controls need the ideapad-laptop module
+                if self.driver_status.as_ref().is_some_and(|status| !status.loaded) {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label(
+                            egui::RichText::new("ideapad-laptop driver not loaded. Run: sudo modprobe ideapad-laptop")
+                                .size(desc_size)
+                                .color(egui::Color32::from_rgb(255, 100, 100)),
+                        );
+                        if ui.small_button("Load driver").on_hover_text(HelpText::get("load_driver")).clicked() {
+                            self.load_driver();
+                        }
+                    });
+                }
+
+                if self.low_battery_safe_mode {
+                    ui.label(
+                        egui::RichText::new(LOW_BATTERY_HINT)
+                            .size(desc_size + 1.0)
+                            .strong()
+                            .color(egui::Color32::from_rgb(255, 165, 0)),
+                    );
+                }
+
+                // Suggest slower polling when the monitor itself costs too much CPU
+                if let Some(pct) = self.overhead_pct.filter(|pct| *pct > OVERHEAD_WARNING_PCT) {
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "Monitor uses {:.1}% CPU - consider increasing the polling interval",
+                            pct
+                        ))
+                        .size(desc_size)
+                        .color(egui::Color32::from_rgb(255, 200, 100)),
+                    );
+                }
+                ui.separator();
+
+                // Temperatures and Performance - side by side on wide, stacked on narrow
+                if is_wide {
+                    ui.horizontal(|ui| {
+                        let half_width = (available_width - 20.0) / 2.0;
+                        ui.group(|ui| {
+                            ui.set_width(half_width);
+                            ui.label(egui::RichText::new("Temperatures").size(13.0).strong());
+                            self.render_temperatures_adaptive(ui, is_medium);
+                            self.render_zone_legend(ui, is_medium);
+                            self.render_process_pie(ui, is_medium);
+                            self.render_keyboard_heatmap(ui);
+                        });
+                        ui.group(|ui| {
+                            ui.set_width(half_width);
+                            ui.label(egui::RichText::new("Performance").size(13.0).strong());
+                            self.render_performance_adaptive(ui, is_medium);
+                            self.render_refresh_rate(ui);
+                            self.render_cpu_boost(ui);
+                            self.render_cpu_topology(ui);
+                        });
+                    });
+                } else {
+                    ui.group(|ui| {
+                        ui.label(egui::RichText::new("Temperatures").size(13.0).strong());
+                        self.render_temperatures_adaptive(ui, is_medium);
+                        self.render_zone_legend(ui, is_medium);
+                        self.render_process_pie(ui, is_medium);
+                        self.render_keyboard_heatmap(ui);
+                    });
+                    ui.group(|ui| {
+                        ui.label(egui::RichText::new("Performance").size(13.0).strong());
+                        self.render_performance_adaptive(ui, is_medium);
+                        self.render_refresh_rate(ui);
+                        self.render_cpu_boost(ui);
+                        self.render_cpu_topology(ui);
+                    });
+                }
+
+                ui.group(|ui| {
+                    ui.label(egui::RichText::new("Custom").size(13.0).strong());
+                    self.render_custom_metrics(ui);
+                });
+
+                // Mode Control - button grid
+                ui.group(|ui| {
+                    ui.label(egui::RichText::new("Mode Control").size(13.0).strong());
+                    self.render_controls_adaptive(ui, available_width);
+                    self.render_benchmark_control(ui);
+                    self.render_power_profiles(ui);
+                });
+
+                // Target and Fan - side by side on wide, stacked on narrow
+                if is_medium {
+                    ui.horizontal(|ui| {
+                        let half_width = (available_width - 20.0) / 2.0;
+                        ui.group(|ui| {
+                            ui.set_width(half_width);
+                            ui.label(egui::RichText::new("Target Temp").size(13.0).strong());
+                            self.render_target_temp_adaptive(ui, is_wide);
+                        });
+                        ui.group(|ui| {
+                            ui.set_width(half_width);
+                            ui.label(egui::RichText::new("Fan").size(13.0).strong());
+                            self.render_fan_control_adaptive(ui, is_wide);
+                        });
+                    });
+                } else {
+                    ui.group(|ui| {
+                        ui.label(egui::RichText::new("Target Temp").size(13.0).strong());
+                        self.render_target_temp_adaptive(ui, is_wide);
+                    });
+                    ui.group(|ui| {
+                        ui.label(egui::RichText::new("Fan").size(13.0).strong());
+                        self.render_fan_control_adaptive(ui, is_wide);
+                    });
+                }
+
+                // History graph - adaptive height
+                let target = self.target_temp;
+                let graph_height = if is_wide { 180.0 } else if is_medium { 120.0 } else { 80.0 };
+                ui.group(|ui| {
+                    ui.label(egui::RichText::new("History").size(13.0).strong())
+                        .on_hover_text(HelpText::get("history"));
+                    self.render_history_adaptive(ui, target, graph_height);
+                    self.render_performance_history_chart(ui, graph_height * 0.6);
+                    self.render_periodicity_chart(ui, graph_height * 0.6);
+                    self.render_event_log(ui);
+                });
+
+                if !self.plugins.is_empty() {
+                    ui.group(|ui| self.render_plugin_panels(ui));
+                }
+
+                // Status bar
+                self.render_status(ui);
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_capacity() {
+        let mut history = TemperatureHistory::new(3);
+        history.push(40.0, 35.0, SystemTime::now());
+        history.push(42.0, 36.0, SystemTime::now());
+        history.push(44.0, 37.0, SystemTime::now());
+        assert_eq!(history.len(), 3);
+
+        history.push(46.0, 38.0, SystemTime::now());
+        assert_eq!(history.len(), 3); // Should not exceed capacity
+    }
+
+    #[test]
+    fn test_history_empty() {
+        let history = TemperatureHistory::new(10);
+        assert!(history.is_empty());
+        assert_eq!(history.len(), 0);
+    }
+
+    #[test]
+    fn test_history_default() {
+        let history = TemperatureHistory::default();
+        assert!(history.is_empty());
+        assert_eq!(history.capacity, HISTORY_CAPACITY);
+    }
+
+    #[test]
+    fn test_history_points() {
+        let mut history = TemperatureHistory::new(10);
+        history.push(40.0, 35.0, SystemTime::now());
+        history.push(42.0, 36.0, SystemTime::now());
+
+        let cpu_segments = history.cpu_segments();
+        let kbd_segments = history.kbd_segments();
+
+        // Verify points are generated correctly
+        assert!(!history.is_empty());
+        assert_eq!(history.len(), 2);
+        assert_eq!(cpu_segments.len(), 1);
+        assert_eq!(kbd_segments[0].points().len(), 2);
+
+        // A gap sentinel splits the line instead of being bridged
+        history.push(f32::NAN, f32::NAN, SystemTime::now());
+        history.push(44.0, 37.0, SystemTime::now());
+        assert_eq!(history.cpu_segments().len(), 2);
+    }
+
+    #[test]
+    fn test_history_fifo_behavior() {
+        let mut history = TemperatureHistory::new(2);
+        history.push(10.0, 5.0, SystemTime::now());  // First in
+        history.push(20.0, 10.0, SystemTime::now());
+        history.push(30.0, 15.0, SystemTime::now()); // Should push out first
+
+        assert_eq!(history.len(), 2);
+        // First value (10.0) should be gone
+    }
+
+    #[test]
+    fn test_threshold_crossings_none() {
+        let mut history = TemperatureHistory::new(10);
+        for t in [40.0, 45.0, 50.0, 49.0] {
+            history.push(t, 30.0, SystemTime::now());
+        }
+        assert!(history.alert_threshold_crossings(55.0).is_empty());
+    }
+
+    #[test]
+    fn test_threshold_crossings_first_sample() {
+        let mut history = TemperatureHistory::new(10);
+        history.push(60.0, 30.0, SystemTime::now());
+        history.push(50.0, 30.0, SystemTime::now());
+        assert_eq!(history.alert_threshold_crossings(55.0), vec![0]);
+    }
+
+    #[test]
+    fn test_threshold_crossings_sustained() {
+        let mut history = TemperatureHistory::new(10);
+        for t in [50.0, 56.0, 60.0, 62.0, 58.0] {
+            history.push(t, 30.0, SystemTime::now());
+        }
+        assert_eq!(history.alert_threshold_crossings(55.0), vec![1]);
+    }
+
+    #[test]
+    fn test_threshold_crossings_multiple() {
+        let mut history = TemperatureHistory::new(10);
+        for t in [50.0, 56.0, 54.0, 55.0, 57.0, 40.0, 60.0] {
+            history.push(t, 30.0, SystemTime::now());
+        }
+        // Equal to threshold is not above it
+        assert_eq!(history.alert_threshold_crossings(55.0), vec![1, 4, 6]);
+    }
+
+    #[test]
+    fn test_channel_min_max_all() {
+        let mut history = TemperatureHistory::new(10);
+        history.push(50.0, 35.0, SystemTime::now());
+        history.push(62.0, 40.0, SystemTime::now());
+        history.push(45.0, 33.0, SystemTime::now());
+
+        let bounds = history.channel_min_max_all();
+        assert_eq!(bounds.len(), 2);
+        assert_eq!(bounds["cpu"], (45.0, 62.0));
+        assert_eq!(bounds["kbd"], (33.0, 40.0));
+    }
+
+    #[test]
+    fn test_channel_min_max_all_empty() {
+        let history = TemperatureHistory::new(10);
+        assert!(history.channel_min_max_all().is_empty());
+    }
+
+    #[test]
+    fn test_history_from_time_series_resamples() {
+        let series = [
+            (Duration::from_millis(10_000), 40.0),
+            (Duration::from_millis(10_500), 41.0), // same 2 s slot, replaces 40.0
+            (Duration::from_millis(12_100), 45.0),
+            (Duration::from_millis(16_000), 50.0),
+        ];
+        let history = TemperatureHistory::from_time_series(&series, Duration::from_secs(2), 10, 28.0);
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.cpu_temps, [41.0, 45.0, 50.0]);
+    }
+
+    #[test]
+    fn test_history_from_time_series_empty_and_capped() {
+        let empty = TemperatureHistory::from_time_series(&[], Duration::from_secs(2), 10, 28.0);
+        assert!(empty.is_empty());
+
+        let series: Vec<_> = (0..20).map(|i| (Duration::from_secs(i * 2), i as f32)).collect();
+        let history = TemperatureHistory::from_time_series(&series, Duration::from_secs(2), 5, 28.0);
+        assert_eq!(history.len(), 5);
+        assert_eq!(history.cpu_temps.back(), Some(&19.0));
+    }
+
+    #[test]
+    fn test_history_elapsed_time_axis() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut history = TemperatureHistory::new(3);
+        for i in 0..4 {
+            history.push(40.0 + i as f32, 35.0, start + Duration::from_secs(i * 2));
+        }
+
+        // Oldest sample was evicted, so the axis starts at the second one
+        assert_eq!(history.oldest_time(), Some(start + Duration::from_secs(2)));
+        assert_eq!(history.elapsed_secs().collect::<Vec<_>>(), vec![0.0, 2.0, 4.0]);
+        assert_eq!(history.span_secs(), 4.0);
+    }
+
+    #[test]
+    fn test_format_iso8601() {
+        assert_eq!(format_iso8601(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(format_iso8601(UNIX_EPOCH + Duration::from_secs(951_827_696)), "2000-02-29T12:34:56Z");
+        assert_eq!(format_iso8601(UNIX_EPOCH + Duration::from_secs(1_735_689_599)), "2024-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let mut history = TemperatureHistory::new(5);
+        history.push(45.0, 35.5, UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        history.push(47.25, 36.0, UNIX_EPOCH + Duration::from_secs(1_700_000_002));
+
+        let mut written = Vec::new();
+        history.export_csv(&mut written).unwrap();
+        assert_eq!(written, history.as_csv_bytes());
+
+        let csv = String::from_utf8(written).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "timestamp,cpu_temp,kbd_temp");
+        assert_eq!(lines[1], "2023-11-14T22:13:20Z,45.0,35.5");
+        assert_eq!(lines[2], "2023-11-14T22:13:22Z,47.2,36.0");
+        assert_eq!(lines.len(), 3);
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut history = TemperatureHistory::new(5);
+        history.push(45.0, 35.5, start);
+        history.push(f32::NAN, f32::NAN, start + Duration::from_secs(2));
+        history.push(47.5, 36.0, start + Duration::from_secs(4));
+
+        let csv = String::from_utf8(history.as_csv_bytes()).unwrap();
+        let parsed = TemperatureHistory::from_csv(&csv, 2).unwrap();
+        assert_eq!(parsed.timestamps, [start + Duration::from_secs(2), start + Duration::from_secs(4)]);
+        assert!(parsed.cpu_temps[0].is_nan());
+        assert_eq!(parsed.cpu_temps[1], 47.5);
+
+        assert!(TemperatureHistory::from_csv("timestamp,cpu_temp,kbd_temp\n2023-11-14T22:13:20Z,45.0\n", 5).is_err());
+        assert!(TemperatureHistory::from_csv("yesterday,45.0,35.0\n", 5).is_err());
+    }
+
+    #[test]
+    fn test_parse_iso8601() {
+        for secs in [0, 951_782_400, 1_700_000_000, 4_102_444_799] {
+            let time = UNIX_EPOCH + Duration::from_secs(secs);
+            assert_eq!(parse_iso8601(&format_iso8601(time)), Some(time));
+        }
+        assert_eq!(parse_iso8601("2023-13-01T00:00:00Z"), None);
+        assert_eq!(parse_iso8601("2023-11-14 22:13:20"), None);
+        assert_eq!(parse_iso8601("1969-12-31T23:59:59Z"), None);
+    }
+
+    #[test]
+    fn test_append_older_history() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut imported = TemperatureHistory::new(10);
+        imported.push(40.0, 33.0, start);
+        imported.push(41.0, 33.5, start + Duration::from_secs(2));
+
+        // Live data continues right after: no gap
+        let mut live = TemperatureHistory::new(4);
+        live.push(42.0, 34.0, start + Duration::from_secs(4));
+        live.push(43.0, 34.5, start + Duration::from_secs(6));
+        let mut merged = live.clone();
+        merged.append(&imported);
+        assert_eq!(merged.cpu_temps, [40.0, 41.0, 42.0, 43.0]);
+        assert_eq!(merged.count_gaps(), 0);
+
+        // Imported samples overlapping the live ones are skipped
+        let mut overlapping = live.clone();
+        overlapping.append(&merged);
+        assert_eq!(overlapping.cpu_temps, [40.0, 41.0, 42.0, 43.0]);
+
+        // Live data an hour later: a sentinel marks the gap and the oldest sample is dropped
+        let mut live = TemperatureHistory::new(4);
+        live.push(50.0, 38.0, start + Duration::from_secs(3600));
+        live.push(51.0, 38.5, start + Duration::from_secs(3602));
+        live.append(&imported);
+        assert_eq!(live.len(), 4);
+        assert_eq!(live.count_gaps(), 1);
+        assert_eq!(live.cpu_temps[0], 41.0);
+        assert!(live.cpu_temps[1].is_nan());
+        assert_eq!(live.timestamps[1], start + Duration::from_secs(4));
+
+        // The plot splits the line around the sentinel
+        assert_eq!(live.cpu_segments().len(), 2);
+    }
+
+    #[test]
+    fn test_channel_add_static() {
+        let mut history = TemperatureHistory::new(4);
+        history.push(50.0, 40.0, UNIX_EPOCH);
+        history.channel_add_static("TJmax", 100.0);
+        history.channel_add_static("Warranty", 90.0);
+        // Re-adding replaces the value instead of duplicating the channel
+        history.channel_add_static("TJmax", 95.0);
+
+        let channels = history.static_channel_points();
+        let names: Vec<&str> = channels.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["Warranty", "TJmax"]);
+        assert_eq!(history.static_channels[1].1, [95.0; 4]);
+        // Sampled channels are untouched
+        assert_eq!(history.len(), 1);
+        assert!(history.channel_stats("TJmax").is_none());
+
+        history.channel_remove_static("Warranty");
+        assert_eq!(history.static_channel_points().len(), 1);
+    }
+
+    #[test]
+    fn test_linear_regression() {
+        let mut history = TemperatureHistory::new(10);
+        assert_eq!(history.linear_regression(), None);
+        history.push(50.0, 40.0, UNIX_EPOCH);
+        assert_eq!(history.linear_regression(), None);
+
+        // Two points
+        history.push(53.0, 40.0, UNIX_EPOCH + Duration::from_secs(2));
+        assert_eq!(history.linear_regression(), Some((3.0, 50.0)));
+
+        // Identical values
+        let mut flat = TemperatureHistory::new(10);
+        for i in 0..5 {
+            flat.push(60.0, 40.0, UNIX_EPOCH + Duration::from_secs(i * 2));
+        }
+        assert_eq!(flat.linear_regression(), Some((0.0, 60.0)));
+
+        // Linearly increasing, with a gap that is skipped
+        let mut rising = TemperatureHistory::new(10);
+        for (i, temp) in [40.0, 42.5, f32::NAN, 47.5, 50.0].into_iter().enumerate() {
+            rising.push(temp, 40.0, UNIX_EPOCH + Duration::from_secs(i as u64 * 2));
+        }
+        let (slope, intercept) = rising.linear_regression().unwrap();
+        assert!((slope - 2.5).abs() < 1e-4 && (intercept - 40.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_forecast_next_n() {
+        let mut history = TemperatureHistory::new(10);
+        assert!(history.forecast_next_n(3, ForecastModel::Linear).is_empty());
+
+        // Heating by 1°C per poll
+        let start = UNIX_EPOCH;
+        for i in 0..5 {
+            let time = start + Duration::from_secs_f32(i as f32 * UPDATE_INTERVAL_SECS);
+            history.push(50.0 + i as f32, 40.0, time);
+        }
+        let close = |a: &[f32], b: &[f32]| a.len() == b.len() && a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-3);
+        assert!(close(&history.forecast_next_n(3, ForecastModel::Linear), &[55.0, 56.0, 57.0]));
+        assert!(close(&history.forecast_next_n(3, ForecastModel::Exponential), &[55.0, 55.8, 56.44]));
+        assert_eq!(history.forecast_next_n(2, ForecastModel::LastValue), [54.0, 54.0]);
+
+        // A single sample has no trend
+        let mut single = TemperatureHistory::new(10);
+        single.push(60.0, 40.0, start);
+        assert_eq!(single.forecast_next_n(2, ForecastModel::Linear), [60.0, 60.0]);
+    }
+
+    #[test]
+    fn test_mode_usage_shares() {
+        assert!(ThermalApp::mode_usage_shares(&HashMap::new()).is_empty());
+
+        let durations = HashMap::from([
+            (Mode::Quiet, Duration::from_secs(20)),
+            (Mode::Balanced, Duration::from_secs(68)),
+            (Mode::Performance, Duration::from_secs(12)),
+            (Mode::Comfort, Duration::ZERO),
+        ]);
+        let shares = ThermalApp::mode_usage_shares(&durations);
+        let modes: Vec<Mode> = shares.iter().map(|(mode, _)| *mode).collect();
+        assert_eq!(modes, [Mode::Balanced, Mode::Quiet, Mode::Performance]);
+        assert!((shares.iter().map(|(_, share)| share).sum::<f32>() - 1.0).abs() < 1e-6);
+        assert_eq!(ThermalApp::format_mode_usage(&shares), "BALANCED: 68% | QUIET: 20% | PERFORMANCE: 12%");
+    }
+
+    #[test]
+    fn test_influxdb_line_protocol() {
+        let mut history = TemperatureHistory::new(5);
+        assert_eq!(history.to_influxdb_line_protocol("thermal"), "");
+        history.push(72.5, 45.0, UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        history.push(73.0, 45.25, UNIX_EPOCH + Duration::from_millis(1_700_000_002_500));
+
+        assert_eq!(
+            history.to_influxdb_line_protocol("thermal"),
+            "thermal cpu_temp=72.5,kbd_temp=45.0 1700000000000000000\n\
+             thermal cpu_temp=73.0,kbd_temp=45.2 1700000002500000000\n"
+        );
+        // Measurement names escape commas and spaces
+        assert!(history.to_influxdb_line_protocol("lenovo thermal,gui").starts_with("lenovo\\ thermal\\,gui cpu_temp="));
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        history.export_influxdb(file.path()).unwrap();
+        assert_eq!(fs::read_to_string(file.path()).unwrap(), history.to_influxdb_line_protocol(INFLUXDB_MEASUREMENT));
+    }
+
+    #[test]
+    fn test_percentile_series() {
+        let mut history = TemperatureHistory::new(10);
+        assert!(history.percentile_series(95.0, 3).is_empty());
+        let start = UNIX_EPOCH;
+        for (i, temp) in [50.0, 60.0, 55.0, 40.0, f32::NAN, f32::NAN, f32::NAN, 45.0].into_iter().enumerate() {
+            history.push(temp, 35.0, start + Duration::from_secs(i as u64 * 2));
+        }
+        let max = history.percentile_series(100.0, 3);
+        assert_eq!(max[..4], [50.0, 60.0, 60.0, 60.0]);
+        // Gaps are skipped; a window of gaps only has no value
+        assert_eq!(max[4..6], [55.0, 40.0]);
+        assert!(max[6].is_nan());
+        assert_eq!(max[7], 45.0);
+        assert_eq!(history.percentile_series(50.0, 3)[3], 55.0);
+        assert_eq!(history.percentile_series(0.0, 3)[2], 50.0);
+        assert_eq!(max.len(), history.len());
+    }
+
+    #[test]
+    fn test_moving_max() {
+        let mut history = TemperatureHistory::new(10);
+        assert!(history.moving_max(3).is_empty());
+        let temps = [50.0, 60.0, 55.0, 40.0, 45.0, 70.0, 42.0];
+        for (i, temp) in temps.into_iter().enumerate() {
+            history.push(temp, 35.0, UNIX_EPOCH + Duration::from_secs(i as u64 * 2));
+        }
+        assert_eq!(history.moving_max(1), temps);
+        assert_eq!(history.moving_max(temps.len()), [70.0; 7]);
+        assert_eq!(history.moving_max(100), [70.0; 7]);
+        assert_eq!(history.moving_max(3), [60.0, 60.0, 60.0, 55.0, 70.0, 70.0, 70.0]);
+
+        // Only samples below their envelope get shaded
+        let polygons = history.envelope_polygons(3);
+        assert_eq!(polygons.len(), 6);
+        assert!(history.envelope_polygons(1).is_empty());
+    }
+
+    #[test]
+    fn test_haar_smooth() {
+        assert!(haar_smooth(&[], 1.0).is_empty());
+        // Level 1 details (±1) are noise, the level 2 step (2) is kept
+        assert_eq!(haar_smooth(&[1.0, 3.0, 5.0, 7.0], 1.5), [2.0, 2.0, 6.0, 6.0]);
+        assert_eq!(haar_smooth(&[1.0, 3.0, 5.0, 7.0], 0.0), [1.0, 3.0, 5.0, 7.0]);
+        // Padding to 4 with the last value; a large threshold leaves the mean
+        assert_eq!(haar_smooth(&[40.0, 42.0, 44.0], 10.0), [42.5, 42.5, 42.5]);
+
+        // A sudden rise survives the wavelet but is smeared by the moving average
+        let step = [40.0, 40.2, 39.8, 40.0, 60.0, 60.2, 59.8, 60.0];
+        assert_eq!(haar_smooth(&step, 0.5), [40.0, 40.0, 40.0, 40.0, 60.0, 60.0, 60.0, 60.0]);
+        assert_eq!(trailing_mean(&step, 2)[4], 50.0);
+    }
+
+    #[test]
+    fn test_smoothed_history_keeps_gaps() {
+        let mut history = TemperatureHistory::new(10);
+        for (i, temp) in [40.0, 42.0, f32::NAN, 50.0, 54.0].into_iter().enumerate() {
+            history.push(temp, 35.0, UNIX_EPOCH + Duration::from_secs(i as u64 * 2));
+        }
+        let averaged = history.moving_average(2);
+        let cpu: Vec<f32> = averaged.cpu_temps.iter().copied().collect();
+        assert_eq!(cpu[..2], [40.0, 41.0]);
+        assert!(cpu[2].is_nan());
+        assert_eq!(cpu[3..], [50.0, 52.0]);
+        let smoothed = history.wavelet_smoothed(5.0);
+        let cpu: Vec<f32> = smoothed.cpu_temps.iter().copied().collect();
+        assert_eq!(cpu[3..], [52.0, 52.0]);
+        assert_eq!(smoothed.len(), history.len());
+    }
+
+    #[test]
+    fn test_channel_stats() {
+        let mut history = TemperatureHistory::new(100);
+        for t in 1..=100 {
+            history.push(t as f32, 30.0, SystemTime::now());
+        }
+
+        let cpu = history.channel_stats("cpu").unwrap();
+        assert!((cpu.avg - 50.5).abs() < 0.001);
+        assert_eq!(cpu.p95, 95.0);
+        assert_eq!(cpu.p99, 99.0);
+        assert_eq!(cpu.max, 100.0);
+        assert_eq!(history.channel_stats("kbd").unwrap().max, 30.0);
+        assert!(history.channel_stats("gpu").is_none());
+        assert!(TemperatureHistory::new(5).channel_stats("cpu").is_none());
+    }
+
+    #[test]
+    fn test_split_segments_and_stats() {
+        let mut history = TemperatureHistory::new(10);
+        let start = SystemTime::now();
+        for (i, t) in [40.0, 44.0, 60.0, 62.0].into_iter().enumerate() {
+            history.push(t, 30.0, start + Duration::from_secs(2 * i as u64));
+        }
+        let split = start + Duration::from_secs(4);
+
+        assert_eq!(history.split_x(split), 4.0);
+        let (a, b) = history.split_segments("cpu", split);
+        assert_eq!(a.len(), 1);
+        assert_eq!(a[0].points().len(), 3);
+        assert_eq!(b[0].points().len(), 2);
+        assert_eq!(history.split_stats("cpu", split), [Some((40.0, 42.0, 44.0)), Some((60.0, 61.0, 62.0))]);
+        assert_eq!(history.split_stats("cpu", start + Duration::from_secs(60))[1], None);
+        assert_eq!(history.split_stats("gpu", split), [None, None]);
+    }
+
+    #[test]
+    fn test_predict_time_to_threshold_linear_heating() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut history = TemperatureHistory::new(60);
+        // 0.5 °C every 2 s = 0.25 °C/s, ending at 60 °C
+        for i in 0..20u64 {
+            history.push(50.5 + i as f32 * 0.5, 35.0, start + Duration::from_secs(i * 2));
+        }
+
+        assert!((history.rate_of_change().unwrap() - 0.25).abs() < 0.001);
+        let eta = history.predict_time_to_threshold(80.0).unwrap();
+        assert!((eta.as_secs_f32() - 80.0).abs() < 0.1);
+        assert_eq!(history.predict_time_to_threshold(55.0), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_predict_time_to_threshold_flat_or_cooling() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut flat = TemperatureHistory::new(60);
+        let mut cooling = TemperatureHistory::new(60);
+        for i in 0..10u64 {
+            flat.push(50.0, 35.0, start + Duration::from_secs(i * 2));
+            cooling.push(60.0 - i as f32, 35.0, start + Duration::from_secs(i * 2));
+        }
+
+        assert_eq!(flat.predict_time_to_threshold(80.0), None);
+        assert_eq!(cooling.predict_time_to_threshold(80.0), None);
+        assert_eq!(TemperatureHistory::new(5).predict_time_to_threshold(80.0), None);
+    }
+
+    #[test]
+    fn test_time_in_zones() {
+        let mut history = TemperatureHistory::new(10);
+        for t in [35.0, 36.0, 52.0, 70.0] {
+            history.push(t, 30.0, SystemTime::now());
+        }
+
+        let zones = history.time_in_zones();
+        assert_eq!(zones.len(), ThermalZone::all().len());
+        assert_eq!(zones[0], (ThermalZone::Cool, 50.0));
+        assert_eq!(zones[3], (ThermalZone::Warm, 25.0));
+        assert_eq!(zones[5], (ThermalZone::Critical, 25.0));
+        assert!(TemperatureHistory::new(5).time_in_zones().iter().all(|(_, pct)| *pct == 0.0));
+    }
+
+    #[test]
+    fn test_comparison_rows_better_worse() {
+        let mut current = TemperatureHistory::new(10);
+        let mut comparison = TemperatureHistory::new(10);
+        for _ in 0..4 {
+            current.push(42.0, 33.0, SystemTime::now());
+            comparison.push(60.0, 33.0, SystemTime::now());
+        }
+
+        let rows = ThermalApp::comparison_rows(&current, &comparison);
+        assert_eq!(rows.len(), 8 + ThermalZone::all().len());
+        let row = |metric: &str| rows.iter().find(|r| r.metric == metric).unwrap();
+        assert_eq!(row("CPU avg").current_is_better(), Some(true));
+        assert_eq!(row("KBD max").current_is_better(), None);
+        assert_eq!(row("Time in COMFORT").current_is_better(), Some(true));
+        assert_eq!(row("Time in HOT").current_is_better(), Some(true));
+        assert_eq!(row("Time in CRITICAL").current_is_better(), None);
+    }
+
+    #[test]
+    fn test_gnuplot_script() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut history = TemperatureHistory::new(5);
+        history.push(45.0, 35.5, start);
+        history.push(47.0, 36.0, start + Duration::from_secs(2));
+
+        let script = history.gnuplot_script(55.0);
+        assert!(script.contains("set terminal pngcairo"));
+        assert!(script.contains("set output \"thermal_history.png\""));
+        assert!(script.contains("set xlabel \"Time (s)\""));
+        assert!(script.contains("$history << EOD\n0.0 45.0 35.5\n2.0 47.0 36.0\nEOD\n"));
+        // 55 °C is the start of the Hot zone
+        assert!(script.contains("55.0 with lines dt 2 lw 1 lc rgb \"#ff9664\" title \"Target\""));
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        history.to_gnuplot_script(file.path(), 55.0).unwrap();
+        assert_eq!(fs::read_to_string(file.path()).unwrap(), script);
+    }
+
+    #[test]
+    fn test_plotly_html() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut history = TemperatureHistory::new(5);
+        history.push(45.0, 35.5, start);
+        history.push(f32::NAN, f32::NAN, start + Duration::from_secs(2));
+        history.push(47.0, 36.0, start + Duration::from_secs(4));
+
+        let html = history.plotly_html(55.0);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains(&format!("<script src=\"{}\"></script>", PLOTLY_CDN_URL)));
+        assert!(html.contains(
+            "const time = [\"2023-11-14T22:13:20Z\",\"2023-11-14T22:13:22Z\",\"2023-11-14T22:13:24Z\"];"
+        ));
+        assert!(html.contains("\"y\":[45.0,null,47.0],\"name\":\"CPU\""));
+        assert!(html.contains("\"y\":[35.5,null,36.0],\"name\":\"Kbd\""));
+        assert!(html.contains("\"x\":[\"2023-11-14T22:13:20Z\",\"2023-11-14T22:13:24Z\"],\"y\":[55.0,55.0],\"name\":\"Target\""));
+        // One background band per zone, open ends clamped to the axis
+        assert_eq!(html.matches("\"type\":\"rect\"").count(), ThermalZone::all().len());
+        assert!(html.contains("\"y0\":0.0,\"y1\":40.0"));
+        assert!(html.contains("\"y0\":65.0,\"y1\":110.0"));
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        history.export_plotly_html(file.path(), 55.0).unwrap();
+        assert_eq!(fs::read_to_string(file.path()).unwrap(), html);
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut history = TemperatureHistory::new(5);
+        for (i, (cpu, kbd)) in [(45.5, 35.25), (50.0, 37.0), (61.75, 41.5)].into_iter().enumerate() {
+            history.push(cpu, kbd, UNIX_EPOCH + Duration::from_millis(1_700_000_000_000 + i as u64 * 2000));
+        }
+
+        let data = history.serialize();
+        assert_eq!(&data[..4], b"LTHM");
+        assert_eq!(data.len(), 12 + 3 * 16);
+
+        let restored = TemperatureHistory::deserialize(&data).unwrap();
+        assert_eq!(restored.capacity, 5);
+        assert_eq!(restored.cpu_temps, history.cpu_temps);
+        assert_eq!(restored.kbd_temps, history.kbd_temps);
+        assert_eq!(restored.timestamps, history.timestamps);
+    }
+
+    #[test]
+    fn test_snapshot_rejects_corrupt_data() {
+        let mut data = TemperatureHistory::new(5).serialize();
+        data[0] = b'X';
+        assert!(TemperatureHistory::deserialize(&data).is_err());
+
+        let mut truncated = TemperatureHistory::new(5).serialize();
+        truncated.extend_from_slice(&[0, 0, 0]);
+        assert!(TemperatureHistory::deserialize(&truncated).is_err());
+
+        assert!(TemperatureHistory::deserialize(b"LTH").is_err());
+
+        let mut oversized = TemperatureHistory::new(5).serialize();
+        oversized[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(TemperatureHistory::deserialize(&oversized).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "simd")]
+    #[test]
+    fn test_as_simd_array_pads_to_lanes() {
+        let mut history = TemperatureHistory::new(20);
+        for t in 0..10 {
+            history.push(40.0 + t as f32, 30.0, SystemTime::now());
+        }
+
+        let packed = history.as_simd_array();
+        assert_eq!(packed.len(), 2);
+        assert_eq!(packed[0].to_array(), [40.0, 41.0, 42.0, 43.0, 44.0, 45.0, 46.0, 47.0]);
+        assert_eq!(packed[1].to_array(), [48.0, 49.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        assert!(TemperatureHistory::new(5).as_simd_array().is_empty());
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_as_ndarray_matches_cpu_points() {
+        let mut history = TemperatureHistory::new(10);
+        let start = SystemTime::now();
+        history.push(40.0, 30.0, start);
+        history.push(f32::NAN, f32::NAN, start + Duration::from_secs(2));
+        history.push(44.0, 32.0, start + Duration::from_secs(4));
+
+        let temps = history.as_ndarray();
+        assert_eq!(temps.dim(), (2, 2));
+        assert!(temps.is_standard_layout());
+        assert_eq!(temps.as_slice(), Some(&[40.0, 30.0, 44.0, 32.0][..]));
+        let points: Vec<f64> = history.cpu_segments().iter().flat_map(|run| run.points().iter().map(|p| p.y)).collect();
+        assert_eq!(points.len(), temps.nrows());
+        assert_eq!(points[1] as f32, temps[[1, 0]]);
+
+        let npy = history.as_npy_bytes();
+        assert!(npy.starts_with(b"\x93NUMPY\x01\x00"));
+        let data_start = 10 + u16::from_le_bytes([npy[8], npy[9]]) as usize;
+        assert_eq!(data_start % 64, 0);
+        let header = std::str::from_utf8(&npy[10..data_start]).unwrap();
+        assert!(header.contains("'shape': (2, 2)") && header.ends_with('\n'));
+        assert_eq!(&npy[data_start..data_start + 4], 40.0f32.to_le_bytes());
+        assert_eq!(npy.len(), data_start + 4 * 4);
+    }
+
+    #[cfg(feature = "intel-rapl")]
+    #[test]
+    fn test_history_power_tracking() {
+        let mut history = TemperatureHistory::new(2);
+        assert_eq!(history.avg_power(), None);
+        history.push_power(10.0);
+        history.push_power(20.0);
+        history.push_power(30.0);
+        assert_eq!(history.avg_power(), Some(25.0));
+    }
+
+    #[test]
+    fn test_keyboard_map_matches_shortcuts() {
+        let map = ThermalApp::keyboard_map();
+        assert_eq!(map.len(), SHORTCUTS.len());
+        assert_eq!(map[0], ("1".to_string(), "Switch to PERFORMANCE mode".to_string()));
+        // Every mode has a shortcut
+        for mode in Mode::all() {
+            assert!(SHORTCUTS.iter().any(|(_, _, action)| *action == ShortcutAction::SetMode(*mode)));
+        }
+        // No key is bound twice
+        for (i, (key, _, _)) in SHORTCUTS.iter().enumerate() {
+            assert!(SHORTCUTS[i + 1..].iter().all(|(other, _, _)| other != key));
+        }
+    }
+
+    #[test]
+    fn test_battery_impact() {
+        assert_eq!(ThermalApp::battery_impact(Mode::Performance, Some(true)), "On AC: no impact");
+        assert_eq!(
+            ThermalApp::battery_impact(Mode::Performance, Some(false)),
+            "On battery: ~2.4h reduction in runtime"
+        );
+        assert_eq!(ThermalApp::battery_impact(Mode::Quiet, Some(false)), "On battery: ~1.4h more runtime");
+        assert_eq!(ThermalApp::battery_impact(Mode::Balanced, Some(false)), "On battery: no change in runtime");
+        assert_eq!(ThermalApp::battery_impact(Mode::Auto, Some(false)), "On battery: impact varies with load");
+        assert_eq!(ThermalApp::battery_impact(Mode::Quiet, None), "Power source unknown");
+    }
+
+    #[test]
+    fn test_fill_gaps() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut history = TemperatureHistory::new(5);
+        for (i, (cpu, kbd)) in [(f32::NAN, f32::NAN), (45.0, 35.0), (f32::NAN, f32::NAN), (f32::NAN, f32::NAN), (50.0, 38.0)]
+            .into_iter()
+            .enumerate()
+        {
+            history.push(cpu, kbd, start + Duration::from_secs(2 * i as u64));
+        }
+        assert_eq!(history.count_gaps(), 3);
+
+        let filled = history.fill_gaps(40.0);
+        assert_eq!(filled.count_gaps(), 0);
+        assert_eq!(filled.cpu_temps, [40.0, 45.0, 45.0, 45.0, 50.0]);
+        assert_eq!(filled.kbd_temps, [40.0, 35.0, 35.0, 35.0, 38.0]);
+        assert_eq!(filled.timestamps, history.timestamps);
+        // The original keeps its sentinels
+        assert_eq!(history.count_gaps(), 3);
+    }
+
+    #[test]
+    fn test_log_rate_limiter() {
+        let start = Instant::now();
+        let mut limiter = LogRateLimiter::default();
+        let decisions: Vec<LogDecision> =
+            (0..11).map(|i| limiter.check(start + Duration::from_secs(2 * i))).collect();
+        assert_eq!(decisions[0], LogDecision::Log);
+        assert_eq!(decisions[1..5], [LogDecision::Suppress; 4]);
+        assert_eq!(decisions[5], LogDecision::Summary(5));
+        assert_eq!(decisions[10], LogDecision::Summary(10));
+
+        // A new window starts once the previous one has passed
+        assert_eq!(limiter.check(start + AUTO_LOG_WINDOW), LogDecision::Log);
+        assert_eq!(limiter.check(start + AUTO_LOG_WINDOW + Duration::from_secs(2)), LogDecision::Suppress);
+    }
+
+    #[test]
+    fn test_perf_bars_use_zone_at_sample_time() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut history = TemperatureHistory::new(3);
+        let mut perf = SensorHistory::new(3);
+        history.push(42.0, 35.0, start);
+        history.push(58.0, 40.0, start + Duration::from_secs(2));
+        perf.push(90.0);
+        history.push(70.0, 45.0, start + Duration::from_secs(4));
+        perf.push(60.0);
+
+        assert_eq!(
+            history.perf_bars(&perf),
+            vec![(2.0, 90.0, ThermalZone::Hot), (4.0, 60.0, ThermalZone::Critical)]
+        );
+    }
+
+    #[test]
+    fn test_crash_log_entry() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_714_572_189);
+        assert_eq!(
+            ThermalApp::crash_log_entry(time, "panicked at src/app.rs:10:5:\nindex out of bounds"),
+            "2024-05-01T14:03:09Z panicked at src/app.rs:10:5: | index out of bounds"
+        );
+        assert_eq!(ThermalApp::crash_log_path().file_name().unwrap(), "crashes.log");
+    }
+
+    #[test]
+    fn test_crash_issue_url() {
+        assert_eq!(percent_encode("a-b_c.d~e"), "a-b_c.d~e");
+        assert_eq!(percent_encode("x y&z=°\n"), "x%20y%26z%3D%C2%B0%0A");
+        let url = ThermalApp::crash_issue_url("Thermal Monitor 1.3.0\n");
+        assert!(url.starts_with("https://github.com/andresgarcia0313/lenovo-ideapad/issues/new?title=Crash%20report&body="));
+        assert!(url.ends_with("%60%60%60%0AThermal%20Monitor%201.3.0%0A%60%60%60%0A"));
+    }
+
+    #[test]
+    fn test_recorded_actions_shell_script() {
+        let actions = [
+            RecordedAction { at: Duration::from_millis(300), step: RecordedStep::Mode(Mode::Quiet) },
+            RecordedAction { at: Duration::from_millis(12_400), step: RecordedStep::FanBoost(true) },
+            RecordedAction { at: Duration::from_millis(12_500), step: RecordedStep::Target(48.0) },
+        ];
+        let script = RecordedAction::shell_script(&actions);
+        let body: Vec<&str> = script.lines().skip(3).collect();
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert_eq!(
+            body,
+            [
+                "thermal-monitor --set-mode quiet",
+                "sleep 12",
+                "thermal-monitor --set-fan-boost on",
+                "thermal-monitor --set-target 48",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_temperature_unit_conversion() {
+        assert_eq!(to_display(100.0, TemperatureUnit::Fahrenheit), 212.0);
+        assert_eq!(to_display(55.0, TemperatureUnit::Celsius), 55.0);
+        assert_eq!(format_temp(50.0, TemperatureUnit::Fahrenheit), "122°F");
+        assert_eq!(format_temp(49.6, TemperatureUnit::Celsius), "50°C");
+        assert!((from_display(to_display(47.5, TemperatureUnit::Fahrenheit), TemperatureUnit::Fahrenheit) - 47.5).abs() < 1e-4);
+        assert_eq!(to_display_delta(5.0, TemperatureUnit::Fahrenheit), 9.0);
+        assert!((from_display_delta(9.0, TemperatureUnit::Fahrenheit) - 5.0).abs() < 1e-4);
+        assert_eq!(from_display_delta(12.0, TemperatureUnit::Celsius), 12.0);
+    }
+
+    #[test]
+    fn test_notification_cooldowns() {
+        let config = Config::default();
+        assert_eq!(AlertKind::ThresholdCrossing.notification_cooldown(&config), Some(Duration::from_secs(60)));
+        assert_eq!(AlertKind::CriticalZone.notification_cooldown(&config), Some(Duration::from_secs(30)));
+        assert_eq!(AlertKind::ModeChange.notification_cooldown(&config), Some(Duration::ZERO));
+        assert_eq!(AlertKind::ZoneTransition.notification_cooldown(&config), None);
+
+        let now = Instant::now();
+        let cooldown = Duration::from_secs(30);
+        assert!(ThermalApp::notification_due(None, now, cooldown));
+        assert!(!ThermalApp::notification_due(Some(now), now + Duration::from_secs(10), cooldown));
+        assert!(ThermalApp::notification_due(Some(now), now + cooldown, cooldown));
+        assert!(ThermalApp::notification_due(Some(now), now, Duration::ZERO));
+    }
+
+    #[test]
+    fn test_learning_observations() {
+        let observation = LearningObservation { session: 1_700_000_000, zone: ThermalZone::Warm, mode: Mode::Balanced };
+        assert_eq!(observation.to_line(), "1700000000 warm balanced");
+        assert_eq!(LearningObservation::parse(&observation.to_line()), Some(observation));
+        // Older lines with the hour of day still parse
+        assert_eq!(LearningObservation::parse("1700000000 warm 14 balanced"), Some(observation));
+        assert_eq!(LearningObservation::parse("1700000000 warm 24 balanced"), None);
+        assert_eq!(LearningObservation::parse("garbage"), None);
 
-            if is_wide {
-                ui.label(egui::RichText::new("Max cooling").size(9.0).color(egui::Color32::DARK_GRAY));
-            }
-        });
+        let log: Vec<LearningObservation> = [
+            "1 warm balanced",
+            "1 warm quiet",
+            "2 warm 11 quiet",
+            "2 cool performance",
+            "3 hot quiet",
+            "3 hot comfort",
+        ]
+        .iter()
+        .filter_map(|line| LearningObservation::parse(line))
+        .collect();
+        assert_eq!(LearningObservation::session_count(&log), 3);
+        assert_eq!(
+            LearningObservation::majority_modes(&log),
+            [
+                (ThermalZone::Cool, Mode::Performance, 1, 1),
+                (ThermalZone::Warm, Mode::Quiet, 2, 3),
+                (ThermalZone::Hot, Mode::Comfort, 1, 2),
+            ]
+        );
     }
 
-    /// Render history graph - adaptive version
-    fn render_history_adaptive(&self, ui: &mut egui::Ui, target_temp: f32, height: f32) {
-        if self.history.is_empty() {
-            ui.label("Collecting data...");
-            return;
-        }
+    #[test]
+    fn test_benchmark_timer_starts_once() {
+        let mut benchmark = Benchmark {
+            saved_mode: Mode::Quiet,
+            saved_fan_boost: false,
+            saved_auto_control: true,
+            duration: Duration::from_secs(60),
+            ends_at: None,
+        };
+        let now = Instant::now();
+        assert_eq!(benchmark.remaining(now), None);
 
-        let cpu_line = Line::new(self.history.cpu_points())
-            .name("CPU")
-            .color(egui::Color32::from_rgb(255, 100, 100))
-            .width(2.0);
+        benchmark.start_timer(now);
+        assert_eq!(benchmark.remaining(now), Some(Duration::from_secs(60)));
+        benchmark.start_timer(now + Duration::from_secs(30));
+        assert_eq!(benchmark.remaining(now + Duration::from_secs(30)), Some(Duration::from_secs(30)));
+        assert_eq!(benchmark.remaining(now + Duration::from_secs(90)), Some(Duration::ZERO));
+    }
 
-        let kbd_line = Line::new(self.history.kbd_points())
-            .name("Kbd")
-            .color(egui::Color32::from_rgb(100, 200, 255))
-            .width(2.0);
+    #[test]
+    fn test_benchmark_mode_to_restore() {
+        let benchmark = |saved_mode| Benchmark {
+            saved_mode,
+            saved_fan_boost: true,
+            saved_auto_control: false,
+            duration: Duration::from_secs(60),
+            ends_at: None,
+        };
+        assert_eq!(benchmark(Mode::Quiet).mode_to_restore(), Some(Mode::Quiet));
+        assert_eq!(benchmark(Mode::Performance).mode_to_restore(), Some(Mode::Performance));
+        assert_eq!(benchmark(Mode::Unknown).mode_to_restore(), None);
+    }
 
-        let target_points: Vec<[f64; 2]> = (0..HISTORY_CAPACITY)
-            .map(|i| [i as f64, target_temp as f64])
-            .collect();
-        let target_line = Line::new(PlotPoints::new(target_points))
-            .name("Target")
-            .color(egui::Color32::from_rgb(255, 200, 100))
-            .width(1.0)
-            .style(egui_plot::LineStyle::dashed_loose());
+    #[test]
+    fn test_headroom_alarm_rearms_after_cooling() {
+        assert!(!ThermalApp::headroom_alarm_active(false, 12.0, 10.0));
+        assert!(ThermalApp::headroom_alarm_active(false, 9.5, 10.0));
+        // Hovering around the threshold keeps it fired
+        assert!(ThermalApp::headroom_alarm_active(true, 10.5, 10.0));
+        assert!(ThermalApp::headroom_alarm_active(true, 12.9, 10.0));
+        assert!(!ThermalApp::headroom_alarm_active(true, 13.0, 10.0));
+    }
 
-        Plot::new("temp_history")
-            .height(height)
-            .show_axes(true)
-            .show_grid(true)
-            .include_y(30.0)
-            .include_y(80.0)
-            .allow_zoom(false)
-            .allow_drag(false)
-            .allow_scroll(false)
-            .legend(egui_plot::Legend::default().position(egui_plot::Corner::RightTop))
-            .show(ui, |plot_ui| {
-                plot_ui.line(cpu_line);
-                plot_ui.line(kbd_line);
-                plot_ui.line(target_line);
-            });
+    #[test]
+    fn test_trip_refresh_allowed() {
+        let now = Instant::now();
+        assert!(ThermalApp::trip_refresh_allowed(None, now));
+        assert!(!ThermalApp::trip_refresh_allowed(Some(now), now + Duration::from_millis(400)));
+        assert!(ThermalApp::trip_refresh_allowed(Some(now), now + TRIP_REFRESH_MIN_INTERVAL));
     }
 
-    /// Render status bar
-    fn render_status(&mut self, ui: &mut egui::Ui) {
-        ui.horizontal(|ui| {
-            // Status message (auto-clear after 3 seconds)
-            if let Some((msg, time)) = &self.status_message {
-                if time.elapsed() < Duration::from_secs(3) {
-                    ui.label(egui::RichText::new(msg).size(12.0).color(egui::Color32::YELLOW));
-                } else {
-                    self.status_message = None;
-                }
-            }
+    #[test]
+    fn test_auto_cpu_boost() {
+        assert_eq!(ThermalApp::auto_cpu_boost(Mode::Quiet), Some(false));
+        assert_eq!(ThermalApp::auto_cpu_boost(Mode::Performance), Some(true));
+        assert_eq!(ThermalApp::auto_cpu_boost(Mode::Balanced), Some(true));
+        assert_eq!(ThermalApp::auto_cpu_boost(Mode::Comfort), None);
+    }
 
-            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                ui.label(
-                    egui::RichText::new("Thermal Monitor v1.3.0")
-                        .size(11.0)
-                        .color(egui::Color32::DARK_GRAY),
-                );
-            });
-        });
+    #[test]
+    fn test_refresh_rate_transition() {
+        assert_eq!(ThermalApp::refresh_rate_transition(true, 144, None), Some(CRITICAL_REFRESH_HZ));
+        assert_eq!(ThermalApp::refresh_rate_transition(true, 60, None), None);
+        assert_eq!(ThermalApp::refresh_rate_transition(true, 60, Some(144)), None);
+        assert_eq!(ThermalApp::refresh_rate_transition(false, 60, Some(144)), Some(144));
+        assert_eq!(ThermalApp::refresh_rate_transition(false, 144, None), None);
     }
-}
 
-impl eframe::App for ThermalApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Update state every UPDATE_INTERVAL_SECS
-        if self.last_update.elapsed() >= Duration::from_secs_f32(UPDATE_INTERVAL_SECS) {
-            self.update_state();
-            self.last_update = Instant::now();
+    #[test]
+    fn test_first_run_needed() {
+        let config = Config::default();
+        assert!(ThermalApp::first_run_needed(false, &config));
+        assert!(!ThermalApp::first_run_needed(true, &config));
+        assert!(ThermalApp::first_run_needed(true, &Config { first_run_completed: false, ..config }));
+    }
+
+    #[test]
+    fn test_update_check() {
+        assert!(version_is_newer("v1.4.0", "1.3.2"));
+        assert!(version_is_newer("2.0", "1.9.9"));
+        assert!(!version_is_newer("v1.3.2", "1.3.2"));
+        assert!(!version_is_newer("v1.3.0-rc1", "1.3.2"));
+        assert!(!version_is_newer("nightly", "1.3.2"));
+
+        let json = r#"{"url":"x","tag_name": "v1.4.0","name":"Release 1.4.0"}"#;
+        assert_eq!(release_tag_from_json(json).as_deref(), Some("v1.4.0"));
+        assert_eq!(release_tag_from_json(r#"{"message":"Not Found"}"#), None);
+
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_050_000);
+        assert_eq!(ThermalApp::cached_release_tag("1700000000 v1.4.0\n", now).as_deref(), Some("v1.4.0"));
+        assert_eq!(ThermalApp::cached_release_tag("1600000000 v1.4.0\n", now), None);
+        assert_eq!(ThermalApp::cached_release_tag("garbage", now), None);
+    }
+
+    #[test]
+    fn test_idle_transition() {
+        let timeout = Some(Duration::from_secs(300));
+        assert_eq!(ThermalApp::idle_transition(false, Duration::from_secs(299), timeout), None);
+        assert_eq!(ThermalApp::idle_transition(false, Duration::from_secs(300), timeout), Some(true));
+        assert_eq!(ThermalApp::idle_transition(true, Duration::from_secs(900), timeout), None);
+        // Input resets the idle time
+        assert_eq!(ThermalApp::idle_transition(true, Duration::ZERO, timeout), Some(false));
+        // Disabled: never enters, and leaves if it was active
+        assert_eq!(ThermalApp::idle_transition(false, Duration::from_secs(3600), None), None);
+        assert_eq!(ThermalApp::idle_transition(true, Duration::from_secs(3600), None), Some(false));
+    }
+
+    #[test]
+    fn test_low_battery_transition() {
+        assert_eq!(ThermalApp::low_battery_transition(false, Some(9), 10), Some(true));
+        assert_eq!(ThermalApp::low_battery_transition(false, Some(10), 10), None);
+        // Stays on between the threshold and the restore level
+        assert_eq!(ThermalApp::low_battery_transition(true, Some(12), 10), None);
+        assert_eq!(ThermalApp::low_battery_transition(true, Some(15), 10), None);
+        assert_eq!(ThermalApp::low_battery_transition(true, Some(16), 10), Some(false));
+        // A threshold above the restore level still leaves room to recover
+        assert_eq!(ThermalApp::low_battery_transition(true, Some(22), 25), None);
+        assert_eq!(ThermalApp::low_battery_transition(true, Some(26), 25), Some(false));
+        // No battery: nothing to do
+        assert_eq!(ThermalApp::low_battery_transition(false, None, 10), None);
+    }
+
+    #[test]
+    fn test_validate_monotone_timestamps() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut history = TemperatureHistory::new(5);
+        assert!(history.validate_monotone_timestamps());
+        for secs in [0, 2, 2, 4] {
+            history.push(45.0, 35.0, start + Duration::from_secs(secs));
         }
+        assert!(history.validate_monotone_timestamps());
 
-        // Request repaint to keep updating
-        ctx.request_repaint_after(Duration::from_millis(100));
+        history.push(46.0, 35.0, start + Duration::from_secs(1));
+        assert!(!history.validate_monotone_timestamps());
 
-        // Dark theme
-        ctx.set_visuals(egui::Visuals::dark());
+        // Out-of-order trace events keep their order when resampled
+        let series = [
+            (Duration::from_secs(10), 45.0),
+            (Duration::from_secs(14), 46.0),
+            (Duration::from_secs(4), 47.0),
+            (Duration::from_secs(16), 48.0),
+        ];
+        let imported = TemperatureHistory::from_time_series(&series, Duration::from_secs(2), 10, 28.0);
+        assert!(!imported.validate_monotone_timestamps());
+    }
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            // Get available width to determine layout
-            let available_width = ui.available_width();
-            let is_wide = available_width > 700.0;
-            let is_medium = available_width > 500.0;
+    #[test]
+    fn test_correlation() {
+        let history = |cpu: &[f32], kbd: &[f32]| {
+            let mut history = TemperatureHistory::new(10);
+            for (&cpu, &kbd) in cpu.iter().zip(kbd) {
+                history.push(cpu, kbd, SystemTime::now());
+            }
+            history
+        };
+        let rising = history(&[40.0, 45.0, 50.0, 60.0], &[30.0, 32.0, 34.0, 38.0]);
+        let falling = history(&[40.0, 45.0, 50.0, 60.0], &[38.0, 36.0, 34.0, 30.0]);
+        let constant = history(&[50.0, 50.0, 50.0, 50.0], &[35.0, 35.0, 35.0, 35.0]);
 
-            // Adaptive spacing
-            let spacing = if is_wide { 8.0 } else { 4.0 };
-            ui.spacing_mut().item_spacing = egui::vec2(spacing, spacing);
+        assert!((rising.correlation(&rising) - 1.0).abs() < 1e-6);
+        assert!((rising.correlation(&falling) + 1.0).abs() < 1e-6);
+        assert!(constant.correlation(&constant).is_nan());
+        assert!(rising.correlation(&constant).is_nan());
+        assert!(TemperatureHistory::new(5).correlation(&rising).is_nan());
+    }
 
-            // Use ScrollArea for small windows
-            egui::ScrollArea::vertical().show(ui, |ui| {
-                // Title - adaptive size
-                let title_size = if is_wide { 22.0 } else if is_medium { 18.0 } else { 16.0 };
-                ui.horizontal(|ui| {
-                    ui.heading(egui::RichText::new("Thermal Monitor").size(title_size));
-                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        ui.label(
-                            egui::RichText::new(format!("{}", self.state.platform_profile))
-                                .size(if is_wide { 12.0 } else { 10.0 })
-                                .color(egui::Color32::GRAY),
-                        );
-                    });
-                });
-                // Localized description
-                let desc_size = if is_wide { 12.0 } else { 10.0 };
-                ui.label(
-                    egui::RichText::new(get_localized_description())
-                        .size(desc_size)
-                        .color(egui::Color32::from_rgb(180, 180, 180))
-                        .italics(),
-                );
-                ui.separator();
+    #[test]
+    fn test_webhook_payload() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_714_572_189);
+        let state = ThermalState { cpu_temp: 56.34, keyboard_temp: 39.0, mode: Mode::Balanced, perf_pct: 80, ..Default::default() };
+        assert_eq!(
+            ThermalApp::webhook_payload(time, "zone_transition", "Zone WARM → HOT \"fast\"", &state),
+            "{\"timestamp\":\"2024-05-01T14:03:09Z\",\"event\":\"zone_transition\",\
+             \"message\":\"Zone WARM → HOT \\\"fast\\\"\",\"mode\":\"BALANCED\",\"zone\":\"HOT\",\
+             \"cpu_temp\":56.3,\"keyboard_temp\":39.0,\"perf_pct\":80,\"fan_boost\":false}"
+        );
+        assert_eq!(json_string("a\\b\nc\t"), "\"a\\\\b\\nc\\u0009\"");
+    }
 
-                // Temperatures and Performance - side by side on wide, stacked on narrow
-                if is_wide {
-                    ui.horizontal(|ui| {
-                        let half_width = (available_width - 20.0) / 2.0;
-                        ui.group(|ui| {
-                            ui.set_width(half_width);
-                            ui.label(egui::RichText::new("Temperatures").size(13.0).strong());
-                            self.render_temperatures_adaptive(ui, is_medium);
-                        });
-                        ui.group(|ui| {
-                            ui.set_width(half_width);
-                            ui.label(egui::RichText::new("Performance").size(13.0).strong());
-                            self.render_performance_adaptive(ui, is_medium);
-                        });
-                    });
-                } else {
-                    ui.group(|ui| {
-                        ui.label(egui::RichText::new("Temperatures").size(13.0).strong());
-                        self.render_temperatures_adaptive(ui, is_medium);
-                    });
-                    ui.group(|ui| {
-                        ui.label(egui::RichText::new("Performance").size(13.0).strong());
-                        self.render_performance_adaptive(ui, is_medium);
-                    });
-                }
+    #[test]
+    fn test_state_json_line() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_714_607_999);
+        assert_eq!(ThermalApp::auto_export_file_name(time), "thermal-2024-05-01.jsonl");
+        assert_eq!(ThermalApp::auto_export_file_name(time + Duration::from_secs(1)), "thermal-2024-05-02.jsonl");
 
-                // Mode Control - wrapping buttons
-                ui.group(|ui| {
-                    ui.label(egui::RichText::new("Mode Control").size(13.0).strong());
-                    self.render_controls_adaptive(ui, available_width);
-                });
+        let state = ThermalState {
+            cpu_temp: 52.0,
+            keyboard_temp: 37.5,
+            ambient_temp: 25.0,
+            perf_pct: 75,
+            current_freq_mhz: 2400,
+            mode: Mode::Comfort,
+            platform_profile: "balanced".into(),
+            fan_rpm: Some(2100),
+            on_ac: Some(true),
+            mem_used_pct: 41.25,
+            ..Default::default()
+        };
+        assert_eq!(
+            ThermalApp::state_json_line(time, &state),
+            "{\"timestamp\":\"2024-05-01T23:59:59Z\",\"cpu_temp\":52.0,\"keyboard_temp\":37.5,\"ambient_temp\":25.0,\
+             \"perf_pct\":75,\"freq_mhz\":2400,\"mode\":\"COMFORT\",\"zone\":\"WARM\",\"platform_profile\":\"balanced\",\
+             \"fan_boost\":false,\"fan_rpm\":2100,\"on_ac\":true,\"battery_pct\":null,\"battery_health_pct\":null,\
+             \"mem_used_pct\":41.2}"
+        );
+    }
 
-                // Target and Fan - side by side on wide, stacked on narrow
-                if is_medium {
-                    ui.horizontal(|ui| {
-                        let half_width = (available_width - 20.0) / 2.0;
-                        ui.group(|ui| {
-                            ui.set_width(half_width);
-                            ui.label(egui::RichText::new("Target Temp").size(13.0).strong());
-                            self.render_target_temp_adaptive(ui, is_wide);
-                        });
-                        ui.group(|ui| {
-                            ui.set_width(half_width);
-                            ui.label(egui::RichText::new("Fan").size(13.0).strong());
-                            self.render_fan_control_adaptive(ui, is_wide);
-                        });
-                    });
-                } else {
-                    ui.group(|ui| {
-                        ui.label(egui::RichText::new("Target Temp").size(13.0).strong());
-                        self.render_target_temp_adaptive(ui, is_wide);
-                    });
-                    ui.group(|ui| {
-                        ui.label(egui::RichText::new("Fan").size(13.0).strong());
-                        self.render_fan_control_adaptive(ui, is_wide);
-                    });
-                }
+    #[test]
+    fn test_wheel_mode() {
+        assert_eq!(ThermalApp::wheel_mode(Mode::Comfort, -1.0), Mode::Balanced);
+        assert_eq!(ThermalApp::wheel_mode(Mode::Comfort, 1.0), Mode::Performance);
+        // Wraps at both ends
+        assert_eq!(ThermalApp::wheel_mode(Mode::Auto, -1.0), Mode::Performance);
+        assert_eq!(ThermalApp::wheel_mode(Mode::Performance, 1.0), Mode::Auto);
+        assert_eq!(ThermalApp::wheel_mode(Mode::Unknown, -1.0), Mode::Performance);
+        assert_eq!(ThermalApp::wheel_mode(Mode::Unknown, 1.0), Mode::Auto);
+    }
 
-                // History graph - adaptive height
-                let target = self.target_temp;
-                let graph_height = if is_wide { 180.0 } else if is_medium { 120.0 } else { 80.0 };
-                ui.group(|ui| {
-                    ui.label(egui::RichText::new("History").size(13.0).strong());
-                    self.render_history_adaptive(ui, target, graph_height);
-                });
+    #[test]
+    fn test_status_bar_line() {
+        assert_eq!(
+            ThermalApp::status_bar_line(DEFAULT_BAR_FORMAT, 72.4, Mode::Performance, ThermalZone::Warm),
+            r##"{"text": "⚡72° Perf", "color": "#ff6464"}"##
+        );
+        assert_eq!(
+            ThermalApp::status_bar_line("{mode} {cpu_temp} {zone}", 48.0, Mode::Quiet, ThermalZone::Comfort),
+            "Quiet 48 COMFORT"
+        );
+        assert!(ThermalApp::status_bar_path().ends_with("lenovo-thermal-monitor/status.txt"));
+    }
 
-                // Status bar
-                self.render_status(ui);
-            });
-        });
+    #[test]
+    fn test_status_bar_file_replaced_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bar").join("status.txt");
+        ThermalApp::write_status_bar_file(&path, "Quiet 48\n").unwrap();
+        ThermalApp::write_status_bar_file(&path, "Perf 72\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Perf 72\n");
+        assert!(!path.with_extension("txt.tmp").exists());
+        ThermalApp::write_status_bar_file(&path, "").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_keyboard_heatmap() {
+        assert_eq!(KEYBOARD_ROWS.map(|keys| keys.iter().sum::<f32>()), [15.0; 6]);
+
+        let (hot_x, hot_y) = KEYBOARD_HOTSPOT;
+        let hottest = ThermalApp::keyboard_surface_temp(40.0, 30.0, hot_x, hot_y);
+        let corner = ThermalApp::keyboard_surface_temp(40.0, 30.0, 1.0, 1.0);
+        assert_eq!(hottest, 43.0);
+        assert_eq!(corner, 37.0);
+        assert_eq!(ThermalApp::keyboard_surface_temp(30.0, 30.0, hot_x, hot_y), 30.0);
+
+        assert_eq!(ThermalApp::keyboard_heat_color(20.0), egui::Color32::from_rgb(100, 200, 255));
+        assert_eq!(ThermalApp::keyboard_heat_color(40.0), egui::Color32::from_rgb(255, 200, 100));
+        assert_eq!(ThermalApp::keyboard_heat_color(60.0), egui::Color32::from_rgb(255, 100, 100));
+    }
 
     #[test]
-    fn test_history_capacity() {
-        let mut history = TemperatureHistory::new(3);
-        history.push(40.0, 35.0);
-        history.push(42.0, 36.0);
-        history.push(44.0, 37.0);
-        assert_eq!(history.len(), 3);
+    fn test_throttle_warning() {
+        assert_eq!(ThermalApp::headroom_color(18.0), egui::Color32::from_rgb(100, 220, 100));
+        assert_eq!(ThermalApp::headroom_color(5.0), egui::Color32::from_rgb(255, 200, 0));
+        assert_eq!(ThermalApp::headroom_color(4.0), egui::Color32::from_rgb(255, 100, 100));
+        assert!(!ThermalApp::throttle_warning_needed(ThermalZone::Hot, Some(5.0)));
+        assert!(ThermalApp::throttle_warning_needed(ThermalZone::Hot, Some(4.5)));
+        assert!(!ThermalApp::throttle_warning_needed(ThermalZone::Hot, None));
+        assert!(ThermalApp::throttle_warning_needed(ThermalZone::Critical, None));
+    }
 
-        history.push(46.0, 38.0);
-        assert_eq!(history.len(), 3); // Should not exceed capacity
+    #[test]
+    fn test_external_mode_change() {
+        assert_eq!(
+            ThermalApp::external_mode_change(Mode::Balanced, Mode::Performance, false),
+            Some("External profile change detected: BALANCED → PERFORMANCE".into())
+        );
+        assert_eq!(ThermalApp::external_mode_change(Mode::Balanced, Mode::Balanced, false), None);
+        // Auto-control switches modes on its own
+        assert_eq!(ThermalApp::external_mode_change(Mode::Balanced, Mode::Quiet, true), None);
     }
 
     #[test]
-    fn test_history_empty() {
-        let history = TemperatureHistory::new(10);
-        assert!(history.is_empty());
-        assert_eq!(history.len(), 0);
+    fn test_sample_x_at() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut history = TemperatureHistory::new(4);
+        assert_eq!(history.sample_x_at(start), None);
+        for i in 0..3 {
+            history.push(50.0, 35.0, start + Duration::from_secs(i * 2));
+        }
+        assert_eq!(history.sample_x_at(start), Some(0.0));
+        // An event between polls lands on the next sample
+        assert_eq!(history.sample_x_at(start + Duration::from_secs(1)), Some(2.0));
+        assert_eq!(history.sample_x_at(start + Duration::from_secs(4)), Some(4.0));
+        assert_eq!(history.sample_x_at(start + Duration::from_secs(5)), None);
+        assert_eq!(history.sample_x_at(start - Duration::from_secs(1)), None);
     }
 
     #[test]
-    fn test_history_default() {
-        let history = TemperatureHistory::default();
-        assert!(history.is_empty());
-        assert_eq!(history.capacity, HISTORY_CAPACITY);
+    fn test_heatmap_data() {
+        let mut history = TemperatureHistory::new(10);
+        assert_eq!(history.heatmap_data(4, 40.0, 60.0), vec![0, 0, 0, 0]);
+        let start = UNIX_EPOCH;
+        for (i, temp) in [40.0, 44.9, 45.0, 50.0, 59.9, 60.0, 25.0, 95.0].into_iter().enumerate() {
+            history.push(temp, 35.0, start + Duration::from_secs(i as u64 * 2));
+        }
+        // Lower edges belong to the bin; out-of-range samples land in the edge bins
+        let counts = history.heatmap_data(4, 40.0, 60.0);
+        assert_eq!(counts, vec![3, 1, 1, 3]);
+        assert_eq!(counts.iter().sum::<u32>() as usize, history.len());
+        assert_eq!(history.heatmap_data(1, 40.0, 60.0), vec![8]);
+        assert!(history.heatmap_data(0, 40.0, 60.0).is_empty());
     }
 
     #[test]
-    fn test_history_points() {
+    fn test_channel_heatmap_data() {
         let mut history = TemperatureHistory::new(10);
-        history.push(40.0, 35.0);
-        history.push(42.0, 36.0);
+        let empty = history.channel_heatmap_data(6);
+        assert_eq!(empty.len(), 24);
+        assert!(empty.iter().all(|row| row == &vec![0; 6]));
 
-        let _cpu_points = history.cpu_points();
-        let _kbd_points = history.kbd_points();
+        // 1 January 2024: two samples at 09:00, one at 21:30 and a gap
+        let day = UNIX_EPOCH + Duration::from_secs(1_704_067_200);
+        history.push(42.0, 35.0, day + Duration::from_secs(9 * 3600));
+        history.push(75.0, 40.0, day + Duration::from_secs(9 * 3600 + 2));
+        history.push(f32::NAN, f32::NAN, day + Duration::from_secs(9 * 3600 + 4));
+        history.push(20.0, 30.0, day + Duration::from_secs(21 * 3600 + 1800));
 
-        // Verify points are generated correctly
-        assert!(!history.is_empty());
-        assert_eq!(history.len(), 2);
+        // 6 bins of 10°C from 30°C
+        let counts = history.channel_heatmap_data(6);
+        assert_eq!(counts[9], vec![0, 1, 0, 0, 1, 0]);
+        assert_eq!(counts[21], vec![1, 0, 0, 0, 0, 0]);
+        assert_eq!(counts.iter().flatten().sum::<u32>(), 3);
+        assert!(history.channel_heatmap_data(0).iter().all(Vec::is_empty));
     }
 
     #[test]
-    fn test_history_fifo_behavior() {
-        let mut history = TemperatureHistory::new(2);
-        history.push(10.0, 5.0);  // First in
-        history.push(20.0, 10.0);
-        history.push(30.0, 15.0); // Should push out first
+    fn test_compute_autocorrelation() {
+        // A cycle of 4 samples: in phase at lag 4, opposite at lag 2
+        let mut history = TemperatureHistory::new(40);
+        for i in 0..40 {
+            history.push([50.0, 55.0, 50.0, 45.0][i % 4], 35.0, SystemTime::now());
+        }
+        assert!(history.compute_autocorrelation(4) > 0.8);
+        assert!(history.compute_autocorrelation(2) < -0.8);
+        assert!(history.compute_autocorrelation(1).abs() < 0.1);
+        assert_eq!(history.compute_autocorrelation(0), 1.0);
+        assert!(history.compute_autocorrelation(40).is_nan());
 
-        assert_eq!(history.len(), 2);
-        // First value (10.0) should be gone
+        let mut flat = TemperatureHistory::new(10);
+        for _ in 0..10 {
+            flat.push(50.0, 35.0, SystemTime::now());
+        }
+        assert!(flat.compute_autocorrelation(1).is_nan());
+
+        // A gap keeps its slot, so the cycle still peaks at lag 4
+        let mut gapped = TemperatureHistory::new(40);
+        for i in 0..40 {
+            let cpu = if i == 21 { f32::NAN } else { [50.0, 55.0, 50.0, 45.0][i % 4] };
+            gapped.push(cpu, 35.0, SystemTime::now());
+        }
+        assert!(gapped.compute_autocorrelation(4) > 0.8);
+        assert!(TemperatureHistory::new(5).compute_autocorrelation(0).is_nan());
+    }
+
+    #[test]
+    fn test_custom_metric_validation_and_points() {
+        assert!(CustomMetric::new("delta".into(), "cpu_temp - kbd_temp".into()).is_ok());
+        assert!(CustomMetric::new("bad name".into(), "cpu_temp".into()).is_err());
+        assert!(CustomMetric::new("".into(), "cpu_temp".into()).is_err());
+        assert!(CustomMetric::new("gpu".into(), "gpu_temp * 2".into()).is_err());
+        assert!(CustomMetric::new("broken".into(), "cpu_temp +".into()).is_err());
+
+        // Values that could not be computed leave a hole in the line
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut history = TemperatureHistory::new(3);
+        let mut series = SensorHistory::new(3);
+        for (i, value) in [20.0, f32::NAN, 22.0].into_iter().enumerate() {
+            history.push(50.0, 35.0, start + Duration::from_secs(2 * i as u64));
+            series.push(value);
+        }
+        let points: Vec<[f64; 2]> = history.series_points(&series).points().iter().map(|p| [p.x, p.y]).collect();
+        assert_eq!(points, vec![[0.0, 20.0], [4.0, 22.0]]);
+    }
+
+    #[test]
+    fn test_mem_points_align_with_newest_samples() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut history = TemperatureHistory::new(3);
+        history.push(45.0, 35.0, start);
+        history.push(46.0, 35.5, start + Duration::from_secs(2));
+        history.push_mem(40.0);
+        history.push(47.0, 36.0, start + Duration::from_secs(4));
+        history.push_mem(42.0);
+        history.push(48.0, 36.5, start + Duration::from_secs(6));
+        history.push_mem(44.0);
+        history.push_mem(46.0);
+
+        let points: Vec<[f64; 2]> = history.mem_points().points().iter().map(|p| [p.x, p.y]).collect();
+        assert_eq!(points, vec![[0.0, 42.0], [2.0, 44.0], [4.0, 46.0]]);
+    }
+
+    #[cfg(feature = "protobuf")]
+    #[test]
+    fn test_protobuf_round_trip() {
+        let mut history = TemperatureHistory::new(8);
+        history.push(48.0, 36.0, UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        history.push(52.5, 38.0, UNIX_EPOCH + Duration::from_secs(1_700_000_002));
+
+        let restored = TemperatureHistory::from_protobuf(&history.as_protobuf()).unwrap();
+        assert_eq!(restored.capacity, 8);
+        assert_eq!(restored.cpu_temps, history.cpu_temps);
+        assert_eq!(restored.kbd_temps, history.kbd_temps);
+        assert_eq!(restored.timestamps, history.timestamps);
+    }
+
+    #[cfg(feature = "protobuf")]
+    #[test]
+    fn test_protobuf_rejects_out_of_range_capacity() {
+        use prost::Message;
+
+        for capacity in [0, u32::MAX] {
+            let message = crate::proto::TemperatureHistory { capacity, ..Default::default() };
+            let err = TemperatureHistory::from_protobuf(&message.encode_to_vec()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_run_control_loop_completes() {
+        let mut out = Vec::new();
+        let mut actions = 0;
+        let code = ThermalApp::run_control_loop(
+            Duration::from_millis(20),
+            Duration::from_millis(5),
+            || 50.0,
+            |_| {
+                actions += 1;
+                "On target".into()
+            },
+            &mut out,
+        );
+        assert_eq!(code, 0);
+        assert!(actions >= 2);
+        assert!(String::from_utf8(out).unwrap().lines().all(|l| l == "50.0°C On target"));
+    }
+
+    #[test]
+    fn test_run_control_loop_exits_on_critical() {
+        let mut temps = [60.0, 70.0, 50.0].into_iter();
+        let mut out = Vec::new();
+        let code = ThermalApp::run_control_loop(
+            Duration::from_secs(60),
+            Duration::ZERO,
+            || temps.next().unwrap(),
+            |_| "Adjusting".into(),
+            &mut out,
+        );
+        assert_eq!(code, 2);
+        assert_eq!(String::from_utf8(out).unwrap(), "60.0°C Adjusting\n70.0°C CRITICAL, stopping\n");
+    }
+
+    #[test]
+    fn test_legend_x_maps_and_clamps() {
+        assert_eq!(ThermalApp::legend_x(30.0, 10.0, 90.0), 10.0);
+        assert_eq!(ThermalApp::legend_x(75.0, 10.0, 90.0), 100.0);
+        assert!((ThermalApp::legend_x(52.5, 10.0, 90.0) - 55.0).abs() < 0.001);
+        assert_eq!(ThermalApp::legend_x(10.0, 10.0, 90.0), 10.0);
+        assert_eq!(ThermalApp::legend_x(95.0, 10.0, 90.0), 100.0);
+    }
+
+    #[test]
+    fn test_pie_angles_cover_full_circle() {
+        let usage = vec![("cargo".to_string(), 30.0), ("firefox".to_string(), 10.0)];
+        let angles = ThermalApp::pie_angles(&usage);
+        assert_eq!(angles.len(), 2);
+        assert!((angles[0].1 - 3.0 * angles[1].1).abs() < 0.001);
+        let total: f32 = angles.iter().map(|(_, sweep)| sweep).sum();
+        assert!((total - std::f32::consts::TAU).abs() < 0.001);
+        assert!((angles[1].0 - (angles[0].0 + angles[0].1)).abs() < 0.001);
+
+        assert!(ThermalApp::pie_angles(&[]).is_empty());
     }
 
     #[test]
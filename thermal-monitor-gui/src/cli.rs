@@ -0,0 +1,222 @@
+//! Command-line flags
+
+use std::time::Duration;
+
+use crate::system::{Mode, ThermalZone};
+
+/// Options parsed from the command line
+#[derive(Debug, Default, PartialEq)]
+pub struct CliArgs {
+    /// Print every UI tooltip to stdout and exit
+    pub help_ui: bool,
+    /// Run auto-control without a window for this long, then exit
+    pub run_for: Option<Duration>,
+    /// Print the newest summary from the session log and exit
+    pub print_last_session: bool,
+    /// Print detected hardware and kernel module versions and exit
+    pub hardware_report: bool,
+    /// InfluxDB write endpoint (e.g. `http://host:8086/write?db=thermal`) that
+    /// `--run-for` posts every reading to
+    pub influxdb_url: Option<String>,
+    /// Exit 0 when the current mode is this one, 1 otherwise
+    pub assert_mode: Option<Mode>,
+    /// Exit 0 when the CPU is in this thermal zone, 1 otherwise
+    pub assert_zone: Option<ThermalZone>,
+    /// Switch to this mode and exit
+    pub set_mode: Option<Mode>,
+    /// Turn fan boost on or off and exit
+    pub set_fan_boost: Option<bool>,
+    /// Store this target temperature in the config file and exit; a running
+    /// window picks it up through the config watcher
+    pub set_target: Option<f32>,
+    /// Write mode and temperature for i3blocks/polybar/waybar every poll
+    pub update_bar: bool,
+    /// Template for `--update-bar` with `{cpu_temp}`, `{mode}`, `{zone}` and
+    /// `{color}` placeholders (default: waybar JSON)
+    pub bar_format: Option<String>,
+}
+
+/// Mode named by the value of `flag`
+fn mode_arg(flag: &str, value: Option<String>) -> Result<Mode, String> {
+    let name = value.unwrap_or_default().to_lowercase();
+    let names: Vec<&str> = Mode::all().iter().map(|mode| mode.command()).collect();
+    Mode::all()
+        .iter()
+        .copied()
+        .find(|mode| mode.command() == name)
+        .ok_or_else(|| format!("{} needs one of: {}", flag, names.join(", ")))
+}
+
+impl CliArgs {
+    /// Parse arguments (without the program name)
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--help-ui" => parsed.help_ui = true,
+                "--print-last-session" => parsed.print_last_session = true,
+                "--hardware-report" => parsed.hardware_report = true,
+                "--assert-mode" => parsed.assert_mode = Some(mode_arg("--assert-mode", args.next())?),
+                "--set-mode" => parsed.set_mode = Some(mode_arg("--set-mode", args.next())?),
+                "--set-fan-boost" => {
+                    parsed.set_fan_boost = Some(match args.next().as_deref() {
+                        Some("on") => true,
+                        Some("off") => false,
+                        _ => return Err("--set-fan-boost needs on or off".into()),
+                    });
+                }
+                "--set-target" => {
+                    let target = args
+                        .next()
+                        .and_then(|v| v.parse::<f32>().ok())
+                        .filter(|target| (40.0..=80.0).contains(target))
+                        .ok_or("--set-target needs a temperature between 40 and 80 °C")?;
+                    parsed.set_target = Some(target);
+                }
+                "--assert-zone" => {
+                    let name = args.next().unwrap_or_default();
+                    let zone = ThermalZone::all().iter().copied().find(|zone| zone.label().eq_ignore_ascii_case(&name));
+                    let names: Vec<String> = ThermalZone::all().iter().map(|zone| zone.label().to_lowercase()).collect();
+                    parsed.assert_zone =
+                        Some(zone.ok_or_else(|| format!("--assert-zone needs one of: {}", names.join(", ")))?);
+                }
+                "--run-for" => {
+                    let secs = args
+                        .next()
+                        .and_then(|v| v.parse::<u64>().ok())
+                        .ok_or("--run-for needs a number of seconds")?;
+                    parsed.run_for = Some(Duration::from_secs(secs));
+                }
+                "--update-bar" => parsed.update_bar = true,
+                "--bar-format" => {
+                    parsed.bar_format = Some(args.next().ok_or("--bar-format needs a template")?);
+                }
+                "--influxdb-url" => {
+                    parsed.influxdb_url = Some(args.next().ok_or("--influxdb-url needs a URL")?);
+                }
+                other => return Err(format!("Unknown argument: {}", other)),
+            }
+        }
+        if parsed.influxdb_url.is_some() && parsed.run_for.is_none() {
+            return Err("--influxdb-url only works together with --run-for".into());
+        }
+        if parsed.bar_format.is_some() && !parsed.update_bar {
+            return Err("--bar-format only works together with --update-bar".into());
+        }
+        Ok(parsed)
+    }
+
+    /// Result lines of the `--assert-*` checks against the current mode and zone
+    /// (`None` when the temperature could not be read), and whether all passed
+    pub fn check_assertions(&self, mode: Mode, zone: Option<ThermalZone>) -> (String, bool) {
+        let mut lines = Vec::new();
+        let mut passed = true;
+        let mut check = |what: &str, current: &str, expected: &str| {
+            if current == expected {
+                lines.push(format!("Current {}: {} — OK", what, current));
+            } else {
+                lines.push(format!("Current {}: {} — expected {}", what, current, expected));
+                passed = false;
+            }
+        };
+        if let Some(expected) = self.assert_mode {
+            // Labels rather than command names: an unknown mode must not match "auto"
+            check("mode", &mode.label().to_lowercase(), &expected.label().to_lowercase());
+        }
+        if let Some(expected) = self.assert_zone {
+            let current = zone.map_or("unknown".into(), |zone| zone.label().to_lowercase());
+            check("zone", &current, &expected.label().to_lowercase());
+        }
+        (lines.join("\n"), passed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliArgs, String> {
+        CliArgs::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn test_parse_no_args() {
+        assert_eq!(parse(&[]), Ok(CliArgs::default()));
+    }
+
+    #[test]
+    fn test_parse_help_ui() {
+        assert!(parse(&["--help-ui"]).unwrap().help_ui);
+        assert!(parse(&["--bogus"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_print_last_session() {
+        let args = parse(&["--print-last-session"]).unwrap();
+        assert!(args.print_last_session);
+        assert!(!args.help_ui);
+    }
+
+    #[test]
+    fn test_parse_hardware_report() {
+        assert!(parse(&["--hardware-report"]).unwrap().hardware_report);
+        assert!(!parse(&["--help-ui"]).unwrap().hardware_report);
+    }
+
+    #[test]
+    fn test_parse_assertions() {
+        let args = parse(&["--assert-mode", "Balanced", "--assert-zone", "warm"]).unwrap();
+        assert_eq!(args.assert_mode, Some(Mode::Balanced));
+        assert_eq!(args.assert_zone, Some(ThermalZone::Warm));
+        assert!(parse(&["--assert-mode", "turbo"]).is_err());
+        assert!(parse(&["--assert-zone"]).is_err());
+
+        assert_eq!(
+            args.check_assertions(Mode::Balanced, Some(ThermalZone::Warm)),
+            ("Current mode: balanced — OK\nCurrent zone: warm — OK".to_string(), true)
+        );
+        let (output, passed) = args.check_assertions(Mode::Performance, None);
+        assert_eq!(output, "Current mode: performance — expected balanced\nCurrent zone: unknown — expected warm");
+        assert!(!passed);
+        let auto = parse(&["--assert-mode", "auto"]).unwrap();
+        assert!(!auto.check_assertions(Mode::Unknown, None).1);
+    }
+
+    #[test]
+    fn test_parse_run_for() {
+        assert_eq!(parse(&["--run-for", "60"]).unwrap().run_for, Some(Duration::from_secs(60)));
+        assert!(parse(&["--run-for"]).is_err());
+        assert!(parse(&["--run-for", "soon"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_influxdb_url() {
+        let args = parse(&["--influxdb-url", "http://localhost:8086/write?db=thermal", "--run-for", "60"]).unwrap();
+        assert_eq!(args.influxdb_url.as_deref(), Some("http://localhost:8086/write?db=thermal"));
+        assert!(parse(&["--influxdb-url", "http://localhost:8086/write"]).is_err());
+        assert!(parse(&["--run-for", "60", "--influxdb-url"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_set_actions() {
+        let args = parse(&["--set-mode", "quiet", "--set-fan-boost", "on", "--set-target", "48.5"]).unwrap();
+        assert_eq!(args.set_mode, Some(Mode::Quiet));
+        assert_eq!(args.set_fan_boost, Some(true));
+        assert_eq!(args.set_target, Some(48.5));
+        assert_eq!(parse(&["--set-fan-boost", "off"]).unwrap().set_fan_boost, Some(false));
+        assert!(parse(&["--set-mode", "turbo"]).is_err());
+        assert!(parse(&["--set-fan-boost", "yes"]).is_err());
+        assert!(parse(&["--set-target", "95"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_update_bar() {
+        let args = parse(&["--update-bar", "--bar-format", "{cpu_temp}° {mode}"]).unwrap();
+        assert!(args.update_bar);
+        assert_eq!(args.bar_format.as_deref(), Some("{cpu_temp}° {mode}"));
+        assert_eq!(parse(&["--update-bar"]).unwrap().bar_format, None);
+        assert!(parse(&["--bar-format", "{mode}"]).is_err());
+        assert!(parse(&["--update-bar", "--bar-format"]).is_err());
+    }
+}
@@ -0,0 +1,597 @@
+//! Persistent user settings
+//!
+//! Stored as a small TOML file. Only the subset we write is parsed (flat
+//! `key = value` lines with strings, numbers and booleans), which keeps the
+//! app free of a TOML dependency.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::system::{FanCurve, Mode, ThermalError};
+
+/// Schema version written by this build (matches the app version)
+pub const CONFIG_VERSION: &str = "1.3.0";
+
+/// Version assumed for files written before the `version` key existed
+const LEGACY_VERSION: &str = "1.1.0";
+
+/// A TOML scalar
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+}
+
+impl Value {
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Integer(i) => Some(*i as f64),
+            Value::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Untyped config contents, as read from disk
+pub type Table = BTreeMap<String, Value>;
+
+/// Parse flat `key = value` TOML. Comments and blank lines are skipped.
+pub fn parse(content: &str) -> Result<Table, ThermalError> {
+    let mut table = Table::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = || ThermalError::InvalidData(format!("config line {}: {}", number + 1, line));
+        let (key, raw) = line.split_once('=').ok_or_else(invalid)?;
+        let raw = raw.trim();
+
+        let value = if let Some(s) = raw.strip_prefix('"').and_then(|r| r.strip_suffix('"')) {
+            Value::String(s.replace("\\\"", "\"").replace("\\\\", "\\"))
+        } else if raw == "true" || raw == "false" {
+            Value::Boolean(raw == "true")
+        } else if let Ok(i) = raw.parse::<i64>() {
+            Value::Integer(i)
+        } else if let Ok(f) = raw.parse::<f64>() {
+            Value::Float(f)
+        } else {
+            return Err(invalid());
+        };
+        table.insert(key.trim().to_string(), value);
+    }
+    Ok(table)
+}
+
+/// Serialize a table as `key = value` lines
+pub fn to_toml(table: &Table) -> String {
+    let mut out = String::new();
+    for (key, value) in table {
+        let value = match value {
+            Value::String(s) => format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
+            Value::Integer(i) => i.to_string(),
+            Value::Float(f) => format!("{:?}", f),
+            Value::Boolean(b) => b.to_string(),
+        };
+        out.push_str(&format!("{} = {}\n", key, value));
+    }
+    out
+}
+
+/// One schema change: source version, target version and the transform
+type Migration = (&'static str, &'static str, fn(&mut Table) -> String);
+
+/// Schema changes in release order
+const MIGRATIONS: &[Migration] = &[
+    ("1.1.0", "1.2.0", |table| {
+        // `target` was renamed when auto-control gained other temperature settings
+        match table.remove("target") {
+            Some(value) => {
+                table.insert("target_temp".into(), value);
+                "renamed target to target_temp".into()
+            }
+            None => "no target to rename".into(),
+        }
+    }),
+    ("1.2.0", "1.3.0", |table| {
+        // Power profiles, zone bands and benchmark arrived in 1.3.0
+        let mut added = Vec::new();
+        for (key, value) in [
+            ("ac_mode", Value::String("keep".into())),
+            ("battery_mode", Value::String("keep".into())),
+            ("show_zone_bands", Value::Boolean(true)),
+            ("benchmark_minutes", Value::Integer(10)),
+        ] {
+            if !table.contains_key(key) {
+                table.insert(key.into(), value);
+                added.push(key);
+            }
+        }
+        format!("added defaults for [{}]", added.join(", "))
+    }),
+];
+
+/// Bring a config written by `from_version` up to `to_version` and decode it.
+/// Each applied step is logged to stderr.
+pub fn migrate(mut table: Table, from_version: &str, to_version: &str) -> Result<Config, ThermalError> {
+    let mut version = from_version;
+    while version != to_version {
+        let (_, next, transform) = MIGRATIONS
+            .iter()
+            .find(|(from, _, _)| *from == version)
+            .ok_or_else(|| {
+                ThermalError::InvalidData(format!("no config migration from {} to {}", from_version, to_version))
+            })?;
+        let step = transform(&mut table);
+        eprintln!("config: migrated {} -> {}: {}", version, next, step);
+        version = next;
+    }
+    table.insert("version".into(), Value::String(to_version.into()));
+    Config::from_table(&table)
+}
+
+/// Scale temperatures are shown in; settings and history stay in °C
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    pub fn all() -> &'static [TemperatureUnit] {
+        &[TemperatureUnit::Celsius, TemperatureUnit::Fahrenheit]
+    }
+
+    /// Suffix shown after values, e.g. `°F`
+    pub fn label(&self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+        }
+    }
+
+    /// Name stored in the config file
+    fn name(&self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "celsius",
+            TemperatureUnit::Fahrenheit => "fahrenheit",
+        }
+    }
+}
+
+/// User settings that survive restarts
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub target_temp: f32,
+    /// Mode applied when the AC adapter is plugged in (None = keep current)
+    pub ac_mode: Option<Mode>,
+    /// Mode applied when running on battery (None = keep current)
+    pub battery_mode: Option<Mode>,
+    pub show_zone_bands: bool,
+    pub benchmark_minutes: u64,
+    /// Alarm when the CPU gets this close to TJmax (°C)
+    pub min_headroom_alarm: f32,
+    /// Custom fan curve applied through the EC when enabled
+    pub fan_curve: FanCurve,
+    /// Append a summary to the session log on exit
+    pub log_sessions: bool,
+    /// User-defined metrics as (name, expression), stored as `metric.<name>` keys
+    pub custom_metrics: Vec<(String, String)>,
+    /// Battery charge (%) below which Quiet mode is enforced
+    pub low_battery_pct: u8,
+    /// Switch to Quiet after this many minutes without input (None = never, stored as 0).
+    /// Off by default: input outside the window is only seen from the built-in
+    /// keyboard and touchpad, so external keyboards and mice would not count.
+    pub idle_timeout_mins: Option<u32>,
+    /// Events are POSTed here as JSON (needs the `webhook` feature)
+    pub webhook_url: Option<String>,
+    /// Directory receiving one JSON line per poll, in a file per day
+    pub auto_export_path: Option<PathBuf>,
+    /// Send desktop notifications for thermal alarms
+    pub notifications: bool,
+    /// The setup wizard was completed; set to false to run it again
+    pub first_run_completed: bool,
+    pub temperature_unit: TemperatureUnit,
+    /// Minimum seconds between two notifications of the same kind: high
+    /// temperature alarm, entering the Critical zone, external mode change
+    pub notify_cooldown_high_temp_secs: u64,
+    pub notify_cooldown_critical_secs: u64,
+    pub notify_cooldown_mode_change_secs: u64,
+    /// CPU boost chosen by hand; `None` lets auto-control manage it with the mode
+    pub cpu_boost_override: Option<bool>,
+    /// Load plugins at startup (needs the `plugins` feature); off by default
+    /// because a plugin runs with the app's privileges
+    pub plugins_enabled: bool,
+    /// Ask GitHub for the latest release once a day (needs the `update-check`
+    /// feature); off unless chosen in the setup wizard or set by hand
+    pub check_updates: bool,
+    /// Wavelet smoothing drops detail coefficients below this many °C as sensor noise
+    pub wavelet_threshold: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            target_temp: 55.0,
+            ac_mode: None,
+            battery_mode: None,
+            show_zone_bands: true,
+            benchmark_minutes: 10,
+            min_headroom_alarm: 10.0,
+            fan_curve: FanCurve::default(),
+            log_sessions: true,
+            custom_metrics: Vec::new(),
+            low_battery_pct: 10,
+            idle_timeout_mins: None,
+            webhook_url: None,
+            auto_export_path: None,
+            notifications: true,
+            first_run_completed: true,
+            temperature_unit: TemperatureUnit::Celsius,
+            notify_cooldown_high_temp_secs: 60,
+            notify_cooldown_critical_secs: 30,
+            notify_cooldown_mode_change_secs: 0,
+            cpu_boost_override: None,
+            plugins_enabled: false,
+            check_updates: false,
+            wavelet_threshold: 0.5,
+        }
+    }
+}
+
+/// Key prefix of custom metric entries
+const METRIC_PREFIX: &str = "metric.";
+
+/// Mode stored by its `cpu-mode` command name, or "keep"
+fn mode_from_value(value: Option<&Value>) -> Option<Mode> {
+    let name = value?.as_str()?;
+    Mode::all().iter().copied().find(|mode| mode.command() == name)
+}
+
+fn mode_to_value(mode: Option<Mode>) -> Value {
+    Value::String(mode.map_or("keep", |m| m.command()).into())
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/lenovo-thermal-monitor/config.toml` (falls back to `~/.config`)
+    pub fn path() -> PathBuf {
+        let base = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .unwrap_or_else(|| PathBuf::from("/tmp"));
+        base.join("lenovo-thermal-monitor").join("config.toml")
+    }
+
+    /// Decode a table at the current schema version; missing keys use defaults
+    pub fn from_table(table: &Table) -> Result<Self, ThermalError> {
+        let defaults = Self::default();
+        let number = |key: &str| match table.get(key) {
+            None => Ok(None),
+            Some(value) => value
+                .as_f64()
+                .map(Some)
+                .ok_or_else(|| ThermalError::InvalidData(format!("config key {} must be a number", key))),
+        };
+        Ok(Self {
+            target_temp: number("target_temp")?.map_or(defaults.target_temp, |t| t as f32),
+            ac_mode: mode_from_value(table.get("ac_mode")),
+            battery_mode: mode_from_value(table.get("battery_mode")),
+            show_zone_bands: match table.get("show_zone_bands") {
+                Some(Value::Boolean(b)) => *b,
+                _ => defaults.show_zone_bands,
+            },
+            benchmark_minutes: number("benchmark_minutes")?.map_or(defaults.benchmark_minutes, |m| m as u64),
+            min_headroom_alarm: number("min_headroom_alarm")?.map_or(defaults.min_headroom_alarm, |m| m as f32),
+            wavelet_threshold: number("wavelet_threshold")?.map_or(defaults.wavelet_threshold, |t| t as f32),
+            fan_curve: match table.get("fan_curve").and_then(Value::as_str) {
+                Some(curve) => FanCurve::parse(curve)?,
+                None => defaults.fan_curve,
+            },
+            log_sessions: match table.get("log_sessions") {
+                Some(Value::Boolean(b)) => *b,
+                _ => defaults.log_sessions,
+            },
+            low_battery_pct: number("low_battery_pct")?.map_or(defaults.low_battery_pct, |p| p.clamp(0.0, 100.0) as u8),
+            idle_timeout_mins: number("idle_timeout_mins")?
+                .map_or(defaults.idle_timeout_mins, |m| Some(m.max(0.0) as u32).filter(|&m| m > 0)),
+            webhook_url: table
+                .get("webhook_url")
+                .and_then(Value::as_str)
+                .filter(|url| !url.is_empty())
+                .map(str::to_string),
+            auto_export_path: table
+                .get("auto_export_path")
+                .and_then(Value::as_str)
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from),
+            notifications: match table.get("notifications") {
+                Some(Value::Boolean(b)) => *b,
+                _ => defaults.notifications,
+            },
+            first_run_completed: match table.get("first_run_completed") {
+                Some(Value::Boolean(b)) => *b,
+                _ => defaults.first_run_completed,
+            },
+            // An unknown unit (e.g. a typo) falls back like a missing one
+            temperature_unit: table
+                .get("temperature_unit")
+                .and_then(Value::as_str)
+                .and_then(|name| TemperatureUnit::all().iter().copied().find(|unit| unit.name() == name))
+                .unwrap_or(defaults.temperature_unit),
+            notify_cooldown_high_temp_secs: number("notify_cooldown_high_temp_secs")?
+                .map_or(defaults.notify_cooldown_high_temp_secs, |s| s.max(0.0) as u64),
+            notify_cooldown_critical_secs: number("notify_cooldown_critical_secs")?
+                .map_or(defaults.notify_cooldown_critical_secs, |s| s.max(0.0) as u64),
+            notify_cooldown_mode_change_secs: number("notify_cooldown_mode_change_secs")?
+                .map_or(defaults.notify_cooldown_mode_change_secs, |s| s.max(0.0) as u64),
+            cpu_boost_override: match table.get("cpu_boost").and_then(Value::as_str) {
+                Some("on") => Some(true),
+                Some("off") => Some(false),
+                _ => defaults.cpu_boost_override,
+            },
+            plugins_enabled: match table.get("plugins_enabled") {
+                Some(Value::Boolean(b)) => *b,
+                _ => defaults.plugins_enabled,
+            },
+            check_updates: match table.get("check_updates") {
+                Some(Value::Boolean(b)) => *b,
+                _ => defaults.check_updates,
+            },
+            custom_metrics: table
+                .iter()
+                .filter_map(|(key, value)| Some((key.strip_prefix(METRIC_PREFIX)?, value)))
+                .map(|(name, value)| match value.as_str() {
+                    Some(expr) => Ok((name.to_string(), expr.to_string())),
+                    None => Err(ThermalError::InvalidData(format!("custom metric {} must be a string", name))),
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table.insert("version".into(), Value::String(CONFIG_VERSION.into()));
+        table.insert("target_temp".into(), Value::Float(self.target_temp as f64));
+        table.insert("ac_mode".into(), mode_to_value(self.ac_mode));
+        table.insert("battery_mode".into(), mode_to_value(self.battery_mode));
+        table.insert("show_zone_bands".into(), Value::Boolean(self.show_zone_bands));
+        table.insert("benchmark_minutes".into(), Value::Integer(self.benchmark_minutes as i64));
+        table.insert("min_headroom_alarm".into(), Value::Float(self.min_headroom_alarm as f64));
+        table.insert("wavelet_threshold".into(), Value::Float(self.wavelet_threshold as f64));
+        table.insert("fan_curve".into(), Value::String(self.fan_curve.to_string()));
+        table.insert("log_sessions".into(), Value::Boolean(self.log_sessions));
+        table.insert("low_battery_pct".into(), Value::Integer(self.low_battery_pct as i64));
+        table.insert("idle_timeout_mins".into(), Value::Integer(self.idle_timeout_mins.unwrap_or(0) as i64));
+        table.insert("notifications".into(), Value::Boolean(self.notifications));
+        table.insert("first_run_completed".into(), Value::Boolean(self.first_run_completed));
+        table.insert("temperature_unit".into(), Value::String(self.temperature_unit.name().into()));
+        let cpu_boost = match self.cpu_boost_override {
+            Some(true) => "on",
+            Some(false) => "off",
+            None => "auto",
+        };
+        table.insert("cpu_boost".into(), Value::String(cpu_boost.into()));
+        table.insert("plugins_enabled".into(), Value::Boolean(self.plugins_enabled));
+        table.insert("check_updates".into(), Value::Boolean(self.check_updates));
+        for (key, secs) in [
+            ("notify_cooldown_high_temp_secs", self.notify_cooldown_high_temp_secs),
+            ("notify_cooldown_critical_secs", self.notify_cooldown_critical_secs),
+            ("notify_cooldown_mode_change_secs", self.notify_cooldown_mode_change_secs),
+        ] {
+            // Beyond i64 the cast would wrap to a negative number of seconds
+            table.insert(key.into(), Value::Integer(i64::try_from(secs).unwrap_or(i64::MAX)));
+        }
+        if let Some(url) = &self.webhook_url {
+            table.insert("webhook_url".into(), Value::String(url.clone()));
+        }
+        if let Some(path) = &self.auto_export_path {
+            table.insert("auto_export_path".into(), Value::String(path.to_string_lossy().into_owned()));
+        }
+        for (name, expr) in &self.custom_metrics {
+            table.insert(format!("{}{}", METRIC_PREFIX, name), Value::String(expr.clone()));
+        }
+        table
+    }
+
+    /// Read the raw config file and the schema version it was written with
+    pub fn read_raw() -> Result<Option<(Table, String)>, ThermalError> {
+        Self::read_raw_from(&Self::path())
+    }
+
+    fn read_raw_from(path: &Path) -> Result<Option<(Table, String)>, ThermalError> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let table = parse(&content)?;
+        let version = table
+            .get("version")
+            .and_then(Value::as_str)
+            .unwrap_or(LEGACY_VERSION)
+            .to_string();
+        Ok(Some((table, version)))
+    }
+
+    /// Decode the config file at `path`, migrating older schema versions
+    pub fn read_from(path: &Path) -> Result<Option<Self>, ThermalError> {
+        match Self::read_raw_from(path)? {
+            Some((table, version)) if version == CONFIG_VERSION => Self::from_table(&table).map(Some),
+            Some((table, version)) => migrate(table, &version, CONFIG_VERSION).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, to_toml(&self.to_table()))
+    }
+}
+
+/// Reloads the config when the file changes on disk, e.g. after editing it
+/// by hand. A background thread polls the modification time and sends each
+/// successfully decoded version over a channel; the UI drains it every frame.
+pub struct ConfigWatcher {
+    rx: Receiver<Config>,
+}
+
+impl ConfigWatcher {
+    /// Watch the config file at `path`, checking every `interval`
+    pub fn spawn(path: PathBuf, interval: Duration) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+        let mut last_modified: Option<SystemTime> = modified(&path);
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            let current = modified(&path);
+            if current == last_modified {
+                continue;
+            }
+            last_modified = current;
+            match Config::read_from(&path) {
+                Ok(Some(config)) => {
+                    // The app is gone: stop watching
+                    if tx.send(config).is_err() {
+                        break;
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("config: not reloaded: {}", e),
+            }
+        });
+        Self { rx }
+    }
+
+    /// The newest reloaded config since the last call, if any
+    pub fn latest(&self) -> Option<Config> {
+        self.rx.try_iter().last()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_serialize_round_trip() {
+        let content = "# settings\nversion = \"1.3.0\"\ntarget_temp = 52.5\nbenchmark_minutes = 15\n\nshow_zone_bands = false\n";
+        let table = parse(content).unwrap();
+        assert_eq!(table.get("target_temp"), Some(&Value::Float(52.5)));
+        assert_eq!(table.get("benchmark_minutes"), Some(&Value::Integer(15)));
+        assert_eq!(table.get("show_zone_bands"), Some(&Value::Boolean(false)));
+        assert_eq!(parse(&to_toml(&table)).unwrap(), table);
+
+        assert!(parse("target_temp 55").is_err());
+        assert!(parse("target_temp = hot").is_err());
+    }
+
+    #[test]
+    fn test_config_table_round_trip() {
+        let config = Config {
+            target_temp: 60.0,
+            ac_mode: Some(Mode::Performance),
+            battery_mode: None,
+            show_zone_bands: false,
+            benchmark_minutes: 30,
+            min_headroom_alarm: 15.0,
+            fan_curve: FanCurve { points: vec![(45.0, 0), (60.5, 2), (75.0, 3)] },
+            log_sessions: false,
+            custom_metrics: vec![
+                ("delta".into(), "cpu_temp - kbd_temp".into()),
+                ("load".into(), "cpu_temp * perf_pct / 100".into()),
+            ],
+            low_battery_pct: 20,
+            idle_timeout_mins: Some(15),
+            webhook_url: Some("http://localhost:8080/thermal".into()),
+            auto_export_path: Some(PathBuf::from("/var/log/thermal")),
+            notifications: false,
+            first_run_completed: false,
+            temperature_unit: TemperatureUnit::Fahrenheit,
+            notify_cooldown_high_temp_secs: 120,
+            notify_cooldown_critical_secs: 15,
+            notify_cooldown_mode_change_secs: 5,
+            cpu_boost_override: Some(false),
+            plugins_enabled: true,
+            check_updates: true,
+            wavelet_threshold: 1.25,
+        };
+        assert_eq!(Config::from_table(&config.to_table()).unwrap(), config);
+        assert_eq!(Config::from_table(&Table::new()).unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_huge_cooldown_does_not_wrap() {
+        let config = Config { notify_cooldown_critical_secs: u64::MAX, ..Default::default() };
+        let table = config.to_table();
+        assert_eq!(table.get("notify_cooldown_critical_secs"), Some(&Value::Integer(i64::MAX)));
+        // Read back through f64, so only roughly i64::MAX
+        assert!(Config::from_table(&table).unwrap().notify_cooldown_critical_secs >= i64::MAX as u64);
+    }
+
+    #[test]
+    fn test_config_watcher_reloads_on_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, to_toml(&Config::default().to_table())).unwrap();
+        let watcher = ConfigWatcher::spawn(path.clone(), Duration::from_millis(10));
+        assert!(watcher.latest().is_none());
+
+        // Make sure the modification time moves even on coarse-grained filesystems
+        thread::sleep(Duration::from_millis(1100));
+        let edited = Config { target_temp: 62.0, ..Default::default() };
+        fs::write(&path, to_toml(&edited.to_table())).unwrap();
+
+        let reloaded = (0..200).find_map(|_| {
+            thread::sleep(Duration::from_millis(10));
+            watcher.latest()
+        });
+        assert_eq!(reloaded, Some(edited));
+    }
+
+    #[test]
+    fn test_migrate_from_legacy() {
+        let table = parse("target = 50\n").unwrap();
+        let config = migrate(table, "1.1.0", CONFIG_VERSION).unwrap();
+        assert_eq!(config.target_temp, 50.0);
+        assert_eq!(config.ac_mode, None);
+        assert!(config.show_zone_bands);
+        assert_eq!(config.benchmark_minutes, 10);
+    }
+
+    #[test]
+    fn test_migrate_keeps_existing_values_and_rejects_unknown() {
+        let table = parse("target_temp = 58\nshow_zone_bands = false\n").unwrap();
+        let config = migrate(table, "1.2.0", "1.3.0").unwrap();
+        assert_eq!(config.target_temp, 58.0);
+        assert!(!config.show_zone_bands);
+
+        assert!(migrate(Table::new(), "0.9.0", CONFIG_VERSION).is_err());
+        assert_eq!(migrate(Table::new(), CONFIG_VERSION, CONFIG_VERSION).unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_unknown_temperature_unit_falls_back() {
+        let table = parse("temperature_unit = \"kelvin\"\ntarget_temp = 58\n").unwrap();
+        let config = Config::from_table(&table).unwrap();
+        assert_eq!(config.temperature_unit, TemperatureUnit::Celsius);
+        assert_eq!(config.target_temp, 58.0);
+    }
+}
@@ -0,0 +1,248 @@
+//! Arithmetic expressions for user-defined metrics
+//!
+//! Supports numbers, sensor names, `+ - * /`, unary minus and parentheses,
+//! e.g. `cpu_temp * perf_pct / 100`. Variables are resolved at evaluation
+//! time so one parsed expression can be evaluated every poll.
+
+use std::fmt;
+
+use crate::system::ThermalError;
+
+/// Binary operator
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+/// Parsed expression tree
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Variable(String),
+    Neg(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(Op),
+    Open,
+    Close,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ThermalError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        let token = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '+' => Token::Op(Op::Add),
+            '-' => Token::Op(Op::Sub),
+            '*' => Token::Op(Op::Mul),
+            '/' => Token::Op(Op::Div),
+            '(' => Token::Open,
+            ')' => Token::Close,
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut end = start;
+                while let Some(&(i, c)) = chars.peek().filter(|(_, c)| c.is_ascii_digit() || *c == '.') {
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                let text = &source[start..end];
+                let value = text
+                    .parse()
+                    .map_err(|_| ThermalError::InvalidData(format!("bad number '{}'", text)))?;
+                tokens.push(Token::Number(value));
+                continue;
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = start;
+                while let Some(&(i, c)) = chars.peek().filter(|(_, c)| c.is_ascii_alphanumeric() || *c == '_') {
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                tokens.push(Token::Ident(source[start..end].to_string()));
+                continue;
+            }
+            c => return Err(ThermalError::InvalidData(format!("unexpected '{}' in expression", c))),
+        };
+        tokens.push(token);
+        chars.next();
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over the token list:
+/// expr = term (('+' | '-') term)*, term = unary (('*' | '/') unary)*,
+/// unary = '-' unary | atom, atom = number | name | '(' expr ')'
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_op(&self, ops: &[Op]) -> Option<Op> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) if ops.contains(op) => Some(*op),
+            _ => None,
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, ThermalError> {
+        let mut lhs = self.term()?;
+        while let Some(op) = self.peek_op(&[Op::Add, Op::Sub]) {
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.term()?));
+        }
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> Result<Expr, ThermalError> {
+        let mut lhs = self.unary()?;
+        while let Some(op) = self.peek_op(&[Op::Mul, Op::Div]) {
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ThermalError> {
+        if self.peek_op(&[Op::Sub]).is_some() {
+            self.pos += 1;
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Expr::Number(value)),
+            Some(Token::Ident(name)) => Ok(Expr::Variable(name)),
+            Some(Token::Open) => {
+                let inner = self.expr()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => Err(ThermalError::InvalidData("missing ')' in expression".into())),
+                }
+            }
+            Some(token) => Err(ThermalError::InvalidData(format!("unexpected {:?} in expression", token))),
+            None => Err(ThermalError::InvalidData("expression ends too early".into())),
+        }
+    }
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Self, ThermalError> {
+        let mut parser = Parser { tokens: tokenize(source)?, pos: 0 };
+        let expr = parser.expr()?;
+        if parser.pos < parser.tokens.len() {
+            return Err(ThermalError::InvalidData(format!(
+                "unexpected {:?} in expression",
+                parser.tokens[parser.pos]
+            )));
+        }
+        Ok(expr)
+    }
+
+    /// Variable names used, in order of appearance
+    pub fn variables(&self) -> Vec<&str> {
+        match self {
+            Expr::Number(_) => Vec::new(),
+            Expr::Variable(name) => vec![name.as_str()],
+            Expr::Neg(inner) => inner.variables(),
+            Expr::Binary(_, lhs, rhs) => {
+                let mut names = lhs.variables();
+                names.extend(rhs.variables());
+                names
+            }
+        }
+    }
+
+    /// Value with variables taken from `lookup`; `None` when a variable is
+    /// unavailable or the result is not finite (e.g. division by zero)
+    pub fn eval(&self, lookup: &impl Fn(&str) -> Option<f64>) -> Option<f64> {
+        let value = match self {
+            Expr::Number(value) => *value,
+            Expr::Variable(name) => lookup(name)?,
+            Expr::Neg(inner) => -inner.eval(lookup)?,
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(lookup)?, rhs.eval(lookup)?);
+                match op {
+                    Op::Add => lhs + rhs,
+                    Op::Sub => lhs - rhs,
+                    Op::Mul => lhs * rhs,
+                    Op::Div => lhs / rhs,
+                }
+            }
+        };
+        value.is_finite().then_some(value)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Number(value) => write!(f, "{}", value),
+            Expr::Variable(name) => write!(f, "{}", name),
+            Expr::Neg(inner) => write!(f, "-({})", inner),
+            Expr::Binary(op, lhs, rhs) => {
+                let symbol = match op {
+                    Op::Add => "+",
+                    Op::Sub => "-",
+                    Op::Mul => "*",
+                    Op::Div => "/",
+                };
+                write!(f, "({} {} {})", lhs, symbol, rhs)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<f64> {
+        match name {
+            "cpu_temp" => Some(60.0),
+            "kbd_temp" => Some(38.0),
+            "perf_pct" => Some(50.0),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_parse_and_eval() {
+        let eval = |source: &str| Expr::parse(source).unwrap().eval(&lookup);
+        assert_eq!(eval("cpu_temp * perf_pct / 100"), Some(30.0));
+        assert_eq!(eval("cpu_temp - kbd_temp"), Some(22.0));
+        assert_eq!(eval("1 + 2 * 3"), Some(7.0));
+        assert_eq!(eval("(1 + 2) * 3"), Some(9.0));
+        assert_eq!(eval("10 - 4 - 3"), Some(3.0));
+        assert_eq!(eval("-kbd_temp + 0.5"), Some(-37.5));
+        // Unknown variables and division by zero have no value
+        assert_eq!(eval("fan_rpm / 10"), None);
+        assert_eq!(eval("cpu_temp / 0"), None);
+
+        let expr = Expr::parse("cpu_temp - kbd_temp * 2").unwrap();
+        assert_eq!(expr.variables(), vec!["cpu_temp", "kbd_temp"]);
+        assert_eq!(expr.to_string(), "(cpu_temp - (kbd_temp * 2))");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for source in ["", "cpu_temp +", "(cpu_temp", "cpu_temp)", "2 ^ 3", "1.2.3", "cpu_temp kbd_temp"] {
+            assert!(Expr::parse(source).is_err(), "{:?} should not parse", source);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Plain-language help for UI elements
+//!
+//! Shown as hover tooltips and printed by `--help-ui` for accessibility tooling.
+
+use eframe::egui;
+
+use crate::system::Mode;
+
+/// Explanations for UI elements, keyed by widget name
+pub struct HelpText;
+
+impl HelpText {
+    pub const ENTRIES: &'static [(&'static str, &'static str)] = &[
+        ("platform_profile", "The firmware power profile (for example low-power, balanced or performance). It sets power limits and fan behavior."),
+        ("cpu_temp", "Current CPU temperature read from the kernel's thermal sensors."),
+        ("kbd_temp", "Estimated keyboard surface temperature, calculated from the CPU and room temperature. It is not measured directly."),
+        ("zone", "How hot the CPU is, from Cool to Critical."),
+        ("process_pie", "Share of CPU time used by the busiest programs since the last update. These are the most likely cause of heat."),
+        ("perf_pct", "Highest CPU speed currently allowed, as a percentage of full speed. Lower values run cooler."),
+        ("freq", "Current CPU clock speed in GHz."),
+        ("mode", "The CPU mode currently active."),
+        ("mem_used", "Share of memory in use. Heavy memory traffic can also warm the CPU."),
+        ("power", "Power drawn by the CPU in watts."),
+        ("mode_buttons", "Switch the CPU mode. Each mode trades speed for heat and fan noise."),
+        ("benchmark", "Run in Performance mode with the fan boosted for the chosen number of minutes, then restore your previous settings."),
+        ("power_profiles", "Mode to switch to when the charger is plugged in or unplugged. Keep leaves the mode unchanged."),
+        ("target_temp", "Temperature that automatic control tries to keep the CPU at or below."),
+        ("auto_control", "When AUTO, the app lowers or raises CPU speed and turns on fan boost to stay near the target temperature."),
+        ("target_status", "How many degrees the CPU is above the target, or OK when it is at or below it."),
+        ("fan_boost", "BOOST runs the fan at full speed for maximum cooling. NORMAL lets the laptop control the fan."),
+        ("fan_curve", "Set the fan speed level (0-3) from the CPU temperature using your own curve. Drag points to change it."),
+        ("battery_impact", "Rough change in battery runtime caused by the current mode, compared with Balanced on a typical battery."),
+        ("turbo", "Lets the CPU briefly run faster than its base speed. Turning it off runs cooler and quieter."),
+        ("fan_rpm", "Current fan speed in revolutions per minute."),
+        ("ec_throttling", "The laptop's embedded controller is slowing the CPU down on its own to protect it from heat."),
+        ("history", "CPU and keyboard temperature over the last two minutes. The dashed line is the target temperature. Vertical lines mark events (yellow: mode change, orange: threshold crossed, red: critical zone, cyan: fan boost); hover one for details."),
+        ("perf_history", "CPU speed limit at each update. Bar colors show how hot the CPU was at that moment."),
+        ("event_log", "Mode changes and automatic control actions. Repeated actions are summarized instead of listed one by one."),
+        ("zone_bands", "Shade the graph background in the zone colors, in 10° bands: up to 45°C, 45-55, 55-65, 65-75, 75-85 and above 85°C."),
+        ("mem_line", "Draw memory usage (0-100%) in the graph to compare it with temperature."),
+        ("history_gaps", "Breaks in the graph, one per suspend. The line is not drawn across a gap, and periodicity treats the gap as the last reading."),
+        ("import_trace", "Load temperature events recorded by the kernel's ftrace buffer and draw them for comparison."),
+        ("import_csv", "Load the CSV saved by Export CSV and show it before the live samples. Samples from the current session are not imported twice."),
+        ("export_csv", "Save the temperature history, with the time of each sample, to a CSV file in /tmp."),
+        ("export_html", "Save an interactive graph of the temperature history to an HTML file in /tmp. Open it in a browser to zoom and hover; it needs an internet connection to load the Plotly library."),
+        ("copy_csv", "Copy the temperature history to the clipboard as CSV, ready to paste into a spreadsheet."),
+        ("export_protobuf", "Save the temperature history to /tmp as a protobuf TemperatureHistory message, for gRPC monitoring backends."),
+        ("import_protobuf", "Load the protobuf file saved by Export Protobuf and show it before the live samples. Samples from the current session are not imported twice."),
+        ("export_npy", "Save the CPU and keyboard temperatures to /tmp as a NumPy array with one row per sample. Load it with numpy.load."),
+        ("export_influxdb", "Save the temperature history to /tmp as InfluxDB line protocol, one point per sample. Load it with `influx write` or POST it to the write API."),
+        ("export_gnuplot", "Save a gnuplot script with the temperature history to /tmp. Run it with gnuplot to get a PNG graph."),
+        ("threshold_crossings", "How many times the CPU went above the target temperature since the app started."),
+        ("measure_overhead", "Measure how much CPU this app itself uses over 30 seconds."),
+        ("load_driver", "Load the ideapad-laptop kernel driver, which most controls in this app need."),
+        ("custom_metric", "Add a derived metric from sensor readings, e.g. cpu_temp * perf_pct / 100. Sensors: cpu_temp, kbd_temp, ambient_temp, perf_pct, freq_mhz, max_freq_mhz, mem_used_pct, tjmax, headroom, fan_rpm."),
+        ("trip_points", "Temperatures defined by the firmware (ACPI trip points): Active turns on a fan, Passive throttles the CPU, Hot warns the OS, Critical shuts the laptop down."),
+        ("cpu_topology", "Temperature of each CPU core, colored by thermal zone. Hover a core for its session maximum; click it to plot it in the history graph."),
+        ("periodicity", "Autocorrelation of the CPU temperature at lags of 1 to 10 samples. A tall bar at lag k means the temperature repeats every k × 2 s (e.g. a fan cycling on and off); bars near zero mean no cycle."),
+        ("correlation", "How closely keyboard and CPU temperatures move together over the history (Pearson correlation, -1 to 1). The keyboard temperature has no sensor: it is estimated linearly from the CPU temperature and the ambient temperature, so the value stays close to 1 and only drops when the ambient reading changes."),
+        ("mode_wheel", "Scroll over the mode buttons to step through the modes."),
+        ("time_of_day", "How often each CPU temperature occurred at each hour of the day (UTC). Stronger colors are more frequent. Import a CSV export from earlier sessions to fill more hours."),
+        ("distribution", "How often each CPU temperature occurred over the history, in 5°C bins. The longest bar is the usual operating temperature."),
+        ("about", "Version and the detected drivers: the platform profile driver decides whether modes can switch the firmware power profile, and the kernel thermal policy decides how the kernel throttles near the trip points."),
+        ("percentile_line", "Dotted line: 95th percentile of the CPU temperature over the last 30 s. Close to the CPU line when heat is sustained, well below it for short spikes."),
+        ("crash_report", "Open a new GitHub issue in your browser with the crash report filled in. Nothing is sent until you submit it."),
+        ("battery_cycles", "Battery charge, health (how much of its original capacity a full charge still holds) and the number of charge cycles so far. Batteries hold noticeably less charge after several hundred cycles."),
+        ("mode_usage", "Share of the time since the app started spent in each mode."),
+        ("trend_line", "Dimmed dashed line: straight-line fit of the CPU temperature over the whole graph. Rising means the CPU is heating up overall."),
+        ("tjmax_line", "Dotted dark red line at TJmax, the temperature at which the CPU throttles itself hard to avoid damage."),
+        ("forecast", "Dashed red line: where the CPU temperature is heading over the next 30 s. Linear continues the current trend, Exponential lets it level off, Last value holds the current reading."),
+        ("idle_mode", "No keyboard or mouse input for a while, so the app switched to Quiet. Your previous mode comes back as soon as you type or move the mouse. Only the built-in keyboard and touchpad count outside this window. Set idle_timeout_mins = 0 in the config file to turn this off."),
+        ("test_notification", "Show a desktop notification now, to check that temperature alerts will reach you."),
+        ("update_check", "A newer release was published on GitHub. The check runs at most once a day when check_updates is on and needs the update-check feature."),
+        ("check_updates", "Ask the GitHub API for the latest release at startup, at most once a day. Nothing about this machine is sent. Change it later with check_updates in the config file."),
+        ("record_actions", "Record mode, fan boost and target changes. Stop & Export writes them to /tmp/lenovo-thermal-recording.sh, a shell script that replays them with the same pauses using --set-mode, --set-fan-boost and --set-target."),
+        ("setup_wizard", "Close the wizard and keep the default settings. Set first_run_completed = false in the config file to run it again."),
+        ("temperature_unit", "Switch between Celsius and Fahrenheit. Only the display changes; settings, logs and exports stay in Celsius."),
+        ("envelope", "Shaded area up to the highest CPU temperature within about a minute around each point: the worst case the CPU reached nearby."),
+        ("refresh_rate", "Screen refresh rate. A lower rate makes the GPU draw fewer frames, which saves power and heat. With auto-control on, the screen drops to 60 Hz in the Critical zone and goes back afterwards. Needs xrandr (X11 or XWayland)."),
+        ("mode_statistics", "Shows which mode you chose most often in each temperature zone, counted from the mode changes of your earlier sessions. Nothing is applied automatically."),
+        ("smoothing", "Thick orange line: the CPU temperature with sensor noise removed. Moving average is the mean of the last 16 s and lags behind sudden rises; Wavelet removes small wiggles but keeps sudden rises sharp."),
+        ("wavelet_threshold", "Wiggles smaller than this are treated as sensor noise and removed by the Wavelet smoothing. Raise it for a calmer line, lower it to keep more detail. Saved as wavelet_threshold in the config."),
+        ("cpu_boost", "Let the CPU clock above its base frequency (Intel Turbo Boost / AMD Precision Boost). Turning it off runs cooler and slower. With auto-control on, boost follows the mode: off in Quiet, on in Balanced and Performance, unless you set it here yourself."),
+        ("cpu_boost_auto", "Forget your CPU boost choice and let auto-control manage it again."),
+        ("plugins", "Panel added by a plugin installed in ~/.local/share/lenovo-thermal-monitor/plugins. Plugins are loaded at startup when plugins_enabled = true is set in the config."),
+        ("throttle_headroom", "Degrees left before the CPU reaches the firmware's passive trip point and gets throttled. Green above 15°C, yellow from 15°C, red under 5°C, when a warning is also shown."),
+        ("ab_split", "Mark the newest sample as the start of period B to compare before and after a change. Period A is drawn faded, and min/avg/max of each period are shown below the graph. Click again to clear the split."),
+        ("cpu_package_temp", "Temperature of the whole CPU package, from the coretemp \"Package\" sensor (the hottest core when there is none)."),
+        ("cpu_core_max_temp", "Temperature of the hottest single core. Cores heat up unevenly and throttle on their own, so throttle headroom is measured from this reading."),
+        ("keyboard_heatmap", "Sketch of how warm the keyboard surface probably is, hottest above the CPU and cooler towards the edges. There is no per-key sensor: the colors are spread around the single keyboard estimate and follow it as it changes."),
+        ("keyboard_map", "List the keyboard shortcuts."),
+        ("reset_defaults", "Put every setting back to its default value. The temperature history is kept."),
+        ("udev_rules", "Allow your user group to change modes and the fan without entering a password."),
+    ];
+
+    /// Explanation for `widget`, empty when there is none
+    pub fn get(widget: &str) -> &'static str {
+        Self::ENTRIES
+            .iter()
+            .find(|(name, _)| *name == widget)
+            .map_or("", |(_, text)| text)
+    }
+
+    /// Every tooltip as `name: text` lines, including the per-mode descriptions
+    pub fn render_all() -> String {
+        let mut out = String::new();
+        for (name, text) in Self::ENTRIES {
+            out.push_str(&format!("{}: {}\n", name, text));
+        }
+        for mode in Mode::all() {
+            out.push_str(&format!("mode_{}: {}\n", mode.command(), mode.description()));
+        }
+        out
+    }
+}
+
+/// Show `text` when hovering whatever `add_contents` adds
+pub fn with_help(ui: &mut egui::Ui, text: &str, add_contents: impl FnOnce(&mut egui::Ui)) {
+    ui.scope(add_contents).response.on_hover_text(text);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_help_entries_unique_and_filled() {
+        for (i, (name, text)) in HelpText::ENTRIES.iter().enumerate() {
+            assert!(!text.is_empty(), "{} has no help text", name);
+            assert!(
+                HelpText::ENTRIES[i + 1..].iter().all(|(other, _)| other != name),
+                "{} is listed twice",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_help_lookup_and_render_all() {
+        assert!(HelpText::get("fan_boost").contains("BOOST"));
+        assert_eq!(HelpText::get("no_such_widget"), "");
+
+        let all = HelpText::render_all();
+        assert!(all.lines().any(|l| l.starts_with("target_temp: ")));
+        assert!(all.lines().any(|l| l.starts_with("mode_performance: ")));
+        assert_eq!(all.lines().count(), HelpText::ENTRIES.len() + Mode::all().len());
+    }
+}
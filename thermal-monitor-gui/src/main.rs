@@ -3,12 +3,99 @@
 //! Minimal thermal monitoring application using egui/eframe.
 //! Displays CPU and estimated keyboard temperatures, allows mode control.
 
+#![cfg_attr(feature = "simd", feature(portable_simd))]
+
 mod app;
+mod cli;
+mod config;
+mod expr;
+mod help;
+mod plugin;
+#[cfg(feature = "protobuf")]
+mod proto;
+mod ring_buffer;
+mod session;
+mod state_machine;
 mod system;
+mod widgets;
 
 use app::ThermalApp;
+use cli::CliArgs;
+use config::Config;
+use help::HelpText;
+use session::SessionStats;
+use system::{read_cpu_temp, read_mode, set_fan_boost, set_mode, ThermalState, ThermalZone};
 
 fn main() -> eframe::Result<()> {
+    let args = match CliArgs::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    if args.help_ui {
+        print!("{}", HelpText::render_all());
+        return Ok(());
+    }
+    if args.print_last_session {
+        match SessionStats::read_last_session() {
+            Ok(Some(summary)) => println!("{}", summary),
+            Ok(None) => println!("No session recorded yet"),
+            Err(e) => {
+                eprintln!("{}: {}", SessionStats::log_path().display(), e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+    if args.hardware_report {
+        print!("{}", ThermalState::read().hardware_report());
+        return Ok(());
+    }
+    if args.assert_mode.is_some() || args.assert_zone.is_some() {
+        // Only the two sysfs reads needed, so scripts get an answer quickly
+        let zone = read_cpu_temp().ok().map(ThermalZone::from_cpu_temp);
+        let (output, passed) = args.check_assertions(read_mode(), zone);
+        println!("{}", output);
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+    if args.set_mode.is_some() || args.set_fan_boost.is_some() || args.set_target.is_some() {
+        let mut failed = false;
+        if let Some(mode) = args.set_mode {
+            if let Err(e) = set_mode(mode) {
+                eprintln!("--set-mode {}: {}", mode.command(), e);
+                failed = true;
+            }
+        }
+        if let Some(enable) = args.set_fan_boost {
+            if let Err(e) = set_fan_boost(enable) {
+                eprintln!("--set-fan-boost: {}", e);
+                failed = true;
+            }
+        }
+        if let Some(target) = args.set_target {
+            let saved = Config::read_from(&Config::path()).map_err(|e| e.to_string()).and_then(|config| {
+                let config = Config { target_temp: target, ..config.unwrap_or_default() };
+                config.save().map_err(|e| e.to_string())
+            });
+            if let Err(e) = saved {
+                eprintln!("--set-target {}: {}", Config::path().display(), e);
+                failed = true;
+            }
+        }
+        std::process::exit(if failed { 1 } else { 0 });
+    }
+    // Log the session summary on SIGTERM/SIGINT too, e.g. at logout
+    ThermalApp::install_terminate_handler();
+    if let Some(duration) = args.run_for {
+        if args.influxdb_url.is_some() && !cfg!(feature = "webhook") {
+            eprintln!("--influxdb-url needs a build with the webhook feature");
+            std::process::exit(2);
+        }
+        std::process::exit(ThermalApp::oneshot_mode(duration, args.influxdb_url));
+    }
+
     let options = eframe::NativeOptions {
         viewport: eframe::egui::ViewportBuilder::default()
             .with_inner_size([800.0, 600.0])
@@ -17,9 +104,17 @@ fn main() -> eframe::Result<()> {
         ..Default::default()
     };
 
+    // Save history if the UI panics; the next start falls back to Balanced
+    ThermalApp::install_panic_handler();
     eframe::run_native(
         "Thermal Monitor",
         options,
-        Box::new(|cc| Ok(Box::new(ThermalApp::new(cc)))),
+        Box::new(move |cc| {
+            let mut app = ThermalApp::new(cc);
+            if args.update_bar {
+                app.enable_status_bar(args.bar_format);
+            }
+            Ok(Box::new(app))
+        }),
     )
 }
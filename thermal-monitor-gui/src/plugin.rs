@@ -0,0 +1,269 @@
+//! Plugins
+//!
+//! Extension point for custom widgets and control logic. A plugin is a
+//! dynamic library in [`plugin_dir`] exporting [`PLUGIN_ENTRY_POINT`]; see
+//! `examples/uptime_plugin.rs`. Only C-layout data crosses the library
+//! boundary ([`PluginInstance`], [`PluginVTable`], [`PluginState`]), so a
+//! plugin may be built by any compiler, or in any language, that targets
+//! [`PLUGIN_ABI_VERSION`]. Loading is off unless `plugins_enabled` is set in
+//! the config.
+
+use std::ffi::{c_char, c_void};
+use std::path::PathBuf;
+
+use eframe::egui;
+
+use crate::system::{Mode, ThermalState};
+
+/// Symbol every plugin library exports, of type [`PluginCreate`]
+#[cfg_attr(not(feature = "plugins"), allow(dead_code))]
+pub const PLUGIN_ENTRY_POINT: &[u8] = b"thermal_plugin_create";
+
+/// Layout version of the types below; plugins built for another one are refused
+#[cfg_attr(not(feature = "plugins"), allow(dead_code))]
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// Signature of [`PLUGIN_ENTRY_POINT`]
+#[cfg_attr(not(feature = "plugins"), allow(dead_code))]
+pub type PluginCreate = unsafe extern "C" fn() -> PluginInstance;
+
+/// Plugin state and the functions operating on it, returned by [`PluginCreate`]
+#[cfg_attr(not(feature = "plugins"), allow(dead_code))]
+#[repr(C)]
+pub struct PluginInstance {
+    /// Opaque to the app; passed back as the first argument of every call
+    pub data: *mut c_void,
+    /// Must stay valid while the library is loaded (normally a `static`)
+    pub vtable: *const PluginVTable,
+}
+
+/// Plugin callbacks. Strings are NUL-terminated UTF-8 owned by the plugin.
+#[cfg_attr(not(feature = "plugins"), allow(dead_code))]
+#[repr(C)]
+pub struct PluginVTable {
+    /// [`PLUGIN_ABI_VERSION`] the plugin was built for; checked before any call
+    pub abi_version: u32,
+    /// Title of the plugin's panel, valid until `destroy`
+    pub name: unsafe extern "C" fn(data: *mut c_void) -> *const c_char,
+    /// Called after every sensor refresh
+    pub on_state_update: unsafe extern "C" fn(data: *mut c_void, state: *const PluginState),
+    /// Called when the mode is changed from the app or outside it, with [`mode_code`]s
+    pub on_mode_change: unsafe extern "C" fn(data: *mut c_void, old: u32, new: u32),
+    /// Panel contents, one label per line, valid until the next call into the plugin
+    pub panel_text: unsafe extern "C" fn(data: *mut c_void) -> *const c_char,
+    /// Free `data`; no call follows
+    pub destroy: unsafe extern "C" fn(data: *mut c_void),
+}
+
+/// Sensor readings handed to [`PluginVTable::on_state_update`]
+#[cfg_attr(not(feature = "plugins"), allow(dead_code))]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PluginState {
+    pub cpu_temp: f32,
+    pub keyboard_temp: f32,
+    pub perf_pct: u32,
+    /// See [`mode_code`]
+    pub mode: u32,
+}
+
+impl From<&ThermalState> for PluginState {
+    fn from(state: &ThermalState) -> Self {
+        Self {
+            cpu_temp: state.cpu_temp,
+            keyboard_temp: state.keyboard_temp,
+            perf_pct: state.perf_pct as u32,
+            mode: mode_code(state.mode),
+        }
+    }
+}
+
+/// Stable number of `mode` in the plugin ABI: 0 Performance, 1 Comfort,
+/// 2 Balanced, 3 Quiet, 4 Auto, 5 Unknown
+#[cfg_attr(not(feature = "plugins"), allow(dead_code))]
+pub fn mode_code(mode: Mode) -> u32 {
+    match mode {
+        Mode::Performance => 0,
+        Mode::Comfort => 1,
+        Mode::Balanced => 2,
+        Mode::Quiet => 3,
+        Mode::Auto => 4,
+        Mode::Unknown => 5,
+    }
+}
+
+/// Hooks the app calls on each loaded plugin
+pub trait ThermalPlugin {
+    /// Title of the plugin's panel
+    fn name(&self) -> &str;
+
+    /// Called after every sensor refresh
+    fn on_state_update(&mut self, state: &ThermalState);
+
+    /// Draw the plugin's panel in the main window
+    fn render_panel(&mut self, ui: &mut egui::Ui);
+
+    /// Called when the performance mode is changed from the app or outside it
+    fn on_mode_change(&mut self, old: Mode, new: Mode);
+}
+
+/// `$XDG_DATA_HOME/lenovo-thermal-monitor/plugins` (falls back to `~/.local/share`)
+#[cfg_attr(not(feature = "plugins"), allow(dead_code))]
+pub fn plugin_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .unwrap_or_else(|| PathBuf::from("/tmp"));
+    base.join("lenovo-thermal-monitor").join("plugins")
+}
+
+/// A plugin living in a loaded library, driven through its vtable
+#[cfg(feature = "plugins")]
+struct DynamicPlugin {
+    instance: PluginInstance,
+    name: String,
+    /// Declared last so the code the vtable points into is unmapped after `destroy`
+    _library: libloading::Library,
+}
+
+#[cfg(feature = "plugins")]
+impl DynamicPlugin {
+    fn vtable(&self) -> &PluginVTable {
+        // SAFETY: checked non-null in `load_plugins`; the plugin keeps it valid
+        // while the library is loaded, which `_library` guarantees
+        unsafe { &*self.instance.vtable }
+    }
+}
+
+/// Copy a plugin-owned string, empty for null
+#[cfg(feature = "plugins")]
+fn plugin_string(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+    // SAFETY: the vtable contract makes non-null strings NUL-terminated and
+    // valid until the next call into the plugin, and this copies it right away
+    unsafe { std::ffi::CStr::from_ptr(ptr) }.to_string_lossy().into_owned()
+}
+
+#[cfg(feature = "plugins")]
+impl ThermalPlugin for DynamicPlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn on_state_update(&mut self, state: &ThermalState) {
+        let state = PluginState::from(state);
+        // SAFETY: `data` came from the same plugin's create function and
+        // `state` outlives the call
+        unsafe { (self.vtable().on_state_update)(self.instance.data, &state) }
+    }
+
+    fn render_panel(&mut self, ui: &mut egui::Ui) {
+        // SAFETY: see `on_state_update`
+        let text = plugin_string(unsafe { (self.vtable().panel_text)(self.instance.data) });
+        for line in text.lines() {
+            ui.label(line);
+        }
+    }
+
+    fn on_mode_change(&mut self, old: Mode, new: Mode) {
+        // SAFETY: see `on_state_update`
+        unsafe { (self.vtable().on_mode_change)(self.instance.data, mode_code(old), mode_code(new)) }
+    }
+}
+
+#[cfg(feature = "plugins")]
+impl Drop for DynamicPlugin {
+    fn drop(&mut self) {
+        // SAFETY: `data` is not used again; the library is still loaded
+        unsafe { (self.vtable().destroy)(self.instance.data) }
+    }
+}
+
+/// Plugins created from the `.so` files in `dir`, in file name order.
+/// Files that fail to load are skipped and described in the error list.
+/// A missing directory means no plugins.
+#[cfg(feature = "plugins")]
+pub fn load_plugins(dir: &std::path::Path) -> (Vec<Box<dyn ThermalPlugin>>, Vec<String>) {
+    let (mut plugins, mut errors) = (Vec::<Box<dyn ThermalPlugin>>::new(), Vec::new());
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return (plugins, errors);
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "so"))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let fail = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+        // SAFETY: loading runs the library's initializers. Plugins are opt-in
+        // and the directory belongs to the user, who chose to install the file.
+        let library = match unsafe { libloading::Library::new(&path) } {
+            Ok(library) => library,
+            Err(e) => {
+                errors.push(fail(&e));
+                continue;
+            }
+        };
+        // SAFETY: the symbol is declared with the C signature `PluginCreate`;
+        // only C-layout types cross the boundary
+        let instance = match unsafe { library.get::<PluginCreate>(PLUGIN_ENTRY_POINT) } {
+            Ok(create) => unsafe { create() },
+            Err(e) => {
+                errors.push(fail(&e));
+                continue;
+            }
+        };
+        if instance.vtable.is_null() {
+            errors.push(fail(&"no vtable"));
+            continue;
+        }
+        // SAFETY: non-null, and `abi_version` is the first field in every version
+        let abi_version = unsafe { (*instance.vtable).abi_version };
+        if abi_version != PLUGIN_ABI_VERSION {
+            // The instance cannot be freed safely through an unknown layout; leak it
+            errors.push(fail(&format!("plugin ABI {}, expected {}", abi_version, PLUGIN_ABI_VERSION)));
+            std::mem::forget(library);
+            continue;
+        }
+        // SAFETY: the version matches, so the vtable has this layout
+        let name = plugin_string(unsafe { ((*instance.vtable).name)(instance.data) });
+        plugins.push(Box::new(DynamicPlugin { instance, name, _library: library }));
+    }
+    (plugins, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plugin_dir() {
+        assert!(plugin_dir().ends_with("lenovo-thermal-monitor/plugins"));
+    }
+
+    #[test]
+    fn test_plugin_state_from_thermal_state() {
+        let state = ThermalState { cpu_temp: 61.5, keyboard_temp: 38.0, perf_pct: 80, mode: Mode::Quiet, ..Default::default() };
+        assert_eq!(
+            PluginState::from(&state),
+            PluginState { cpu_temp: 61.5, keyboard_temp: 38.0, perf_pct: 80, mode: 3 }
+        );
+        assert_eq!(mode_code(Mode::Unknown), 5);
+    }
+
+    #[cfg(feature = "plugins")]
+    #[test]
+    fn test_load_plugins_skips_missing_and_foreign_files() {
+        let (plugins, errors) = load_plugins(std::path::Path::new("/nonexistent/plugins"));
+        assert!(plugins.is_empty() && errors.is_empty());
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not a plugin").unwrap();
+        let (plugins, errors) = load_plugins(dir.path());
+        assert!(plugins.is_empty());
+        assert!(errors.is_empty());
+    }
+}
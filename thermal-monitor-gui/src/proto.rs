@@ -0,0 +1,27 @@
+//! Protobuf messages for exporting temperature history
+//!
+//! Hand-written prost types mirroring `proto/temperature_history.proto`,
+//! so no build script or protoc is needed.
+
+/// One named series of samples, oldest first
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Channel {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(float, repeated, tag = "2")]
+    pub samples: Vec<f32>,
+}
+
+/// Complete history snapshot
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TemperatureHistory {
+    #[prost(uint32, tag = "1")]
+    pub capacity: u32,
+    #[prost(uint32, tag = "2")]
+    pub sample_count: u32,
+    /// Unix time in milliseconds for each sample, empty when not recorded
+    #[prost(int64, repeated, tag = "3")]
+    pub timestamps_unix_ms: Vec<i64>,
+    #[prost(message, repeated, tag = "4")]
+    pub channels: Vec<Channel>,
+}
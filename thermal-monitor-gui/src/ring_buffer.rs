@@ -0,0 +1,138 @@
+//! Fixed-capacity ring buffer
+//!
+//! Samples live in one contiguous allocation that grows with the first
+//! pushes; once full, each push overwrites the oldest sample. Iteration walks at most two slices, which
+//! keeps the per-frame plot point generation cache friendly.
+
+use std::ops::Index;
+
+/// Samples reserved up front; larger buffers grow as they fill
+const INITIAL_ALLOCATION: usize = 1024;
+
+/// Ring buffer of samples, oldest first
+#[derive(Debug, Clone)]
+pub struct RingBuffer<T> {
+    buf: Vec<T>,
+    capacity: usize,
+    /// Index of the oldest sample, nonzero only once full
+    head: usize,
+}
+
+impl<T> RingBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self { buf: Vec::with_capacity(capacity.min(INITIAL_ALLOCATION)), capacity, head: 0 }
+    }
+
+    /// Append `value`, dropping the oldest sample when full
+    pub fn push(&mut self, value: T) {
+        if self.buf.len() < self.capacity {
+            self.buf.push(value);
+        } else if self.capacity > 0 {
+            self.buf[self.head] = value;
+            self.head = (self.head + 1) % self.capacity;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Sample `index` positions after the oldest one
+    pub fn get(&self, index: usize) -> Option<&T> {
+        (index < self.len()).then(|| &self.buf[(self.head + index) % self.len()])
+    }
+
+    /// Newest sample
+    pub fn back(&self) -> Option<&T> {
+        self.len().checked_sub(1).and_then(|last| self.get(last))
+    }
+
+    /// Contents as (older, newer) slices
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let (newer, older) = self.buf.split_at(self.head);
+        (older, newer)
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + Clone + '_ {
+        let (older, newer) = self.as_slices();
+        older.iter().chain(newer)
+    }
+
+    pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut T> + '_ {
+        let (newer, older) = self.buf.split_at_mut(self.head);
+        older.iter_mut().chain(newer.iter_mut())
+    }
+}
+
+impl<T> Index<usize> for RingBuffer<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        self.get(index).expect("ring buffer index out of range")
+    }
+}
+
+impl<'a, T> IntoIterator for &'a RingBuffer<T> {
+    type Item = &'a T;
+    type IntoIter = std::iter::Chain<std::slice::Iter<'a, T>, std::slice::Iter<'a, T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let (older, newer) = self.as_slices();
+        older.iter().chain(newer.iter())
+    }
+}
+
+/// Equal when both hold the same samples in the same order, regardless of capacity
+impl<T: PartialEq> PartialEq for RingBuffer<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: PartialEq, const N: usize> PartialEq<[T; N]> for RingBuffer<T> {
+    fn eq(&self, other: &[T; N]) -> bool {
+        self.len() == N && self.iter().eq(other.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_wraps_and_drops_oldest() {
+        let mut ring = RingBuffer::new(3);
+        assert!(ring.is_empty());
+        assert_eq!(ring.back(), None);
+        for value in 1..=5 {
+            ring.push(value);
+        }
+        assert_eq!(ring.len(), 3);
+        assert_eq!(ring, [3, 4, 5]);
+        assert_eq!(ring.as_slices(), (&[3][..], &[4, 5][..]));
+        assert_eq!((ring.get(0), ring[2], ring.get(3)), (Some(&3), 5, None));
+        assert_eq!(ring.back(), Some(&5));
+        assert_eq!(ring.iter().rev().copied().collect::<Vec<_>>(), vec![5, 4, 3]);
+
+        for value in ring.iter_mut() {
+            *value *= 10;
+        }
+        assert_eq!(ring, [30, 40, 50]);
+
+        let mut empty = RingBuffer::new(0);
+        empty.push(1);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_new_does_not_allocate_full_capacity() {
+        let mut ring = RingBuffer::new(usize::MAX);
+        assert!(ring.buf.capacity() <= INITIAL_ALLOCATION);
+        ring.push(1u8);
+        assert_eq!(ring, [1]);
+    }
+}
@@ -0,0 +1,232 @@
+//! Session summary
+//!
+//! Collected while the app runs, printed on exit and appended to a log so
+//! `--print-last-session` can show it later.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::app::format_iso8601;
+use crate::system::ThermalZone;
+
+/// First line of every summary in the log
+const SESSION_HEADER: &str = "=== Session ";
+
+/// Size past which the log is moved to `sessions.log.1` before the next append
+const SESSION_LOG_MAX_BYTES: u64 = 512 * 1024;
+
+/// Minimum, maximum and mean of a stream of readings
+#[derive(Debug, Clone, Copy, Default)]
+struct RunningStats {
+    min: f32,
+    max: f32,
+    sum: f64,
+    count: u64,
+}
+
+impl RunningStats {
+    fn push(&mut self, value: f32) {
+        if self.count == 0 {
+            (self.min, self.max) = (value, value);
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.sum += value as f64;
+        self.count += 1;
+    }
+
+    fn describe(&self) -> String {
+        if self.count == 0 {
+            return "no samples".into();
+        }
+        format!(
+            "peak {:.1}°C, average {:.1}°C, minimum {:.1}°C",
+            self.max,
+            self.sum / self.count as f64,
+            self.min
+        )
+    }
+}
+
+/// Counters and temperature statistics for the current run
+#[derive(Debug)]
+pub struct SessionStats {
+    started: SystemTime,
+    start_instant: Instant,
+    pub mode_changes: u32,
+    pub fan_boost_activations: u32,
+    pub auto_control_interventions: u32,
+    cpu: RunningStats,
+    kbd: RunningStats,
+    zone_time: Vec<(ThermalZone, Duration)>,
+}
+
+impl Default for SessionStats {
+    fn default() -> Self {
+        Self {
+            started: SystemTime::now(),
+            start_instant: Instant::now(),
+            mode_changes: 0,
+            fan_boost_activations: 0,
+            auto_control_interventions: 0,
+            cpu: RunningStats::default(),
+            kbd: RunningStats::default(),
+            zone_time: ThermalZone::all().iter().map(|&zone| (zone, Duration::ZERO)).collect(),
+        }
+    }
+}
+
+/// `1h 02m 03s`, `4m 05s` or `6s`
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {:02}s", m, s),
+        (h, m, s) => format!("{}h {:02}m {:02}s", h, m, s),
+    }
+}
+
+impl SessionStats {
+    /// Add one poll; the CPU zone is credited with `interval`
+    pub fn record(&mut self, cpu: f32, kbd: f32, interval: Duration) {
+        self.cpu.push(cpu);
+        self.kbd.push(kbd);
+        let zone = ThermalZone::from_cpu_temp(cpu);
+        if let Some((_, time)) = self.zone_time.iter_mut().find(|(z, _)| *z == zone) {
+            *time += interval;
+        }
+    }
+
+    /// Summary text for a session that lasted `runtime`
+    pub fn report(&self, runtime: Duration) -> String {
+        let zones: Vec<String> = self
+            .zone_time
+            .iter()
+            .map(|(zone, time)| format!("{} {}", zone.label(), format_duration(*time)))
+            .collect();
+        format!(
+            "{}{} ===\n\
+             Runtime: {}\n\
+             Mode changes: {}\n\
+             Fan boost activations: {}\n\
+             Auto-control interventions: {}\n\
+             CPU temperature: {}\n\
+             Keyboard temperature: {}\n\
+             Time in zones: {}\n",
+            SESSION_HEADER,
+            format_iso8601(self.started),
+            format_duration(runtime),
+            self.mode_changes,
+            self.fan_boost_activations,
+            self.auto_control_interventions,
+            self.cpu.describe(),
+            self.kbd.describe(),
+            zones.join(", ")
+        )
+    }
+
+    /// Summary up to now
+    pub fn report_now(&self) -> String {
+        self.report(self.start_instant.elapsed())
+    }
+
+    /// `$XDG_DATA_HOME/lenovo-thermal-monitor/sessions.log` (falls back to `~/.local/share`)
+    pub fn log_path() -> PathBuf {
+        let base = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+            .unwrap_or_else(|| PathBuf::from("/tmp"));
+        base.join("lenovo-thermal-monitor").join("sessions.log")
+    }
+
+    /// Append `report` to the session log
+    pub fn append_to_log(report: &str) -> io::Result<()> {
+        Self::append_to(&Self::log_path(), report, SESSION_LOG_MAX_BYTES)
+    }
+
+    /// Append `report` to the log at `path`, first replacing `<path>.1` with
+    /// the log if it has grown past `max_bytes`, so at most two logs are kept
+    fn append_to(path: &Path, report: &str, max_bytes: u64) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        if fs::metadata(path).is_ok_and(|metadata| metadata.len() > max_bytes) {
+            let mut rotated = path.as_os_str().to_owned();
+            rotated.push(".1");
+            fs::rename(path, rotated)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", report)
+    }
+
+    /// The newest summary in a session log
+    pub fn last_session(log: &str) -> Option<&str> {
+        log.rfind(SESSION_HEADER).map(|start| log[start..].trim_end())
+    }
+
+    /// The newest summary from the session log file (`None` before the first session)
+    pub fn read_last_session() -> io::Result<Option<String>> {
+        match fs::read_to_string(Self::log_path()) {
+            Ok(log) => Ok(Self::last_session(&log).map(str::to_string)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let mut stats = SessionStats {
+            mode_changes: 2,
+            fan_boost_activations: 1,
+            auto_control_interventions: 5,
+            ..Default::default()
+        };
+        stats.record(42.0, 33.0, Duration::from_secs(2));
+        stats.record(60.0, 41.0, Duration::from_secs(2));
+        stats.record(51.0, 37.0, Duration::from_secs(2));
+
+        let report = stats.report(Duration::from_secs(3723));
+        assert!(report.starts_with(SESSION_HEADER));
+        assert!(report.contains("Runtime: 1h 02m 03s\n"));
+        assert!(report.contains("Mode changes: 2\n"));
+        assert!(report.contains("Fan boost activations: 1\n"));
+        assert!(report.contains("Auto-control interventions: 5\n"));
+        assert!(report.contains("CPU temperature: peak 60.0°C, average 51.0°C, minimum 42.0°C\n"));
+        assert!(report.contains("Keyboard temperature: peak 41.0°C, average 37.0°C, minimum 33.0°C\n"));
+        assert!(report.contains("COMFORT 2s, OPTIMAL 0s, WARM 2s, HOT 2s"));
+
+        assert!(SessionStats::default().report(Duration::from_secs(65)).contains("CPU temperature: no samples"));
+        assert_eq!(format_duration(Duration::from_secs(65)), "1m 05s");
+    }
+
+    #[test]
+    fn test_last_session() {
+        let first = SessionStats::default().report(Duration::from_secs(10));
+        let second = SessionStats { mode_changes: 7, ..Default::default() }.report(Duration::from_secs(20));
+        let log = format!("{}\n{}\n", first, second);
+        assert_eq!(SessionStats::last_session(&log), Some(second.trim_end()));
+        assert_eq!(SessionStats::last_session(""), None);
+    }
+
+    #[test]
+    fn test_append_rotates_large_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sessions.log");
+        SessionStats::append_to(&path, "first", 8).unwrap();
+        SessionStats::append_to(&path, "second", 8).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+
+        // Past the limit: the old log moves aside and the new one starts fresh
+        SessionStats::append_to(&path, "third", 8).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third\n");
+        assert_eq!(fs::read_to_string(dir.path().join("sessions.log.1")).unwrap(), "first\nsecond\n");
+    }
+}
@@ -0,0 +1,102 @@
+//! Validation of control actions
+//!
+//! Some combinations of mode, fan and power source work against each other.
+//! Hard violations are refused; soft ones go ahead with an inline warning.
+
+use crate::system::{Mode, ThermalState};
+
+/// Control action proposed by the user or the app
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ControlAction {
+    SetMode(Mode),
+    SetFanBoost(bool),
+}
+
+/// Outcome of [`ThermalStateMachine::validate`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationResult {
+    pub allowed: bool,
+    /// Why the action was refused, or what to watch out for when it is allowed
+    pub warning: Option<String>,
+}
+
+impl ValidationResult {
+    fn ok() -> Self {
+        Self { allowed: true, warning: None }
+    }
+
+    fn warn(warning: &str) -> Self {
+        Self { allowed: true, warning: Some(warning.into()) }
+    }
+
+    fn deny(reason: &str) -> Self {
+        Self { allowed: false, warning: Some(reason.into()) }
+    }
+}
+
+/// Current control state that proposed actions are checked against
+#[derive(Debug, Clone, Copy)]
+pub struct ThermalStateMachine<'a> {
+    pub state: &'a ThermalState,
+    pub auto_control: bool,
+    /// Manual fan boost is on
+    pub fan_boost: bool,
+    /// Battery charge (%) the config treats as low
+    pub low_battery_pct: u8,
+}
+
+impl ThermalStateMachine<'_> {
+    pub fn validate(&self, action: ControlAction) -> ValidationResult {
+        let on_battery = self.state.on_ac == Some(false);
+        match action {
+            ControlAction::SetFanBoost(true) if self.state.mode == Mode::Quiet => {
+                ValidationResult::deny("Fan boost is not available in Quiet mode")
+            }
+            ControlAction::SetMode(Mode::Performance) if on_battery && self.auto_control => {
+                ValidationResult::warn("Performance on battery: auto-control will keep throttling it")
+            }
+            ControlAction::SetMode(Mode::Performance)
+                if on_battery && self.state.battery_pct.is_some_and(|pct| pct <= self.low_battery_pct + 10) =>
+            {
+                ValidationResult::warn("Performance mode drains a low battery quickly")
+            }
+            ControlAction::SetMode(Mode::Quiet) if self.fan_boost => {
+                ValidationResult::warn("Fan boost stays on in Quiet mode")
+            }
+            _ => ValidationResult::ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let state = ThermalState { mode: Mode::Quiet, on_ac: Some(false), battery_pct: Some(80), ..Default::default() };
+        let machine = ThermalStateMachine { state: &state, auto_control: true, fan_boost: false, low_battery_pct: 10 };
+
+        // Hard violation: refused
+        let boost = machine.validate(ControlAction::SetFanBoost(true));
+        assert!(!boost.allowed && boost.warning.is_some());
+        assert_eq!(machine.validate(ControlAction::SetFanBoost(false)), ValidationResult::ok());
+
+        // Soft violations: allowed with a warning
+        let performance = machine.validate(ControlAction::SetMode(Mode::Performance));
+        assert!(performance.allowed && performance.warning.is_some());
+        let manual = ThermalStateMachine { auto_control: false, ..machine };
+        assert_eq!(manual.validate(ControlAction::SetMode(Mode::Performance)), ValidationResult::ok());
+        let low = ThermalState { battery_pct: Some(18), ..state.clone() };
+        let low_machine = ThermalStateMachine { state: &low, ..manual };
+        assert!(low_machine.validate(ControlAction::SetMode(Mode::Performance)).warning.is_some());
+
+        let plugged = ThermalState { on_ac: Some(true), ..state.clone() };
+        let plugged_machine = ThermalStateMachine { state: &plugged, ..machine };
+        assert_eq!(plugged_machine.validate(ControlAction::SetMode(Mode::Performance)), ValidationResult::ok());
+
+        let boosted = ThermalStateMachine { fan_boost: true, ..plugged_machine };
+        assert!(boosted.validate(ControlAction::SetMode(Mode::Quiet)).warning.is_some());
+        assert_eq!(boosted.validate(ControlAction::SetMode(Mode::Balanced)), ValidationResult::ok());
+    }
+}
@@ -3,9 +3,16 @@
 //! This module reads directly from Linux sysfs to minimize dependencies.
 //! All temperatures are in Celsius, frequencies in MHz.
 
+use std::collections::HashMap;
+use std::fmt;
 use std::fs;
-use std::io::{self, ErrorKind};
-use std::process::Command;
+use std::io::{self, BufRead, BufReader, ErrorKind};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
 
 /// Thermal attenuation factor for keyboard temperature estimation
 /// Based on physical model: T_kbd = T_amb + (T_cpu - T_amb) * ATTENUATION
@@ -14,8 +21,56 @@ const THERMAL_ATTENUATION: f32 = 0.45;
 /// Default ambient temperature when not measurable
 const DEFAULT_AMBIENT: f32 = 28.0;
 
+/// Sensor names usable as variables in custom metric expressions
+pub const SENSOR_NAMES: &[&str] = &[
+    "cpu_temp",
+    "kbd_temp",
+    "ambient_temp",
+    "perf_pct",
+    "freq_mhz",
+    "max_freq_mhz",
+    "mem_used_pct",
+    "tjmax",
+    "headroom",
+    "fan_rpm",
+];
+
+/// Errors from hardware access that need more context than `io::Error`
+#[derive(Debug)]
+pub enum ThermalError {
+    /// Underlying file or process I/O failed
+    Io(io::Error),
+    /// Data was read but could not be decoded
+    InvalidData(String),
+    /// A mode was written but the hardware reports a different one
+    VerificationFailed { written: Mode, actual: Mode },
+}
+
+impl fmt::Display for ThermalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThermalError::Io(e) => write!(f, "{}", e),
+            ThermalError::InvalidData(msg) => write!(f, "Invalid data: {}", msg),
+            ThermalError::VerificationFailed { written, actual } => write!(
+                f,
+                "Mode change failed — hardware rejected '{}' (reports '{}')",
+                written.label(),
+                actual.label()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ThermalError {}
+
+impl From<io::Error> for ThermalError {
+    fn from(e: io::Error) -> Self {
+        ThermalError::Io(e)
+    }
+}
+
 /// CPU mode enumeration
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum Mode {
     Performance,
     Comfort,
@@ -38,6 +93,18 @@ impl Mode {
         }
     }
 
+    /// Compact name for status bars
+    pub fn short_label(&self) -> &'static str {
+        match self {
+            Mode::Performance => "Perf",
+            Mode::Comfort => "Comfort",
+            Mode::Balanced => "Balanced",
+            Mode::Quiet => "Quiet",
+            Mode::Auto => "Auto",
+            Mode::Unknown => "Unknown",
+        }
+    }
+
     pub fn command(&self) -> &'static str {
         match self {
             Mode::Performance => "performance",
@@ -60,11 +127,46 @@ impl Mode {
         }
     }
 
+    /// Rough power draw compared with Balanced
+    pub fn power_draw_estimate(&self) -> &'static str {
+        match self {
+            Mode::Performance => "~5–10W above baseline",
+            Mode::Comfort => "~1W below baseline",
+            Mode::Balanced => "Baseline",
+            Mode::Quiet => "~2W below baseline",
+            Mode::Auto => "Varies with load",
+            Mode::Unknown => "Unknown",
+        }
+    }
+
+    /// Midpoint of `power_draw_estimate` in watts (`None` when it varies)
+    pub fn power_delta_watts(&self) -> Option<f32> {
+        match self {
+            Mode::Performance => Some(7.5),
+            Mode::Comfort => Some(-1.0),
+            Mode::Balanced => Some(0.0),
+            Mode::Quiet => Some(-2.0),
+            Mode::Auto | Mode::Unknown => None,
+        }
+    }
+
     pub fn all() -> &'static [Mode] {
         &[Mode::Performance, Mode::Comfort, Mode::Balanced, Mode::Quiet, Mode::Auto]
     }
 }
 
+/// CPU load (%) below which the zones are judged more strictly
+const LOW_LOAD_PCT: f32 = 20.0;
+
+/// Degrees added to a lightly loaded CPU's temperature before classifying it
+const LOW_LOAD_TEMP_OFFSET: f32 = 5.0;
+
+/// Load (%) above [`LOW_LOAD_PCT`] a lightly loaded CPU must reach to count as loaded again
+const LOW_LOAD_HYSTERESIS_PCT: f32 = 10.0;
+
+/// Degrees past a zone boundary the temperature must move before the zone changes
+const ZONE_HYSTERESIS: f32 = 1.0;
+
 /// Thermal zone classification
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ThermalZone {
@@ -88,6 +190,79 @@ impl ThermalZone {
         }
     }
 
+    /// Whether a CPU at `cpu_load_pct` counts as lightly loaded: below
+    /// [`LOW_LOAD_PCT`], or, when it already did, until the load passes it by
+    /// [`LOW_LOAD_HYSTERESIS_PCT`]
+    pub fn is_low_load(cpu_load_pct: f32, was_low_load: bool) -> bool {
+        let limit = if was_low_load { LOW_LOAD_PCT + LOW_LOAD_HYSTERESIS_PCT } else { LOW_LOAD_PCT };
+        cpu_load_pct < limit
+    }
+
+    /// Like [`ThermalZone::from_cpu_temp`], but a CPU that is hot while
+    /// nearly idle (`low_load`, see [`ThermalZone::is_low_load`]) is
+    /// classified one zone up, as the heat is not explained by its work. The
+    /// `previous` zone is kept until the temperature is [`ZONE_HYSTERESIS`]
+    /// past its range. Critical stays a plain temperature limit since it
+    /// triggers protective actions.
+    pub fn from_temp_and_load(cpu_temp: f32, low_load: bool, previous: Option<ThermalZone>) -> Self {
+        let classify = |temp: f32| {
+            if !low_load {
+                return Self::from_cpu_temp(temp);
+            }
+            match Self::from_cpu_temp(temp + LOW_LOAD_TEMP_OFFSET) {
+                ThermalZone::Critical if Self::from_cpu_temp(temp) != ThermalZone::Critical => ThermalZone::Hot,
+                zone => zone,
+            }
+        };
+        match (classify(cpu_temp), previous) {
+            (ThermalZone::Critical, _) => ThermalZone::Critical,
+            (zone, Some(previous))
+                if zone != previous
+                    && (classify(cpu_temp - ZONE_HYSTERESIS) == previous
+                        || classify(cpu_temp + ZONE_HYSTERESIS) == previous) =>
+            {
+                previous
+            }
+            (zone, _) => zone,
+        }
+    }
+
+    pub fn all() -> &'static [ThermalZone] {
+        &[
+            ThermalZone::Cool,
+            ThermalZone::Comfort,
+            ThermalZone::Optimal,
+            ThermalZone::Warm,
+            ThermalZone::Hot,
+            ThermalZone::Critical,
+        ]
+    }
+
+    /// CPU temperature range covered by this zone (lower inclusive, upper exclusive)
+    pub fn temp_range(&self) -> (f32, f32) {
+        match self {
+            ThermalZone::Cool => (f32::NEG_INFINITY, 40.0),
+            ThermalZone::Comfort => (40.0, 45.0),
+            ThermalZone::Optimal => (45.0, 50.0),
+            ThermalZone::Warm => (50.0, 55.0),
+            ThermalZone::Hot => (55.0, 65.0),
+            ThermalZone::Critical => (65.0, f32::INFINITY),
+        }
+    }
+
+    /// Band shaded in this zone's color behind the history graph, in 10°C
+    /// steps from 45°C up to 85°C (lower inclusive, upper exclusive)
+    pub fn heatmap_band(&self) -> (f32, f32) {
+        match self {
+            ThermalZone::Cool => (f32::NEG_INFINITY, 45.0),
+            ThermalZone::Comfort => (45.0, 55.0),
+            ThermalZone::Optimal => (55.0, 65.0),
+            ThermalZone::Warm => (65.0, 75.0),
+            ThermalZone::Hot => (75.0, 85.0),
+            ThermalZone::Critical => (85.0, f32::INFINITY),
+        }
+    }
+
     pub fn label(&self) -> &'static str {
         match self {
             ThermalZone::Cool => "COOL",
@@ -112,13 +287,105 @@ impl ThermalZone {
     }
 }
 
+/// Error for a failed privileged write. A read-only sysfs reported on its
+/// stderr becomes the matching OS error (EROFS or EPERM), so
+/// [`is_read_only_error`] sees it like a direct write failure.
+fn privileged_write_error(action: &str, output: &Output) -> io::Error {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lower = stderr.to_lowercase();
+    if lower.contains("read-only file system") {
+        io::Error::from_raw_os_error(30)
+    } else if lower.contains("operation not permitted") {
+        io::Error::from_raw_os_error(1)
+    } else if stderr.trim().is_empty() {
+        io::Error::other(action.to_string())
+    } else {
+        io::Error::other(format!("{}: {}", action, stderr.trim()))
+    }
+}
+
+/// Whether a write failed because sysfs is read-only (EPERM or EROFS),
+/// e.g. under Secure Boot lockdown or an IMA policy. Other permission
+/// errors (EACCES, a cancelled pkexec prompt) do not count.
+pub fn is_read_only_error(e: &io::Error) -> bool {
+    matches!(e.raw_os_error(), Some(1) | Some(30))
+}
+
 /// Read a single value from a sysfs file
 fn read_sysfs_value(path: &str) -> io::Result<String> {
     fs::read_to_string(path).map(|s| s.trim().to_string())
 }
 
+/// Hardware monitoring devices
+const HWMON_PATH: &str = "/sys/class/hwmon";
+
+/// hwmon device directory under `hwmon_root` whose `name` is `name`
+fn hwmon_dir_by_name(hwmon_root: &Path, name: &str) -> Option<PathBuf> {
+    fs::read_dir(hwmon_root)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|dir| fs::read_to_string(dir.join("name")).is_ok_and(|n| n.trim() == name))
+}
+
+/// Read a millidegree `file` (e.g. `temp1_input`) from the hwmon device called `name`, in °C.
+/// `None` when the device is missing or its value cannot be read; this runs
+/// on every refresh, so failures are not logged.
+fn read_hwmon_temp(hwmon_root: &Path, name: &str, file: &str) -> Option<f32> {
+    let path = hwmon_dir_by_name(hwmon_root, name)?.join(file);
+    let millicelsius: i32 = fs::read_to_string(path).ok()?.trim().parse().ok()?;
+    Some(millicelsius as f32 / 1000.0)
+}
+
+/// Per-core temperatures from the coretemp hwmon device, ordered by core number.
+/// Only `temp*_input` files labelled "Core N" are used (the package sensor is skipped).
+fn read_per_core_temps(hwmon_root: &Path) -> Vec<f32> {
+    let Some(dir) = hwmon_dir_by_name(hwmon_root, "coretemp") else {
+        return Vec::new();
+    };
+    let mut cores: Vec<(u32, f32)> = fs::read_dir(&dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name();
+            let sensor = file_name.to_str()?.strip_suffix("_label")?.to_string();
+            let core: u32 = fs::read_to_string(entry.path()).ok()?.trim().strip_prefix("Core ")?.parse().ok()?;
+            let millicelsius: i32 =
+                fs::read_to_string(dir.join(format!("{}_input", sensor))).ok()?.trim().parse().ok()?;
+            Some((core, millicelsius as f32 / 1000.0))
+        })
+        .collect();
+    cores.sort_by_key(|&(core, _)| core);
+    cores.into_iter().map(|(_, temp)| temp).collect()
+}
+
+/// CPU package temperature from the coretemp sensor labelled "Package id N" under
+/// `hwmon_root`, falling back to the hottest core when there is no package sensor
+fn read_cpu_package_temp_from(hwmon_root: &Path) -> Result<f32, ThermalError> {
+    let dir = hwmon_dir_by_name(hwmon_root, "coretemp")
+        .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "No coretemp sensor found"))?;
+    let package = fs::read_dir(&dir).into_iter().flatten().flatten().find_map(|entry| {
+        let file_name = entry.file_name();
+        let sensor = file_name.to_str()?.strip_suffix("_label")?.to_string();
+        if !fs::read_to_string(entry.path()).ok()?.trim().starts_with("Package") {
+            return None;
+        }
+        let millicelsius: i32 = fs::read_to_string(dir.join(format!("{}_input", sensor))).ok()?.trim().parse().ok()?;
+        Some(millicelsius as f32 / 1000.0)
+    });
+    package
+        .or_else(|| read_per_core_temps(hwmon_root).into_iter().reduce(f32::max))
+        .ok_or_else(|| ThermalError::InvalidData(format!("{}: no package or core sensor", dir.display())))
+}
+
+/// CPU package temperature, as opposed to the hottest core (see [`read_cpu_package_temp_from`])
+pub fn read_cpu_package_temp() -> Result<f32, ThermalError> {
+    read_cpu_package_temp_from(Path::new(HWMON_PATH))
+}
+
 /// Read CPU temperature from thermal zones
-/// Tries x86_pkg_temp first, then TCPU, then any available
+/// Tries x86_pkg_temp first, then TCPU, then any available, then the coretemp/k10temp hwmon
 pub fn read_cpu_temp() -> io::Result<f32> {
     // Try known thermal zone paths
     let paths = [
@@ -154,7 +421,10 @@ pub fn read_cpu_temp() -> io::Result<f32> {
         }
     }
 
-    Err(io::Error::new(ErrorKind::NotFound, "No CPU temperature sensor found"))
+    // No matching thermal zone (common on AMD): ask the CPU hwmon driver directly
+    ThermalState::read_from_hwmon_by_name("coretemp")
+        .or_else(|| ThermalState::read_from_hwmon_by_name("k10temp"))
+        .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "No CPU temperature sensor found"))
 }
 
 /// Read ambient temperature (from ACPI thermal zone)
@@ -171,16 +441,227 @@ pub fn read_ambient_temp() -> f32 {
     DEFAULT_AMBIENT
 }
 
+/// ACPI thermal zone whose trip points are shown in the history graph
+const TRIP_POINT_ZONE_PATH: &str = "/sys/class/thermal/thermal_zone0";
+
+/// Kind of ACPI trip point, i.e. what the firmware does when it is crossed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TripType {
+    /// Turn on a fan
+    Active,
+    /// Throttle the CPU
+    Passive,
+    /// Notify the OS that the system is about to overheat
+    Hot,
+    /// Emergency shutdown
+    Critical,
+}
+
+impl TripType {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "active" => Some(TripType::Active),
+            "passive" => Some(TripType::Passive),
+            "hot" => Some(TripType::Hot),
+            "critical" => Some(TripType::Critical),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TripType::Active => "Active",
+            TripType::Passive => "Passive",
+            TripType::Hot => "Hot",
+            TripType::Critical => "Critical",
+        }
+    }
+}
+
+/// Firmware-defined temperature threshold of a thermal zone
+#[derive(Debug, Clone, PartialEq)]
+pub struct TripPoint {
+    /// sysfs name, e.g. `trip_point_0`
+    pub name: String,
+    pub trip_type: TripType,
+    pub temp_celsius: f32,
+}
+
+impl TripPoint {
+    /// Graph label, e.g. "Passive (90°)"
+    pub fn label(&self) -> String {
+        format!("{} ({:.0}°)", self.trip_type.label(), self.temp_celsius)
+    }
+}
+
+/// Trip points of the thermal zone at `zone_dir` (`trip_point_N_type` and
+/// `trip_point_N_temp` files), coolest first. Disabled or unreadable ones are skipped.
+fn read_trip_points(zone_dir: &Path) -> Vec<TripPoint> {
+    let Ok(entries) = fs::read_dir(zone_dir) else {
+        return Vec::new();
+    };
+    let mut points: Vec<TripPoint> = entries
+        .filter_map(|entry| {
+            let file_name = entry.ok()?.file_name();
+            let name = file_name.to_str()?.strip_suffix("_type")?.to_string();
+            if !name.starts_with("trip_point_") {
+                return None;
+            }
+            let trip_type = TripType::parse(&fs::read_to_string(zone_dir.join(&file_name)).ok()?)?;
+            let millicelsius: i32 = fs::read_to_string(zone_dir.join(format!("{}_temp", name)))
+                .ok()?
+                .trim()
+                .parse()
+                .ok()?;
+            // Unused trip points read as 0 or negative
+            (millicelsius > 0).then(|| TripPoint { name, trip_type, temp_celsius: millicelsius as f32 / 1000.0 })
+        })
+        .collect();
+    points.sort_by(|a, b| a.temp_celsius.total_cmp(&b.temp_celsius));
+    points
+}
+
+/// ACPI trip points of `thermal_zone0`
+pub fn read_thermal_trip_points() -> Vec<TripPoint> {
+    read_trip_points(Path::new(TRIP_POINT_ZONE_PATH))
+}
+
+/// Throttle headroom below which the app warns even outside the Critical zone (°C)
+pub const THROTTLE_WARNING_HEADROOM: f32 = 5.0;
+
+/// Degrees left before the coolest passive trip point in `trip_points`, where
+/// the firmware starts throttling; `tjmax` stands in when there is none
+fn throttle_headroom(trip_points: &[TripPoint], tjmax: f32, cpu_temp: f32) -> f32 {
+    let limit = trip_points
+        .iter()
+        .filter(|point| point.trip_type == TripType::Passive)
+        .map(|point| point.temp_celsius)
+        .min_by(f32::total_cmp)
+        .unwrap_or(tjmax);
+    limit - cpu_temp
+}
+
+/// Degrees C before hardware throttling kicks in: the passive trip point of
+/// `thermal_zone0` minus the hottest core in `state` (cores throttle
+/// individually), or minus the CPU temperature without per-core sensors.
+/// `None` when `state` holds no temperature. Trip points are read on every
+/// call since the firmware may move them.
+pub fn estimate_throttle_headroom(state: &ThermalState) -> Option<f32> {
+    let hottest = state
+        .cpu_core_max_temp
+        .or(Some(state.cpu_temp))
+        .filter(|temp| temp.is_finite() && *temp > 0.0)?;
+    Some(throttle_headroom(&read_thermal_trip_points(), state.tjmax, hottest))
+}
+
+/// Kernel thermal governor of `thermal_zone0` (e.g. `step_wise`,
+/// `power_allocator`, `user_space`). The kernel exposes no separate ACPI
+/// policy switch; the governor decides how it throttles near trip points.
+pub fn read_kernel_thermal_policy() -> Option<String> {
+    read_sysfs_value(&format!("{}/policy", TRIP_POINT_ZONE_PATH)).ok().filter(|policy| !policy.is_empty())
+}
+
 /// Calculate estimated keyboard temperature using thermal physics model
 /// Formula: T_kbd = T_amb + (T_cpu - T_amb) * attenuation_factor
 pub fn calculate_keyboard_temp(cpu_temp: f32, ambient_temp: f32) -> f32 {
     ambient_temp + (cpu_temp - ambient_temp) * THERMAL_ATTENUATION
 }
 
-/// Read current performance percentage from intel_pstate
-pub fn read_perf_pct() -> io::Result<u8> {
-    let content = read_sysfs_value("/sys/devices/system/cpu/intel_pstate/max_perf_pct")?;
-    content.parse::<u8>().map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+/// intel_pstate global turbo switch (1 = turbo disabled)
+const INTEL_NO_TURBO_PATH: &str = "/sys/devices/system/cpu/intel_pstate/no_turbo";
+
+/// Generic cpufreq boost switch (1 = boost enabled)
+const CPUFREQ_BOOST_PATH: &str = "/sys/devices/system/cpu/cpufreq/boost";
+
+/// Read the active cpufreq scaling driver (e.g. `intel_pstate`, `acpi-cpufreq`, `amd-pstate-epp`)
+pub fn read_cpufreq_driver() -> String {
+    read_sysfs_value("/sys/devices/system/cpu/cpu0/cpufreq/scaling_driver").unwrap_or_default()
+}
+
+/// Whether the driver has a turbo/boost switch we know how to toggle
+pub fn supports_turbo_toggle(driver: &str) -> bool {
+    matches!(driver, "intel_pstate" | "amd-pstate-epp")
+}
+
+/// Read whether turbo/boost is enabled (`None` when the driver has no switch)
+pub fn read_turbo_enabled(driver: &str) -> Option<bool> {
+    match driver {
+        "intel_pstate" => read_sysfs_value(INTEL_NO_TURBO_PATH).ok().map(|v| v == "0"),
+        "amd-pstate-epp" => read_sysfs_value(CPUFREQ_BOOST_PATH).ok().map(|v| v == "1"),
+        _ => None,
+    }
+}
+
+/// cpufreq boost switch value: `1` enabled, `0` disabled
+fn parse_cpu_boost(value: &str) -> Result<bool, ThermalError> {
+    match value.trim() {
+        "1" => Ok(true),
+        "0" => Ok(false),
+        other => Err(ThermalError::InvalidData(format!("{}: unexpected value {:?}", CPUFREQ_BOOST_PATH, other))),
+    }
+}
+
+/// Whether the generic cpufreq boost switch exists (acpi-cpufreq, amd-pstate, ...)
+pub fn cpu_boost_supported() -> bool {
+    Path::new(CPUFREQ_BOOST_PATH).exists()
+}
+
+/// Read the generic cpufreq boost switch (Intel Turbo Boost / AMD Precision Boost)
+pub fn cpu_boost_control() -> Result<bool, ThermalError> {
+    parse_cpu_boost(&read_sysfs_value(CPUFREQ_BOOST_PATH)?)
+}
+
+/// Turn the generic cpufreq boost switch on or off using pkexec
+pub fn set_cpu_boost(enabled: bool) -> Result<(), ThermalError> {
+    if !cpu_boost_supported() {
+        return Err(ThermalError::InvalidData(format!("{} not available", CPUFREQ_BOOST_PATH)));
+    }
+    let command = format!("echo {} > {}", if enabled { 1 } else { 0 }, CPUFREQ_BOOST_PATH);
+    let output = Command::new("pkexec").args(["bash", "-c", &command]).output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(privileged_write_error("Failed to set CPU boost", &output).into())
+    }
+}
+
+/// Enable or disable turbo/boost using pkexec
+pub fn set_turbo(driver: &str, enable: bool) -> io::Result<()> {
+    let command = match driver {
+        "intel_pstate" => format!("echo {} > {}", if enable { 0 } else { 1 }, INTEL_NO_TURBO_PATH),
+        "amd-pstate-epp" => format!("echo {} > {}", if enable { 1 } else { 0 }, CPUFREQ_BOOST_PATH),
+        _ => {
+            return Err(io::Error::new(
+                ErrorKind::Unsupported,
+                format!("Turbo toggle not supported by {}", driver),
+            ))
+        }
+    };
+    let output = Command::new("pkexec").args(["bash", "-c", &command]).output()?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(privileged_write_error("Failed to set turbo", &output))
+    }
+}
+
+/// Read current performance percentage: intel_pstate's limit, or
+/// scaling_max_freq relative to cpuinfo_max_freq for other drivers
+pub fn read_perf_pct(driver: &str) -> io::Result<u8> {
+    if driver == "intel_pstate" {
+        let content = read_sysfs_value("/sys/devices/system/cpu/intel_pstate/max_perf_pct")?;
+        return content.parse::<u8>().map_err(|e| io::Error::new(ErrorKind::InvalidData, e));
+    }
+    let read_khz = |path: &str| -> io::Result<u64> {
+        read_sysfs_value(path)?.parse().map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+    };
+    let limit = read_khz("/sys/devices/system/cpu/cpu0/cpufreq/scaling_max_freq")?;
+    let max = read_khz("/sys/devices/system/cpu/cpu0/cpufreq/cpuinfo_max_freq")?;
+    if max == 0 {
+        return Err(io::Error::new(ErrorKind::InvalidData, "cpuinfo_max_freq is 0"));
+    }
+    Ok((limit * 100 / max).min(100) as u8)
 }
 
 /// Read current CPU frequency in MHz
@@ -199,6 +680,14 @@ pub fn read_max_freq() -> io::Result<u32> {
 
 /// Read current mode from cpu-mode status file
 pub fn read_mode() -> Mode {
+    match get_platform_profile_driver().mode_write(Mode::Balanced) {
+        ModeWrite::Sysfs { path, .. } => read_preset(path).map_or(Mode::Unknown, |value| mode_from_preset(&value)),
+        ModeWrite::Helper | ModeWrite::Unsupported => read_helper_mode(),
+    }
+}
+
+/// Mode last applied by the cpu-mode helper
+fn read_helper_mode() -> Mode {
     if let Ok(content) = read_sysfs_value("/tmp/cpu-mode.current") {
         let lower = content.to_lowercase();
         if lower.contains("performance") {
@@ -223,6 +712,294 @@ pub fn read_mode() -> Mode {
     }
 }
 
+/// Read whether the AC adapter is connected (`None` when no mains supply is exposed)
+pub fn read_on_ac() -> Option<bool> {
+    fs::read_dir("/sys/class/power_supply")
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| read_sysfs_value(&path.join("type").to_string_lossy()).is_ok_and(|t| t == "Mains"))
+        .find_map(|path| read_sysfs_value(&path.join("online").to_string_lossy()).ok())
+        .map(|online| online == "1")
+}
+
+/// Sysfs power supply class
+const POWER_SUPPLY_PATH: &str = "/sys/class/power_supply";
+
+/// `attribute` of the first battery under `power_supply_root` that has it
+fn read_battery_attribute<T: std::str::FromStr>(power_supply_root: &Path, attribute: &str) -> Option<T> {
+    fs::read_dir(power_supply_root)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| read_sysfs_value(&path.join("type").to_string_lossy()).is_ok_and(|t| t == "Battery"))
+        .find_map(|path| read_sysfs_value(&path.join(attribute).to_string_lossy()).ok()?.parse().ok())
+}
+
+/// Read the battery charge in percent (`None` without a battery)
+pub fn read_battery_pct() -> Option<u8> {
+    read_battery_attribute(Path::new(POWER_SUPPLY_PATH), "capacity")
+}
+
+/// Read the battery charge cycle count (`None` without a battery or when not reported)
+pub fn read_battery_cycle_count() -> Option<u32> {
+    read_battery_attribute(Path::new(POWER_SUPPLY_PATH), "cycle_count")
+}
+
+/// Battery `status` attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChargingStatus {
+    Charging,
+    Discharging,
+    NotCharging,
+    Full,
+    Unknown,
+}
+
+impl ChargingStatus {
+    pub fn parse(status: &str) -> Self {
+        match status.trim() {
+            "Charging" => ChargingStatus::Charging,
+            "Discharging" => ChargingStatus::Discharging,
+            "Not charging" => ChargingStatus::NotCharging,
+            "Full" => ChargingStatus::Full,
+            _ => ChargingStatus::Unknown,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChargingStatus::Charging => "charging",
+            ChargingStatus::Discharging => "discharging",
+            ChargingStatus::NotCharging => "not charging",
+            ChargingStatus::Full => "full",
+            ChargingStatus::Unknown => "unknown",
+        }
+    }
+}
+
+/// Below this share of its design capacity a battery counts as degraded
+pub const BATTERY_DEGRADED_PCT: f32 = 70.0;
+
+/// Battery wear: how much of its design capacity a full charge still holds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerSupplyHealth {
+    pub capacity_design_mwh: u32,
+    pub capacity_full_mwh: u32,
+    /// Full charge as a share of the design capacity
+    pub health_pct: f32,
+    pub cycle_count: u32,
+    pub status: ChargingStatus,
+}
+
+impl PowerSupplyHealth {
+    pub fn is_degraded(&self) -> bool {
+        self.health_pct < BATTERY_DEGRADED_PCT
+    }
+}
+
+/// Read the health of the first battery (`None` without one or when it
+/// reports no capacities)
+pub fn read_power_supply_health() -> Option<PowerSupplyHealth> {
+    read_power_supply_health_from(Path::new(POWER_SUPPLY_PATH))
+}
+
+/// Batteries report either `energy_*` (µWh) or `charge_*` (µAh) attributes;
+/// charges are converted with the design voltage
+fn read_power_supply_health_from(power_supply_root: &Path) -> Option<PowerSupplyHealth> {
+    let battery = fs::read_dir(power_supply_root)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| read_sysfs_value(&path.join("type").to_string_lossy()).is_ok_and(|t| t == "Battery"))?;
+    let value = |attribute: &str| -> Option<u64> {
+        read_sysfs_value(&battery.join(attribute).to_string_lossy()).ok()?.parse().ok()
+    };
+    let (design_mwh, full_mwh) = match (value("energy_full_design"), value("energy_full")) {
+        (Some(design), Some(full)) => (design / 1000, full / 1000),
+        _ => {
+            let volts = value("voltage_min_design")? as f64 / 1e6;
+            let to_mwh = |micro_ah: u64| (micro_ah as f64 / 1000.0 * volts) as u64;
+            (to_mwh(value("charge_full_design")?), to_mwh(value("charge_full")?))
+        }
+    };
+    if design_mwh == 0 {
+        return None;
+    }
+    Some(PowerSupplyHealth {
+        capacity_design_mwh: design_mwh as u32,
+        capacity_full_mwh: full_mwh as u32,
+        health_pct: full_mwh as f32 / design_mwh as f32 * 100.0,
+        cycle_count: value("cycle_count").unwrap_or(0) as u32,
+        status: ChargingStatus::parse(&read_sysfs_value(&battery.join("status").to_string_lossy()).unwrap_or_default()),
+    })
+}
+
+/// Read whether the display is on
+/// Checks DRM connectors first (`enabled`), then backlight power (`bl_power`, 0 = on).
+/// Assumes the screen is on when neither is readable.
+pub fn read_screen_on() -> bool {
+    let entries = |dir: &str| -> Vec<std::path::PathBuf> {
+        fs::read_dir(dir)
+            .map(|rd| rd.flatten().map(|e| e.path()).collect())
+            .unwrap_or_default()
+    };
+
+    let connectors: Vec<String> = entries("/sys/class/drm")
+        .iter()
+        .filter_map(|p| fs::read_to_string(p.join("enabled")).ok())
+        .collect();
+    if !connectors.is_empty() {
+        return connectors.iter().any(|s| s.trim() == "enabled");
+    }
+
+    let backlights: Vec<String> = entries("/sys/class/backlight")
+        .iter()
+        .filter_map(|p| fs::read_to_string(p.join("bl_power")).ok())
+        .collect();
+    if !backlights.is_empty() {
+        return backlights.iter().any(|s| s.trim() == "0");
+    }
+
+    true
+}
+
+/// Kernel driver behind the platform profile (the firmware power/fan preset)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlatformProfileDriver {
+    IdeapadAcpi,
+    ThinkpadAcpi,
+    /// No profile driver; amd-pstate EPP is the only power preset
+    AmdPstate,
+    /// `platform_profile` provided by another driver (e.g. amd-pmf, hp-wmi)
+    Generic,
+    None,
+}
+
+impl PlatformProfileDriver {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PlatformProfileDriver::IdeapadAcpi => "ideapad_acpi",
+            PlatformProfileDriver::ThinkpadAcpi => "thinkpad_acpi",
+            PlatformProfileDriver::AmdPstate => "amd-pstate (EPP only)",
+            PlatformProfileDriver::Generic => "generic platform_profile",
+            PlatformProfileDriver::None => "none",
+        }
+    }
+
+    /// File modes write the profile to, relative to `/sys`; `None` when the
+    /// driver has no platform profile and modes only change EPP and perf limits
+    pub fn profile_path(&self) -> Option<&'static str> {
+        match self {
+            PlatformProfileDriver::IdeapadAcpi
+            | PlatformProfileDriver::ThinkpadAcpi
+            | PlatformProfileDriver::Generic => Some("firmware/acpi/platform_profile"),
+            PlatformProfileDriver::AmdPstate | PlatformProfileDriver::None => None,
+        }
+    }
+
+    /// Detect the driver from which of its sysfs paths exist under `sys_root`
+    fn detect(sys_root: &Path) -> Self {
+        if sys_root.join("bus/platform/drivers/ideapad_acpi").exists() {
+            PlatformProfileDriver::IdeapadAcpi
+        } else if sys_root.join("bus/platform/drivers/thinkpad_acpi").exists() {
+            PlatformProfileDriver::ThinkpadAcpi
+        } else if sys_root.join("firmware/acpi/platform_profile").exists() {
+            PlatformProfileDriver::Generic
+        } else if sys_root.join("devices/system/cpu/amd_pstate").exists() {
+            PlatformProfileDriver::AmdPstate
+        } else {
+            PlatformProfileDriver::None
+        }
+    }
+}
+
+/// Driver providing the platform profile on this machine
+pub fn get_platform_profile_driver() -> PlatformProfileDriver {
+    PlatformProfileDriver::detect(Path::new("/sys"))
+}
+
+/// amd-pstate energy/performance preference of every CPU, relative to `/sys`
+const AMD_EPP_PATH: &str = "devices/system/cpu/cpu*/cpufreq/energy_performance_preference";
+
+/// How [`set_mode`] applies a mode with a given driver
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModeWrite {
+    /// The cpu-mode helper, which also sets the IdeaPad perf limits and EPP
+    Helper,
+    /// `value` written to every file matching `path` (relative to `/sys`, may hold a `cpu*` glob)
+    Sysfs { path: &'static str, value: &'static str },
+    /// The driver offers nothing to write
+    Unsupported,
+}
+
+impl PlatformProfileDriver {
+    /// How `mode` is applied with this driver. Other drivers get the
+    /// platform_profile choices thinkpad_acpi and most others support, or the
+    /// matching EPP on amd-pstate. Comfort shares Quiet's preset and Auto,
+    /// which has no firmware counterpart, shares Balanced's.
+    pub fn mode_write(&self, mode: Mode) -> ModeWrite {
+        let preset = match (self, mode) {
+            (PlatformProfileDriver::IdeapadAcpi, _) => return ModeWrite::Helper,
+            (PlatformProfileDriver::None, _) | (_, Mode::Unknown) => return ModeWrite::Unsupported,
+            (_, Mode::Performance) => 0,
+            (_, Mode::Balanced | Mode::Auto) => 1,
+            (_, Mode::Comfort | Mode::Quiet) => 2,
+        };
+        match self.profile_path() {
+            Some(path) => ModeWrite::Sysfs { path, value: ["performance", "balanced", "low-power"][preset] },
+            None => ModeWrite::Sysfs { path: AMD_EPP_PATH, value: ["performance", "balance_performance", "power"][preset] },
+        }
+    }
+}
+
+/// Mode shown for a platform_profile or EPP value
+fn mode_from_preset(value: &str) -> Mode {
+    match value {
+        "performance" => Mode::Performance,
+        "balanced" | "balance_performance" => Mode::Balanced,
+        "low-power" | "quiet" | "power" | "balance_power" => Mode::Quiet,
+        _ => Mode::Unknown,
+    }
+}
+
+/// Files of a [`ModeWrite::Sysfs`] path under `sys_root`, one per CPU for a
+/// `cpu*` glob
+fn sysfs_targets(sys_root: &Path, path: &str) -> Vec<PathBuf> {
+    let Some((dir, attribute)) = path.split_once("cpu*/") else {
+        return vec![sys_root.join(path)];
+    };
+    let mut targets: Vec<PathBuf> = fs::read_dir(sys_root.join(dir))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            let cpu = name.to_str().and_then(|name| name.strip_prefix("cpu"));
+            cpu.is_some_and(|cpu| !cpu.is_empty() && cpu.bytes().all(|b| b.is_ascii_digit()))
+        })
+        .map(|entry| entry.path().join(attribute))
+        .filter(|target| target.exists())
+        .collect();
+    targets.sort();
+    targets
+}
+
+/// Write `value` to every file of a [`ModeWrite::Sysfs`] path without
+/// privileges, which works once the udev rules made them group-writable
+fn write_sysfs_direct(sys_root: &Path, path: &str, value: &str) -> io::Result<()> {
+    let targets = sysfs_targets(sys_root, path);
+    if targets.is_empty() {
+        return Err(io::Error::new(ErrorKind::NotFound, format!("{}: not found", sys_root.join(path).display())));
+    }
+    targets.iter().try_for_each(|target| fs::write(target, value))
+}
+
+/// Current value of a [`ModeWrite::Sysfs`] path (the first CPU's for globs)
+fn read_preset(path: &str) -> Option<String> {
+    read_sysfs_value(&format!("/sys/{}", path.replace("cpu*", "cpu0"))).ok()
+}
+
 /// Read platform profile
 pub fn read_platform_profile() -> String {
     read_sysfs_value("/sys/firmware/acpi/platform_profile").unwrap_or_else(|_| "unknown".into())
@@ -236,6 +1013,559 @@ pub fn read_fan_mode() -> u8 {
         .unwrap_or(0)
 }
 
+/// Read the kernel's count of registered wakeup events. Every wakeup source
+/// adds to it (wakeup-capable devices, RTC alarms, …) while the system is
+/// running too, so an increase alone does not mean a resume.
+pub fn read_wake_count() -> Option<u64> {
+    read_sysfs_value("/sys/power/wake_count").ok()?.parse().ok()
+}
+
+/// Whether the system resumed from suspend between two polls: the wakeup
+/// event count went up and the wall clock ran more than `poll_interval` ahead
+/// of the monotonic clock, which stops while suspended (`asleep`)
+pub fn resumed_from_suspend(
+    previous: Option<u64>,
+    current: Option<u64>,
+    asleep: Duration,
+    poll_interval: Duration,
+) -> bool {
+    let woke = matches!((previous, current), (Some(previous), Some(current)) if current > previous);
+    woke && asleep > poll_interval
+}
+
+/// Interrupts raised so far by the built-in keyboard and touchpad (the i8042
+/// controller), summed over all CPUs. Unlike window events this also sees
+/// typing in other applications, but USB and Bluetooth keyboards and mice
+/// do not raise i8042 interrupts and go unseen.
+pub fn read_input_interrupts() -> Option<u64> {
+    parse_input_interrupts(&fs::read_to_string("/proc/interrupts").ok()?)
+}
+
+/// Sum the per-CPU counts of the `i8042` lines of `/proc/interrupts`
+fn parse_input_interrupts(content: &str) -> Option<u64> {
+    let counts: Vec<u64> = content
+        .lines()
+        .filter(|line| line.split_whitespace().any(|field| field == "i8042"))
+        .map(|line| {
+            line.split_whitespace()
+                .skip(1)
+                .map_while(|field| field.parse::<u64>().ok())
+                .sum()
+        })
+        .collect();
+    (!counts.is_empty()).then(|| counts.iter().sum())
+}
+
+/// Assumed TJmax when no CPU hwmon reports a critical temperature
+pub const DEFAULT_TJMAX: f32 = 100.0;
+
+/// CPU critical temperature (°C) from the coretemp/k10temp device under `hwmon_root`
+pub fn read_tjmax(hwmon_root: &Path) -> Option<f32> {
+    ["coretemp", "k10temp"]
+        .iter()
+        .find_map(|name| read_hwmon_temp(hwmon_root, name, "temp1_crit"))
+}
+
+/// Show a desktop notification through `notify-send`
+pub fn send_notification(summary: &str, body: &str) -> io::Result<()> {
+    let status = Command::new("notify-send")
+        .args(["--app-name=Thermal Monitor", "--urgency=critical", summary, body])
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(ErrorKind::Other, "notify-send failed"))
+    }
+}
+
+/// XDG autostart entry that launches the app with the desktop session
+pub fn autostart_path() -> PathBuf {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("/tmp"));
+    base.join("autostart").join("thermal-monitor.desktop")
+}
+
+/// Create or remove the autostart entry
+pub fn set_autostart(enabled: bool) -> io::Result<()> {
+    let path = autostart_path();
+    if !enabled {
+        return match fs::remove_file(&path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(
+        path,
+        "[Desktop Entry]\n\
+         Name=Thermal Monitor\n\
+         Exec=thermal-monitor\n\
+         Icon=utilities-system-monitor\n\
+         Terminal=false\n\
+         Type=Application\n\
+         X-GNOME-Autostart-enabled=true\n",
+    )
+}
+
+/// Open `url` in the default browser through `xdg-open`
+pub fn open_url(url: &str) -> io::Result<()> {
+    Command::new("xdg-open").arg(url).spawn().map(|_| ())
+}
+
+/// Whether the desktop asks for high contrast: GNOME's accessibility switch
+/// or a HighContrast GTK theme. `false` when gsettings is not available.
+pub fn read_system_high_contrast() -> bool {
+    let gsettings = |schema: &str, key: &str| {
+        Command::new("gsettings")
+            .args(["get", schema, key])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+            .unwrap_or_default()
+    };
+    high_contrast_requested(
+        &gsettings("org.gnome.desktop.a11y.interface", "high-contrast"),
+        &gsettings("org.gnome.desktop.interface", "gtk-theme"),
+    )
+}
+
+/// `gsettings get` outputs: `true`/`false` and a quoted theme name
+fn high_contrast_requested(a11y_high_contrast: &str, gtk_theme: &str) -> bool {
+    a11y_high_contrast.trim() == "true" || gtk_theme.to_lowercase().contains("highcontrast")
+}
+
+/// Refresh rate the screen drops to while auto-control is in the Critical zone
+pub const CRITICAL_REFRESH_HZ: u32 = 60;
+
+/// Connected display output and the refresh rates of its current resolution
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScreenRefresh {
+    pub output: String,
+    /// Active rate, rounded to whole Hz
+    pub current: u32,
+    /// Rates available at the current resolution, highest first
+    pub rates: Vec<u32>,
+}
+
+/// First connected output with an active mode in `xrandr --query` output.
+/// The active mode line marks the current rate with `*`:
+/// `   1920x1080    144.00*+  60.01    59.97`
+fn parse_xrandr_query(text: &str) -> Option<ScreenRefresh> {
+    let mut output = None;
+    for line in text.lines() {
+        if !line.starts_with(char::is_whitespace) {
+            let mut words = line.split_whitespace();
+            output = match (words.next(), words.next()) {
+                (Some(name), Some("connected")) => Some(name),
+                _ => None,
+            };
+            continue;
+        }
+        let Some(name) = output else {
+            continue;
+        };
+        if !line.contains('*') {
+            continue;
+        }
+        let mut current = None;
+        let mut rates = Vec::new();
+        for word in line.split_whitespace().skip(1) {
+            let Ok(rate) = word.trim_end_matches(['*', '+']).parse::<f32>() else {
+                continue;
+            };
+            let rate = rate.round() as u32;
+            if word.contains('*') {
+                current = Some(rate);
+            }
+            rates.push(rate);
+        }
+        rates.sort_unstable_by(|a, b| b.cmp(a));
+        rates.dedup();
+        return Some(ScreenRefresh { output: name.to_string(), current: current?, rates });
+    }
+    None
+}
+
+/// Current screen refresh rate and the alternatives, through `xrandr`.
+/// `None` without xrandr or a connected output (e.g. on a pure Wayland session).
+pub fn read_screen_refresh() -> Option<ScreenRefresh> {
+    let output = Command::new("xrandr").arg("--query").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_xrandr_query(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Switch the first connected output to `hz`; xrandr picks the closest mode rate
+pub fn set_screen_refresh_rate(hz: u32) -> Result<(), ThermalError> {
+    let screen = read_screen_refresh()
+        .ok_or_else(|| ThermalError::InvalidData("no display found through xrandr".into()))?;
+    let output = Command::new("xrandr")
+        .args(["--output", &screen.output, "--rate", &hz.to_string()])
+        .output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(ThermalError::InvalidData(format!(
+            "xrandr --rate {}: {}",
+            hz,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+/// Read fan speed in RPM from the first hwmon device exposing `fan1_input`
+pub fn read_fan_rpm() -> Option<u32> {
+    fs::read_dir(HWMON_PATH)
+        .ok()?
+        .flatten()
+        .find_map(|entry| {
+            fs::read_to_string(entry.path().join("fan1_input"))
+                .ok()
+                .and_then(|s| s.trim().parse().ok())
+        })
+}
+
+/// Embedded Controller register dump (requires `ec_sys` loaded and debugfs mounted)
+#[cfg(feature = "ec_access")]
+const EC_IO_PATH: &str = "/sys/kernel/debug/ec/ec0/io";
+
+/// Known IdeaPad EC register offsets.
+///
+/// Taken from EC dumps of IdeaPad 5/Slim 5 models; layouts differ between
+/// firmware generations, so treat the values as best-effort:
+/// - `0x2F` fan level (bits 0-2 level, bit 6 full speed, see [`EC_FAN_LEVEL_AUTO`])
+/// - `0x78` CPU temperature as seen by the EC (°C)
+/// - `0x84`/`0x85` fan tachometer, RPM as little-endian u16
+/// - `0x0D` keyboard backlight level
+#[cfg(feature = "ec_access")]
+const EC_REGISTERS: [(&str, usize); 5] = [
+    ("fan_level", 0x2F),
+    ("cpu_temp", 0x78),
+    ("fan_rpm_lo", 0x84),
+    ("fan_rpm_hi", 0x85),
+    ("kbd_backlight", 0x0D),
+];
+
+/// `fan_level` value handing the fan back to EC automatic control: the
+/// "level auto" value thinkpad-acpi documents for the same Lenovo register
+/// (HFSP, Documentation/admin-guide/laptops/thinkpad-acpi.rst)
+#[cfg(feature = "ec_access")]
+pub const EC_FAN_LEVEL_AUTO: u8 = 0x80;
+
+/// EC temperature at which the firmware starts throttling (PROCHOT)
+#[cfg(feature = "ec_access")]
+const EC_THROTTLE_TEMP: u8 = 95;
+
+/// Decode the known registers from a raw EC dump
+#[cfg(feature = "ec_access")]
+pub fn decode_ec_registers(dump: &[u8]) -> Result<HashMap<String, u8>, ThermalError> {
+    if dump.len() < 0x100 {
+        return Err(ThermalError::InvalidData(format!(
+            "EC dump is {} bytes, expected 256",
+            dump.len()
+        )));
+    }
+    Ok(EC_REGISTERS
+        .iter()
+        .map(|&(name, offset)| (name.to_string(), dump[offset]))
+        .collect())
+}
+
+/// Read and decode the Embedded Controller registers
+#[cfg(feature = "ec_access")]
+pub fn read_ec_registers() -> Result<HashMap<String, u8>, ThermalError> {
+    let dump = fs::read(EC_IO_PATH)?;
+    decode_ec_registers(&dump)
+}
+
+/// Fan RPM from decoded EC registers
+#[cfg(feature = "ec_access")]
+pub fn ec_fan_rpm(registers: &HashMap<String, u8>) -> Option<u32> {
+    let lo = *registers.get("fan_rpm_lo")? as u32;
+    let hi = *registers.get("fan_rpm_hi")? as u32;
+    Some(hi << 8 | lo)
+}
+
+/// Whether the EC is throttling the CPU on its own (EC temperature at PROCHOT level)
+#[cfg(feature = "ec_access")]
+pub fn ec_throttling(registers: &HashMap<String, u8>) -> bool {
+    registers.get("cpu_temp").is_some_and(|&t| t >= EC_THROTTLE_TEMP)
+}
+
+/// Highest intermediate fan level understood by the EC
+pub const FAN_LEVEL_MAX: u8 = 3;
+
+/// Custom fan curve: (temperature °C, fan level 0-3) points sorted by temperature
+#[derive(Debug, Clone, PartialEq)]
+pub struct FanCurve {
+    pub points: Vec<(f32, u8)>,
+}
+
+impl Default for FanCurve {
+    fn default() -> Self {
+        Self { points: vec![(40.0, 0), (55.0, 1), (70.0, 2), (85.0, 3)] }
+    }
+}
+
+impl fmt::Display for FanCurve {
+    /// `temp:level` pairs separated by commas, as stored in the config
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pairs: Vec<String> = self.points.iter().map(|(temp, level)| format!("{}:{}", temp, level)).collect();
+        write!(f, "{}", pairs.join(","))
+    }
+}
+
+impl FanCurve {
+    /// Parse the `temp:level,...` form written by `Display`
+    pub fn parse(text: &str) -> Result<Self, ThermalError> {
+        let invalid = || ThermalError::InvalidData(format!("fan curve: {}", text));
+        let mut points = text
+            .split(',')
+            .map(|pair| {
+                let (temp, level) = pair.trim().split_once(':').ok_or_else(invalid)?;
+                let temp: f32 = temp.parse().map_err(|_| invalid())?;
+                let level: u8 = level.parse().map_err(|_| invalid())?;
+                Ok((temp, level.min(FAN_LEVEL_MAX)))
+            })
+            .collect::<Result<Vec<_>, ThermalError>>()?;
+        if points.len() < 2 {
+            return Err(invalid());
+        }
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(Self { points })
+    }
+}
+
+/// Evaluation and editing of the curve, only needed where it can be written to the EC
+#[cfg(feature = "ec_access")]
+impl FanCurve {
+    /// Fan level for `temp`, interpolated linearly between points and held flat past the ends
+    pub fn level_at(&self, temp: f32) -> u8 {
+        let (Some(&first), Some(&last)) = (self.points.first(), self.points.last()) else {
+            return 0;
+        };
+        if temp <= first.0 {
+            return first.1;
+        }
+        if temp >= last.0 {
+            return last.1;
+        }
+        self.points
+            .windows(2)
+            .find(|pair| temp <= pair[1].0)
+            .map_or(last.1, |pair| {
+                let ((t0, l0), (t1, l1)) = (pair[0], pair[1]);
+                let fraction = (temp - t0) / (t1 - t0);
+                (l0 as f32 + fraction * (l1 as f32 - l0 as f32)).round() as u8
+            })
+    }
+
+    /// Index of the point closest to (`temp`, `level`), with levels scaled to
+    /// `degrees_per_level` so both axes weigh the same on screen
+    pub fn nearest_point(&self, temp: f32, level: f32, degrees_per_level: f32) -> Option<usize> {
+        let distance = |&(t, l): &(f32, u8)| (t - temp).hypot((l as f32 - level) * degrees_per_level);
+        (0..self.points.len()).min_by(|&a, &b| distance(&self.points[a]).total_cmp(&distance(&self.points[b])))
+    }
+
+    /// Move a point, keeping it between its neighbours and the level in range
+    pub fn move_point(&mut self, index: usize, temp: f32, level: f32) {
+        let low = index.checked_sub(1).map_or(f32::MIN, |i| self.points[i].0 + 1.0);
+        let high = self.points.get(index + 1).map_or(f32::MAX, |p| p.0 - 1.0);
+        if let Some(point) = self.points.get_mut(index) {
+            *point = (temp.clamp(low, high.max(low)), level.round().clamp(0.0, FAN_LEVEL_MAX as f32) as u8);
+        }
+    }
+
+    /// Split the widest temperature gap with a point on the curve; returns its index
+    pub fn add_point(&mut self) -> usize {
+        let gap = (1..self.points.len())
+            .max_by(|&a, &b| {
+                let width = |i: usize| self.points[i].0 - self.points[i - 1].0;
+                width(a).total_cmp(&width(b))
+            })
+            .unwrap_or(1);
+        let temp = match self.points.len() {
+            0 => 50.0,
+            1 => self.points[0].0 + 10.0,
+            _ => (self.points[gap - 1].0 + self.points[gap].0) / 2.0,
+        };
+        let index = gap.min(self.points.len());
+        self.points.insert(index, (temp, self.level_at(temp)));
+        index
+    }
+
+    /// Remove a point; a curve always keeps at least two
+    pub fn remove_point(&mut self, index: usize) -> bool {
+        if self.points.len() <= 2 || index >= self.points.len() {
+            return false;
+        }
+        self.points.remove(index);
+        true
+    }
+}
+
+/// Write one EC register (requires `ec_sys` loaded with `write_support=1`)
+#[cfg(feature = "ec_access")]
+fn write_ec_register(offset: usize, value: u8) -> io::Result<()> {
+    let output = Command::new("pkexec")
+        .args(["bash", "-c", &format!(
+            "printf '\\x{:02x}' | dd of={} bs=1 seek={} count=1 conv=notrunc status=none",
+            value, EC_IO_PATH, offset
+        )])
+        .output()?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(privileged_write_error("Failed to write EC register", &output))
+    }
+}
+
+/// Set the EC fan level from `curve` for `current_temp`; returns the level written
+#[cfg(feature = "ec_access")]
+pub fn apply_fan_curve(curve: &FanCurve, current_temp: f32) -> Result<u8, ThermalError> {
+    let level = curve.level_at(current_temp);
+    // A plain level (no auto bit) takes the fan off EC automatic control
+    write_ec_register(fan_level_offset()?, level)?;
+    Ok(level)
+}
+
+/// Return the fan to EC automatic control after a fan curve is turned off
+#[cfg(feature = "ec_access")]
+pub fn release_fan_curve() -> Result<(), ThermalError> {
+    release_fan_curve_with(write_ec_register)
+}
+
+#[cfg(feature = "ec_access")]
+fn release_fan_curve_with(write: impl FnOnce(usize, u8) -> io::Result<()>) -> Result<(), ThermalError> {
+    write(fan_level_offset()?, EC_FAN_LEVEL_AUTO)?;
+    Ok(())
+}
+
+#[cfg(feature = "ec_access")]
+fn fan_level_offset() -> Result<usize, ThermalError> {
+    EC_REGISTERS
+        .iter()
+        .find(|(name, _)| *name == "fan_level")
+        .map(|&(_, offset)| offset)
+        .ok_or_else(|| ThermalError::InvalidData("no fan_level EC register".into()))
+}
+
+/// Intel RAPL package power zone
+#[cfg(feature = "intel-rapl")]
+const RAPL_PACKAGE_PATH: &str = "/sys/class/powercap/intel-rapl/intel-rapl:0";
+
+/// Measured power draw in watts
+#[cfg(feature = "intel-rapl")]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TdpInfo {
+    pub package_watts: f32,
+    pub core_watts: f32,
+    pub dram_watts: f32,
+}
+
+/// One reading of the RAPL energy counters (microjoules)
+#[cfg(feature = "intel-rapl")]
+#[derive(Debug, Clone, Copy)]
+pub struct RaplSample {
+    pub package_uj: u64,
+    pub core_uj: Option<u64>,
+    pub dram_uj: Option<u64>,
+    /// Counter wrap-around point
+    pub max_uj: u64,
+    pub taken_at: Instant,
+}
+
+/// Read a RAPL energy counter in microjoules
+#[cfg(feature = "intel-rapl")]
+fn read_energy_uj(zone: &Path) -> Result<u64, ThermalError> {
+    let content = read_sysfs_value(&zone.join("energy_uj").to_string_lossy())?;
+    content
+        .parse()
+        .map_err(|_| ThermalError::InvalidData(format!("energy_uj: {}", content)))
+}
+
+/// Read the package counter plus the `core` and `dram` subzones when present
+#[cfg(feature = "intel-rapl")]
+pub fn read_rapl_sample() -> Result<RaplSample, ThermalError> {
+    let package = Path::new(RAPL_PACKAGE_PATH);
+    let subzone = |name: &str| {
+        fs::read_dir(package).ok()?.flatten().map(|e| e.path()).find(|p| {
+            read_sysfs_value(&p.join("name").to_string_lossy()).is_ok_and(|n| n == name)
+        })
+    };
+
+    Ok(RaplSample {
+        package_uj: read_energy_uj(package)?,
+        core_uj: subzone("core").and_then(|p| read_energy_uj(&p).ok()),
+        dram_uj: subzone("dram").and_then(|p| read_energy_uj(&p).ok()),
+        max_uj: read_sysfs_value(&package.join("max_energy_range_uj").to_string_lossy())
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(u64::MAX),
+        taken_at: Instant::now(),
+    })
+}
+
+/// Average power between two RAPL samples, handling counter wrap-around
+#[cfg(feature = "intel-rapl")]
+pub fn rapl_power_between(previous: &RaplSample, current: &RaplSample) -> Option<TdpInfo> {
+    let secs = current.taken_at.duration_since(previous.taken_at).as_secs_f32();
+    if secs <= 0.0 {
+        return None;
+    }
+    let watts = |prev: u64, cur: u64| {
+        let delta = if cur >= prev { cur - prev } else { current.max_uj - prev + cur };
+        delta as f32 / 1_000_000.0 / secs
+    };
+    let optional_watts = |prev: Option<u64>, cur: Option<u64>| match (prev, cur) {
+        (Some(p), Some(c)) => watts(p, c),
+        _ => 0.0,
+    };
+
+    Some(TdpInfo {
+        package_watts: watts(previous.package_uj, current.package_uj),
+        core_watts: optional_watts(previous.core_uj, current.core_uj),
+        dram_watts: optional_watts(previous.dram_uj, current.dram_uj),
+    })
+}
+
+/// AMD fallback: average package power reported by the amdgpu hwmon (µW)
+#[cfg(feature = "intel-rapl")]
+fn read_amd_power() -> Option<TdpInfo> {
+    let dir = hwmon_dir_by_name(Path::new(HWMON_PATH), "amdgpu")?;
+    let microwatts: f32 = read_sysfs_value(&dir.join("power1_average").to_string_lossy())
+        .ok()?
+        .parse()
+        .ok()?;
+    Some(TdpInfo {
+        package_watts: microwatts / 1_000_000.0,
+        ..Default::default()
+    })
+}
+
+/// Measure power draw. RAPL counters need two samples, so pass the sample cached
+/// from the previous poll; the first call only primes the cache. Falls back to the
+/// amdgpu power sensor on AMD systems without RAPL.
+#[cfg(feature = "intel-rapl")]
+pub fn get_tdp_info(previous: Option<&RaplSample>) -> Result<(TdpInfo, Option<RaplSample>), ThermalError> {
+    match read_rapl_sample() {
+        Ok(current) => {
+            let info = previous
+                .and_then(|prev| rapl_power_between(prev, &current))
+                .unwrap_or_default();
+            Ok((info, Some(current)))
+        }
+        Err(e) => read_amd_power().map(|info| (info, None)).ok_or(e),
+    }
+}
+
 /// Activate fan boost (max speed) - Lenovo IdeaPad specific
 pub fn set_fan_boost(enable: bool) -> io::Result<()> {
     let value = if enable { "1" } else { "0" };
@@ -249,29 +1579,37 @@ pub fn set_fan_boost(enable: bool) -> io::Result<()> {
     if output.status.success() {
         Ok(())
     } else {
-        Err(io::Error::new(ErrorKind::Other, "Failed to set fan mode"))
+        Err(privileged_write_error("Failed to set fan mode", &output))
     }
 }
 
-/// Set performance percentage directly
-pub fn set_perf_pct(pct: u8) -> io::Result<()> {
-    let pct = pct.clamp(20, 100);
-    let output = Command::new("pkexec")
-        .args(["bash", "-c", &format!(
-            "echo {} > /sys/devices/system/cpu/intel_pstate/max_perf_pct 2>/dev/null || \
-             echo {} > /sys/devices/system/cpu/amd_pstate/max_perf_pct 2>/dev/null || \
+/// Shell command that limits performance to `pct` with the driver's own API
+fn perf_limit_command(driver: &str, pct: u8) -> String {
+    if driver == "intel_pstate" {
+        format!("echo {} > /sys/devices/system/cpu/intel_pstate/max_perf_pct", pct)
+    } else {
+        // acpi-cpufreq, amd-pstate and others only expose per-CPU frequency limits
+        format!(
+            "max=$(cat /sys/devices/system/cpu/cpu0/cpufreq/cpuinfo_max_freq); \
              for cpu in /sys/devices/system/cpu/cpu*/cpufreq/scaling_max_freq; do \
-               max=$(cat /sys/devices/system/cpu/cpu0/cpufreq/cpuinfo_max_freq); \
                echo $((max * {} / 100)) > $cpu; \
              done",
-            pct, pct, pct
-        )])
+            pct
+        )
+    }
+}
+
+/// Set performance percentage directly
+pub fn set_perf_pct(driver: &str, pct: u8) -> io::Result<()> {
+    let pct = pct.clamp(20, 100);
+    let output = Command::new("pkexec")
+        .args(["bash", "-c", &perf_limit_command(driver, pct)])
         .output()?;
 
     if output.status.success() {
         Ok(())
     } else {
-        Err(io::Error::new(ErrorKind::Other, "Failed to set performance"))
+        Err(privileged_write_error("Failed to set performance", &output))
     }
 }
 
@@ -287,47 +1625,543 @@ pub fn calc_perf_for_target(current_temp: f32, target_temp: f32, current_perf: u
     }
 }
 
-/// Apply thermal control to reach target temperature
-pub fn apply_thermal_control(current_temp: f32, target_temp: f32) -> io::Result<String> {
-    let current_perf = read_perf_pct().unwrap_or(75);
+/// Apply thermal control to reach target temperature, using `driver`'s performance API
+pub fn apply_thermal_control(current_temp: f32, target_temp: f32, driver: &str) -> io::Result<String> {
+    let current_perf = read_perf_pct(driver).unwrap_or(75);
     let diff = current_temp - target_temp;
 
     if diff > 10.0 {
         // Critical: fan boost + aggressive throttle
         let _ = set_fan_boost(true);
-        set_perf_pct(30)?;
+        set_perf_pct(driver, 30)?;
         Ok("CRITICAL: Fan boost + 30%".into())
     } else if diff > 5.0 {
         // High: fan boost + moderate throttle
         let _ = set_fan_boost(true);
-        set_perf_pct(50)?;
+        set_perf_pct(driver, 50)?;
         Ok("HIGH: Fan boost + 50%".into())
     } else if diff > 0.0 {
         // Slight overshoot: gradual reduction
         let new_perf = calc_perf_for_target(current_temp, target_temp, current_perf);
-        set_perf_pct(new_perf)?;
+        set_perf_pct(driver, new_perf)?;
         Ok(format!("Adjusting to {}%", new_perf))
     } else if diff < -5.0 {
         // Well below target: can increase
         let new_perf = (current_perf + 10).min(100);
-        set_perf_pct(new_perf)?;
+        set_perf_pct(driver, new_perf)?;
         Ok(format!("Increasing to {}%", new_perf))
     } else {
-        Ok("On target".into())
+        Ok("On target".into())
+    }
+}
+
+/// How long to wait for a written mode to be reported back
+const MODE_VERIFY_TIMEOUT: Duration = Duration::from_millis(500);
+const MODE_VERIFY_POLL: Duration = Duration::from_millis(50);
+
+/// Change CPU mode using pkexec and confirm the hardware applied it
+///
+/// The write depends on the platform profile driver, see
+/// [`PlatformProfileDriver::mode_write`].
+pub fn set_mode(mode: Mode) -> Result<(), ThermalError> {
+    let (path, value) = match get_platform_profile_driver().mode_write(mode) {
+        ModeWrite::Helper => {
+            let output = Command::new("pkexec")
+                .args(["/usr/local/bin/cpu-mode", mode.command()])
+                .output()?;
+            return if output.status.success() {
+                verify_mode_change(mode)
+            } else {
+                Err(privileged_write_error("Failed to change mode", &output).into())
+            };
+        }
+        ModeWrite::Sysfs { path, value } => (path, value),
+        ModeWrite::Unsupported => {
+            return Err(ThermalError::InvalidData(format!("no platform profile driver to set {}", mode.label())))
+        }
+    };
+
+    // Without a password when the udev rules are installed, through pkexec otherwise
+    if write_sysfs_direct(Path::new("/sys"), path, value).is_err() {
+        let command = format!("echo {} | tee /sys/{} > /dev/null", value, path);
+        let output = Command::new("pkexec").args(["bash", "-c", &command]).output()?;
+        if !output.status.success() {
+            return Err(privileged_write_error("Failed to change mode", &output).into());
+        }
+    }
+    // Modes sharing a preset read back the same, so compare the value written
+    poll_mode(mode, MODE_VERIFY_TIMEOUT, MODE_VERIFY_POLL, || match read_preset(path) {
+        Some(actual) if actual == value => mode,
+        actual => actual.map_or(Mode::Unknown, |actual| mode_from_preset(&actual)),
+    })
+}
+
+/// Wait until the reported mode matches `expected`
+pub fn verify_mode_change(expected: Mode) -> Result<(), ThermalError> {
+    poll_mode(expected, MODE_VERIFY_TIMEOUT, MODE_VERIFY_POLL, read_mode)
+}
+
+/// Poll `read` every `interval` until it returns `expected` or `timeout` elapses
+fn poll_mode(
+    expected: Mode,
+    timeout: Duration,
+    interval: Duration,
+    mut read: impl FnMut() -> Mode,
+) -> Result<(), ThermalError> {
+    let start = Instant::now();
+    loop {
+        let actual = read();
+        if actual == expected {
+            return Ok(());
+        }
+        if start.elapsed() >= timeout {
+            return Err(ThermalError::VerificationFailed { written: expected, actual });
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+/// Cumulative CPU time per process, taken from `/proc/*/stat`
+#[derive(Debug, Clone, Default)]
+pub struct ProcessCpuSample {
+    /// pid -> (command name, utime + stime in clock ticks)
+    processes: HashMap<u32, (String, u64)>,
+    /// Total CPU ticks across all cores from `/proc/stat`
+    total_ticks: u64,
+}
+
+/// Parse `/proc/<pid>/stat` into (command name, utime + stime)
+fn parse_proc_stat(content: &str) -> Option<(String, u64)> {
+    // The command name is wrapped in parentheses and may contain spaces
+    let open = content.find('(')?;
+    let close = content.rfind(')')?;
+    let name = content.get(open + 1..close)?.to_string();
+    // Fields after the name start at field 3 (state); utime and stime are fields 14 and 15
+    let fields: Vec<&str> = content[close + 1..].split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some((name, utime + stime))
+}
+
+/// Memory totals from `/proc/meminfo` (kB)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemInfo {
+    pub total_kb: u64,
+    pub available_kb: u64,
+}
+
+impl MemInfo {
+    /// Parse the `MemTotal` and `MemAvailable` lines
+    pub fn parse(content: &str) -> Option<Self> {
+        let field = |name: &str| {
+            content
+                .lines()
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+                .and_then(|rest| rest.split_whitespace().next()?.parse::<u64>().ok())
+        };
+        Some(Self {
+            total_kb: field("MemTotal")?,
+            available_kb: field("MemAvailable")?,
+        })
+    }
+
+    /// Share of memory in use, in percent
+    pub fn used_pct(&self) -> f32 {
+        if self.total_kb == 0 {
+            return 0.0;
+        }
+        self.total_kb.saturating_sub(self.available_kb) as f32 / self.total_kb as f32 * 100.0
+    }
+}
+
+/// Read memory usage in percent
+pub fn read_mem_used_pct() -> Option<f32> {
+    MemInfo::parse(&fs::read_to_string("/proc/meminfo").ok()?).map(|info| info.used_pct())
+}
+
+/// Sum of all CPU time columns from the aggregate `cpu` line of `/proc/stat`
+fn parse_total_ticks(content: &str) -> Option<u64> {
+    let line = content.lines().find(|l| l.starts_with("cpu "))?;
+    Some(line.split_whitespace().skip(1).filter_map(|v| v.parse::<u64>().ok()).sum())
+}
+
+/// Aggregate CPU time from `/proc/stat`, in clock ticks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CpuTicks {
+    /// Ticks not spent idle or waiting for I/O
+    pub busy: u64,
+    pub total: u64,
+}
+
+impl CpuTicks {
+    /// Parse the aggregate `cpu` line; idle and iowait are columns 4 and 5
+    pub fn parse(content: &str) -> Option<Self> {
+        let line = content.lines().find(|l| l.starts_with("cpu "))?;
+        let values: Vec<u64> = line.split_whitespace().skip(1).filter_map(|v| v.parse().ok()).collect();
+        let idle = values.get(3)? + values.get(4).unwrap_or(&0);
+        let total = values.iter().sum();
+        Some(Self { busy: total - idle, total })
+    }
+
+    /// CPU load (%) between `previous` and this reading
+    pub fn load_pct_since(&self, previous: &CpuTicks) -> Option<f32> {
+        let total = self.total.checked_sub(previous.total).filter(|&t| t > 0)?;
+        let busy = self.busy.saturating_sub(previous.busy);
+        Some((busy as f32 / total as f32 * 100.0).min(100.0))
+    }
+}
+
+/// Read the aggregate CPU time counters
+pub fn read_cpu_ticks() -> Option<CpuTicks> {
+    CpuTicks::parse(&fs::read_to_string("/proc/stat").ok()?)
+}
+
+/// Read CPU time for every running process
+pub fn read_process_cpu_sample() -> ProcessCpuSample {
+    let total_ticks = fs::read_to_string("/proc/stat")
+        .ok()
+        .and_then(|content| parse_total_ticks(&content))
+        .unwrap_or(0);
+
+    let processes = fs::read_dir("/proc")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let pid = entry.file_name().to_str()?.parse::<u32>().ok()?;
+            let content = fs::read_to_string(entry.path().join("stat")).ok()?;
+            Some((pid, parse_proc_stat(&content)?))
+        })
+        .collect();
+
+    ProcessCpuSample { processes, total_ticks }
+}
+
+/// Top `count` processes by share of total CPU time (0-100%) between two samples
+pub fn top_process_usage(previous: &ProcessCpuSample, current: &ProcessCpuSample, count: usize) -> Vec<(String, f32)> {
+    let total = current.total_ticks.saturating_sub(previous.total_ticks);
+    if total == 0 {
+        return Vec::new();
+    }
+
+    let mut usage: Vec<(String, f32)> = current
+        .processes
+        .iter()
+        .filter_map(|(pid, (name, ticks))| {
+            let (_, before) = previous.processes.get(pid)?;
+            let delta = ticks.saturating_sub(*before);
+            (delta > 0).then(|| (name.clone(), delta as f32 / total as f32 * 100.0))
+        })
+        .collect();
+
+    usage.sort_by(|a, b| b.1.total_cmp(&a.1));
+    usage.truncate(count);
+    usage
+}
+
+/// Clock ticks per second used by `/proc/*/stat` (USER_HZ, fixed at 100 on Linux)
+const USER_HZ: f32 = 100.0;
+
+/// CPU time (utime + stime, clock ticks) used so far by this process
+pub fn read_self_cpu_ticks() -> io::Result<u64> {
+    let content = fs::read_to_string("/proc/self/stat")?;
+    parse_proc_stat(&content)
+        .map(|(_, ticks)| ticks)
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "Malformed /proc/self/stat"))
+}
+
+/// Percentage of one CPU used by `ticks` over `elapsed`
+pub fn cpu_percent(ticks: u64, elapsed: Duration) -> f32 {
+    let secs = elapsed.as_secs_f32();
+    if secs <= 0.0 {
+        return 0.0;
+    }
+    ticks as f32 / USER_HZ / secs * 100.0
+}
+
+/// Bound ideapad_acpi platform driver (present only when ideapad-laptop is loaded)
+const IDEAPAD_DRIVER_PATH: &str = "/sys/bus/platform/drivers/ideapad_acpi";
+const IDEAPAD_PARAMETERS_PATH: &str = "/sys/module/ideapad_laptop/parameters";
+
+/// Loaded kernel modules, one directory each
+const SYS_MODULE_PATH: &str = "/sys/module";
+
+/// Kernel modules the monitor relies on (coretemp on Intel, k10temp on AMD)
+pub const REQUIRED_MODULES: &[&str] = &["ideapad_laptop", "platform_profile", "coretemp", "k10temp"];
+
+/// Version of `module` under a sysfs module root; `None` when the module is
+/// not loaded or does not declare a version (common for in-tree modules)
+fn read_module_version(root: &Path, module: &str) -> Option<String> {
+    fs::read_to_string(root.join(module).join("version"))
+        .ok()
+        .map(|version| version.trim().to_string())
+        .filter(|version| !version.is_empty())
+}
+
+/// One line of the hardware report for `module`
+fn module_report_line(root: &Path, module: &str) -> String {
+    let status = match read_module_version(root, module) {
+        Some(version) => version,
+        None if root.join(module).is_dir() => "loaded, no version".into(),
+        None => "not loaded".into(),
+    };
+    format!("  {}: {}", module, status)
+}
+
+/// State of the ideapad-laptop kernel module
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DriverStatus {
+    pub loaded: bool,
+    /// Module version from `modinfo` (in-tree modules usually have none)
+    pub version: Option<String>,
+    pub parameters: HashMap<String, String>,
+}
+
+/// Read every module parameter file in `dir` as name -> value
+fn read_module_parameters(dir: &Path) -> io::Result<HashMap<String, String>> {
+    let mut parameters = HashMap::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        // Some parameters are write-only or root-only; skip what we cannot read
+        if let Ok(value) = fs::read_to_string(entry.path()) {
+            parameters.insert(entry.file_name().to_string_lossy().into_owned(), value.trim().to_string());
+        }
+    }
+    Ok(parameters)
+}
+
+/// Check whether the ideapad-laptop module is loaded and collect its version and parameters
+pub fn ideapad_acpi_driver_check() -> Result<DriverStatus, ThermalError> {
+    let loaded = Path::new(IDEAPAD_DRIVER_PATH).exists();
+    let version = ThermalState::module_version("ideapad_laptop").or_else(|| {
+        Command::new("modinfo")
+            .args(["-F", "version", "ideapad_laptop"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .filter(|version| !version.is_empty())
+    });
+    let parameters = match read_module_parameters(Path::new(IDEAPAD_PARAMETERS_PATH)) {
+        Ok(parameters) => parameters,
+        Err(e) if e.kind() == ErrorKind::NotFound => HashMap::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(DriverStatus { loaded, version, parameters })
+}
+
+/// Load the ideapad-laptop module using pkexec
+pub fn load_ideapad_driver() -> io::Result<()> {
+    let output = Command::new("pkexec")
+        .args(["modprobe", "ideapad-laptop"])
+        .output()?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(io::Error::new(ErrorKind::Other, format!("Failed to load driver: {}", stderr)))
+    }
+}
+
+/// Default ftrace output file
+pub const FTRACE_PATH: &str = "/sys/kernel/debug/tracing/trace";
+
+/// Parse one ftrace `thermal_temperature` line into (trace timestamp, °C)
+///
+/// Example line:
+/// `kworker/0:1-123 [000] ....  1234.567890: thermal_temperature: thermal_zone=x86_pkg_temp id=10 temp_prev=45000 temp=46000`
+fn parse_trace_line(line: &str) -> Option<(Duration, f32)> {
+    let (prefix, event) = line.split_once(": thermal_temperature:")?;
+    let timestamp: f64 = prefix.split_whitespace().last()?.parse().ok()?;
+    let millicelsius: i32 = event
+        .split_whitespace()
+        .find_map(|field| field.strip_prefix("temp="))?
+        .parse()
+        .ok()?;
+    Some((Duration::from_secs_f64(timestamp), millicelsius as f32 / 1000.0))
+}
+
+/// Destination of the generated udev rules file
+pub const UDEV_RULES_PATH: &str = "/etc/udev/rules.d/99-lenovo-thermal.rules";
+
+/// Sysfs nodes the GUI writes to (fan boost, performance cap, platform
+/// profile, amd-pstate EPP); `cpu*` stands for every CPU
+const WRITABLE_SYSFS_PATHS: [&str; 6] = [
+    "/sys/devices/pci0000:00/0000:00:1f.0/PNP0C09:00/VPC2004:00/fan_mode",
+    "/sys/devices/system/cpu/intel_pstate/max_perf_pct",
+    "/sys/devices/system/cpu/intel_pstate/min_perf_pct",
+    "/sys/devices/system/cpu/amd_pstate/max_perf_pct",
+    "/sys/devices/system/cpu/cpu*/cpufreq/energy_performance_preference",
+    "/sys/firmware/acpi/platform_profile",
+];
+
+/// List the writable sysfs nodes present on this machine (globs by their first CPU)
+pub fn discover_writable_paths() -> Vec<&'static str> {
+    WRITABLE_SYSFS_PATHS
+        .iter()
+        .copied()
+        .filter(|path| Path::new(&path.replace("cpu*", "cpu0")).exists())
+        .collect()
+}
+
+/// Pick the group that should own the sysfs nodes (plugdev on Debian-likes, wheel elsewhere)
+pub fn detect_udev_group() -> &'static str {
+    let groups = fs::read_to_string("/etc/group").unwrap_or_default();
+    if groups.lines().any(|line| line.starts_with("plugdev:")) {
+        "plugdev"
+    } else {
+        "wheel"
+    }
+}
+
+/// udev match keys selecting the one device event after which `path`
+/// exists, and the path as written in the rule (`%p` is the device path)
+fn udev_rule_target(path: &str) -> (String, String) {
+    if let Some(attribute) = path.strip_prefix("/sys/devices/system/cpu/cpu*/") {
+        ("SUBSYSTEM==\"cpu\", KERNEL==\"cpu[0-9]*\"".into(), format!("/sys%p/{}", attribute))
+    } else if path.starts_with("/sys/devices/system/cpu/") {
+        // The pstate directories belong to no device; they exist once cpu0 does
+        ("SUBSYSTEM==\"cpu\", KERNEL==\"cpu0\"".into(), path.to_string())
+    } else if let Some((device, attribute)) =
+        path.strip_prefix("/sys").filter(|p| p.starts_with("/devices/")).and_then(|p| p.rsplit_once('/'))
+    {
+        (format!("DEVPATH==\"{}\"", device), format!("/sys%p/{}", attribute))
+    } else {
+        // /sys/firmware/acpi/platform_profile appears with the platform-profile class device
+        ("SUBSYSTEM==\"platform-profile\"".into(), path.to_string())
+    }
+}
+
+/// Build udev rules granting `group` write access to each path. Each rule
+/// only fires for the device owning the path and touches that file alone.
+pub fn udev_rules_content(paths: &[&str], group: &str) -> String {
+    let mut rules = String::from(
+        "# Generated by Thermal Monitor - grants non-root write access to thermal controls\n",
+    );
+    for path in paths {
+        let (device, target) = udev_rule_target(path);
+        rules.push_str(&format!(
+            "ACTION!=\"remove\", {device}, RUN+=\"/bin/chgrp {group} {target}\", RUN+=\"/bin/chmod g+w {target}\"\n",
+        ));
     }
+    rules
 }
 
-/// Change CPU mode using pkexec
-pub fn set_mode(mode: Mode) -> io::Result<()> {
+/// Install udev rules with pkexec and reload udev so they apply immediately
+pub fn install_udev_rules(content: &str) -> io::Result<()> {
+    let script = format!(
+        "printf '%s' \"$1\" > {} && udevadm control --reload-rules && \
+         udevadm trigger --subsystem-match=platform --subsystem-match=cpu \
+         --subsystem-match=platform-profile",
+        UDEV_RULES_PATH
+    );
     let output = Command::new("pkexec")
-        .args(["/usr/local/bin/cpu-mode", mode.command()])
+        .args(["bash", "-c", &script, "bash", content])
         .output()?;
 
     if output.status.success() {
         Ok(())
     } else {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        Err(io::Error::new(ErrorKind::Other, format!("Failed to change mode: {}", stderr)))
+        Err(io::Error::new(ErrorKind::Other, format!("Failed to install udev rules: {}", stderr)))
+    }
+}
+
+/// acpid's event socket, readable by any user while acpid runs
+const ACPID_SOCKET_PATH: &str = "/var/run/acpid.socket";
+
+/// Kernel event file used when acpid is not running (single reader, usually root)
+const PROC_ACPI_EVENT_PATH: &str = "/proc/acpi/event";
+
+/// Device class of an ACPI notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcpiEventType {
+    /// A thermal zone crossed a trip point or changed its trip points
+    ThermalZoneTrip,
+    AcAdapter,
+    Battery,
+    Button,
+    Other,
+}
+
+/// One line from the ACPI event source, e.g.
+/// `thermal_zone LNXTHERM:00 00000081 00000000`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AcpiEvent {
+    pub event_type: AcpiEventType,
+    /// Everything after the device class
+    pub data: String,
+}
+
+impl AcpiEvent {
+    /// Decode an event line; `None` for blank lines
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        let (class, data) = line.split_once(' ').unwrap_or((line, ""));
+        if class.is_empty() {
+            return None;
+        }
+        let event_type = match class.split('/').next().unwrap_or(class) {
+            "thermal_zone" => AcpiEventType::ThermalZoneTrip,
+            "ac_adapter" => AcpiEventType::AcAdapter,
+            "battery" => AcpiEventType::Battery,
+            "button" => AcpiEventType::Button,
+            _ => AcpiEventType::Other,
+        };
+        Some(Self { event_type, data: data.trim().to_string() })
+    }
+}
+
+/// Open acpid's socket, or `/proc/acpi/event` when acpid is not running
+fn open_acpi_event_source() -> io::Result<Box<dyn BufRead + Send>> {
+    match UnixStream::connect(ACPID_SOCKET_PATH) {
+        Ok(stream) => Ok(Box::new(BufReader::new(stream))),
+        Err(_) => Ok(Box::new(BufReader::new(fs::File::open(PROC_ACPI_EVENT_PATH)?))),
+    }
+}
+
+/// Block until the next event arrives on `source`
+pub fn read_acpi_events(source: &mut impl BufRead) -> Result<AcpiEvent, ThermalError> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if source.read_line(&mut line)? == 0 {
+            return Err(ThermalError::InvalidData("ACPI event source closed".into()));
+        }
+        if let Some(event) = AcpiEvent::parse(&line) {
+            return Ok(event);
+        }
+    }
+}
+
+/// ACPI events read on a background thread; the UI drains them every frame
+pub struct AcpiEventStream {
+    rx: Receiver<AcpiEvent>,
+}
+
+impl AcpiEventStream {
+    /// Start reading events, calling `on_event` after each one is queued so a
+    /// sleeping UI can wake up. `None` when no event source can be opened.
+    pub fn spawn(on_event: impl Fn() + Send + 'static) -> Option<Self> {
+        let mut source = open_acpi_event_source().ok()?;
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            while let Ok(event) = read_acpi_events(&mut source) {
+                // The app is gone: stop reading
+                if tx.send(event).is_err() {
+                    break;
+                }
+                on_event();
+            }
+        });
+        Some(Self { rx })
+    }
+
+    /// Events received since the last call, oldest first
+    pub fn drain(&self) -> impl Iterator<Item = AcpiEvent> + '_ {
+        self.rx.try_iter()
     }
 }
 
@@ -343,31 +2177,242 @@ pub struct ThermalState {
     pub mode: Mode,
     pub platform_profile: String,
     pub fan_boost: bool,
+    pub screen_on: bool,
+    pub fan_rpm: Option<u32>,
+    /// EC is throttling independently of the OS (only known with `ec_access`)
+    pub ec_throttling: bool,
+    pub on_ac: Option<bool>,
+    /// Active cpufreq scaling driver, empty when unknown
+    pub cpufreq_driver: String,
+    /// Turbo/boost state (`None` when the driver cannot toggle it)
+    pub turbo_enabled: Option<bool>,
+    /// Generic cpufreq boost switch (`None` when the kernel has none)
+    pub cpu_boost: Option<bool>,
+    /// Temperature at which the CPU throttles itself (°C)
+    pub tjmax: f32,
+    /// Memory in use (%), 0 when unknown
+    pub mem_used_pct: f32,
+    /// Per-core temperatures by core number (empty without coretemp)
+    pub per_core_temps: Vec<f32>,
+    /// Package sensor, see [`read_cpu_package_temp`]; `None` without coretemp
+    pub cpu_package_temp: Option<f32>,
+    /// Hottest core; `None` without coretemp
+    pub cpu_core_max_temp: Option<f32>,
+    /// Battery charge (%), `None` without a battery
+    pub battery_pct: Option<u8>,
+    /// Battery charge cycles so far; high counts mean reduced capacity
+    pub power_supply_cycle_count: Option<u32>,
+    /// Battery wear, `None` without a battery
+    pub battery_health: Option<PowerSupplyHealth>,
+    /// Kernel thermal governor, `None` when the thermal zone is missing
+    pub kernel_thermal_policy: Option<String>,
+    /// CPU load (%) since the previous reading, `None` on the first one
+    pub cpu_load_pct: Option<f32>,
+    /// Load judged light, with hysteresis, see [`ThermalZone::is_low_load`]
+    pub low_load: bool,
+    /// Zone of the previous reading, kept for the zone dead-band
+    pub previous_zone: Option<ThermalZone>,
+    /// CPU time counters, kept to compute the load on the next poll
+    pub cpu_ticks: Option<CpuTicks>,
+    #[cfg(feature = "intel-rapl")]
+    pub tdp: Option<TdpInfo>,
+    /// Previous RAPL reading, kept to compute power on the next poll
+    #[cfg(feature = "intel-rapl")]
+    pub rapl_sample: Option<RaplSample>,
+}
+
+/// What changed between two successive [`ThermalState`] readings
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ThermalStateDiff {
+    pub cpu_delta: f32,
+    pub kbd_delta: f32,
+    pub mode_changed: bool,
+    pub zone_changed: bool,
+    pub fan_changed: bool,
+}
+
+impl ThermalStateDiff {
+    /// Nothing the control loop reacts to has changed
+    pub fn is_unchanged(&self) -> bool {
+        *self == Self::default()
+    }
 }
 
 impl ThermalState {
     /// Read complete thermal state from system
     pub fn read() -> Self {
         let cpu_temp = read_cpu_temp().unwrap_or(50.0);
+        let cpufreq_driver = read_cpufreq_driver();
         let ambient_temp = read_ambient_temp();
         let keyboard_temp = calculate_keyboard_temp(cpu_temp, ambient_temp);
+        let per_core_temps = read_per_core_temps(Path::new(HWMON_PATH));
+
+        // The EC tachometer is more accurate than hwmon when available
+        #[cfg(feature = "ec_access")]
+        let ec_registers = read_ec_registers().ok();
+        #[cfg(feature = "ec_access")]
+        let (fan_rpm, ec_throttling) = match &ec_registers {
+            Some(regs) => (ec_fan_rpm(regs).or_else(read_fan_rpm), ec_throttling(regs)),
+            None => (read_fan_rpm(), false),
+        };
+        #[cfg(not(feature = "ec_access"))]
+        let (fan_rpm, ec_throttling) = (read_fan_rpm(), false);
 
         Self {
             cpu_temp,
             keyboard_temp,
             ambient_temp,
-            perf_pct: read_perf_pct().unwrap_or(50),
+            perf_pct: read_perf_pct(&cpufreq_driver).unwrap_or(50),
             current_freq_mhz: read_current_freq().unwrap_or(1000),
             max_freq_mhz: read_max_freq().unwrap_or(4400),
             mode: read_mode(),
             platform_profile: read_platform_profile(),
             fan_boost: read_fan_mode() == 1,
+            screen_on: read_screen_on(),
+            fan_rpm,
+            ec_throttling,
+            on_ac: read_on_ac(),
+            turbo_enabled: read_turbo_enabled(&cpufreq_driver),
+            cpu_boost: cpu_boost_control().ok(),
+            cpufreq_driver,
+            tjmax: read_tjmax(Path::new(HWMON_PATH)).unwrap_or(DEFAULT_TJMAX),
+            mem_used_pct: read_mem_used_pct().unwrap_or(0.0),
+            cpu_package_temp: read_cpu_package_temp().ok(),
+            cpu_core_max_temp: per_core_temps.iter().copied().reduce(f32::max),
+            per_core_temps,
+            battery_pct: read_battery_pct(),
+            power_supply_cycle_count: read_battery_cycle_count(),
+            battery_health: read_power_supply_health(),
+            kernel_thermal_policy: read_kernel_thermal_policy(),
+            cpu_load_pct: None,
+            low_load: false,
+            previous_zone: None,
+            cpu_ticks: read_cpu_ticks(),
+            #[cfg(feature = "intel-rapl")]
+            tdp: None,
+            #[cfg(feature = "intel-rapl")]
+            rapl_sample: None,
+        }
+    }
+
+    /// Read state, carrying cached counters from the previous snapshot
+    pub fn read_after(previous: &ThermalState) -> Self {
+        let mut state = Self::read();
+        state.cpu_load_pct = match (&state.cpu_ticks, &previous.cpu_ticks) {
+            (Some(current), Some(previous)) => current.load_pct_since(previous),
+            _ => None,
+        };
+        state.low_load = state.cpu_load_pct.is_some_and(|load| ThermalZone::is_low_load(load, previous.low_load));
+        state.previous_zone = Some(previous.thermal_zone());
+        #[cfg(feature = "intel-rapl")]
+        if let Ok((tdp, sample)) = get_tdp_info(previous.rapl_sample.as_ref()) {
+            // Report power only once two RAPL samples exist (or for AMD direct readings)
+            if previous.rapl_sample.is_some() || sample.is_none() {
+                state.tdp = Some(tdp);
+            }
+            state.rapl_sample = sample;
         }
+        state
+    }
+
+    /// `temp1_input` of the hwmon device called `name` (e.g. `coretemp`, `nvme`), in °C
+    pub fn read_from_hwmon_by_name(name: &str) -> Option<f32> {
+        read_hwmon_temp(Path::new(HWMON_PATH), name, "temp1_input")
+    }
+
+    /// Parse ftrace `thermal_temperature` events into a (timestamp, °C) series.
+    /// Unreadable files and unrelated lines yield an empty or shorter series.
+    pub fn read_from_procfs_trace(trace_file: &Path) -> Vec<(Duration, f32)> {
+        fs::read_to_string(trace_file)
+            .map(|content| content.lines().filter_map(parse_trace_line).collect())
+            .unwrap_or_default()
     }
 
-    /// Get thermal zone classification
+    /// Get thermal zone classification, load-aware once the load is known
     pub fn thermal_zone(&self) -> ThermalZone {
-        ThermalZone::from_cpu_temp(self.cpu_temp)
+        match self.cpu_load_pct {
+            Some(_) => ThermalZone::from_temp_and_load(self.cpu_temp, self.low_load, self.previous_zone),
+            None => ThermalZone::from_cpu_temp(self.cpu_temp),
+        }
+    }
+
+    /// Why the kernel thermal policy works against the platform profile, if it does
+    pub fn thermal_policy_conflict(&self) -> Option<&'static str> {
+        match (self.kernel_thermal_policy.as_deref()?, self.platform_profile.as_str()) {
+            ("power_allocator", "performance") => {
+                Some("The power_allocator thermal governor caps CPU power, holding back the performance profile")
+            }
+            ("user_space", _) if !self.platform_profile.is_empty() && self.platform_profile != "unknown" => {
+                Some("The user_space thermal governor leaves throttling to a daemon; without one only the trip points protect the CPU")
+            }
+            _ => None,
+        }
+    }
+
+    /// Degrees left before TJmax
+    pub fn thermal_headroom(&self) -> f32 {
+        self.tjmax - self.cpu_temp
+    }
+
+    /// Changes from the `previous` reading to this one
+    pub fn diff(&self, previous: &ThermalState) -> ThermalStateDiff {
+        ThermalStateDiff {
+            cpu_delta: self.cpu_temp - previous.cpu_temp,
+            kbd_delta: self.keyboard_temp - previous.keyboard_temp,
+            mode_changed: self.mode != previous.mode,
+            zone_changed: self.thermal_zone() != previous.thermal_zone(),
+            fan_changed: self.fan_boost != previous.fan_boost,
+        }
+    }
+
+    /// Version of a loaded kernel module from `/sys/module/<module>/version`
+    pub fn module_version(module: &str) -> Option<String> {
+        read_module_version(Path::new(SYS_MODULE_PATH), module)
+    }
+
+    /// Plain-text summary of the detected hardware and kernel modules, for bug reports
+    pub fn hardware_report(&self) -> String {
+        let mut report = format!(
+            "Thermal Monitor {}\n\
+             cpufreq driver: {}\n\
+             Platform profile: {} ({})\n\
+             Kernel thermal policy: {}\n\
+             TJmax: {:.0}°C\n\
+             ideapad_acpi bound: {}\n\
+             Battery cycles: {}\n\
+             Kernel modules:\n",
+            env!("CARGO_PKG_VERSION"),
+            if self.cpufreq_driver.is_empty() { "unknown" } else { &self.cpufreq_driver },
+            self.platform_profile,
+            get_platform_profile_driver().label(),
+            self.kernel_thermal_policy.as_deref().unwrap_or("unknown"),
+            self.tjmax,
+            if Path::new(IDEAPAD_DRIVER_PATH).exists() { "yes" } else { "no" },
+            self.power_supply_cycle_count.map_or("unknown".into(), |cycles| cycles.to_string()),
+        );
+        for module in REQUIRED_MODULES {
+            report.push_str(&module_report_line(Path::new(SYS_MODULE_PATH), module));
+            report.push('\n');
+        }
+        report
+    }
+
+    /// Reading by the name custom metric expressions use (see SENSOR_NAMES)
+    pub fn sensor(&self, name: &str) -> Option<f64> {
+        let value = match name {
+            "cpu_temp" => self.cpu_temp as f64,
+            "kbd_temp" => self.keyboard_temp as f64,
+            "ambient_temp" => self.ambient_temp as f64,
+            "perf_pct" => self.perf_pct as f64,
+            "freq_mhz" => self.current_freq_mhz as f64,
+            "max_freq_mhz" => self.max_freq_mhz as f64,
+            "mem_used_pct" => self.mem_used_pct as f64,
+            "tjmax" => self.tjmax as f64,
+            "headroom" => self.thermal_headroom() as f64,
+            "fan_rpm" => self.fan_rpm? as f64,
+            _ => return None,
+        };
+        Some(value)
     }
 
     /// Get current frequency in GHz
@@ -395,6 +2440,46 @@ mod tests {
         assert_eq!(ThermalZone::from_cpu_temp(70.0), ThermalZone::Critical);
     }
 
+    #[test]
+    fn test_thermal_zone_from_temp_and_load() {
+        // Expected heat under load keeps the plain classification
+        assert_eq!(ThermalZone::from_temp_and_load(47.0, false, None), ThermalZone::Optimal);
+        // The same temperature while nearly idle is suspicious
+        assert_eq!(ThermalZone::from_temp_and_load(47.0, true, None), ThermalZone::Warm);
+        assert_eq!(ThermalZone::from_temp_and_load(36.0, true, None), ThermalZone::Comfort);
+        // Critical is only reached by temperature
+        assert_eq!(ThermalZone::from_temp_and_load(62.0, true, None), ThermalZone::Hot);
+        assert_eq!(ThermalZone::from_temp_and_load(66.0, true, None), ThermalZone::Critical);
+
+        let state = ThermalState { cpu_temp: 47.0, ..Default::default() };
+        assert_eq!(state.thermal_zone(), ThermalZone::Optimal);
+        let idle = ThermalState { cpu_load_pct: Some(5.0), low_load: true, ..state };
+        assert_eq!(idle.thermal_zone(), ThermalZone::Warm);
+    }
+
+    #[test]
+    fn test_low_load_hysteresis() {
+        assert!(ThermalZone::is_low_load(5.0, false));
+        assert!(!ThermalZone::is_low_load(20.0, false));
+        // Once light, the load must clearly rise before the judgement flips back
+        assert!(ThermalZone::is_low_load(25.0, true));
+        assert!(!ThermalZone::is_low_load(30.0, true));
+    }
+
+    #[test]
+    fn test_zone_hysteresis() {
+        // Hovering just past a boundary keeps the previous zone
+        assert_eq!(ThermalZone::from_temp_and_load(50.4, false, Some(ThermalZone::Optimal)), ThermalZone::Optimal);
+        assert_eq!(ThermalZone::from_temp_and_load(49.6, false, Some(ThermalZone::Warm)), ThermalZone::Warm);
+        // A clear move changes it
+        assert_eq!(ThermalZone::from_temp_and_load(51.0, false, Some(ThermalZone::Optimal)), ThermalZone::Warm);
+        assert_eq!(ThermalZone::from_temp_and_load(48.9, false, Some(ThermalZone::Warm)), ThermalZone::Optimal);
+        // Critical is entered right at its limit, left only after cooling
+        assert_eq!(ThermalZone::from_temp_and_load(65.0, false, Some(ThermalZone::Hot)), ThermalZone::Critical);
+        assert_eq!(ThermalZone::from_temp_and_load(64.5, false, Some(ThermalZone::Critical)), ThermalZone::Critical);
+        assert_eq!(ThermalZone::from_temp_and_load(63.9, false, Some(ThermalZone::Critical)), ThermalZone::Hot);
+    }
+
     #[test]
     fn test_thermal_zone_boundary_values() {
         assert_eq!(ThermalZone::from_cpu_temp(39.9), ThermalZone::Cool);
@@ -407,6 +2492,28 @@ mod tests {
         assert_eq!(ThermalZone::from_cpu_temp(65.0), ThermalZone::Critical);
     }
 
+    #[test]
+    fn test_heatmap_bands_are_contiguous() {
+        let bands: Vec<(f32, f32)> = ThermalZone::all().iter().map(ThermalZone::heatmap_band).collect();
+        assert_eq!(bands[0].0, f32::NEG_INFINITY);
+        assert_eq!(bands[5], (85.0, f32::INFINITY));
+        assert!(bands.windows(2).all(|pair| pair[0].1 == pair[1].0));
+        assert!(bands[1..5].iter().all(|(low, high)| high - low == 10.0));
+    }
+
+    #[test]
+    fn test_thermal_zone_ranges_match_classification() {
+        for zone in ThermalZone::all() {
+            let (low, high) = zone.temp_range();
+            if low.is_finite() {
+                assert_eq!(ThermalZone::from_cpu_temp(low), *zone);
+            }
+            if high.is_finite() {
+                assert_ne!(ThermalZone::from_cpu_temp(high), *zone);
+            }
+        }
+    }
+
     #[test]
     fn test_thermal_zone_labels() {
         assert_eq!(ThermalZone::Cool.label(), "COOL");
@@ -551,12 +2658,710 @@ mod tests {
         assert_eq!(state.thermal_zone(), ThermalZone::Optimal);
     }
 
+    #[test]
+    fn test_thermal_state_diff() {
+        let state = ThermalState { cpu_temp: 48.0, keyboard_temp: 36.0, ..Default::default() };
+        let diff = state.diff(&state.clone());
+        assert_eq!(diff, ThermalStateDiff::default());
+        assert!(diff.is_unchanged());
+
+        let hotter = ThermalState { cpu_temp: 52.0, keyboard_temp: 37.0, fan_boost: true, ..state.clone() };
+        let diff = hotter.diff(&state);
+        assert_eq!((diff.cpu_delta, diff.kbd_delta), (4.0, 1.0));
+        assert!(diff.zone_changed && diff.fan_changed && !diff.mode_changed);
+        assert!(!diff.is_unchanged());
+        assert_eq!(state.diff(&hotter).cpu_delta, -4.0);
+    }
+
+    #[test]
+    fn test_read_tjmax_and_headroom() {
+        let root = tempfile::tempdir().unwrap();
+        let acpi = root.path().join("hwmon0");
+        fs::create_dir(&acpi).unwrap();
+        fs::write(acpi.join("name"), "acpitz\n").unwrap();
+        fs::write(acpi.join("temp1_crit"), "120000\n").unwrap();
+        assert_eq!(read_tjmax(root.path()), None);
+
+        let coretemp = root.path().join("hwmon3");
+        fs::create_dir(&coretemp).unwrap();
+        fs::write(coretemp.join("name"), "coretemp\n").unwrap();
+        fs::write(coretemp.join("temp1_crit"), "100000\n").unwrap();
+        assert_eq!(read_tjmax(root.path()), Some(100.0));
+
+        let state = ThermalState { cpu_temp: 92.5, tjmax: 100.0, ..Default::default() };
+        assert_eq!(state.thermal_headroom(), 7.5);
+    }
+
+    #[test]
+    fn test_throttle_headroom() {
+        let trip = |trip_type, temp_celsius| TripPoint { name: "trip_point_0".into(), trip_type, temp_celsius };
+        let points = [trip(TripType::Active, 60.0), trip(TripType::Passive, 90.0), trip(TripType::Critical, 105.0)];
+        assert_eq!(throttle_headroom(&points, 100.0, 72.0), 18.0);
+        assert_eq!(throttle_headroom(&points[..1], 100.0, 72.0), 28.0);
+        assert_eq!(throttle_headroom(&[], 100.0, 103.0), -3.0);
+        // The coolest passive trip wins whatever the order
+        let points = [trip(TripType::Passive, 95.0), trip(TripType::Passive, 85.0)];
+        assert_eq!(throttle_headroom(&points, 100.0, 72.0), 13.0);
+
+        assert_eq!(estimate_throttle_headroom(&ThermalState::default()), None);
+        assert_eq!(estimate_throttle_headroom(&ThermalState { cpu_temp: f32::NAN, ..Default::default() }), None);
+    }
+
+    #[test]
+    fn test_read_per_core_temps() {
+        let root = tempfile::tempdir().unwrap();
+        assert!(read_per_core_temps(root.path()).is_empty());
+
+        let coretemp = root.path().join("hwmon4");
+        fs::create_dir(&coretemp).unwrap();
+        fs::write(coretemp.join("name"), "coretemp\n").unwrap();
+        for (sensor, label, millicelsius) in
+            [(1, "Package id 0", 60000), (2, "Core 0", 55000), (3, "Core 8", 58000), (10, "Core 4", 52500)]
+        {
+            fs::write(coretemp.join(format!("temp{}_label", sensor)), format!("{}\n", label)).unwrap();
+            fs::write(coretemp.join(format!("temp{}_input", sensor)), format!("{}\n", millicelsius)).unwrap();
+        }
+        assert_eq!(read_per_core_temps(root.path()), vec![55.0, 52.5, 58.0]);
+        assert_eq!(read_cpu_package_temp_from(root.path()).unwrap(), 60.0);
+
+        // Without a package sensor the hottest core stands in
+        fs::remove_file(coretemp.join("temp1_label")).unwrap();
+        assert_eq!(read_cpu_package_temp_from(root.path()).unwrap(), 58.0);
+    }
+
+    #[test]
+    fn test_read_cpu_package_temp_without_sensors() {
+        let root = tempfile::tempdir().unwrap();
+        assert!(matches!(read_cpu_package_temp_from(root.path()), Err(ThermalError::Io(_))));
+
+        let coretemp = root.path().join("hwmon2");
+        fs::create_dir(&coretemp).unwrap();
+        fs::write(coretemp.join("name"), "coretemp\n").unwrap();
+        assert!(matches!(read_cpu_package_temp_from(root.path()), Err(ThermalError::InvalidData(_))));
+
+    }
+
+    #[test]
+    fn test_thermal_policy_conflict() {
+        let state = |policy: Option<&str>, profile: &str| ThermalState {
+            kernel_thermal_policy: policy.map(str::to_string),
+            platform_profile: profile.into(),
+            ..Default::default()
+        };
+        assert!(state(Some("power_allocator"), "performance").thermal_policy_conflict().is_some());
+        assert!(state(Some("power_allocator"), "balanced").thermal_policy_conflict().is_none());
+        assert!(state(Some("step_wise"), "performance").thermal_policy_conflict().is_none());
+        assert!(state(Some("user_space"), "low-power").thermal_policy_conflict().is_some());
+        assert!(state(Some("user_space"), "unknown").thermal_policy_conflict().is_none());
+        assert!(state(None, "performance").thermal_policy_conflict().is_none());
+    }
+
+    #[test]
+    fn test_read_trip_points() {
+        let zone = tempfile::tempdir().unwrap();
+        for (index, trip_type, millicelsius) in
+            [(0, "critical", "110000"), (1, "passive", "90000"), (2, "active", "0"), (3, "bogus", "50000")]
+        {
+            fs::write(zone.path().join(format!("trip_point_{}_type", index)), format!("{}\n", trip_type)).unwrap();
+            fs::write(zone.path().join(format!("trip_point_{}_temp", index)), format!("{}\n", millicelsius)).unwrap();
+        }
+        fs::write(zone.path().join("type"), "acpitz\n").unwrap();
+
+        let points = read_trip_points(zone.path());
+        assert_eq!(
+            points,
+            vec![
+                TripPoint { name: "trip_point_1".into(), trip_type: TripType::Passive, temp_celsius: 90.0 },
+                TripPoint { name: "trip_point_0".into(), trip_type: TripType::Critical, temp_celsius: 110.0 },
+            ]
+        );
+        assert_eq!(points[0].label(), "Passive (90°)");
+        assert!(read_trip_points(&zone.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn test_read_module_version() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("ideapad_laptop")).unwrap();
+        fs::write(root.path().join("ideapad_laptop/version"), "1.2.3\n").unwrap();
+        fs::create_dir_all(root.path().join("coretemp")).unwrap();
+
+        assert_eq!(read_module_version(root.path(), "ideapad_laptop"), Some("1.2.3".into()));
+        assert_eq!(read_module_version(root.path(), "coretemp"), None);
+        assert_eq!(module_report_line(root.path(), "ideapad_laptop"), "  ideapad_laptop: 1.2.3");
+        assert_eq!(module_report_line(root.path(), "coretemp"), "  coretemp: loaded, no version");
+        assert_eq!(module_report_line(root.path(), "k10temp"), "  k10temp: not loaded");
+    }
+
+    #[test]
+    fn test_hardware_report_lines() {
+        let state = ThermalState { cpufreq_driver: "intel_pstate".into(), tjmax: 100.0, ..Default::default() };
+        let report = state.hardware_report();
+        let lines: Vec<&str> = report.lines().collect();
+        assert!(lines[0].starts_with("Thermal Monitor "));
+        assert_eq!(lines[1], "cpufreq driver: intel_pstate");
+        assert_eq!(lines[4], "TJmax: 100°C");
+        assert_eq!(lines[7], "Kernel modules:");
+        // Only the module lines are indented, by exactly two spaces
+        assert!(lines[..8].iter().all(|line| !line.starts_with(' ')));
+        assert_eq!(lines.len(), 8 + REQUIRED_MODULES.len());
+        assert!(lines[8..].iter().all(|line| line.starts_with("  ") && !line.starts_with("   ")));
+    }
+
+    #[test]
+    fn test_platform_profile_driver_detect() {
+        let root = tempfile::tempdir().unwrap();
+        let detect = || PlatformProfileDriver::detect(root.path());
+        assert_eq!(detect(), PlatformProfileDriver::None);
+
+        fs::create_dir_all(root.path().join("devices/system/cpu/amd_pstate")).unwrap();
+        assert_eq!(detect(), PlatformProfileDriver::AmdPstate);
+        assert_eq!(detect().profile_path(), None);
+
+        fs::create_dir_all(root.path().join("firmware/acpi")).unwrap();
+        fs::write(root.path().join("firmware/acpi/platform_profile"), "balanced\n").unwrap();
+        assert_eq!(detect(), PlatformProfileDriver::Generic);
+
+        fs::create_dir_all(root.path().join("bus/platform/drivers/thinkpad_acpi")).unwrap();
+        assert_eq!(detect(), PlatformProfileDriver::ThinkpadAcpi);
+
+        fs::create_dir_all(root.path().join("bus/platform/drivers/ideapad_acpi")).unwrap();
+        assert_eq!(detect(), PlatformProfileDriver::IdeapadAcpi);
+        assert_eq!(detect().profile_path(), Some("firmware/acpi/platform_profile"));
+    }
+
+    #[test]
+    fn test_mode_write_per_driver() {
+        assert_eq!(PlatformProfileDriver::IdeapadAcpi.mode_write(Mode::Comfort), ModeWrite::Helper);
+        assert_eq!(PlatformProfileDriver::None.mode_write(Mode::Quiet), ModeWrite::Unsupported);
+        assert_eq!(PlatformProfileDriver::Generic.mode_write(Mode::Unknown), ModeWrite::Unsupported);
+        assert_eq!(
+            PlatformProfileDriver::ThinkpadAcpi.mode_write(Mode::Quiet),
+            ModeWrite::Sysfs { path: "firmware/acpi/platform_profile", value: "low-power" }
+        );
+        assert_eq!(
+            PlatformProfileDriver::Generic.mode_write(Mode::Auto),
+            ModeWrite::Sysfs { path: "firmware/acpi/platform_profile", value: "balanced" }
+        );
+        assert_eq!(
+            PlatformProfileDriver::AmdPstate.mode_write(Mode::Performance),
+            ModeWrite::Sysfs { path: AMD_EPP_PATH, value: "performance" }
+        );
+
+        // Every value written reads back as a mode
+        for driver in [PlatformProfileDriver::ThinkpadAcpi, PlatformProfileDriver::AmdPstate] {
+            for &mode in Mode::all() {
+                let ModeWrite::Sysfs { value, .. } = driver.mode_write(mode) else {
+                    panic!("{:?} has no sysfs write", driver);
+                };
+                assert_ne!(mode_from_preset(value), Mode::Unknown);
+            }
+        }
+        assert_eq!(mode_from_preset("balance_performance"), Mode::Balanced);
+        assert_eq!(mode_from_preset("custom"), Mode::Unknown);
+    }
+
+    #[test]
+    fn test_read_battery_attribute() {
+        let root = tempfile::tempdir().unwrap();
+        let ac = root.path().join("AC");
+        fs::create_dir(&ac).unwrap();
+        fs::write(ac.join("type"), "Mains\n").unwrap();
+        fs::write(ac.join("cycle_count"), "7\n").unwrap();
+        assert_eq!(read_battery_attribute::<u32>(root.path(), "cycle_count"), None);
+
+        let battery = root.path().join("BAT0");
+        fs::create_dir(&battery).unwrap();
+        fs::write(battery.join("type"), "Battery\n").unwrap();
+        fs::write(battery.join("capacity"), "81\n").unwrap();
+        fs::write(battery.join("cycle_count"), "312\n").unwrap();
+        assert_eq!(read_battery_attribute::<u8>(root.path(), "capacity"), Some(81));
+        assert_eq!(read_battery_attribute::<u32>(root.path(), "cycle_count"), Some(312));
+        assert_eq!(read_battery_attribute::<u32>(root.path(), "charge_full"), None);
+    }
+
+    #[test]
+    fn test_read_power_supply_health() {
+        let root = tempfile::tempdir().unwrap();
+        assert_eq!(read_power_supply_health_from(root.path()), None);
+
+        let battery = root.path().join("BAT0");
+        fs::create_dir(&battery).unwrap();
+        for (attribute, value) in [
+            ("type", "Battery"),
+            ("status", "Not charging"),
+            ("cycle_count", "412"),
+            ("voltage_min_design", "15400000"),
+            ("charge_full_design", "3800000"),
+            ("charge_full", "2470000"),
+        ] {
+            fs::write(battery.join(attribute), format!("{}\n", value)).unwrap();
+        }
+        let health = read_power_supply_health_from(root.path()).unwrap();
+        assert_eq!(health.capacity_design_mwh, 58520);
+        assert_eq!(health.capacity_full_mwh, 38038);
+        assert!((health.health_pct - 65.0).abs() < 0.01);
+        assert_eq!(health.cycle_count, 412);
+        assert_eq!(health.status, ChargingStatus::NotCharging);
+        assert!(health.is_degraded());
+
+        // Energy attributes take precedence and need no voltage
+        fs::write(battery.join("energy_full_design"), "50000000\n").unwrap();
+        fs::write(battery.join("energy_full"), "45000000\n").unwrap();
+        let health = read_power_supply_health_from(root.path()).unwrap();
+        assert_eq!((health.capacity_design_mwh, health.capacity_full_mwh), (50000, 45000));
+        assert!((health.health_pct - 90.0).abs() < 0.01 && !health.is_degraded());
+    }
+
+    #[test]
+    fn test_parse_meminfo() {
+        let content = "MemTotal:       16000000 kB\nMemFree:         2000000 kB\nMemAvailable:    4000000 kB\nBuffers:          100000 kB\n";
+        let info = MemInfo::parse(content).unwrap();
+        assert_eq!(info, MemInfo { total_kb: 16_000_000, available_kb: 4_000_000 });
+        assert!((info.used_pct() - 75.0).abs() < 0.001);
+
+        // Kernels before 3.14 have no MemAvailable
+        assert_eq!(MemInfo::parse("MemTotal: 16000000 kB\nMemFree: 2000000 kB\n"), None);
+        assert_eq!(MemInfo { total_kb: 0, available_kb: 0 }.used_pct(), 0.0);
+    }
+
+    #[test]
+    fn test_read_hwmon_temp_by_name() {
+        let root = tempfile::tempdir().unwrap();
+        for (dir, name, temp) in [("hwmon0", "acpitz", "45000"), ("hwmon1", "nvme", "38850"), ("hwmon2", "broken", "n/a")] {
+            let dir = root.path().join(dir);
+            fs::create_dir(&dir).unwrap();
+            fs::write(dir.join("name"), format!("{}\n", name)).unwrap();
+            fs::write(dir.join("temp1_input"), format!("{}\n", temp)).unwrap();
+        }
+        assert_eq!(hwmon_dir_by_name(root.path(), "nvme"), Some(root.path().join("hwmon1")));
+        assert_eq!(read_hwmon_temp(root.path(), "nvme", "temp1_input"), Some(38.85));
+        assert_eq!(read_hwmon_temp(root.path(), "acpitz", "temp1_input"), Some(45.0));
+        assert_eq!(read_hwmon_temp(root.path(), "broken", "temp1_input"), None);
+        assert_eq!(read_hwmon_temp(root.path(), "nvme", "temp2_input"), None);
+        assert_eq!(read_hwmon_temp(root.path(), "coretemp", "temp1_input"), None);
+    }
+
     #[test]
     fn test_mode_default() {
         let mode = Mode::default();
         assert_eq!(mode, Mode::Auto);
     }
 
+    #[test]
+    fn test_udev_rules_content() {
+        let rules = udev_rules_content(&["/sys/firmware/acpi/platform_profile"], "plugdev");
+        assert!(rules.starts_with('#'));
+        assert!(rules.contains("SUBSYSTEM==\"platform-profile\""));
+        assert!(rules.contains("RUN+=\"/bin/chgrp plugdev /sys/firmware/acpi/platform_profile\""));
+        assert!(rules.contains("RUN+=\"/bin/chmod g+w /sys/firmware/acpi/platform_profile\""));
+        assert!(!rules.contains("/bin/sh"));
+    }
+
+    #[test]
+    fn test_udev_rules_scoped_to_owning_device() {
+        let rules = udev_rules_content(&WRITABLE_SYSFS_PATHS, "wheel");
+        let lines: Vec<&str> = rules.lines().filter(|l| l.starts_with("ACTION")).collect();
+        assert_eq!(lines.len(), WRITABLE_SYSFS_PATHS.len());
+        assert!(lines[0].contains("DEVPATH==\"/devices/pci0000:00/0000:00:1f.0/PNP0C09:00/VPC2004:00\""));
+        assert!(lines[0].contains("chgrp wheel /sys%p/fan_mode\""));
+        assert!(lines[1].contains("KERNEL==\"cpu0\""));
+        assert!(lines[1].contains("chmod g+w /sys/devices/system/cpu/intel_pstate/max_perf_pct\""));
+        assert!(lines[4].contains("KERNEL==\"cpu[0-9]*\""));
+        assert!(lines[4].contains("/sys%p/cpufreq/energy_performance_preference\""));
+
+        let empty = udev_rules_content(&[], "wheel");
+        assert_eq!(empty.lines().filter(|l| l.starts_with("ACTION")).count(), 0);
+    }
+
+    #[test]
+    fn test_write_sysfs_direct() {
+        let sys = tempfile::tempdir().unwrap();
+        let cpus = sys.path().join("devices/system/cpu");
+        for cpu in ["cpu0", "cpu1"] {
+            fs::create_dir_all(cpus.join(cpu).join("cpufreq")).unwrap();
+            fs::write(cpus.join(cpu).join("cpufreq/energy_performance_preference"), "balance_performance").unwrap();
+        }
+        fs::create_dir_all(cpus.join("cpufreq")).unwrap();
+        write_sysfs_direct(sys.path(), AMD_EPP_PATH, "power").unwrap();
+        for cpu in ["cpu0", "cpu1"] {
+            let epp = fs::read_to_string(cpus.join(cpu).join("cpufreq/energy_performance_preference")).unwrap();
+            assert_eq!(epp, "power");
+        }
+
+        fs::create_dir_all(sys.path().join("firmware/acpi")).unwrap();
+        fs::write(sys.path().join("firmware/acpi/platform_profile"), "balanced").unwrap();
+        write_sysfs_direct(sys.path(), "firmware/acpi/platform_profile", "low-power").unwrap();
+        assert_eq!(fs::read_to_string(sys.path().join("firmware/acpi/platform_profile")).unwrap(), "low-power");
+
+        let missing = write_sysfs_direct(sys.path(), "devices/system/cpu/cpu*/cpufreq/missing", "x");
+        assert_eq!(missing.unwrap_err().kind(), ErrorKind::NotFound);
+    }
+
+    #[cfg(feature = "ec_access")]
+    #[test]
+    fn test_decode_ec_registers() {
+        let mut dump = vec![0u8; 256];
+        dump[0x78] = 62;
+        dump[0x84] = 0xB8;
+        dump[0x85] = 0x0B;
+        let regs = decode_ec_registers(&dump).unwrap();
+        assert_eq!(regs["cpu_temp"], 62);
+        assert_eq!(ec_fan_rpm(&regs), Some(3000));
+        assert!(!ec_throttling(&regs));
+
+        dump[0x78] = 97;
+        let regs = decode_ec_registers(&dump).unwrap();
+        assert!(ec_throttling(&regs));
+    }
+
+    #[cfg(feature = "ec_access")]
+    #[test]
+    fn test_decode_ec_registers_short_dump() {
+        assert!(matches!(
+            decode_ec_registers(&[0u8; 16]),
+            Err(ThermalError::InvalidData(_))
+        ));
+    }
+
+    #[cfg(feature = "ec_access")]
+    #[test]
+    fn test_fan_curve_interpolation() {
+        let curve = FanCurve::default();
+        assert_eq!(curve.level_at(30.0), 0);
+        assert_eq!(curve.level_at(40.0), 0);
+        assert_eq!(curve.level_at(47.0), 0);
+        assert_eq!(curve.level_at(48.0), 1);
+        assert_eq!(curve.level_at(62.5), 2);
+        assert_eq!(curve.level_at(85.0), 3);
+        assert_eq!(curve.level_at(99.0), 3);
+        assert_eq!(FanCurve { points: Vec::new() }.level_at(60.0), 0);
+    }
+
+    #[test]
+    fn test_fan_curve_parse_and_display() {
+        let curve = FanCurve::parse("70:2, 40:0,55.5:1").unwrap();
+        assert_eq!(curve.points, vec![(40.0, 0), (55.5, 1), (70.0, 2)]);
+        assert_eq!(FanCurve::parse(&curve.to_string()).unwrap(), curve);
+        assert_eq!(FanCurve::parse("40:0,90:9").unwrap().points[1], (90.0, FAN_LEVEL_MAX));
+
+        assert!(FanCurve::parse("40:0").is_err());
+        assert!(FanCurve::parse("40-0,50:1").is_err());
+        assert!(FanCurve::parse("hot:0,50:1").is_err());
+    }
+
+    #[cfg(feature = "ec_access")]
+    #[test]
+    fn test_fan_curve_editing() {
+        let mut curve = FanCurve::default();
+        assert_eq!(curve.nearest_point(56.0, 1.2, 10.0), Some(1));
+
+        // Points stay between their neighbours and on a valid level
+        curve.move_point(1, 80.0, 7.0);
+        assert_eq!(curve.points[1], (69.0, FAN_LEVEL_MAX));
+        curve.move_point(1, 55.0, 0.6);
+        assert_eq!(curve.points[1], (55.0, 1));
+
+        let index = curve.add_point();
+        assert_eq!(curve.points.len(), 5);
+        assert!(curve.points.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(curve.points[index].1, curve.level_at(curve.points[index].0));
+
+        while curve.remove_point(0) {}
+        assert_eq!(curve.points.len(), 2);
+    }
+
+    #[cfg(feature = "ec_access")]
+    #[test]
+    fn test_release_fan_curve_writes_auto_level() {
+        let mut written = None;
+        release_fan_curve_with(|offset, value| {
+            written = Some((offset, value));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(written, Some((0x2F, EC_FAN_LEVEL_AUTO)));
+
+        let denied = release_fan_curve_with(|_, _| Err(io::Error::from(io::ErrorKind::PermissionDenied)));
+        assert!(matches!(denied, Err(ThermalError::Io(_))));
+    }
+
+    #[test]
+    fn test_parse_trace_line() {
+        let line = "     kworker/0:1-123     [000] .....  1234.500000: thermal_temperature: \
+                    thermal_zone=x86_pkg_temp id=10 temp_prev=45000 temp=46500";
+        let (time, temp) = parse_trace_line(line).unwrap();
+        assert_eq!(time, Duration::from_millis(1_234_500));
+        assert!((temp - 46.5).abs() < 0.001);
+
+        assert!(parse_trace_line("# tracer: nop").is_none());
+        assert!(parse_trace_line("  bash-1 [001] 10.0: sched_switch: prev=1").is_none());
+    }
+
+    #[test]
+    fn test_poll_mode_eventually_matches() {
+        let mut reads = [Mode::Comfort, Mode::Comfort, Mode::Performance].into_iter();
+        let result = poll_mode(Mode::Performance, Duration::from_millis(500), Duration::ZERO, || {
+            reads.next().unwrap_or(Mode::Comfort)
+        });
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_poll_mode_verification_failed() {
+        let result = poll_mode(Mode::Performance, Duration::from_millis(20), Duration::from_millis(5), || Mode::Quiet);
+        match result {
+            Err(ThermalError::VerificationFailed { written, actual }) => {
+                assert_eq!(written, Mode::Performance);
+                assert_eq!(actual, Mode::Quiet);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let message = ThermalError::VerificationFailed { written: Mode::Performance, actual: Mode::Quiet }.to_string();
+        assert_eq!(message, "Mode change failed — hardware rejected 'PERFORMANCE' (reports 'QUIET')");
+    }
+
+    #[test]
+    fn test_parse_proc_stat() {
+        let stat = "1234 (Web Content) S 1 1234 1234 0 -1 4194560 500 0 0 0 150 50 0 0 20 0 1 0 100";
+        assert_eq!(parse_proc_stat(stat), Some(("Web Content".to_string(), 200)));
+        assert!(parse_proc_stat("1234 (short) S 1").is_none());
+
+        let proc_stat = "cpu  100 0 50 800 10 0 5 0 0 0\ncpu0 50 0 25 400 5 0 2 0 0 0\n";
+        assert_eq!(parse_total_ticks(proc_stat), Some(965));
+        assert_eq!(CpuTicks::parse(proc_stat), Some(CpuTicks { busy: 155, total: 965 }));
+        assert!(CpuTicks::parse("intr 12 0 3\n").is_none());
+    }
+
+    #[test]
+    fn test_cpu_load_pct_since() {
+        let previous = CpuTicks { busy: 155, total: 965 };
+        assert_eq!(CpuTicks { busy: 180, total: 1065 }.load_pct_since(&previous), Some(25.0));
+        assert_eq!(previous.load_pct_since(&previous), None);
+        // Counters restarted (e.g. a saved snapshot from before a reboot)
+        assert_eq!(CpuTicks { busy: 10, total: 20 }.load_pct_since(&previous), None);
+    }
+
+    #[test]
+    fn test_cpu_percent() {
+        assert!((cpu_percent(60, Duration::from_secs(30)) - 2.0).abs() < 0.001);
+        assert_eq!(cpu_percent(0, Duration::from_secs(30)), 0.0);
+        assert_eq!(cpu_percent(10, Duration::ZERO), 0.0);
+    }
+
+    #[test]
+    fn test_top_process_usage() {
+        let sample = |total_ticks, entries: &[(u32, &str, u64)]| ProcessCpuSample {
+            processes: entries.iter().map(|&(pid, name, ticks)| (pid, (name.to_string(), ticks))).collect(),
+            total_ticks,
+        };
+        let previous = sample(1000, &[(1, "idle", 10), (2, "cargo", 100), (3, "firefox", 50)]);
+        let current = sample(1200, &[(1, "idle", 10), (2, "cargo", 200), (3, "firefox", 70), (4, "new", 5)]);
+
+        let usage = top_process_usage(&previous, &current, 5);
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[0].0, "cargo");
+        assert!((usage[0].1 - 50.0).abs() < 0.001);
+        assert_eq!(usage[1].0, "firefox");
+
+        assert_eq!(top_process_usage(&previous, &current, 1).len(), 1);
+        assert!(top_process_usage(&current, &current, 5).is_empty());
+    }
+
+    #[test]
+    fn test_read_from_procfs_trace() {
+        use std::io::Write;
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            "# tracer: nop\n\
+             kworker-1 [000] .... 10.000000: thermal_temperature: thermal_zone=acpitz id=0 temp_prev=40000 temp=41000\n\
+             kworker-1 [000] .... 12.000000: thermal_temperature: thermal_zone=acpitz id=0 temp_prev=41000 temp=43000\n"
+        )
+        .unwrap();
+
+        let series = ThermalState::read_from_procfs_trace(file.path());
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[1].0, Duration::from_secs(12));
+        assert!((series[1].1 - 43.0).abs() < 0.001);
+        assert!(ThermalState::read_from_procfs_trace(Path::new("/nonexistent")).is_empty());
+    }
+
+    #[cfg(feature = "intel-rapl")]
+    #[test]
+    fn test_rapl_power_between() {
+        let start = Instant::now();
+        let previous = RaplSample {
+            package_uj: 1_000_000,
+            core_uj: Some(500_000),
+            dram_uj: None,
+            max_uj: u64::MAX,
+            taken_at: start,
+        };
+        let current = RaplSample {
+            package_uj: 21_000_000,
+            core_uj: Some(10_500_000),
+            dram_uj: None,
+            max_uj: u64::MAX,
+            taken_at: start + Duration::from_secs(2),
+        };
+        let info = rapl_power_between(&previous, &current).unwrap();
+        assert!((info.package_watts - 10.0).abs() < 0.01);
+        assert!((info.core_watts - 5.0).abs() < 0.01);
+        assert_eq!(info.dram_watts, 0.0);
+    }
+
+    #[cfg(feature = "intel-rapl")]
+    #[test]
+    fn test_rapl_power_counter_wrap() {
+        let start = Instant::now();
+        let previous = RaplSample {
+            package_uj: 9_000_000,
+            core_uj: None,
+            dram_uj: None,
+            max_uj: 10_000_000,
+            taken_at: start,
+        };
+        let current = RaplSample {
+            package_uj: 1_000_000,
+            taken_at: start + Duration::from_secs(1),
+            ..previous
+        };
+        let info = rapl_power_between(&previous, &current).unwrap();
+        assert!((info.package_watts - 2.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_supports_turbo_toggle() {
+        assert!(supports_turbo_toggle("intel_pstate"));
+        assert!(supports_turbo_toggle("amd-pstate-epp"));
+        assert!(!supports_turbo_toggle("acpi-cpufreq"));
+        assert!(!supports_turbo_toggle("amd-pstate"));
+        assert!(!supports_turbo_toggle(""));
+    }
+
+    #[test]
+    fn test_perf_limit_command_per_driver() {
+        assert_eq!(
+            perf_limit_command("intel_pstate", 60),
+            "echo 60 > /sys/devices/system/cpu/intel_pstate/max_perf_pct"
+        );
+        let generic = perf_limit_command("acpi-cpufreq", 60);
+        assert!(generic.contains("scaling_max_freq"));
+        assert!(generic.contains("max * 60 / 100"));
+        assert!(!generic.contains("intel_pstate"));
+    }
+
+    #[test]
+    fn test_read_module_parameters() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("allow_v4_dytc"), "N\n").unwrap();
+        fs::write(dir.path().join("no_bt_rfkill"), "Y\n").unwrap();
+
+        let parameters = read_module_parameters(dir.path()).unwrap();
+        assert_eq!(parameters.len(), 2);
+        assert_eq!(parameters.get("allow_v4_dytc").map(String::as_str), Some("N"));
+        assert_eq!(parameters.get("no_bt_rfkill").map(String::as_str), Some("Y"));
+
+        assert!(read_module_parameters(&dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_privileged_write_error_classification() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let output = |stderr: &str| Output {
+            status: std::process::ExitStatus::from_raw(1 << 8),
+            stdout: Vec::new(),
+            stderr: stderr.as_bytes().to_vec(),
+        };
+        let erofs = privileged_write_error("Failed", &output("bash: line 1: /sys/x: Read-only file system\n"));
+        assert_eq!(erofs.kind(), ErrorKind::ReadOnlyFilesystem);
+        assert!(is_read_only_error(&erofs));
+
+        let eperm = privileged_write_error("Failed", &output("echo: write error: Operation not permitted"));
+        assert!(is_read_only_error(&eperm));
+
+        let other = privileged_write_error("Failed to set fan mode", &output(""));
+        assert!(!is_read_only_error(&other));
+        assert_eq!(other.to_string(), "Failed to set fan mode");
+
+        assert!(is_read_only_error(&io::Error::from_raw_os_error(30)));
+        assert!(!is_read_only_error(&io::Error::from_raw_os_error(2)));
+        // EACCES is a plain permission problem, not a locked-down sysfs
+        assert!(!is_read_only_error(&io::Error::from_raw_os_error(13)));
+        assert!(!is_read_only_error(&io::Error::from(ErrorKind::PermissionDenied)));
+    }
+
+    #[test]
+    fn test_acpi_events() {
+        let event = AcpiEvent::parse("thermal_zone LNXTHERM:00 00000081 00000000\n").unwrap();
+        assert_eq!(event.event_type, AcpiEventType::ThermalZoneTrip);
+        assert_eq!(event.data, "LNXTHERM:00 00000081 00000000");
+        assert_eq!(AcpiEvent::parse("button/power PBTN 00000080 00000000").unwrap().event_type, AcpiEventType::Button);
+        assert_eq!(AcpiEvent::parse("video/brightnessup BRTUP 00000086").unwrap().event_type, AcpiEventType::Other);
+        assert!(AcpiEvent::parse("  \n").is_none());
+
+        let mut source = io::Cursor::new("\nac_adapter ACPI0003:00 00000080 00000001\n");
+        assert_eq!(read_acpi_events(&mut source).unwrap().event_type, AcpiEventType::AcAdapter);
+        assert!(read_acpi_events(&mut source).is_err());
+    }
+
+    #[test]
+    fn test_parse_cpu_boost() {
+        assert!(parse_cpu_boost("1\n").unwrap());
+        assert!(!parse_cpu_boost("0").unwrap());
+        assert!(parse_cpu_boost("on").is_err());
+    }
+
+    #[test]
+    fn test_parse_xrandr_query() {
+        let query = "Screen 0: minimum 320 x 200, current 1920 x 1080, maximum 16384 x 16384\n\
+                     HDMI-1 disconnected (normal left inverted right x axis y axis)\n\
+                     eDP-1 connected primary 1920x1080+0+0 (normal left inverted right x axis y axis) 344mm x 194mm\n\
+                     \x20  1920x1080    144.00*+  60.01    59.97    59.96    59.93  \n\
+                     \x20  1680x1050     59.95    59.88  \n";
+        let screen = parse_xrandr_query(query).unwrap();
+        assert_eq!(screen.output, "eDP-1");
+        assert_eq!(screen.current, 144);
+        assert_eq!(screen.rates, [144, 60]);
+        assert_eq!(parse_xrandr_query("eDP-1 disconnected\n   1920x1080    60.00*+\n"), None);
+        assert_eq!(parse_xrandr_query(""), None);
+    }
+
+    #[test]
+    fn test_high_contrast_requested() {
+        assert!(high_contrast_requested("true\n", "'Adwaita'\n"));
+        assert!(high_contrast_requested("false\n", "'HighContrastInverse'\n"));
+        assert!(!high_contrast_requested("false\n", "'Yaru-dark'\n"));
+        assert!(!high_contrast_requested("", ""));
+    }
+
+    #[test]
+    fn test_resumed_from_suspend() {
+        let interval = Duration::from_secs(2);
+        let slept = Duration::from_secs(600);
+        assert!(resumed_from_suspend(Some(3), Some(4), slept, interval));
+        assert!(!resumed_from_suspend(Some(4), Some(4), slept, interval));
+        assert!(!resumed_from_suspend(None, Some(4), slept, interval));
+        assert!(!resumed_from_suspend(Some(4), None, slept, interval));
+        // A wakeup source firing while the system runs is not a resume
+        assert!(!resumed_from_suspend(Some(3), Some(4), Duration::ZERO, interval));
+        assert!(!resumed_from_suspend(Some(3), Some(4), interval, interval));
+    }
+
+    #[test]
+    fn test_parse_input_interrupts() {
+        let interrupts = "           CPU0       CPU1\n  \
+             1:       1200         34  IR-IO-APIC    1-edge      i8042\n  \
+             9:          0          3  IR-IO-APIC    9-fasteoi   acpi\n \
+             12:        500          0  IR-IO-APIC   12-edge      i8042\n\
+             NMI:          0          0   Non-maskable interrupts\n";
+        assert_eq!(parse_input_interrupts(interrupts), Some(1734));
+        assert_eq!(parse_input_interrupts("           CPU0\n  9:  3  IR-IO-APIC  9-fasteoi  acpi\n"), None);
+    }
+
     #[test]
     fn test_thermal_state_default() {
         let state = ThermalState::default();
@@ -0,0 +1,151 @@
+//! Reusable layout widgets
+
+use eframe::egui;
+
+/// One button of a [`ButtonGrid`]
+pub struct ButtonItem<'a> {
+    pub button: egui::Button<'a>,
+    pub enabled: bool,
+    pub hover_text: String,
+    pub disabled_hover_text: &'a str,
+}
+
+/// Equal-width buttons laid out in rows of `cols` columns
+pub struct ButtonGrid<'a> {
+    pub cols: usize,
+    pub items: Vec<ButtonItem<'a>>,
+    pub button_height: f32,
+}
+
+impl<'a> ButtonGrid<'a> {
+    /// Grid with as many columns as fit `available_width` without any button
+    /// getting narrower than `button_width_min`, capped at `max_cols`
+    pub fn new(
+        items: Vec<ButtonItem<'a>>,
+        available_width: f32,
+        button_width_min: f32,
+        spacing: f32,
+        max_cols: usize,
+    ) -> Self {
+        let cols = Self::columns_for(available_width, button_width_min, spacing, items.len()).min(max_cols.max(1));
+        Self { cols, items, button_height: 24.0 }
+    }
+
+    pub fn button_height(mut self, height: f32) -> Self {
+        self.button_height = height;
+        self
+    }
+
+    /// Number of columns of at least `button_width_min` that fit, between 1 and `count`
+    pub fn columns_for(available_width: f32, button_width_min: f32, spacing: f32, count: usize) -> usize {
+        let fit = ((available_width + spacing) / (button_width_min + spacing)).floor();
+        (fit.max(1.0) as usize).clamp(1, count.max(1))
+    }
+
+    /// Lay out the buttons row by row; returns the index of the clicked one
+    pub fn show(self, ui: &mut egui::Ui) -> Option<usize> {
+        let cols = self.cols.max(1);
+        let height = self.button_height;
+        let mut clicked = None;
+        let mut items = self.items.into_iter().enumerate().peekable();
+        while items.peek().is_some() {
+            let row: Vec<_> = items.by_ref().take(cols).collect();
+            ui.columns(cols, |columns| {
+                for ((index, item), column) in row.into_iter().zip(columns.iter_mut()) {
+                    let width = column.available_width();
+                    let button = item.button.min_size(egui::vec2(width, height));
+                    if column
+                        .add_enabled(item.enabled, button)
+                        .on_hover_text(item.hover_text)
+                        .on_disabled_hover_text(item.disabled_hover_text)
+                        .clicked()
+                    {
+                        clicked = Some(index);
+                    }
+                }
+            });
+        }
+        clicked
+    }
+}
+
+/// Tiny line chart of `data` without axes, scaled to its own range.
+/// Cheaper than an `egui_plot::Plot` for inline use.
+pub fn render_sparkline(data: &[f32], width: f32, height: f32, color: egui::Color32, ui: &mut egui::Ui) {
+    let (_, rect) = ui.allocate_space(egui::vec2(width, height));
+    let points = sparkline_points(data, rect);
+    match points.as_slice() {
+        [] => {}
+        [point] => {
+            ui.painter().circle_filled(*point, 1.5, color);
+        }
+        _ => {
+            ui.painter().add(egui::Shape::line(points, egui::Stroke::new(1.0, color)));
+        }
+    }
+}
+
+/// Screen positions of `data` spread evenly across `rect`, the lowest value
+/// at the bottom and the highest at the top. Non-finite values are skipped;
+/// a flat series sits in the middle.
+pub fn sparkline_points(data: &[f32], rect: egui::Rect) -> Vec<egui::Pos2> {
+    let (min, max) = data
+        .iter()
+        .filter(|v| v.is_finite())
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+    let step = rect.width() / (data.len().max(2) - 1) as f32;
+    data.iter()
+        .enumerate()
+        .filter(|(_, v)| v.is_finite())
+        .map(|(i, &v)| {
+            let y = if max > min {
+                rect.bottom() - (v - min) / (max - min) * rect.height()
+            } else {
+                rect.center().y
+            };
+            egui::pos2(rect.left() + i as f32 * step, y)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparkline_points() {
+        let rect = egui::Rect::from_min_size(egui::pos2(10.0, 20.0), egui::vec2(30.0, 10.0));
+        assert!(sparkline_points(&[], rect).is_empty());
+        assert!(sparkline_points(&[f32::NAN], rect).is_empty());
+        assert_eq!(sparkline_points(&[50.0], rect), [egui::pos2(10.0, 25.0)]);
+        assert_eq!(
+            sparkline_points(&[40.0, 60.0, f32::NAN, 50.0], rect),
+            [egui::pos2(10.0, 30.0), egui::pos2(20.0, 20.0), egui::pos2(40.0, 25.0)]
+        );
+        // Flat series
+        assert_eq!(sparkline_points(&[45.0, 45.0], rect), [egui::pos2(10.0, 25.0), egui::pos2(40.0, 25.0)]);
+    }
+
+    #[test]
+    fn test_render_sparkline_short_data() {
+        let ctx = egui::Context::default();
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                render_sparkline(&[], 40.0, 10.0, egui::Color32::WHITE, ui);
+                render_sparkline(&[50.0], 40.0, 10.0, egui::Color32::WHITE, ui);
+                render_sparkline(&[40.0, 60.0], 40.0, 10.0, egui::Color32::WHITE, ui);
+            });
+        });
+    }
+
+    #[test]
+    fn test_columns_for() {
+        // 4 buttons of 70 px with 4 px spacing need 292 px
+        assert_eq!(ButtonGrid::columns_for(292.0, 70.0, 4.0, 4), 4);
+        assert_eq!(ButtonGrid::columns_for(291.0, 70.0, 4.0, 4), 3);
+        assert_eq!(ButtonGrid::columns_for(1000.0, 70.0, 4.0, 4), 4);
+        // Never fewer than one column, even when nothing fits
+        assert_eq!(ButtonGrid::columns_for(40.0, 70.0, 4.0, 4), 1);
+        assert_eq!(ButtonGrid::columns_for(500.0, 70.0, 4.0, 0), 1);
+    }
+}